@@ -14,9 +14,11 @@ namespace Azure.Iot.Operations.Services.SchemaRegistry.SchemaRegistry
     [System.CodeDom.Compiler.GeneratedCode("Azure.Iot.Operations.ProtocolCompiler", "0.10.0.0")]
     public enum Format
     {
+        [EnumMember(Value = @"Avro/1.0")]
+        Avro1 = 0,
         [EnumMember(Value = @"Delta/1.0")]
-        Delta1 = 0,
+        Delta1 = 1,
         [EnumMember(Value = @"JsonSchema/draft-07")]
-        JsonSchemaDraft07 = 1,
+        JsonSchemaDraft07 = 2,
     }
 }
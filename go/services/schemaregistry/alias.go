@@ -12,6 +12,7 @@ type Schema = schemaregistry.Schema
 type Format = schemaregistry.Format
 
 const (
+	Avro1             = schemaregistry.Avro1
 	Delta1            = schemaregistry.Delta1
 	JSONSchemaDraft07 = schemaregistry.JsonSchemaDraft07
 )
@@ -10,12 +10,15 @@ import (
 type Format int32
 
 const (
+	Avro1 Format = iota
 	Delta1 Format = iota
 	JsonSchemaDraft07 Format = iota
 )
 
 func (v Format) String() string {
 	switch v {
+	case Avro1:
+		return "Avro1"
 	case Delta1:
 		return "Delta1"
 	case JsonSchemaDraft07:
@@ -28,6 +31,8 @@ func (v Format) String() string {
 func (v Format) MarshalJSON() ([]byte, error) {
 	var s string
 	switch v {
+	case Avro1:
+		s = "Avro/1.0"
 	case Delta1:
 		s = "Delta/1.0"
 	case JsonSchemaDraft07:
@@ -46,6 +51,8 @@ func (v *Format) UnmarshalJSON(b []byte) error {
 	}
 
 	switch s {
+	case "Avro/1.0":
+		*v = Avro1
 	case "Delta/1.0":
 		*v = Delta1
 	case "JsonSchema/draft-07":
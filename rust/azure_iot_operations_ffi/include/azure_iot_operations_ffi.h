@@ -0,0 +1,300 @@
+/* Copyright (c) Microsoft Corporation.
+   Licensed under the MIT License. */
+
+#ifndef AZURE_IOT_OPERATIONS_FFI_H
+#define AZURE_IOT_OPERATIONS_FFI_H
+
+/* Generated by cbindgen from the azure_iot_operations_ffi crate. Do not edit manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result status of a function of the bindings
+typedef enum {
+  // The operation succeeded
+  AIO_STATUS_OK = 0,
+  // An argument was null or otherwise invalid
+  AIO_STATUS_INVALID_ARGUMENT = 1,
+  // The provided configuration was invalid
+  AIO_STATUS_CONFIGURATION_INVALID = 2,
+  // The operation did not complete within the allotted time
+  AIO_STATUS_TIMEOUT = 3,
+  // The operation was cancelled
+  AIO_STATUS_CANCELLED = 4,
+  // The operation failed due to an error of the MQTT client
+  AIO_STATUS_MQTT_ERROR = 5,
+  // The operation failed due to an error in the protocol
+  AIO_STATUS_PROTOCOL_ERROR = 6,
+  // The MQTT session failed or ended
+  AIO_STATUS_SESSION_ERROR = 7,
+} AioStatus;
+
+// Executor of a command with raw byte payloads.
+//
+// Created with `aio_command_executor_new` and released with `aio_command_executor_free`.
+typedef struct AioCommandExecutor AioCommandExecutor;
+
+// Invoker of a command with raw byte payloads.
+//
+// Created with `aio_command_invoker_new` and released with `aio_command_invoker_free`.
+typedef struct AioCommandInvoker AioCommandInvoker;
+
+// Response to a command request, populated by an [`AioCommandHandler`].
+//
+// Owned by the bindings, and only valid for the duration of the handler.
+typedef struct AioCommandResponse AioCommandResponse;
+
+// MQTT session running on an internal runtime.
+//
+// Created with `aio_session_new` and released with `aio_session_free`.
+typedef struct AioSession AioSession;
+
+// Receiver of telemetry with raw byte payloads.
+//
+// Created with `aio_telemetry_receiver_new` and released with `aio_telemetry_receiver_free`.
+typedef struct AioTelemetryReceiver AioTelemetryReceiver;
+
+// Sender of telemetry with raw byte payloads.
+//
+// Created with `aio_telemetry_sender_new` and released with `aio_telemetry_sender_free`.
+typedef struct AioTelemetrySender AioTelemetrySender;
+
+// Byte buffer allocated by the bindings and owned by the caller.
+//
+// Must be released with `aio_buffer_free`.
+typedef struct {
+  // Pointer to the bytes of the buffer. Null if the buffer is empty.
+  uint8_t *data;
+  // Number of bytes in the buffer
+  size_t len;
+} AioBuffer;
+
+// Handler invoked for each command request received by an [`AioCommandExecutor`].
+//
+// `request` points to `len` bytes of request payload, which are only valid for the duration of
+// the handler. The response payload is set with `aio_command_response_set_payload`, and is empty
+// if not set. The response is sent once the handler returns.
+typedef void (*AioCommandHandler)(void *context,
+                                  const uint8_t *request,
+                                  size_t len,
+                                  AioCommandResponse *response);
+
+// Settings for connecting to an MQTT broker
+typedef struct {
+  // Client identifier. If null, a client identifier is generated.
+  const char *client_id;
+  // Host name of the broker. Must not be null.
+  const char *hostname;
+  // TCP port of the broker
+  uint16_t tcp_port;
+  // Indicates if TLS is used for the connection
+  bool use_tls;
+  // Path to a PEM file with the CA certificates used to validate the broker. May be null.
+  const char *ca_file;
+  // Username used for authentication. May be null.
+  const char *username;
+  // Path to a file containing the password used for authentication. May be null.
+  const char *password_file;
+  // Path to a file containing a SAT token used for authentication. May be null.
+  const char *sat_file;
+  // Keep alive interval in seconds. If 0, the default keep alive interval is used.
+  uint32_t keep_alive_secs;
+} AioConnectionSettings;
+
+// Callback invoked for each telemetry message received by an [`AioTelemetryReceiver`].
+//
+// `topic` is the nul-terminated topic the message was received on, and `payload` points to `len`
+// bytes of payload. Both are only valid for the duration of the callback. The message is
+// acknowledged once the callback returns.
+typedef void (*AioTelemetryCallback)(void *context,
+                                     const char *topic,
+                                     const uint8_t *payload,
+                                     size_t len);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Release a buffer returned by the bindings, and reset it to be empty.
+//
+// # Safety
+// `buffer` must be null or point to an [`AioBuffer`] returned by the bindings that has not
+// already been released.
+void aio_buffer_free(AioBuffer *buffer);
+
+// Return a description of the last error that occurred on the calling thread, or null if no
+// error has occurred.
+//
+// The returned string is owned by the bindings, and is valid until the next call to a function of
+// the bindings on the same thread.
+const char *aio_last_error_message(void);
+
+// Create a new command invoker that publishes requests to the provided request topic pattern.
+//
+// If `response_topic_pattern` is null, the response topic is derived from the request topic.
+//
+// # Safety
+// `session` must be null or a valid session handle, the strings must be null or nul-terminated,
+// and `out` must be null or valid for writes.
+AioStatus aio_command_invoker_new(AioSession *session,
+                                  const char *command_name,
+                                  const char *request_topic_pattern,
+                                  const char *response_topic_pattern,
+                                  AioCommandInvoker **out);
+
+// Invoke the command with the provided request payload, and block until the response is received
+// or `timeout_ms` elapses. The timeout is rounded up to the nearest second.
+//
+// On success, the response payload is written to `response_out`, and must be released with
+// `aio_buffer_free`. On failure, `response_out` is not modified.
+//
+// # Safety
+// `invoker` must be null or a valid invoker handle, `payload` must be null or point to at least
+// `len` readable bytes, and `response_out` must be null or valid for writes.
+AioStatus aio_command_invoke(AioCommandInvoker *invoker,
+                             const uint8_t *payload,
+                             size_t len,
+                             uint32_t timeout_ms,
+                             AioBuffer *response_out);
+
+// Release a command invoker.
+//
+// # Safety
+// `invoker` must be null or a valid invoker handle, which must not be used after this call.
+void aio_command_invoker_free(AioCommandInvoker *invoker);
+
+// Create a new command executor that subscribes to the provided request topic pattern, and
+// invokes `handler` with `context` for each request received.
+//
+// Requests are received once the session is connected.
+//
+// # Safety
+// `session` must be null or a valid session handle, the strings must be null or nul-terminated,
+// and `out` must be null or valid for writes. `context` must be safe to use from the threads of
+// the internal runtime until the executor is freed.
+AioStatus aio_command_executor_new(AioSession *session,
+                                   const char *command_name,
+                                   const char *request_topic_pattern,
+                                   AioCommandHandler handler,
+                                   void *context,
+                                   AioCommandExecutor **out);
+
+// Set the payload of a command response, replacing any payload previously set.
+//
+// # Safety
+// `response` must be null or the response provided to the running handler, and `data` must be
+// null or point to at least `len` readable bytes.
+AioStatus aio_command_response_set_payload(AioCommandResponse *response,
+                                           const uint8_t *data,
+                                           size_t len);
+
+// Release a command executor. Blocks until any handler in progress has returned, after which no
+// further handlers are invoked.
+//
+// # Safety
+// `executor` must be null or a valid executor handle, which must not be used after this call.
+// Must not be called from within the handler of the executor.
+void aio_command_executor_free(AioCommandExecutor *executor);
+
+// Create a new session from the provided connection settings. The session does not connect to
+// the broker until started with `aio_session_start`.
+//
+// # Safety
+// `settings` must be null or point to a valid [`AioConnectionSettings`], and `out` must be null
+// or valid for writes.
+AioStatus aio_session_new(const AioConnectionSettings *settings, AioSession **out);
+
+// Start running the session on its internal runtime. Returns immediately.
+//
+// # Safety
+// `session` must be null or a valid session handle.
+AioStatus aio_session_start(AioSession *session);
+
+// Block until the session ends, either due to `aio_session_exit` or a fatal error.
+//
+// Returns `AIO_STATUS_SESSION_ERROR` if the session ended due to an error, was never started, or
+// is already being waited on.
+//
+// # Safety
+// `session` must be null or a valid session handle.
+AioStatus aio_session_wait(AioSession *session);
+
+// Gracefully end the session, and block until it has ended.
+//
+// If the session cannot be ended gracefully (e.g. because the broker is unavailable), it is
+// forcefully ended. If the session is being waited on by `aio_session_wait`, the outcome of the
+// session is reported there instead.
+//
+// # Safety
+// `session` must be null or a valid session handle.
+AioStatus aio_session_exit(AioSession *session);
+
+// Release a session. If the session is running, it is forcefully ended.
+//
+// Handles created from the session remain valid, but their operations fail once the session has
+// ended.
+//
+// # Safety
+// `session` must be null or a valid session handle, which must not be used after this call.
+void aio_session_free(AioSession *session);
+
+// Create a new telemetry sender that publishes to the provided topic pattern.
+//
+// # Safety
+// `session` must be null or a valid session handle, `topic_pattern` must be null or
+// nul-terminated, and `out` must be null or valid for writes.
+AioStatus aio_telemetry_sender_new(AioSession *session,
+                                   const char *topic_pattern,
+                                   AioTelemetrySender **out);
+
+// Send a telemetry message with the provided payload, and block until it has been acknowledged
+// by the broker.
+//
+// The message is sent at least once (quality of service 1) with content type
+// `application/octet-stream`. If `message_expiry_secs` is 0, the default message expiry is used.
+//
+// # Safety
+// `sender` must be null or a valid sender handle, and `payload` must be null or point to at least
+// `len` readable bytes.
+AioStatus aio_telemetry_send(AioTelemetrySender *sender,
+                             const uint8_t *payload,
+                             size_t len,
+                             uint32_t message_expiry_secs);
+
+// Release a telemetry sender.
+//
+// # Safety
+// `sender` must be null or a valid sender handle, which must not be used after this call.
+void aio_telemetry_sender_free(AioTelemetrySender *sender);
+
+// Create a new telemetry receiver that subscribes to the provided topic pattern, and invokes
+// `callback` with `context` for each message received.
+//
+// Messages are received once the session is connected.
+//
+// # Safety
+// `session` must be null or a valid session handle, `topic_pattern` must be null or
+// nul-terminated, and `out` must be null or valid for writes. `context` must be safe to use from
+// the threads of the internal runtime until the receiver is freed.
+AioStatus aio_telemetry_receiver_new(AioSession *session,
+                                     const char *topic_pattern,
+                                     AioTelemetryCallback callback,
+                                     void *context,
+                                     AioTelemetryReceiver **out);
+
+// Release a telemetry receiver. Blocks until any callback in progress has returned, after which
+// no further callbacks are invoked.
+//
+// # Safety
+// `receiver` must be null or a valid receiver handle, which must not be used after this call.
+// Must not be called from within the callback of the receiver.
+void aio_telemetry_receiver_free(AioTelemetryReceiver *receiver);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* AZURE_IOT_OPERATIONS_FFI_H */
@@ -0,0 +1,55 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Byte buffers owned by the caller.
+
+/// Byte buffer allocated by the bindings and owned by the caller.
+///
+/// Must be released with `aio_buffer_free`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct AioBuffer {
+    /// Pointer to the bytes of the buffer. Null if the buffer is empty.
+    pub data: *mut u8,
+    /// Number of bytes in the buffer
+    pub len: usize,
+}
+
+impl AioBuffer {
+    /// An empty buffer, which does not need to be released
+    pub(crate) const EMPTY: Self = Self {
+        data: std::ptr::null_mut(),
+        len: 0,
+    };
+
+    /// Transfer ownership of the provided bytes to a new [`AioBuffer`].
+    pub(crate) fn from_vec(bytes: Vec<u8>) -> Self {
+        if bytes.is_empty() {
+            return Self::EMPTY;
+        }
+        let bytes = bytes.into_boxed_slice();
+        let len = bytes.len();
+        Self {
+            data: Box::into_raw(bytes).cast::<u8>(),
+            len,
+        }
+    }
+}
+
+/// Release a buffer returned by the bindings, and reset it to be empty.
+///
+/// # Safety
+/// `buffer` must be null or point to an [`AioBuffer`] returned by the bindings that has not
+/// already been released.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_buffer_free(buffer: *mut AioBuffer) {
+    // SAFETY: The caller guarantees the buffer is null or valid
+    let Some(buffer) = (unsafe { buffer.as_mut() }) else {
+        return;
+    };
+    if !buffer.data.is_null() {
+        // SAFETY: Non-empty buffers are created from a boxed slice of `len` bytes in `from_vec`
+        drop(unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)) });
+    }
+    *buffer = AioBuffer::EMPTY;
+}
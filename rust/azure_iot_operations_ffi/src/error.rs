@@ -0,0 +1,76 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Status codes and error reporting.
+
+use std::cell::RefCell;
+use std::ffi::{CString, c_char};
+use std::fmt::Display;
+
+use azure_iot_operations_protocol::common::aio_protocol_error::{
+    AIOProtocolError, AIOProtocolErrorKind,
+};
+
+/// Result status of a function of the bindings
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AioStatus {
+    /// The operation succeeded
+    Ok = 0,
+    /// An argument was null or otherwise invalid
+    InvalidArgument = 1,
+    /// The provided configuration was invalid
+    ConfigurationInvalid = 2,
+    /// The operation did not complete within the allotted time
+    Timeout = 3,
+    /// The operation was cancelled
+    Cancelled = 4,
+    /// The operation failed due to an error of the MQTT client
+    MqttError = 5,
+    /// The operation failed due to an error in the protocol
+    ProtocolError = 6,
+    /// The MQTT session failed or ended
+    SessionError = 7,
+}
+
+thread_local! {
+    /// Description of the last error that occurred on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Record the description of an error for this thread and return the provided status.
+pub(crate) fn fail(status: AioStatus, error: impl Display) -> AioStatus {
+    let message = error.to_string().replace('\0', " ");
+    log::debug!("{status:?}: {message}");
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = CString::new(message).ok();
+    });
+    status
+}
+
+/// Record the description of an [`AIOProtocolError`] for this thread and return the
+/// corresponding status.
+pub(crate) fn fail_protocol(error: &AIOProtocolError) -> AioStatus {
+    let status = match error.kind {
+        AIOProtocolErrorKind::Timeout => AioStatus::Timeout,
+        AIOProtocolErrorKind::Cancellation => AioStatus::Cancelled,
+        AIOProtocolErrorKind::ConfigurationInvalid => AioStatus::ConfigurationInvalid,
+        AIOProtocolErrorKind::ClientError => AioStatus::MqttError,
+        _ => AioStatus::ProtocolError,
+    };
+    fail(status, error)
+}
+
+/// Return a description of the last error that occurred on the calling thread, or null if no
+/// error has occurred.
+///
+/// The returned string is owned by the bindings, and is valid until the next call to a function of
+/// the bindings on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn aio_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
@@ -0,0 +1,53 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+#![warn(missing_docs)]
+
+//! C bindings for the Azure IoT Operations MQTT and Protocol libraries.
+//!
+//! The C header for these bindings is generated into `include/azure_iot_operations_ffi.h` when
+//! the crate is built.
+//!
+//! # Ownership
+//! * Handles (e.g. `AioSession`, `AioTelemetrySender`) are created by a `*_new` function, which
+//!   writes the handle to an out parameter, and are owned by the caller until released with the
+//!   corresponding `*_free` function. Passing a null handle to a `*_free` function has no effect.
+//! * Handles created from an `AioSession` keep the resources of the session alive, so they may be
+//!   freed in any order.
+//! * Strings and byte buffers passed into the bindings are borrowed for the duration of the call
+//!   only, and are copied if needed afterwards.
+//! * Byte buffers returned by the bindings (`AioBuffer`) are owned by the caller and must be
+//!   released with `aio_buffer_free`.
+//! * Strings and byte buffers passed to callbacks are only valid for the duration of the callback.
+//!
+//! # Thread safety
+//! * All functions other than the `*_free` functions may be called concurrently from any thread,
+//!   including on the same handle. A `*_free` function must not be called concurrently with any
+//!   other function using the same handle.
+//! * Each `AioSession` owns an internal runtime that runs the MQTT session and the handles created
+//!   from it. Blocking functions (e.g. `aio_telemetry_send`, `aio_command_invoke`) block the
+//!   calling thread until the operation completes.
+//! * Callbacks are invoked on threads of the internal runtime that are allowed to block, so
+//!   blocking functions may be called from within callbacks. Callbacks of a single receiver or
+//!   executor are invoked one at a time, but callbacks of different receivers or executors may be
+//!   invoked concurrently. The `context` pointer provided with a callback must be safe to use from
+//!   these threads.
+//! * A `*_free` function must not be called from within a callback of the handle being freed.
+//!
+//! # Errors
+//! Functions that can fail return an `AioStatus`. When a function returns a status other than
+//! `AIO_STATUS_OK`, a description of the error can be retrieved on the same thread with
+//! `aio_last_error_message`.
+
+mod buffer;
+mod error;
+mod rpc;
+mod session;
+mod telemetry;
+mod util;
+
+pub use buffer::*;
+pub use error::*;
+pub use rpc::*;
+pub use session::*;
+pub use telemetry::*;
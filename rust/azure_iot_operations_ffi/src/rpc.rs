@@ -0,0 +1,391 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Bindings for invoking and executing commands with raw byte payloads.
+
+use std::ffi::{c_char, c_void};
+use std::sync::Arc;
+use std::time::Duration;
+
+use azure_iot_operations_mqtt::session::SessionManagedClient;
+use azure_iot_operations_protocol::common::payload_serialize::BypassPayload;
+use azure_iot_operations_protocol::rpc_command;
+
+use crate::buffer::AioBuffer;
+use crate::error::{AioStatus, fail, fail_protocol};
+use crate::session::{AioSession, SharedRuntime};
+use crate::util::{
+    CallbackContext, CallbackLoop, block_on, bytes, optional_str, required_str, write_out,
+};
+
+/// Invoker of a command with raw byte payloads.
+///
+/// Created with `aio_command_invoker_new` and released with `aio_command_invoker_free`.
+pub struct AioCommandInvoker {
+    runtime: Arc<SharedRuntime>,
+    invoker: rpc_command::Invoker<Vec<u8>, BypassPayload, SessionManagedClient>,
+}
+
+/// Executor of a command with raw byte payloads.
+///
+/// Created with `aio_command_executor_new` and released with `aio_command_executor_free`.
+pub struct AioCommandExecutor {
+    _callback_loop: CallbackLoop,
+}
+
+/// Response to a command request, populated by an [`AioCommandHandler`].
+///
+/// Owned by the bindings, and only valid for the duration of the handler.
+pub struct AioCommandResponse {
+    payload: Vec<u8>,
+}
+
+/// Handler invoked for each command request received by an [`AioCommandExecutor`].
+///
+/// `request` points to `len` bytes of request payload, which are only valid for the duration of
+/// the handler. The response payload is set with `aio_command_response_set_payload`, and is empty
+/// if not set. The response is sent once the handler returns.
+pub type AioCommandHandler = Option<
+    unsafe extern "C" fn(
+        context: *mut c_void,
+        request: *const u8,
+        len: usize,
+        response: *mut AioCommandResponse,
+    ),
+>;
+
+/// Create a new command invoker that publishes requests to the provided request topic pattern.
+///
+/// If `response_topic_pattern` is null, the response topic is derived from the request topic.
+///
+/// # Safety
+/// `session` must be null or a valid session handle, the strings must be null or nul-terminated,
+/// and `out` must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_command_invoker_new(
+    session: *mut AioSession,
+    command_name: *const c_char,
+    request_topic_pattern: *const c_char,
+    response_topic_pattern: *const c_char,
+    out: *mut *mut AioCommandInvoker,
+) -> AioStatus {
+    // SAFETY: The caller guarantees the pointers are null or valid
+    unsafe {
+        new_invoker(
+            session,
+            command_name,
+            request_topic_pattern,
+            response_topic_pattern,
+            out,
+        )
+    }
+    .err()
+    .unwrap_or(AioStatus::Ok)
+}
+
+unsafe fn new_invoker(
+    session: *mut AioSession,
+    command_name: *const c_char,
+    request_topic_pattern: *const c_char,
+    response_topic_pattern: *const c_char,
+    out: *mut *mut AioCommandInvoker,
+) -> Result<(), AioStatus> {
+    // SAFETY: The caller guarantees the handle is null or valid
+    let Some(session) = (unsafe { session.as_ref() }) else {
+        return Err(fail(AioStatus::InvalidArgument, "session must not be null"));
+    };
+    // SAFETY: The caller guarantees the strings are null or nul-terminated
+    let invoker_options = unsafe {
+        rpc_command::invoker::OptionsBuilder::default()
+            .command_name(required_str(command_name, "command_name")?)
+            .request_topic_pattern(required_str(
+                request_topic_pattern,
+                "request_topic_pattern",
+            )?)
+            .response_topic_pattern(optional_str(
+                response_topic_pattern,
+                "response_topic_pattern",
+            )?)
+            .build()
+    }
+    .map_err(|e| fail(AioStatus::ConfigurationInvalid, e))?;
+    let invoker = {
+        // The invoker must be created within the context of the runtime it will run on
+        let _runtime_guard = session.runtime.handle().enter();
+        rpc_command::Invoker::new(
+            session.application_context.clone(),
+            session.managed_client.clone(),
+            invoker_options,
+        )
+        .map_err(|e| fail_protocol(&e))?
+    };
+    let invoker = AioCommandInvoker {
+        runtime: session.runtime.clone(),
+        invoker,
+    };
+
+    // SAFETY: The caller guarantees `out` is null or valid for writes
+    unsafe { write_out(out, Box::into_raw(Box::new(invoker)), "out") }
+}
+
+/// Invoke the command with the provided request payload, and block until the response is received
+/// or `timeout_ms` elapses. The timeout is rounded up to the nearest second.
+///
+/// On success, the response payload is written to `response_out`, and must be released with
+/// `aio_buffer_free`. On failure, `response_out` is not modified.
+///
+/// # Safety
+/// `invoker` must be null or a valid invoker handle, `payload` must be null or point to at least
+/// `len` readable bytes, and `response_out` must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_command_invoke(
+    invoker: *mut AioCommandInvoker,
+    payload: *const u8,
+    len: usize,
+    timeout_ms: u32,
+    response_out: *mut AioBuffer,
+) -> AioStatus {
+    // SAFETY: The caller guarantees the pointers are null or valid
+    unsafe { invoke(invoker, payload, len, timeout_ms, response_out) }
+        .err()
+        .unwrap_or(AioStatus::Ok)
+}
+
+unsafe fn invoke(
+    invoker: *mut AioCommandInvoker,
+    payload: *const u8,
+    len: usize,
+    timeout_ms: u32,
+    response_out: *mut AioBuffer,
+) -> Result<(), AioStatus> {
+    // SAFETY: The caller guarantees the handle is null or valid
+    let Some(invoker) = (unsafe { invoker.as_ref() }) else {
+        return Err(fail(AioStatus::InvalidArgument, "invoker must not be null"));
+    };
+    if response_out.is_null() {
+        return Err(fail(
+            AioStatus::InvalidArgument,
+            "response_out must not be null",
+        ));
+    }
+    // SAFETY: The caller guarantees `len` bytes are readable
+    let payload = unsafe { bytes(payload, len, "payload") }?;
+    let request = rpc_command::invoker::RequestBuilder::default()
+        .payload(payload)
+        .map_err(|e| fail_protocol(&e))?
+        .timeout(Duration::from_millis(u64::from(timeout_ms)))
+        .build()
+        .map_err(|e| fail(AioStatus::InvalidArgument, e))?;
+    let response = block_on(invoker.runtime.handle(), invoker.invoker.invoke(request))
+        .map_err(|e| fail_protocol(&e))?;
+
+    // SAFETY: The caller guarantees `response_out` is valid for writes
+    unsafe {
+        write_out(
+            response_out,
+            AioBuffer::from_vec(response.payload.payload),
+            "response_out",
+        )
+    }
+}
+
+/// Release a command invoker.
+///
+/// # Safety
+/// `invoker` must be null or a valid invoker handle, which must not be used after this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_command_invoker_free(invoker: *mut AioCommandInvoker) {
+    if invoker.is_null() {
+        return;
+    }
+    // SAFETY: The caller guarantees the handle is valid and no longer used
+    let invoker = unsafe { Box::from_raw(invoker) };
+    let runtime = invoker.runtime.clone();
+    if let Err(e) = block_on(runtime.handle(), invoker.invoker.shutdown()) {
+        log::warn!("Error shutting down command invoker: {e}");
+    }
+    // The invoker must be dropped within the context of the runtime it runs on
+    let _runtime_guard = runtime.handle().enter();
+    drop(invoker);
+}
+
+/// Create a new command executor that subscribes to the provided request topic pattern, and
+/// invokes `handler` with `context` for each request received.
+///
+/// Requests are received once the session is connected.
+///
+/// # Safety
+/// `session` must be null or a valid session handle, the strings must be null or nul-terminated,
+/// and `out` must be null or valid for writes. `context` must be safe to use from the threads of
+/// the internal runtime until the executor is freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_command_executor_new(
+    session: *mut AioSession,
+    command_name: *const c_char,
+    request_topic_pattern: *const c_char,
+    handler: AioCommandHandler,
+    context: *mut c_void,
+    out: *mut *mut AioCommandExecutor,
+) -> AioStatus {
+    // SAFETY: The caller guarantees the pointers are null or valid
+    unsafe {
+        new_executor(
+            session,
+            command_name,
+            request_topic_pattern,
+            handler,
+            context,
+            out,
+        )
+    }
+    .err()
+    .unwrap_or(AioStatus::Ok)
+}
+
+unsafe fn new_executor(
+    session: *mut AioSession,
+    command_name: *const c_char,
+    request_topic_pattern: *const c_char,
+    handler: AioCommandHandler,
+    context: *mut c_void,
+    out: *mut *mut AioCommandExecutor,
+) -> Result<(), AioStatus> {
+    // SAFETY: The caller guarantees the handle is null or valid
+    let Some(session) = (unsafe { session.as_ref() }) else {
+        return Err(fail(AioStatus::InvalidArgument, "session must not be null"));
+    };
+    let Some(handler) = handler else {
+        return Err(fail(AioStatus::InvalidArgument, "handler must not be null"));
+    };
+    if out.is_null() {
+        return Err(fail(AioStatus::InvalidArgument, "out must not be null"));
+    }
+    // SAFETY: The caller guarantees the strings are null or nul-terminated
+    let executor_options = unsafe {
+        rpc_command::executor::OptionsBuilder::default()
+            .command_name(required_str(command_name, "command_name")?)
+            .request_topic_pattern(required_str(
+                request_topic_pattern,
+                "request_topic_pattern",
+            )?)
+            .build()
+    }
+    .map_err(|e| fail(AioStatus::ConfigurationInvalid, e))?;
+    let mut executor: rpc_command::Executor<BypassPayload, Vec<u8>, SessionManagedClient> = {
+        // The executor must be created within the context of the runtime it will run on
+        let _runtime_guard = session.runtime.handle().enter();
+        rpc_command::Executor::new(
+            session.application_context.clone(),
+            session.managed_client.clone(),
+            executor_options,
+        )
+        .map_err(|e| fail_protocol(&e))?
+    };
+
+    let context = CallbackContext(context);
+    let callback_loop =
+        CallbackLoop::spawn(session.runtime.clone(), |mut shutdown_rx| async move {
+            loop {
+                let request = tokio::select! {
+                    _ = &mut shutdown_rx => break,
+                    request = executor.recv() => request,
+                };
+                let mut request = match request {
+                    Some(Ok(request)) => request,
+                    Some(Err(e)) => {
+                        log::warn!("Error receiving command request: {e}");
+                        continue;
+                    }
+                    None => break,
+                };
+                let payload = std::mem::take(&mut request.payload.payload);
+                let invocation = tokio::task::spawn_blocking(move || {
+                    let mut response = AioCommandResponse {
+                        payload: Vec::new(),
+                    };
+                    // SAFETY: The payload and response are valid for the duration of the handler, and
+                    // the caller guarantees the context is safe to use from this thread
+                    unsafe {
+                        handler(
+                            context.get(),
+                            payload.as_ptr(),
+                            payload.len(),
+                            &mut response,
+                        );
+                    };
+                    response
+                });
+                let response_payload = match invocation.await {
+                    Ok(response) => response.payload,
+                    Err(e) => {
+                        // Dropping the request sends an error response to the invoker
+                        log::error!("Command handler panicked: {e}");
+                        continue;
+                    }
+                };
+                let mut response_builder = rpc_command::executor::ResponseBuilder::default();
+                if let Err(e) = response_builder.payload(response_payload) {
+                    log::error!("Error serializing command response: {e}");
+                    continue;
+                }
+                let response = match response_builder.build() {
+                    Ok(response) => response,
+                    Err(e) => {
+                        log::error!("Error building command response: {e}");
+                        continue;
+                    }
+                };
+                if let Err(e) = request.complete(response).await {
+                    log::warn!("Error completing command request: {e}");
+                }
+            }
+            if let Err(e) = executor.shutdown().await {
+                log::warn!("Error shutting down command executor: {e}");
+            }
+        });
+    let executor = AioCommandExecutor {
+        _callback_loop: callback_loop,
+    };
+
+    // SAFETY: The caller guarantees `out` is valid for writes
+    unsafe { write_out(out, Box::into_raw(Box::new(executor)), "out") }
+}
+
+/// Set the payload of a command response, replacing any payload previously set.
+///
+/// # Safety
+/// `response` must be null or the response provided to the running handler, and `data` must be
+/// null or point to at least `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_command_response_set_payload(
+    response: *mut AioCommandResponse,
+    data: *const u8,
+    len: usize,
+) -> AioStatus {
+    // SAFETY: The caller guarantees the response is null or valid
+    let Some(response) = (unsafe { response.as_mut() }) else {
+        return fail(AioStatus::InvalidArgument, "response must not be null");
+    };
+    // SAFETY: The caller guarantees `len` bytes are readable
+    match unsafe { bytes(data, len, "data") } {
+        Ok(payload) => {
+            response.payload = payload;
+            AioStatus::Ok
+        }
+        Err(status) => status,
+    }
+}
+
+/// Release a command executor. Blocks until any handler in progress has returned, after which no
+/// further handlers are invoked.
+///
+/// # Safety
+/// `executor` must be null or a valid executor handle, which must not be used after this call.
+/// Must not be called from within the handler of the executor.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_command_executor_free(executor: *mut AioCommandExecutor) {
+    if !executor.is_null() {
+        // SAFETY: The caller guarantees the handle is valid and no longer used
+        drop(unsafe { Box::from_raw(executor) });
+    }
+}
@@ -0,0 +1,335 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Bindings for creating and running a [`Session`].
+
+use std::ffi::c_char;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use azure_iot_operations_mqtt::session::{
+    Session, SessionError, SessionExitHandle, SessionManagedClient, SessionOptionsBuilder,
+};
+use azure_iot_operations_mqtt::{MqttConnectionSettings, MqttConnectionSettingsBuilder};
+use azure_iot_operations_protocol::application::{ApplicationContext, ApplicationContextBuilder};
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::oneshot;
+
+use crate::error::{AioStatus, fail};
+use crate::util::{block_on, optional_str, required_str, write_out};
+
+/// Settings for connecting to an MQTT broker
+#[repr(C)]
+#[derive(Debug)]
+pub struct AioConnectionSettings {
+    /// Client identifier. If null, a client identifier is generated.
+    pub client_id: *const c_char,
+    /// Host name of the broker. Must not be null.
+    pub hostname: *const c_char,
+    /// TCP port of the broker
+    pub tcp_port: u16,
+    /// Indicates if TLS is used for the connection
+    pub use_tls: bool,
+    /// Path to a PEM file with the CA certificates used to validate the broker. May be null.
+    pub ca_file: *const c_char,
+    /// Username used for authentication. May be null.
+    pub username: *const c_char,
+    /// Path to a file containing the password used for authentication. May be null.
+    pub password_file: *const c_char,
+    /// Path to a file containing a SAT token used for authentication. May be null.
+    pub sat_file: *const c_char,
+    /// Keep alive interval in seconds. If 0, the default keep alive interval is used.
+    pub keep_alive_secs: u32,
+}
+
+/// Runtime shared by a session and the handles created from it.
+///
+/// Shuts down in the background when dropped, so it may be dropped on any thread, including
+/// threads of the runtime itself.
+pub(crate) struct SharedRuntime(Option<Runtime>);
+
+impl SharedRuntime {
+    pub(crate) fn handle(&self) -> &Handle {
+        self.0
+            .as_ref()
+            .expect("Runtime is only taken when dropped")
+            .handle()
+    }
+}
+
+impl Drop for SharedRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// MQTT session running on an internal runtime.
+///
+/// Created with `aio_session_new` and released with `aio_session_free`.
+pub struct AioSession {
+    pub(crate) runtime: Arc<SharedRuntime>,
+    pub(crate) managed_client: SessionManagedClient,
+    pub(crate) application_context: ApplicationContext,
+    exit_handle: SessionExitHandle,
+    started: AtomicBool,
+    /// Signals the session thread to start running the session
+    start_tx: Mutex<Option<oneshot::Sender<()>>>,
+    /// Outcome of the session, reported by the session thread once the session has ended
+    run_task: Mutex<Option<oneshot::Receiver<Result<(), SessionError>>>>,
+}
+
+/// Create a new session from the provided connection settings. The session does not connect to
+/// the broker until started with `aio_session_start`.
+///
+/// # Safety
+/// `settings` must be null or point to a valid [`AioConnectionSettings`], and `out` must be null
+/// or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_session_new(
+    settings: *const AioConnectionSettings,
+    out: *mut *mut AioSession,
+) -> AioStatus {
+    // SAFETY: The caller guarantees the pointers are null or valid
+    unsafe { new_session(settings, out) }
+        .err()
+        .unwrap_or(AioStatus::Ok)
+}
+
+unsafe fn new_session(
+    settings: *const AioConnectionSettings,
+    out: *mut *mut AioSession,
+) -> Result<(), AioStatus> {
+    // SAFETY: The caller guarantees the settings are null or valid
+    let Some(settings) = (unsafe { settings.as_ref() }) else {
+        return Err(fail(
+            AioStatus::InvalidArgument,
+            "settings must not be null",
+        ));
+    };
+    // SAFETY: The caller guarantees the strings of the settings are null or nul-terminated
+    let mut builder = unsafe {
+        MqttConnectionSettingsBuilder::default()
+            .hostname(required_str(settings.hostname, "hostname")?)
+            .tcp_port(settings.tcp_port)
+            .use_tls(settings.use_tls)
+            .ca_file(optional_str(settings.ca_file, "ca_file")?)
+            .username(optional_str(settings.username, "username")?)
+            .password_file(optional_str(settings.password_file, "password_file")?)
+            .sat_file(optional_str(settings.sat_file, "sat_file")?)
+    };
+    // SAFETY: The caller guarantees the client ID is null or nul-terminated
+    if let Some(client_id) = unsafe { optional_str(settings.client_id, "client_id") }? {
+        builder = builder.client_id(client_id);
+    }
+    if settings.keep_alive_secs > 0 {
+        builder = builder.keep_alive(Duration::from_secs(u64::from(settings.keep_alive_secs)));
+    }
+    let connection_settings = builder
+        .build()
+        .map_err(|e| fail(AioStatus::ConfigurationInvalid, e))?;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("aio-ffi")
+        .build()
+        .map_err(|e| fail(AioStatus::SessionError, e))?;
+    let (start_tx, start_rx) = oneshot::channel();
+    let (run_tx, run_rx) = oneshot::channel();
+    let (managed_client, exit_handle) = spawn_session_thread(
+        runtime.handle().clone(),
+        connection_settings,
+        start_rx,
+        run_tx,
+    )
+    .map_err(|e| fail(AioStatus::ConfigurationInvalid, e))?;
+    let application_context = ApplicationContextBuilder::default()
+        .build()
+        .map_err(|e| fail(AioStatus::ConfigurationInvalid, e))?;
+    let session = AioSession {
+        runtime: Arc::new(SharedRuntime(Some(runtime))),
+        managed_client,
+        application_context,
+        exit_handle,
+        started: AtomicBool::new(false),
+        start_tx: Mutex::new(Some(start_tx)),
+        run_task: Mutex::new(Some(run_rx)),
+    };
+
+    // SAFETY: The caller guarantees `out` is null or valid for writes
+    unsafe { write_out(out, Box::into_raw(Box::new(session)), "out") }
+}
+
+/// Spawn the thread that creates the session and runs it once signalled by `start_rx`, reporting
+/// the outcome on `run_tx`.
+///
+/// A [`Session`] cannot be moved between threads, so it is created and driven on the internal
+/// runtime by a dedicated thread.
+///
+/// Returns the handles of the session, or a description of the error if it could not be created.
+fn spawn_session_thread(
+    runtime: Handle,
+    connection_settings: MqttConnectionSettings,
+    start_rx: oneshot::Receiver<()>,
+    run_tx: oneshot::Sender<Result<(), SessionError>>,
+) -> Result<(SessionManagedClient, SessionExitHandle), String> {
+    let (created_tx, created_rx) = std::sync::mpsc::channel();
+    let spawn_result = std::thread::Builder::new()
+        .name("aio-ffi-session".to_string())
+        .spawn(move || {
+            // The session must be created and dropped within the context of the runtime it runs on
+            let _runtime_guard = runtime.enter();
+            let session = SessionOptionsBuilder::default()
+                .connection_settings(connection_settings)
+                .build()
+                .map_err(|e| e.to_string())
+                .and_then(|options| Session::new(options).map_err(|e| e.to_string()));
+            let session = match session {
+                Ok(session) => {
+                    let _ = created_tx.send(Ok((
+                        session.create_managed_client(),
+                        session.create_exit_handle(),
+                    )));
+                    session
+                }
+                Err(e) => {
+                    let _ = created_tx.send(Err(e));
+                    return;
+                }
+            };
+            // The start signal is dropped if the session is released without being started
+            if start_rx.blocking_recv().is_ok() {
+                let _ = run_tx.send(runtime.block_on(session.run()));
+            }
+        });
+    if let Err(e) = spawn_result {
+        return Err(e.to_string());
+    }
+    created_rx
+        .recv()
+        .unwrap_or_else(|_| Err("session thread ended unexpectedly".to_string()))
+}
+
+/// Start running the session on its internal runtime. Returns immediately.
+///
+/// # Safety
+/// `session` must be null or a valid session handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_session_start(session: *mut AioSession) -> AioStatus {
+    // SAFETY: The caller guarantees the handle is null or valid
+    let Some(session) = (unsafe { session.as_ref() }) else {
+        return fail(AioStatus::InvalidArgument, "session must not be null");
+    };
+    let Some(start_tx) = session
+        .start_tx
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+    else {
+        return fail(AioStatus::SessionError, "session has already been started");
+    };
+    session.started.store(true, Ordering::SeqCst);
+    if start_tx.send(()).is_err() {
+        return fail(AioStatus::SessionError, "session thread ended unexpectedly");
+    }
+    AioStatus::Ok
+}
+
+/// Block until the session ends, either due to `aio_session_exit` or a fatal error.
+///
+/// Returns `AIO_STATUS_SESSION_ERROR` if the session ended due to an error, was never started, or
+/// is already being waited on.
+///
+/// # Safety
+/// `session` must be null or a valid session handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_session_wait(session: *mut AioSession) -> AioStatus {
+    // SAFETY: The caller guarantees the handle is null or valid
+    let Some(session) = (unsafe { session.as_ref() }) else {
+        return fail(AioStatus::InvalidArgument, "session must not be null");
+    };
+    if !session.started.load(Ordering::SeqCst) {
+        return fail(AioStatus::SessionError, "session has not been started");
+    }
+    // Bind the task first, so the lock is not held while joining
+    let run_task = session
+        .run_task
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    match run_task {
+        Some(run_task) => session.join(run_task),
+        None => fail(AioStatus::SessionError, "session is not running"),
+    }
+}
+
+/// Gracefully end the session, and block until it has ended.
+///
+/// If the session cannot be ended gracefully (e.g. because the broker is unavailable), it is
+/// forcefully ended. If the session is being waited on by `aio_session_wait`, the outcome of the
+/// session is reported there instead.
+///
+/// # Safety
+/// `session` must be null or a valid session handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_session_exit(session: *mut AioSession) -> AioStatus {
+    // SAFETY: The caller guarantees the handle is null or valid
+    let Some(session) = (unsafe { session.as_ref() }) else {
+        return fail(AioStatus::InvalidArgument, "session must not be null");
+    };
+    if !session.started.load(Ordering::SeqCst) {
+        return fail(AioStatus::SessionError, "session has not been started");
+    }
+    let runtime = session.runtime.handle();
+    if let Err(e) = block_on(
+        runtime,
+        session
+            .exit_handle
+            .try_exit_timeout(Duration::from_secs(10)),
+    ) {
+        log::warn!("Graceful session exit failed ({e}). Forcing exit.");
+        block_on(runtime, session.exit_handle.exit_force());
+    }
+    // Bind the task first, so the lock is not held while joining
+    let run_task = session
+        .run_task
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    match run_task {
+        Some(run_task) => session.join(run_task),
+        None => AioStatus::Ok,
+    }
+}
+
+/// Release a session. If the session is running, it is forcefully ended.
+///
+/// Handles created from the session remain valid, but their operations fail once the session has
+/// ended.
+///
+/// # Safety
+/// `session` must be null or a valid session handle, which must not be used after this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_session_free(session: *mut AioSession) {
+    if session.is_null() {
+        return;
+    }
+    // SAFETY: The caller guarantees the handle is valid and no longer used
+    let session = unsafe { Box::from_raw(session) };
+    if session.started.load(Ordering::SeqCst) {
+        block_on(session.runtime.handle(), session.exit_handle.exit_force());
+    }
+}
+
+impl AioSession {
+    /// Wait for the session thread to report the outcome of the session.
+    fn join(&self, run_task: oneshot::Receiver<Result<(), SessionError>>) -> AioStatus {
+        match block_on(self.runtime.handle(), run_task) {
+            Ok(Ok(())) => AioStatus::Ok,
+            Ok(Err(e)) => fail(AioStatus::SessionError, e),
+            Err(_) => fail(AioStatus::SessionError, "session thread ended unexpectedly"),
+        }
+    }
+}
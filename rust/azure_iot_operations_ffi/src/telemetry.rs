@@ -0,0 +1,275 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Bindings for sending and receiving telemetry with raw byte payloads.
+
+use std::ffi::{CString, c_char, c_void};
+use std::sync::Arc;
+use std::time::Duration;
+
+use azure_iot_operations_mqtt::session::SessionManagedClient;
+use azure_iot_operations_protocol::common::payload_serialize::BypassPayload;
+use azure_iot_operations_protocol::telemetry;
+
+use crate::error::{AioStatus, fail, fail_protocol};
+use crate::session::{AioSession, SharedRuntime};
+use crate::util::{CallbackContext, CallbackLoop, block_on, bytes, required_str, write_out};
+
+/// Sender of telemetry with raw byte payloads.
+///
+/// Created with `aio_telemetry_sender_new` and released with `aio_telemetry_sender_free`.
+pub struct AioTelemetrySender {
+    runtime: Arc<SharedRuntime>,
+    sender: telemetry::Sender<Vec<u8>, SessionManagedClient>,
+}
+
+/// Receiver of telemetry with raw byte payloads.
+///
+/// Created with `aio_telemetry_receiver_new` and released with `aio_telemetry_receiver_free`.
+pub struct AioTelemetryReceiver {
+    _callback_loop: CallbackLoop,
+}
+
+/// Callback invoked for each telemetry message received by an [`AioTelemetryReceiver`].
+///
+/// `topic` is the nul-terminated topic the message was received on, and `payload` points to `len`
+/// bytes of payload. Both are only valid for the duration of the callback. The message is
+/// acknowledged once the callback returns.
+pub type AioTelemetryCallback = Option<
+    unsafe extern "C" fn(
+        context: *mut c_void,
+        topic: *const c_char,
+        payload: *const u8,
+        len: usize,
+    ),
+>;
+
+/// Create a new telemetry sender that publishes to the provided topic pattern.
+///
+/// # Safety
+/// `session` must be null or a valid session handle, `topic_pattern` must be null or
+/// nul-terminated, and `out` must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_telemetry_sender_new(
+    session: *mut AioSession,
+    topic_pattern: *const c_char,
+    out: *mut *mut AioTelemetrySender,
+) -> AioStatus {
+    // SAFETY: The caller guarantees the pointers are null or valid
+    unsafe { new_sender(session, topic_pattern, out) }
+        .err()
+        .unwrap_or(AioStatus::Ok)
+}
+
+unsafe fn new_sender(
+    session: *mut AioSession,
+    topic_pattern: *const c_char,
+    out: *mut *mut AioTelemetrySender,
+) -> Result<(), AioStatus> {
+    // SAFETY: The caller guarantees the handle is null or valid
+    let Some(session) = (unsafe { session.as_ref() }) else {
+        return Err(fail(AioStatus::InvalidArgument, "session must not be null"));
+    };
+    // SAFETY: The caller guarantees the string is null or nul-terminated
+    let topic_pattern = unsafe { required_str(topic_pattern, "topic_pattern") }?;
+    let sender_options = telemetry::sender::OptionsBuilder::default()
+        .topic_pattern(topic_pattern)
+        .build()
+        .map_err(|e| fail(AioStatus::ConfigurationInvalid, e))?;
+    let sender = telemetry::Sender::new(
+        session.application_context.clone(),
+        session.managed_client.clone(),
+        sender_options,
+    )
+    .map_err(|e| fail_protocol(&e))?;
+    let sender = AioTelemetrySender {
+        runtime: session.runtime.clone(),
+        sender,
+    };
+
+    // SAFETY: The caller guarantees `out` is null or valid for writes
+    unsafe { write_out(out, Box::into_raw(Box::new(sender)), "out") }
+}
+
+/// Send a telemetry message with the provided payload, and block until it has been acknowledged
+/// by the broker.
+///
+/// The message is sent at least once (quality of service 1) with content type
+/// `application/octet-stream`. If `message_expiry_secs` is 0, the default message expiry is used.
+///
+/// # Safety
+/// `sender` must be null or a valid sender handle, and `payload` must be null or point to at least
+/// `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_telemetry_send(
+    sender: *mut AioTelemetrySender,
+    payload: *const u8,
+    len: usize,
+    message_expiry_secs: u32,
+) -> AioStatus {
+    // SAFETY: The caller guarantees the handle is null or valid
+    let Some(sender) = (unsafe { sender.as_ref() }) else {
+        return fail(AioStatus::InvalidArgument, "sender must not be null");
+    };
+    // SAFETY: The caller guarantees `len` bytes are readable
+    let payload = match unsafe { bytes(payload, len, "payload") } {
+        Ok(payload) => payload,
+        Err(status) => return status,
+    };
+
+    let mut message_builder = telemetry::sender::MessageBuilder::default();
+    if let Err(e) = message_builder.payload(payload) {
+        return fail_protocol(&e);
+    }
+    if message_expiry_secs > 0 {
+        message_builder.message_expiry(Duration::from_secs(u64::from(message_expiry_secs)));
+    }
+    let message = match message_builder.build() {
+        Ok(message) => message,
+        Err(e) => return fail(AioStatus::InvalidArgument, e),
+    };
+    match block_on(sender.runtime.handle(), sender.sender.send(message)) {
+        Ok(()) => AioStatus::Ok,
+        Err(e) => fail_protocol(&e),
+    }
+}
+
+/// Release a telemetry sender.
+///
+/// # Safety
+/// `sender` must be null or a valid sender handle, which must not be used after this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_telemetry_sender_free(sender: *mut AioTelemetrySender) {
+    if !sender.is_null() {
+        // SAFETY: The caller guarantees the handle is valid and no longer used
+        drop(unsafe { Box::from_raw(sender) });
+    }
+}
+
+/// Create a new telemetry receiver that subscribes to the provided topic pattern, and invokes
+/// `callback` with `context` for each message received.
+///
+/// Messages are received once the session is connected.
+///
+/// # Safety
+/// `session` must be null or a valid session handle, `topic_pattern` must be null or
+/// nul-terminated, and `out` must be null or valid for writes. `context` must be safe to use from
+/// the threads of the internal runtime until the receiver is freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_telemetry_receiver_new(
+    session: *mut AioSession,
+    topic_pattern: *const c_char,
+    callback: AioTelemetryCallback,
+    context: *mut c_void,
+    out: *mut *mut AioTelemetryReceiver,
+) -> AioStatus {
+    // SAFETY: The caller guarantees the pointers are null or valid
+    unsafe { new_receiver(session, topic_pattern, callback, context, out) }
+        .err()
+        .unwrap_or(AioStatus::Ok)
+}
+
+unsafe fn new_receiver(
+    session: *mut AioSession,
+    topic_pattern: *const c_char,
+    callback: AioTelemetryCallback,
+    context: *mut c_void,
+    out: *mut *mut AioTelemetryReceiver,
+) -> Result<(), AioStatus> {
+    // SAFETY: The caller guarantees the handle is null or valid
+    let Some(session) = (unsafe { session.as_ref() }) else {
+        return Err(fail(AioStatus::InvalidArgument, "session must not be null"));
+    };
+    let Some(callback) = callback else {
+        return Err(fail(
+            AioStatus::InvalidArgument,
+            "callback must not be null",
+        ));
+    };
+    if out.is_null() {
+        return Err(fail(AioStatus::InvalidArgument, "out must not be null"));
+    }
+    // SAFETY: The caller guarantees the string is null or nul-terminated
+    let topic_pattern = unsafe { required_str(topic_pattern, "topic_pattern") }?;
+    let receiver_options = telemetry::receiver::OptionsBuilder::default()
+        .topic_pattern(topic_pattern)
+        .auto_ack(false)
+        .build()
+        .map_err(|e| fail(AioStatus::ConfigurationInvalid, e))?;
+    let mut receiver: telemetry::Receiver<BypassPayload, SessionManagedClient> = {
+        // The receiver must be created within the context of the runtime it will run on
+        let _runtime_guard = session.runtime.handle().enter();
+        telemetry::Receiver::new(
+            session.application_context.clone(),
+            session.managed_client.clone(),
+            receiver_options,
+        )
+        .map_err(|e| fail_protocol(&e))?
+    };
+
+    let context = CallbackContext(context);
+    let callback_loop =
+        CallbackLoop::spawn(session.runtime.clone(), |mut shutdown_rx| async move {
+            loop {
+                let message = tokio::select! {
+                    _ = &mut shutdown_rx => break,
+                    message = receiver.recv() => message,
+                };
+                let (message, ack_token) = match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        log::warn!("Error receiving telemetry: {e}");
+                        continue;
+                    }
+                    None => break,
+                };
+                // Topics cannot contain a nul character, as it is not valid in an MQTT topic
+                let topic = CString::new(message.topic).unwrap_or_default();
+                let payload = message.payload.payload;
+                let invocation = tokio::task::spawn_blocking(move || {
+                    // SAFETY: The strings and payload are valid for the duration of the callback, and
+                    // the caller guarantees the context is safe to use from this thread
+                    unsafe {
+                        callback(
+                            context.get(),
+                            topic.as_ptr(),
+                            payload.as_ptr(),
+                            payload.len(),
+                        );
+                    };
+                });
+                if let Err(e) = invocation.await {
+                    log::error!("Telemetry callback panicked: {e}");
+                }
+                let Some(ack_token) = ack_token else {
+                    continue;
+                };
+                if let Err(e) = ack_token.ack().await {
+                    log::warn!("Error acknowledging telemetry: {e}");
+                }
+            }
+            if let Err(e) = receiver.shutdown().await {
+                log::warn!("Error shutting down telemetry receiver: {e}");
+            }
+        });
+    let receiver = AioTelemetryReceiver {
+        _callback_loop: callback_loop,
+    };
+
+    // SAFETY: The caller guarantees `out` is valid for writes
+    unsafe { write_out(out, Box::into_raw(Box::new(receiver)), "out") }
+}
+
+/// Release a telemetry receiver. Blocks until any callback in progress has returned, after which
+/// no further callbacks are invoked.
+///
+/// # Safety
+/// `receiver` must be null or a valid receiver handle, which must not be used after this call.
+/// Must not be called from within the callback of the receiver.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aio_telemetry_receiver_free(receiver: *mut AioTelemetryReceiver) {
+    if !receiver.is_null() {
+        // SAFETY: The caller guarantees the handle is valid and no longer used
+        drop(unsafe { Box::from_raw(receiver) });
+    }
+}
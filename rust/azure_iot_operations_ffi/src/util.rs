@@ -0,0 +1,159 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Helpers for converting arguments and running async operations.
+
+use std::ffi::{CStr, c_char, c_void};
+use std::future::Future;
+use std::sync::Arc;
+
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::error::{AioStatus, fail};
+use crate::session::SharedRuntime;
+
+/// Convert a required C string argument to a [`String`].
+///
+/// # Safety
+/// `value` must be null or point to a nul-terminated string.
+pub(crate) unsafe fn required_str(value: *const c_char, name: &str) -> Result<String, AioStatus> {
+    // SAFETY: The caller guarantees the string is null or nul-terminated
+    unsafe { optional_str(value, name) }?.ok_or_else(|| {
+        fail(
+            AioStatus::InvalidArgument,
+            format!("{name} must not be null"),
+        )
+    })
+}
+
+/// Convert an optional C string argument to an [`Option<String>`].
+///
+/// # Safety
+/// `value` must be null or point to a nul-terminated string.
+pub(crate) unsafe fn optional_str(
+    value: *const c_char,
+    name: &str,
+) -> Result<Option<String>, AioStatus> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    // SAFETY: The caller guarantees the string is nul-terminated
+    unsafe { CStr::from_ptr(value) }
+        .to_str()
+        .map(|s| Some(s.to_string()))
+        .map_err(|e| {
+            fail(
+                AioStatus::InvalidArgument,
+                format!("{name} is not UTF-8: {e}"),
+            )
+        })
+}
+
+/// Copy a byte buffer argument to a [`Vec<u8>`]. A null buffer is only allowed if `len` is 0.
+///
+/// # Safety
+/// `data` must be null or point to at least `len` readable bytes.
+pub(crate) unsafe fn bytes(data: *const u8, len: usize, name: &str) -> Result<Vec<u8>, AioStatus> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    if data.is_null() {
+        return Err(fail(
+            AioStatus::InvalidArgument,
+            format!("{name} must not be null if its length is not 0"),
+        ));
+    }
+    // SAFETY: The caller guarantees `len` bytes are readable
+    Ok(unsafe { std::slice::from_raw_parts(data, len) }.to_vec())
+}
+
+/// Write a value to a required out parameter.
+///
+/// # Safety
+/// `out` must be null or valid for writes.
+pub(crate) unsafe fn write_out<T>(out: *mut T, value: T, name: &str) -> Result<(), AioStatus> {
+    if out.is_null() {
+        return Err(fail(
+            AioStatus::InvalidArgument,
+            format!("{name} must not be null"),
+        ));
+    }
+    // SAFETY: The caller guarantees `out` is valid for writes
+    unsafe { out.write(value) };
+    Ok(())
+}
+
+/// Run a future to completion on the provided runtime, blocking the calling thread.
+///
+/// Can be called from threads of the runtime that are allowed to block (i.e. callbacks).
+pub(crate) fn block_on<F: Future>(runtime: &Handle, future: F) -> F::Output {
+    if Handle::try_current().is_ok() {
+        tokio::task::block_in_place(|| runtime.block_on(future))
+    } else {
+        runtime.block_on(future)
+    }
+}
+
+/// Callback context provided by the caller.
+///
+/// The caller is responsible for the context being safe to use from the threads of the runtime.
+#[derive(Clone, Copy)]
+pub(crate) struct CallbackContext(pub(crate) *mut c_void);
+
+impl CallbackContext {
+    /// Get the pointer provided by the caller.
+    ///
+    /// Closures must use this rather than the field, so that they capture the whole (`Send`)
+    /// context rather than only the pointer.
+    pub(crate) fn get(self) -> *mut c_void {
+        self.0
+    }
+}
+
+// SAFETY: Thread safety of the context is a documented responsibility of the caller
+unsafe impl Send for CallbackContext {}
+// SAFETY: Thread safety of the context is a documented responsibility of the caller
+unsafe impl Sync for CallbackContext {}
+
+/// Loop running on the runtime of a session until stopped, used to deliver callbacks.
+pub(crate) struct CallbackLoop {
+    runtime: Arc<SharedRuntime>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl CallbackLoop {
+    /// Spawn a loop onto the provided runtime. The loop is provided a receiver that completes when
+    /// the loop is requested to stop.
+    pub(crate) fn spawn<F, Fut>(runtime: Arc<SharedRuntime>, f: F) -> Self
+    where
+        F: FnOnce(oneshot::Receiver<()>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let task = runtime.handle().spawn(f(shutdown_rx));
+        Self {
+            runtime,
+            shutdown_tx: Some(shutdown_tx),
+            task: Some(task),
+        }
+    }
+}
+
+impl Drop for CallbackLoop {
+    /// Request the loop to stop, and block until it has. No callbacks are invoked afterwards.
+    fn drop(&mut self) {
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            // The loop may have already ended on its own
+            let _ = shutdown_tx.send(());
+        }
+        let Some(task) = self.task.take() else {
+            return;
+        };
+        if let Err(e) = block_on(self.runtime.handle(), task) {
+            log::warn!("Callback loop ended abnormally: {e}");
+        }
+    }
+}
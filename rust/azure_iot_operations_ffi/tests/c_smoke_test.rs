@@ -0,0 +1,68 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Compiles C test programs against the generated header and the `cdylib` of this crate, and runs
+//! them.
+
+#![cfg(unix)]
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory containing the libraries of the crate built for the test (e.g. `target/debug/deps`)
+fn artifact_dir() -> PathBuf {
+    // Test binaries are built alongside the libraries in `target/<profile>/deps`. The libraries are
+    // only copied to `target/<profile>` by `cargo build`, so they may be out of date there.
+    let test_exe = env::current_exe().unwrap();
+    test_exe.parent().unwrap().to_path_buf()
+}
+
+/// Compile the C test program `tests/<name>.c` and run it, asserting that it succeeds.
+fn compile_and_run(name: &str) {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let artifact_dir = artifact_dir();
+    let out_dir = env::temp_dir().join(format!("aio_ffi_{name}_{}", std::process::id()));
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let test_exe = out_dir.join(name);
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(compiler)
+        .arg(crate_dir.join("tests").join(format!("{name}.c")))
+        .arg("-I")
+        .arg(env!("OUT_DIR"))
+        .arg("-L")
+        .arg(&artifact_dir)
+        .arg("-lazure_iot_operations_ffi")
+        .arg("-o")
+        .arg(&test_exe)
+        .status()
+        .expect("C compiler should be available");
+    assert!(status.success(), "Compiling {name}.c failed");
+
+    let output = Command::new(&test_exe)
+        .env("LD_LIBRARY_PATH", &artifact_dir)
+        .env("DYLD_LIBRARY_PATH", &artifact_dir)
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&out_dir);
+    assert!(
+        output.status.success(),
+        "{name} failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn c_smoke_test() {
+    compile_and_run("smoke");
+}
+
+#[test]
+fn c_pubsub_network_test() {
+    if env::var("ENABLE_NETWORK_TESTS").is_err() {
+        eprintln!("This test is skipped. Set ENABLE_NETWORK_TESTS to run.");
+        return;
+    }
+    compile_and_run("pubsub");
+}
@@ -0,0 +1,30 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Verifies that the checked in C header matches the header generated by the build script.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Header generated by the build script
+const GENERATED_HEADER: &str =
+    include_str!(concat!(env!("OUT_DIR"), "/azure_iot_operations_ffi.h"));
+
+#[test]
+fn header_up_to_date() {
+    let header_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("include")
+        .join("azure_iot_operations_ffi.h");
+    // Set AIO_FFI_UPDATE_HEADER to update the checked in header
+    if env::var("AIO_FFI_UPDATE_HEADER").is_ok() {
+        fs::write(&header_path, GENERATED_HEADER).unwrap();
+        return;
+    }
+    let checked_in = fs::read_to_string(&header_path).unwrap();
+    assert!(
+        checked_in == GENERATED_HEADER,
+        "{} is out of date. Run `AIO_FFI_UPDATE_HEADER=1 cargo test -p azure_iot_operations_ffi --test header` to update it.",
+        header_path.display()
+    );
+}
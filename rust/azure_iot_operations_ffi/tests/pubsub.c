@@ -0,0 +1,117 @@
+/* Copyright (c) Microsoft Corporation.
+   Licensed under the MIT License. */
+
+/* Publish/receive test of the C bindings. Requires an MQTT broker on localhost:1883. */
+
+#include <stdatomic.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "azure_iot_operations_ffi.h"
+
+#define CHECK(condition)                                                      \
+    do {                                                                      \
+        if (!(condition)) {                                                   \
+            fprintf(stderr, "%s:%d: check failed: %s (%s)\n", __FILE__,       \
+                    __LINE__, #condition,                                     \
+                    aio_last_error_message() ? aio_last_error_message() : ""); \
+            return 1;                                                         \
+        }                                                                     \
+    } while (0)
+
+#define TELEMETRY_TOPIC "ffi/test/telemetry"
+#define COMMAND_TOPIC "ffi/test/command"
+#define ATTEMPTS 20
+
+static const uint8_t TELEMETRY_PAYLOAD[] = "ffi telemetry payload";
+static const uint8_t REQUEST_PAYLOAD[] = "ffi request payload";
+
+typedef struct ReceivedTelemetry {
+    atomic_int received;
+    char topic[64];
+    uint8_t payload[64];
+    size_t len;
+} ReceivedTelemetry;
+
+static void on_telemetry(void *context, const char *topic, const uint8_t *payload, size_t len) {
+    ReceivedTelemetry *received = context;
+    if (atomic_load(&received->received) || len > sizeof(received->payload)) {
+        return;
+    }
+    strncpy(received->topic, topic, sizeof(received->topic) - 1);
+    memcpy(received->payload, payload, len);
+    received->len = len;
+    atomic_store(&received->received, 1);
+}
+
+static void on_command(void *context, const uint8_t *request, size_t len,
+                       AioCommandResponse *response) {
+    atomic_int *invocations = context;
+    atomic_fetch_add(invocations, 1);
+    aio_command_response_set_payload(response, request, len);
+}
+
+int main(void) {
+    AioConnectionSettings settings;
+    memset(&settings, 0, sizeof(settings));
+    settings.client_id = "ffi_network_test_pubsub";
+    settings.hostname = "localhost";
+    settings.tcp_port = 1883;
+    settings.use_tls = false;
+
+    AioSession *session = NULL;
+    CHECK(aio_session_new(&settings, &session) == AIO_STATUS_OK);
+
+    ReceivedTelemetry received;
+    memset(&received, 0, sizeof(received));
+    AioTelemetryReceiver *receiver = NULL;
+    CHECK(aio_telemetry_receiver_new(session, TELEMETRY_TOPIC, on_telemetry, &received,
+                                     &receiver) == AIO_STATUS_OK);
+    AioTelemetrySender *sender = NULL;
+    CHECK(aio_telemetry_sender_new(session, TELEMETRY_TOPIC, &sender) == AIO_STATUS_OK);
+
+    atomic_int invocations = 0;
+    AioCommandExecutor *executor = NULL;
+    CHECK(aio_command_executor_new(session, "echo", COMMAND_TOPIC, on_command, &invocations,
+                                   &executor) == AIO_STATUS_OK);
+    AioCommandInvoker *invoker = NULL;
+    CHECK(aio_command_invoker_new(session, "echo", COMMAND_TOPIC, NULL, &invoker) ==
+          AIO_STATUS_OK);
+
+    CHECK(aio_session_start(session) == AIO_STATUS_OK);
+
+    /* The receiver subscribes once connected, so send until the telemetry is received */
+    for (int i = 0; i < ATTEMPTS && !atomic_load(&received.received); i++) {
+        CHECK(aio_telemetry_send(sender, TELEMETRY_PAYLOAD, sizeof(TELEMETRY_PAYLOAD), 0) ==
+              AIO_STATUS_OK);
+        usleep(500 * 1000);
+    }
+    CHECK(atomic_load(&received.received));
+    CHECK(strcmp(received.topic, TELEMETRY_TOPIC) == 0);
+    CHECK(received.len == sizeof(TELEMETRY_PAYLOAD));
+    CHECK(memcmp(received.payload, TELEMETRY_PAYLOAD, received.len) == 0);
+
+    /* The executor subscribes once connected, so retry invocations that time out */
+    AioBuffer response = {NULL, 0};
+    AioStatus status = AIO_STATUS_TIMEOUT;
+    for (int i = 0; i < ATTEMPTS && status == AIO_STATUS_TIMEOUT; i++) {
+        status = aio_command_invoke(invoker, REQUEST_PAYLOAD, sizeof(REQUEST_PAYLOAD), 1000,
+                                    &response);
+    }
+    CHECK(status == AIO_STATUS_OK);
+    CHECK(atomic_load(&invocations) >= 1);
+    CHECK(response.len == sizeof(REQUEST_PAYLOAD));
+    CHECK(memcmp(response.data, REQUEST_PAYLOAD, response.len) == 0);
+    aio_buffer_free(&response);
+
+    aio_command_invoker_free(invoker);
+    aio_command_executor_free(executor);
+    aio_telemetry_sender_free(sender);
+    aio_telemetry_receiver_free(receiver);
+    CHECK(aio_session_exit(session) == AIO_STATUS_OK);
+    aio_session_free(session);
+
+    printf("ffi pubsub test passed\n");
+    return 0;
+}
@@ -0,0 +1,89 @@
+/* Copyright (c) Microsoft Corporation.
+   Licensed under the MIT License. */
+
+/* Smoke test of the C bindings. Does not require a broker, as the session is never started. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "azure_iot_operations_ffi.h"
+
+#define CHECK(condition)                                                      \
+    do {                                                                      \
+        if (!(condition)) {                                                   \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                    #condition);                                              \
+            return 1;                                                         \
+        }                                                                     \
+    } while (0)
+
+static void on_telemetry(void *context, const char *topic, const uint8_t *payload, size_t len) {
+    (void)context;
+    (void)topic;
+    (void)payload;
+    (void)len;
+}
+
+static void on_command(void *context, const uint8_t *request, size_t len,
+                       AioCommandResponse *response) {
+    (void)context;
+    aio_command_response_set_payload(response, request, len);
+}
+
+int main(void) {
+    AioConnectionSettings settings;
+    memset(&settings, 0, sizeof(settings));
+    settings.client_id = "ffi_smoke_test";
+    settings.hostname = "localhost";
+    settings.tcp_port = 1883;
+    settings.use_tls = false;
+
+    /* Invalid arguments are reported with a status and a description */
+    AioSession *session = NULL;
+    CHECK(aio_session_new(NULL, &session) == AIO_STATUS_INVALID_ARGUMENT);
+    CHECK(session == NULL);
+    CHECK(aio_last_error_message() != NULL);
+    CHECK(aio_session_new(&settings, NULL) == AIO_STATUS_INVALID_ARGUMENT);
+    CHECK(aio_session_start(NULL) == AIO_STATUS_INVALID_ARGUMENT);
+
+    CHECK(aio_session_new(&settings, &session) == AIO_STATUS_OK);
+    CHECK(session != NULL);
+    CHECK(aio_session_exit(session) == AIO_STATUS_SESSION_ERROR);
+
+    AioTelemetrySender *sender = NULL;
+    CHECK(aio_telemetry_sender_new(session, NULL, &sender) == AIO_STATUS_INVALID_ARGUMENT);
+    CHECK(aio_telemetry_sender_new(session, "smoke/telemetry", &sender) == AIO_STATUS_OK);
+    CHECK(aio_telemetry_send(sender, NULL, 4, 0) == AIO_STATUS_INVALID_ARGUMENT);
+
+    AioTelemetryReceiver *receiver = NULL;
+    CHECK(aio_telemetry_receiver_new(session, "smoke/telemetry", NULL, NULL, &receiver) ==
+          AIO_STATUS_INVALID_ARGUMENT);
+    CHECK(aio_telemetry_receiver_new(session, "smoke/telemetry", on_telemetry, NULL, &receiver) ==
+          AIO_STATUS_OK);
+
+    AioCommandInvoker *invoker = NULL;
+    CHECK(aio_command_invoker_new(session, "echo", "smoke/command", NULL, &invoker) ==
+          AIO_STATUS_OK);
+    AioBuffer response = {NULL, 0};
+    CHECK(aio_command_invoke(invoker, NULL, 0, 1000, NULL) == AIO_STATUS_INVALID_ARGUMENT);
+    CHECK(response.data == NULL);
+
+    AioCommandExecutor *executor = NULL;
+    CHECK(aio_command_executor_new(session, "", "smoke/command", on_command, NULL, &executor) ==
+          AIO_STATUS_CONFIGURATION_INVALID);
+    CHECK(aio_command_executor_new(session, "echo", "smoke/command", on_command, NULL, &executor) ==
+          AIO_STATUS_OK);
+
+    /* Handles may be released in any order, and releasing null handles has no effect */
+    aio_session_free(session);
+    aio_telemetry_receiver_free(receiver);
+    aio_command_executor_free(executor);
+    aio_telemetry_sender_free(sender);
+    aio_command_invoker_free(invoker);
+    aio_buffer_free(&response);
+    aio_buffer_free(NULL);
+    aio_session_free(NULL);
+
+    printf("ffi smoke test passed\n");
+    return 0;
+}
@@ -14,6 +14,7 @@ use tokio::sync::Notify;
 
 use crate::error::ReauthError;
 use crate::interface::MqttClient;
+use crate::runtime;
 
 /// Used as the authentication method for the MQTT client when using SAT.
 pub const SAT_AUTHENTICATION_METHOD: &str = "K8S-SAT";
@@ -174,7 +175,7 @@ impl SatAuthContext {
                     None => Err(SatReauthError::AuthWatcherClosed),
                 }
             }
-            () = tokio::time::sleep(timeout) => Err(SatReauthError::Timeout),
+            () = runtime::sleep(timeout) => Err(SatReauthError::Timeout),
         }
     }
 }
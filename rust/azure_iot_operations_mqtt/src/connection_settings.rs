@@ -7,6 +7,16 @@ use std::env::{self, VarError};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+
+use crate::interface::ConnectionTarget;
+
+/// Length of generated client IDs.
+/// Brokers are only required to accept client IDs between 1 and 23 alphanumeric characters.
+/// See: MQTT 5.0 spec, 3.1.3.1
+const GENERATED_CLIENT_ID_LENGTH: usize = 23;
+
 // TODO: Split up this struct to avoid weird combinations and separate concern.
 // Things like having both password and password_file don't make much sense,
 // nor frankly does combining MQTT and TLS settings.
@@ -15,8 +25,17 @@ use std::time::Duration;
 #[derive(Builder, Clone, Debug, Getters)]
 #[builder(pattern = "owned", setter(into), build_fn(validate = "Self::validate"))]
 pub struct MqttConnectionSettings {
-    /// Client identifier
+    /// Client identifier.
+    ///
+    /// If not provided, the client identifier is read from the `client_id_file` if present,
+    /// or otherwise a random client identifier is generated (and persisted to the `client_id_file`
+    /// if one was provided).
+    #[builder(default = "self.default_client_id()?")]
     pub(crate) client_id: String,
+    /// Path to a file used to persist a generated client identifier across restarts, so that
+    /// the MQTT session can be resumed. Has no effect if `client_id` is provided.
+    #[builder(default = "None")]
+    pub(crate) client_id_file: Option<String>,
     /// FQDN of the host to connect to
     pub(crate) hostname: String,
     /// TCP port to connect to the host on
@@ -132,7 +151,9 @@ impl MqttConnectionSettingsBuilder {
         // the errors from .validate() will not be particularly clear in this case, as it has no
         // way of knowing if the values originally came from the environment or were set by the user.
         if client_id.is_none() {
-            log::warn!("AIO_MQTT_CLIENT_ID is not set in environment");
+            log::warn!(
+                "AIO_MQTT_CLIENT_ID is not set in environment. A random client ID will be generated if not provided."
+            );
         }
         if hostname.is_none() {
             log::warn!("AIO_BROKER_HOSTNAME is not set in environment");
@@ -272,7 +293,9 @@ impl MqttConnectionSettingsBuilder {
         // particularly clear in this case, as it has no way of knowing if the values came from the
         // configmap or were set by the user.
         if client_id.is_none() {
-            log::warn!("AIO_MQTT_CLIENT_ID is not set in AEP configmap");
+            log::warn!(
+                "AIO_MQTT_CLIENT_ID is not set in AEP configmap. A random client ID will be generated if not provided."
+            );
         }
         if hostname.is_none() || tcp_port.is_none() {
             log::warn!("BROKER_TARGET_ADDRESS is not set in AEP configmap");
@@ -289,6 +312,33 @@ impl MqttConnectionSettingsBuilder {
         })
     }
 
+    /// Get the client ID to use when none was provided, reading it from (or persisting it to)
+    /// the `client_id_file` if one was provided.
+    ///
+    /// # Errors
+    /// Returns a `String` describing the error if the `client_id_file` cannot be read or written
+    fn default_client_id(&self) -> Result<String, String> {
+        let Some(Some(client_id_file)) = self.client_id_file.as_ref() else {
+            return Ok(random_client_id());
+        };
+        let client_id_path = Path::new(client_id_file);
+        if client_id_path.exists() {
+            let client_id = std::fs::read_to_string(client_id_path)
+                .map_err(|e| format!("Could not read client_id_file: {e}"))?;
+            let client_id = client_id.trim();
+            if client_id.is_empty() {
+                return Err("client_id_file cannot be empty".to_string());
+            }
+            Ok(client_id.to_string())
+        } else {
+            let client_id = random_client_id();
+            std::fs::write(client_id_path, &client_id)
+                .map_err(|e| format!("Could not write client_id_file: {e}"))?;
+            log::info!("Generated client ID {client_id} and persisted it to {client_id_file}");
+            Ok(client_id)
+        }
+    }
+
     /// Validate the MQTT Connection Settings.
     ///
     /// # Errors
@@ -300,6 +350,11 @@ impl MqttConnectionSettingsBuilder {
         if self.client_id.as_ref().is_some_and(String::is_empty) {
             return Err("client_id cannot be empty".to_string());
         }
+        if let Some(Some(client_id_file)) = self.client_id_file.as_ref() {
+            if client_id_file.is_empty() {
+                return Err("client_id_file cannot be empty".to_string());
+            }
+        }
         if [
             self.password.as_ref(),
             self.password_file.as_ref(),
@@ -330,6 +385,68 @@ impl MqttConnectionSettingsBuilder {
     }
 }
 
+impl MqttConnectionSettings {
+    /// Create a copy of these settings with the broker target address and TLS settings updated
+    /// to the values currently present in the file mounts (see
+    /// [`MqttConnectionSettingsBuilder::from_file_mount`]). Values that are not present in the
+    /// file mounts, as well as all other settings, are left unchanged.
+    ///
+    /// # Errors
+    /// Returns a `String` describing the error if the file mounts cannot be read, or contain
+    /// invalid values
+    pub(crate) fn with_file_mount_target(&self) -> Result<Self, String> {
+        let builder = MqttConnectionSettingsBuilder::from_file_mount()?;
+        let mut settings = self.clone();
+        if let Some(hostname) = builder.hostname {
+            if hostname.is_empty() {
+                return Err("Host name cannot be empty".to_string());
+            }
+            settings.hostname = hostname;
+        }
+        if let Some(tcp_port) = builder.tcp_port {
+            settings.tcp_port = tcp_port;
+        }
+        if let Some(use_tls) = builder.use_tls {
+            settings.use_tls = use_tls;
+        }
+        if let Some(Some(ca_file)) = builder.ca_file {
+            settings.ca_file = Some(ca_file);
+        }
+        Ok(settings)
+    }
+
+    /// Returns true if the broker target address and TLS settings are the same as those of the
+    /// other settings.
+    pub(crate) fn same_target(&self, other: &Self) -> bool {
+        self.hostname == other.hostname
+            && self.tcp_port == other.tcp_port
+            && self.use_tls == other.use_tls
+            && self.ca_file == other.ca_file
+    }
+
+    /// Returns the broker target address and TLS settings as a [`ConnectionTarget`].
+    pub(crate) fn connection_target(&self) -> ConnectionTarget {
+        ConnectionTarget {
+            hostname: self.hostname.clone(),
+            tcp_port: self.tcp_port,
+            use_tls: self.use_tls,
+            ca_file: self.ca_file.clone(),
+            cert_file: self.cert_file.clone(),
+            key_file: self.key_file.clone(),
+            key_password_file: self.key_password_file.clone(),
+        }
+    }
+}
+
+/// Generate a random client ID that any MQTT 5.0 compliant broker will accept.
+fn random_client_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(GENERATED_CLIENT_ID_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
 /// Helper function to get an environment variable as a string.
 fn string_from_environment(key: &str) -> Result<Option<String>, String> {
     match env::var(key) {
@@ -413,6 +530,57 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn client_id_generated() {
+        let settings1 = MqttConnectionSettingsBuilder::default()
+            .hostname("test_host".to_string())
+            .build()
+            .unwrap();
+        let settings2 = MqttConnectionSettingsBuilder::default()
+            .hostname("test_host".to_string())
+            .build()
+            .unwrap();
+        for client_id in [&settings1.client_id, &settings2.client_id] {
+            assert_eq!(client_id.len(), GENERATED_CLIENT_ID_LENGTH);
+            assert!(client_id.chars().all(|c| c.is_ascii_alphanumeric()));
+        }
+        assert_ne!(settings1.client_id, settings2.client_id);
+    }
+
+    #[test]
+    fn client_id_persisted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let client_id_file = dir.path().join("client_id").to_str().unwrap().to_string();
+
+        // First build generates and persists the client ID
+        let settings1 = MqttConnectionSettingsBuilder::default()
+            .hostname("test_host".to_string())
+            .client_id_file(Some(client_id_file.clone()))
+            .build()
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&client_id_file).unwrap(),
+            settings1.client_id
+        );
+
+        // Subsequent builds reuse the persisted client ID
+        let settings2 = MqttConnectionSettingsBuilder::default()
+            .hostname("test_host".to_string())
+            .client_id_file(Some(client_id_file.clone()))
+            .build()
+            .unwrap();
+        assert_eq!(settings1.client_id, settings2.client_id);
+
+        // An explicitly provided client ID takes precedence
+        let settings3 = MqttConnectionSettingsBuilder::default()
+            .hostname("test_host".to_string())
+            .client_id("test_client_id".to_string())
+            .client_id_file(Some(client_id_file))
+            .build()
+            .unwrap();
+        assert_eq!(settings3.client_id, "test_client_id");
+    }
+
     #[test]
     fn password_combos() {
         // The password and password_file cannot be used at the same time
@@ -699,8 +867,7 @@ mod tests {
     }
 
     #[test_case(None, None; "All required values missing")]
-    #[test_case(Some("test-client-id"), None; "Client ID missing")]
-    #[test_case(None, Some("test.hostname.com"); "Hostname missing")]
+    #[test_case(Some("test-client-id"), None; "Hostname missing")]
     fn from_environment_missing_required_values(client_id: Option<&str>, hostname: Option<&str>) {
         // No environment variables
         temp_env::with_vars(
@@ -717,6 +884,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn from_environment_missing_client_id() {
+        temp_env::with_vars(
+            [
+                ("AIO_MQTT_CLIENT_ID", None),
+                ("AIO_BROKER_HOSTNAME", Some("test.hostname.com")),
+            ],
+            || {
+                let builder = MqttConnectionSettingsBuilder::from_environment().unwrap();
+                assert_eq!(builder.client_id, None);
+                // A client ID is generated when building
+                let settings = builder.build().unwrap();
+                assert_eq!(settings.client_id.len(), GENERATED_CLIENT_ID_LENGTH);
+            },
+        );
+    }
+
     // NOTE: This test does NOT cover the case where environment variable is set to a value
     // that cannot be parsed as a unicode string. While there is error handling for that case
     // in the implementation, we cannot programmatically set environment variables to invalid
@@ -852,7 +1036,6 @@ mod tests {
     }
 
     #[test_case("BROKER_TARGET_ADDRESS"; "BROKER_TARGET_ADDRESS")]
-    #[test_case("AIO_MQTT_CLIENT_ID"; "AIO_MQTT_CLIENT_ID")]
     fn from_file_mount_missing_files_with_required_values(missing_filename: &str) {
         let aep_configmap_manager = TempConfigMapManager::new("aep_configmap");
         if missing_filename != "BROKER_TARGET_ADDRESS" {
@@ -873,6 +1056,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn from_file_mount_missing_client_id_file() {
+        let aep_configmap_manager = TempConfigMapManager::new("aep_configmap");
+        aep_configmap_manager.add_file("BROKER_TARGET_ADDRESS", "test.hostname.com:8883");
+
+        temp_env::with_var(
+            "AEP_CONFIGMAP_MOUNT_PATH",
+            Some(aep_configmap_manager.path().to_str().unwrap()),
+            || {
+                let builder = MqttConnectionSettingsBuilder::from_file_mount().unwrap();
+                // A client ID is generated when none is provided
+                let settings = builder.build().unwrap();
+                assert!(!settings.client_id.is_empty());
+            },
+        );
+    }
+
     // NOTE: this will need test cases if there are ever additional mounts with required values
     #[test]
     fn from_file_mount_no_env_var_for_mount_with_required_values() {
@@ -922,4 +1122,53 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn with_file_mount_target() {
+        let settings = MqttConnectionSettingsBuilder::default()
+            .client_id("test-client-id".to_string())
+            .hostname("old.hostname.com".to_string())
+            .tcp_port(1883u16)
+            .use_tls(false)
+            .build()
+            .unwrap();
+
+        let aep_configmap_manager = TempConfigMapManager::new("aep_configmap");
+        aep_configmap_manager.add_file("BROKER_TARGET_ADDRESS", "new.hostname.com:8883");
+        aep_configmap_manager.add_file("AIO_MQTT_CLIENT_ID", "other-client-id");
+        aep_configmap_manager.add_file("BROKER_USE_TLS", "true");
+
+        temp_env::with_vars(
+            [
+                (
+                    "AEP_CONFIGMAP_MOUNT_PATH",
+                    Some(aep_configmap_manager.path().to_str().unwrap()),
+                ),
+                ("BROKER_SAT_MOUNT_PATH", None),
+                (
+                    "BROKER_TLS_TRUST_BUNDLE_CACERT_MOUNT_PATH",
+                    Some("/path/to/ca/certs"),
+                ),
+            ],
+            || {
+                let updated = settings.with_file_mount_target().unwrap();
+                // Target values are updated from the file mounts
+                assert_eq!(updated.hostname, "new.hostname.com");
+                assert_eq!(updated.tcp_port, 8883);
+                assert!(updated.use_tls);
+                assert_eq!(updated.ca_file, Some("/path/to/ca/certs".to_string()));
+                assert!(!updated.same_target(&settings));
+                // Other values are unchanged
+                assert_eq!(updated.client_id, "test-client-id");
+                assert_eq!(updated.sat_file, None);
+                // Re-reading unchanged file mounts results in the same target
+                assert!(
+                    updated
+                        .with_file_mount_target()
+                        .unwrap()
+                        .same_target(&updated)
+                );
+            },
+        );
+    }
 }
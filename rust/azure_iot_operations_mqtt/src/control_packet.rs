@@ -21,3 +21,61 @@ pub type SubscribeProperties = rumqttc::v5::mqttbytes::v5::SubscribeProperties;
 pub type UnsubscribeProperties = rumqttc::v5::mqttbytes::v5::UnsubscribeProperties;
 /// Properties for an AUTH packet
 pub type AuthProperties = rumqttc::v5::mqttbytes::v5::AuthProperties;
+
+/// Reason code for a CONNACK packet
+pub type ConnectReturnCode = rumqttc::v5::mqttbytes::v5::ConnectReturnCode;
+/// Reason code for a DISCONNECT packet
+pub type DisconnectReasonCode = rumqttc::v5::mqttbytes::v5::DisconnectReasonCode;
+
+/// Maximum value of a subscription identifier (variable byte integer)
+pub const MAX_SUBSCRIPTION_IDENTIFIER: usize = 268_435_455;
+
+/// Indicates whether retained messages are sent when a subscription is established.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RetainHandling {
+    /// Send retained messages at the time of the subscribe (0)
+    #[default]
+    SendOnSubscribe,
+    /// Send retained messages at the time of the subscribe only if the subscription does not
+    /// already exist (1)
+    SendOnNewSubscribe,
+    /// Do not send retained messages at the time of the subscribe (2)
+    DoNotSend,
+}
+
+/// MQTT 5.0 options for a subscription
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubscriptionOptions {
+    /// If true, application messages will not be forwarded to a connection with a client ID
+    /// equal to the client ID of the publishing connection
+    pub no_local: bool,
+    /// If true, application messages forwarded using this subscription keep the RETAIN flag
+    /// they were published with
+    pub retain_as_published: bool,
+    /// Indicates whether retained messages are sent when the subscription is established
+    pub retain_handling: RetainHandling,
+    /// Identifier of the subscription, included in all publishes delivered because of it.
+    /// Must be between 1 and [`MAX_SUBSCRIPTION_IDENTIFIER`] (inclusive).
+    pub subscription_identifier: Option<usize>,
+}
+
+/// Reason code for negatively acknowledging a received PUBLISH packet (`QoS` 1 or 2).
+///
+/// These correspond to the failure reason codes defined for PUBACK/PUBREC in the MQTT 5.0 spec
+/// that are applicable for an application to report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NackReasonCode {
+    /// The receiver does not accept the publish but either does not want to reveal the reason,
+    /// or it does not match one of the other values (0x80)
+    UnspecifiedError,
+    /// The publish is valid but is not accepted by the receiver (0x83)
+    ImplementationSpecificError,
+    /// The publish is not authorized (0x87)
+    NotAuthorized,
+    /// The topic name is correctly formed, but is not accepted by the receiver (0x90)
+    TopicNameInvalid,
+    /// An implementation or administrative imposed limit has been exceeded (0x97)
+    QuotaExceeded,
+    /// The payload format does not match the specified payload format indicator (0x99)
+    PayloadFormatInvalid,
+}
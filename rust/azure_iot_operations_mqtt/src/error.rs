@@ -7,6 +7,8 @@ use std::fmt;
 
 use thiserror::Error;
 
+use crate::control_packet::{ConnectReturnCode, DisconnectReasonCode};
+
 /// Error type for MQTT connection
 pub type ConnectionError = rumqttc::v5::ConnectionError;
 /// Error type for completion tokens
@@ -14,10 +16,156 @@ pub type CompletionError = rumqttc::NoticeError;
 /// Error subtype for MQTT connection error caused by state
 pub type StateError = rumqttc::v5::StateError;
 
+/// Classification of an error, used to inform whether the operation that caused it should be
+/// retried.
+pub trait ErrorClassification {
+    /// Returns true if the condition that caused the error may be transient, and thus the
+    /// operation may succeed if retried.
+    fn is_retriable(&self) -> bool;
+
+    /// Returns true if the condition that caused the error will not resolve without intervention
+    /// (e.g. a configuration change), and thus the operation should not be retried.
+    fn is_fatal(&self) -> bool {
+        !self.is_retriable()
+    }
+
+    /// Returns true if the error was caused by a failure to authenticate or authorize.
+    fn is_auth_related(&self) -> bool {
+        false
+    }
+}
+
+impl ErrorClassification for ConnectionError {
+    fn is_retriable(&self) -> bool {
+        match self {
+            ConnectionError::ConnectionRefused(rc) => rc.is_retriable(),
+            ConnectionError::MqttState(e) => e.is_retriable(),
+            ConnectionError::Io(e) => is_retriable_io_error(e),
+            // The broker did not respond in time, or responded unexpectedly, which is resolved by
+            // reconnecting
+            ConnectionError::Timeout(_) | ConnectionError::NotConnAck(_) => true,
+            // The client has been dropped, so there is nothing left to retry for, and TLS,
+            // websocket and proxy errors are caused by configuration that will fail again
+            _ => false,
+        }
+    }
+
+    fn is_auth_related(&self) -> bool {
+        match self {
+            ConnectionError::ConnectionRefused(rc) => rc.is_auth_related(),
+            ConnectionError::MqttState(e) => e.is_auth_related(),
+            _ => false,
+        }
+    }
+}
+
+impl ErrorClassification for StateError {
+    fn is_retriable(&self) -> bool {
+        match self {
+            StateError::ServerDisconnect { reason_code, .. } => reason_code.is_retriable(),
+            StateError::ConnFail { reason } => reason.is_retriable(),
+            StateError::Io(e) => is_retriable_io_error(e),
+            // Protocol state errors and dropped connections are resolved by reconnecting
+            StateError::InvalidState
+            | StateError::Unsolicited(_)
+            | StateError::AwaitPingResp
+            | StateError::WrongPacket
+            | StateError::CollisionTimeout
+            | StateError::Deserialization(_)
+            | StateError::ConnectionAborted => true,
+            // Rejections of individual requests by the broker and size limits will be repeated on
+            // retry
+            _ => false,
+        }
+    }
+
+    fn is_auth_related(&self) -> bool {
+        match self {
+            StateError::ServerDisconnect { reason_code, .. } => reason_code.is_auth_related(),
+            StateError::ConnFail { reason } => reason.is_auth_related(),
+            _ => false,
+        }
+    }
+}
+
+impl ErrorClassification for ConnectReturnCode {
+    fn is_retriable(&self) -> bool {
+        matches!(
+            self,
+            ConnectReturnCode::ServiceUnavailable
+                | ConnectReturnCode::UnspecifiedError
+                | ConnectReturnCode::ImplementationSpecificError
+                | ConnectReturnCode::ServerUnavailable
+                | ConnectReturnCode::ServerBusy
+                | ConnectReturnCode::QuotaExceeded
+                | ConnectReturnCode::UseAnotherServer
+                | ConnectReturnCode::ServerMoved
+                | ConnectReturnCode::ConnectionRateExceeded
+        )
+    }
+
+    fn is_auth_related(&self) -> bool {
+        matches!(
+            self,
+            ConnectReturnCode::BadUserNamePassword
+                | ConnectReturnCode::NotAuthorized
+                | ConnectReturnCode::Banned
+                | ConnectReturnCode::BadAuthenticationMethod
+        )
+    }
+}
+
+impl ErrorClassification for DisconnectReasonCode {
+    fn is_retriable(&self) -> bool {
+        matches!(
+            self,
+            DisconnectReasonCode::NormalDisconnection
+                | DisconnectReasonCode::DisconnectWithWillMessage
+                | DisconnectReasonCode::UnspecifiedError
+                | DisconnectReasonCode::ImplementationSpecificError
+                | DisconnectReasonCode::ServerBusy
+                | DisconnectReasonCode::ServerShuttingDown
+                | DisconnectReasonCode::KeepAliveTimeout
+                | DisconnectReasonCode::ReceiveMaximumExceeded
+                | DisconnectReasonCode::MessageRateTooHigh
+                | DisconnectReasonCode::QuotaExceeded
+                | DisconnectReasonCode::AdministrativeAction
+                | DisconnectReasonCode::UseAnotherServer
+                | DisconnectReasonCode::ServerMoved
+                | DisconnectReasonCode::ConnectionRateExceeded
+                | DisconnectReasonCode::MaximumConnectTime
+        )
+    }
+
+    fn is_auth_related(&self) -> bool {
+        matches!(self, DisconnectReasonCode::NotAuthorized)
+    }
+}
+
 // NOTE: While these errors may seem redundant and candidates for consolidation, we need this
 // flexibility because the same error types are used in both the low-level and high-level APIs.
 // If the Client/ManagedClient/PubReceiver traits were concretized, we could simplify this.
 
+/// Returns true if the I/O error was caused by a network condition that may be transient.
+/// Other I/O errors (e.g. a missing or unreadable file) will occur again on retry.
+fn is_retriable_io_error(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        std::io::ErrorKind::ConnectionRefused
+            | std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::NotConnected
+            | std::io::ErrorKind::HostUnreachable
+            | std::io::ErrorKind::NetworkUnreachable
+            | std::io::ErrorKind::NetworkDown
+            | std::io::ErrorKind::AddrNotAvailable
+            | std::io::ErrorKind::BrokenPipe
+            | std::io::ErrorKind::TimedOut
+            | std::io::ErrorKind::Interrupted
+            | std::io::ErrorKind::UnexpectedEof
+    )
+}
+
 /// Error executing an MQTT publish
 #[derive(Debug, Error, Clone)]
 #[error("{kind}")]
@@ -46,6 +194,8 @@ pub enum PublishErrorKind {
     DetachedClient,
     /// Invalid topic name provided
     InvalidTopicName,
+    /// Publish not allowed by the configured rate limits
+    RateLimited,
 }
 
 impl fmt::Display for PublishErrorKind {
@@ -55,10 +205,28 @@ impl fmt::Display for PublishErrorKind {
                 write!(f, "client is detached from connection/event loop")
             }
             PublishErrorKind::InvalidTopicName => write!(f, "invalid topic name"),
+            PublishErrorKind::RateLimited => write!(f, "publish not allowed by rate limits"),
         }
     }
 }
 
+impl ErrorClassification for PublishErrorKind {
+    fn is_retriable(&self) -> bool {
+        match self {
+            // A detached client remains detached, and an invalid topic name remains invalid
+            PublishErrorKind::DetachedClient | PublishErrorKind::InvalidTopicName => false,
+            // Rate limits allow the publish once enough time has passed
+            PublishErrorKind::RateLimited => true,
+        }
+    }
+}
+
+impl ErrorClassification for PublishError {
+    fn is_retriable(&self) -> bool {
+        self.kind.is_retriable()
+    }
+}
+
 /// Error executing an MQTT subscribe
 #[derive(Debug, Error, Clone)]
 #[error("{kind}")]
@@ -87,6 +255,8 @@ pub enum SubscribeErrorKind {
     DetachedClient,
     /// Invalid topic filter provided
     InvalidTopicFilter,
+    /// Invalid subscription identifier provided
+    InvalidSubscriptionIdentifier,
 }
 
 impl fmt::Display for SubscribeErrorKind {
@@ -96,10 +266,30 @@ impl fmt::Display for SubscribeErrorKind {
                 write!(f, "client is detached from connection/event loop")
             }
             SubscribeErrorKind::InvalidTopicFilter => write!(f, "invalid topic filter"),
+            SubscribeErrorKind::InvalidSubscriptionIdentifier => {
+                write!(f, "invalid subscription identifier")
+            }
         }
     }
 }
 
+impl ErrorClassification for SubscribeErrorKind {
+    fn is_retriable(&self) -> bool {
+        match self {
+            // A detached client remains detached, and invalid arguments remain invalid
+            SubscribeErrorKind::DetachedClient
+            | SubscribeErrorKind::InvalidTopicFilter
+            | SubscribeErrorKind::InvalidSubscriptionIdentifier => false,
+        }
+    }
+}
+
+impl ErrorClassification for SubscribeError {
+    fn is_retriable(&self) -> bool {
+        self.kind.is_retriable()
+    }
+}
+
 /// Error executing an MQTT unsubscribe
 #[derive(Debug, Error, Clone)]
 #[error("{kind}")]
@@ -141,6 +331,23 @@ impl fmt::Display for UnsubscribeErrorKind {
     }
 }
 
+impl ErrorClassification for UnsubscribeErrorKind {
+    fn is_retriable(&self) -> bool {
+        match self {
+            // A detached client remains detached, and an invalid topic filter remains invalid
+            UnsubscribeErrorKind::DetachedClient | UnsubscribeErrorKind::InvalidTopicFilter => {
+                false
+            }
+        }
+    }
+}
+
+impl ErrorClassification for UnsubscribeError {
+    fn is_retriable(&self) -> bool {
+        self.kind.is_retriable()
+    }
+}
+
 /// Error executing an MQTT ack
 #[derive(Debug, Error, Clone)]
 #[error("{kind}")]
@@ -220,6 +427,21 @@ impl fmt::Display for DisconnectErrorKind {
     }
 }
 
+/// Error updating the connection target of an MQTT event loop
+#[derive(Debug, Error)]
+#[error("{msg}")]
+pub struct ConnectionTargetError {
+    msg: String,
+}
+
+impl ConnectionTargetError {
+    /// Create a new [`ConnectionTargetError`]
+    #[must_use]
+    pub fn new(msg: String) -> Self {
+        Self { msg }
+    }
+}
+
 /// Error executing an MQTT reauth
 #[derive(Debug, Error)]
 #[error("{kind}")]
@@ -257,3 +479,56 @@ impl fmt::Display for ReauthErrorKind {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(ConnectionError::Io(std::io::Error::from(std::io::ErrorKind::ConnectionReset)), true, false; "io connection reset")]
+    #[test_case(ConnectionError::Io(std::io::Error::from(std::io::ErrorKind::NotFound)), false, false; "io not found")]
+    #[test_case(ConnectionError::Io(std::io::Error::from(std::io::ErrorKind::InvalidInput)), false, false; "io invalid input")]
+    #[test_case(ConnectionError::RequestsDone, false, false; "requests done")]
+    #[test_case(ConnectionError::ConnectionRefused(ConnectReturnCode::ServerBusy), true, false; "refused server busy")]
+    #[test_case(ConnectionError::ConnectionRefused(ConnectReturnCode::NotAuthorized), false, true; "refused not authorized")]
+    #[test_case(ConnectionError::ConnectionRefused(ConnectReturnCode::ClientIdentifierNotValid), false, false; "refused invalid client id")]
+    #[test_case(ConnectionError::MqttState(StateError::ServerDisconnect { reason_code: DisconnectReasonCode::ServerShuttingDown, reason_string: None }), true, false; "server shutting down")]
+    #[test_case(ConnectionError::MqttState(StateError::ServerDisconnect { reason_code: DisconnectReasonCode::SessionTakenOver, reason_string: None }), false, false; "session taken over")]
+    #[test_case(ConnectionError::MqttState(StateError::ServerDisconnect { reason_code: DisconnectReasonCode::NotAuthorized, reason_string: None }), false, true; "server disconnect not authorized")]
+    #[test_case(ConnectionError::MqttState(StateError::AwaitPingResp), true, false; "await ping response")]
+    #[test_case(ConnectionError::MqttState(StateError::OutgoingPacketTooLarge { pkt_size: 2, max: 1 }), false, false; "outgoing packet too large")]
+    #[test_case(ConnectionError::MqttState(StateError::Io(std::io::Error::from(std::io::ErrorKind::PermissionDenied))), false, false; "state io permission denied")]
+    #[test_case(ConnectionError::MqttState(StateError::ConnectionAborted), true, false; "connection aborted")]
+    #[test_case(ConnectionError::MqttState(StateError::ConnFail { reason: ConnectReturnCode::Success }), false, false; "conn fail success")]
+    #[allow(clippy::needless_pass_by_value)]
+    fn connection_error_classification(error: ConnectionError, retriable: bool, auth: bool) {
+        assert_eq!(error.is_retriable(), retriable);
+        assert_eq!(error.is_fatal(), !retriable);
+        assert_eq!(error.is_auth_related(), auth);
+    }
+
+    #[test]
+    fn request_error_classification() {
+        for kind in [
+            PublishErrorKind::DetachedClient,
+            PublishErrorKind::InvalidTopicName,
+        ] {
+            assert!(PublishError::new(kind).is_fatal());
+        }
+        assert!(PublishError::new(PublishErrorKind::RateLimited).is_retriable());
+        for kind in [
+            SubscribeErrorKind::DetachedClient,
+            SubscribeErrorKind::InvalidTopicFilter,
+            SubscribeErrorKind::InvalidSubscriptionIdentifier,
+        ] {
+            assert!(SubscribeError::new(kind).is_fatal());
+        }
+        for kind in [
+            UnsubscribeErrorKind::DetachedClient,
+            UnsubscribeErrorKind::InvalidTopicFilter,
+        ] {
+            assert!(UnsubscribeError::new(kind).is_fatal());
+        }
+    }
+}
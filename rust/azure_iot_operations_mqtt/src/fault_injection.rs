@@ -0,0 +1,743 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Fault-injecting decorators for the [`MqttClient`] and [`MqttEventLoop`] traits.
+//!
+//! A [`FaultyClient`] and [`FaultyEventLoop`] wrap an existing client and event loop, applying
+//! the faults configured on a shared [`FaultController`]. Faults on publishes, subscribes and
+//! unsubscribes are declared per topic filter via [`FaultRule`]s, while connection-level faults
+//! (refused CONNACKs and disconnects) are triggered directly on the [`FaultController`].
+//!
+//! These can be combined with the [`interface_mocks`](crate::interface_mocks) or a real client
+//! and used in a [`Session`](crate::session::session::Session) created via
+//! [`Session::new_from_injection`](crate::session::session::Session::new_from_injection) to
+//! chaos-test higher level logic.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::control_packet::{
+    AuthProperties, ConnectReturnCode, NackReasonCode, Publish, PublishProperties, QoS,
+    SubscribeProperties, SubscriptionOptions, UnsubscribeProperties,
+};
+use crate::error::{
+    AckError, ConnectionError, ConnectionTargetError, DisconnectError, PublishError,
+    PublishErrorKind, ReauthError, SubscribeError, SubscribeErrorKind, UnsubscribeError,
+    UnsubscribeErrorKind,
+};
+use crate::interface::{
+    CompletionToken, ConnectionTarget, Event, Incoming, MqttAck, MqttClient, MqttDisconnect,
+    MqttEventLoop, MqttPubSub,
+};
+use crate::topic::{TopicFilter, TopicName, TopicParseError};
+
+/// A fault to apply to MQTT traffic matching a [`FaultRule`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+    /// Drop a matching incoming publish so it is never delivered
+    DropIncoming,
+    /// Delay delivery of a matching incoming publish.
+    ///
+    /// Note that this stalls the event loop for the duration, as a real network delay would.
+    DelayIncoming(Duration),
+    /// Deliver a matching incoming publish twice, the second time with the DUP flag set
+    DuplicateIncoming,
+    /// Hold back a matching incoming publish until after the next incoming publish is delivered,
+    /// or until the given duration has elapsed without another incoming publish
+    ReorderIncoming(Duration),
+    /// Drop the connection when a matching incoming publish arrives, without delivering it
+    DisconnectOnIncoming,
+    /// Fail a matching publish call with the given error kind
+    FailPublish(PublishErrorKind),
+    /// Fail a matching subscribe call with the given error kind
+    FailSubscribe(SubscribeErrorKind),
+    /// Fail a matching unsubscribe call with the given error kind
+    FailUnsubscribe(UnsubscribeErrorKind),
+}
+
+/// Rule applying a [`Fault`] to MQTT traffic on topics matching a topic filter
+#[derive(Clone, Debug)]
+pub struct FaultRule {
+    topic_filter: TopicFilter,
+    fault: Fault,
+    remaining: Option<usize>,
+}
+
+impl FaultRule {
+    /// Create a new [`FaultRule`] applying the fault to all traffic matching the topic filter.
+    ///
+    /// # Errors
+    /// Returns a [`TopicParseError`] if the topic filter is invalid.
+    pub fn new(topic_filter: &str, fault: Fault) -> Result<Self, TopicParseError> {
+        Ok(Self {
+            topic_filter: TopicFilter::from_string(topic_filter.to_string())?,
+            fault,
+            remaining: None,
+        })
+    }
+
+    /// Only apply the fault to the next `count` matches
+    #[must_use]
+    pub fn times(mut self, count: usize) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+
+    /// Returns true if the rule applies to the topic. Topic filters (e.g. for subscribes) match
+    /// if they are identical to the topic filter of the rule.
+    fn matches(&self, topic: &str) -> bool {
+        match TopicName::from_string(topic.to_string()) {
+            Ok(topic_name) => topic_name.matches_topic_filter(&self.topic_filter),
+            Err(_) => self.topic_filter.as_str() == topic,
+        }
+    }
+}
+
+#[derive(Default)]
+struct FaultState {
+    rules: Vec<FaultRule>,
+    connack_refusals: VecDeque<ConnectReturnCode>,
+    pending_disconnect: bool,
+}
+
+impl FaultState {
+    /// Find the first applicable fault for the topic out of those accepted by the selector,
+    /// consuming one use of the rule that provides it.
+    fn take_fault<T>(&mut self, topic: &str, selector: impl Fn(&Fault) -> Option<T>) -> Option<T> {
+        let rule = self.rules.iter_mut().find(|rule| {
+            rule.remaining != Some(0) && selector(&rule.fault).is_some() && rule.matches(topic)
+        })?;
+        if let Some(remaining) = rule.remaining.as_mut() {
+            *remaining -= 1;
+        }
+        selector(&rule.fault)
+    }
+}
+
+/// Controls the faults applied by a [`FaultyClient`] and [`FaultyEventLoop`].
+///
+/// Cloned instances control the same faults, and faults can be changed at any time.
+#[derive(Clone, Default)]
+pub struct FaultController {
+    state: Arc<Mutex<FaultState>>,
+}
+
+impl FaultController {
+    /// Create a new [`FaultController`] with no faults configured
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a [`FaultRule`]. Rules are evaluated in the order they were added, and only the
+    /// first applicable rule is applied.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn add_rule(&self, rule: FaultRule) {
+        self.state.lock().unwrap().rules.push(rule);
+    }
+
+    /// Remove all [`FaultRule`]s
+    #[allow(clippy::missing_panics_doc)]
+    pub fn clear_rules(&self) {
+        self.state.lock().unwrap().rules.clear();
+    }
+
+    /// Refuse the next successful CONNACK with the provided reason code
+    #[allow(clippy::missing_panics_doc)]
+    pub fn refuse_connack(&self, code: ConnectReturnCode) {
+        self.state.lock().unwrap().connack_refusals.push_back(code);
+    }
+
+    /// Drop the connection on the next poll of the event loop. The wrapped event loop
+    /// reconnects on the following poll.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn disconnect(&self) {
+        self.state.lock().unwrap().pending_disconnect = true;
+    }
+}
+
+fn connection_aborted() -> ConnectionError {
+    ConnectionError::Io(io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        "injected disconnect",
+    ))
+}
+
+// ---------- Client ----------
+
+/// [`MqttClient`] decorator that fails requests according to a [`FaultController`]
+#[derive(Clone)]
+pub struct FaultyClient<C> {
+    inner: C,
+    controller: FaultController,
+}
+
+impl<C> FaultyClient<C> {
+    /// Wrap the provided client, applying the faults of the provided [`FaultController`]
+    pub fn new(inner: C, controller: FaultController) -> Self {
+        Self { inner, controller }
+    }
+
+    fn publish_fault(&self, topic: &str) -> Result<(), PublishError> {
+        let mut state = self.controller.state.lock().unwrap();
+        match state.take_fault(topic, |fault| match fault {
+            Fault::FailPublish(kind) => Some(*kind),
+            _ => None,
+        }) {
+            Some(kind) => Err(PublishError::new(kind)),
+            None => Ok(()),
+        }
+    }
+
+    fn subscribe_fault(&self, topic: &str) -> Result<(), SubscribeError> {
+        let mut state = self.controller.state.lock().unwrap();
+        match state.take_fault(topic, |fault| match fault {
+            Fault::FailSubscribe(kind) => Some(*kind),
+            _ => None,
+        }) {
+            Some(kind) => Err(SubscribeError::new(kind)),
+            None => Ok(()),
+        }
+    }
+
+    fn unsubscribe_fault(&self, topic: &str) -> Result<(), UnsubscribeError> {
+        let mut state = self.controller.state.lock().unwrap();
+        match state.take_fault(topic, |fault| match fault {
+            Fault::FailUnsubscribe(kind) => Some(*kind),
+            _ => None,
+        }) {
+            Some(kind) => Err(UnsubscribeError::new(kind)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<C> MqttPubSub for FaultyClient<C>
+where
+    C: MqttPubSub + Send + Sync,
+{
+    async fn publish(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Bytes> + Send,
+    ) -> Result<CompletionToken, PublishError> {
+        let topic = topic.into();
+        self.publish_fault(&topic)?;
+        self.inner.publish(topic, qos, retain, payload).await
+    }
+
+    async fn publish_with_properties(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Bytes> + Send,
+        properties: PublishProperties,
+    ) -> Result<CompletionToken, PublishError> {
+        let topic = topic.into();
+        self.publish_fault(&topic)?;
+        self.inner
+            .publish_with_properties(topic, qos, retain, payload, properties)
+            .await
+    }
+
+    async fn subscribe(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+    ) -> Result<CompletionToken, SubscribeError> {
+        let topic = topic.into();
+        self.subscribe_fault(&topic)?;
+        self.inner.subscribe(topic, qos).await
+    }
+
+    async fn subscribe_with_properties(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        properties: SubscribeProperties,
+    ) -> Result<CompletionToken, SubscribeError> {
+        let topic = topic.into();
+        self.subscribe_fault(&topic)?;
+        self.inner
+            .subscribe_with_properties(topic, qos, properties)
+            .await
+    }
+
+    async fn subscribe_with_options(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        options: SubscriptionOptions,
+    ) -> Result<CompletionToken, SubscribeError> {
+        let topic = topic.into();
+        self.subscribe_fault(&topic)?;
+        self.inner.subscribe_with_options(topic, qos, options).await
+    }
+
+    async fn unsubscribe(
+        &self,
+        topic: impl Into<String> + Send,
+    ) -> Result<CompletionToken, UnsubscribeError> {
+        let topic = topic.into();
+        self.unsubscribe_fault(&topic)?;
+        self.inner.unsubscribe(topic).await
+    }
+
+    async fn unsubscribe_with_properties(
+        &self,
+        topic: impl Into<String> + Send,
+        properties: UnsubscribeProperties,
+    ) -> Result<CompletionToken, UnsubscribeError> {
+        let topic = topic.into();
+        self.unsubscribe_fault(&topic)?;
+        self.inner
+            .unsubscribe_with_properties(topic, properties)
+            .await
+    }
+}
+
+#[async_trait]
+impl<C> MqttAck for FaultyClient<C>
+where
+    C: MqttAck + Send + Sync,
+{
+    async fn ack(&self, publish: &Publish) -> Result<CompletionToken, AckError> {
+        self.inner.ack(publish).await
+    }
+
+    async fn nack(
+        &self,
+        publish: &Publish,
+        reason_code: NackReasonCode,
+        reason_string: Option<String>,
+    ) -> Result<CompletionToken, AckError> {
+        self.inner.nack(publish, reason_code, reason_string).await
+    }
+}
+
+#[async_trait]
+impl<C> MqttDisconnect for FaultyClient<C>
+where
+    C: MqttDisconnect + Send + Sync,
+{
+    async fn disconnect(&self) -> Result<(), DisconnectError> {
+        self.inner.disconnect().await
+    }
+}
+
+#[async_trait]
+impl<C> MqttClient for FaultyClient<C>
+where
+    C: MqttClient + Send + Sync,
+{
+    async fn reauth(&self, auth_props: AuthProperties) -> Result<(), ReauthError> {
+        self.inner.reauth(auth_props).await
+    }
+}
+
+// ---------- Event Loop ----------
+
+/// [`MqttEventLoop`] decorator that alters incoming traffic and the connection according to a
+/// [`FaultController`]
+pub struct FaultyEventLoop<EL> {
+    inner: EL,
+    controller: FaultController,
+    /// Events ready to be yielded before polling the inner event loop again
+    ready: VecDeque<Event>,
+    /// Publishes held back to be delivered after the next incoming publish, along with the time
+    /// at which they are delivered regardless
+    held: VecDeque<(Publish, Instant)>,
+}
+
+impl<EL> FaultyEventLoop<EL> {
+    /// Wrap the provided event loop, applying the faults of the provided [`FaultController`]
+    pub fn new(inner: EL, controller: FaultController) -> Self {
+        Self {
+            inner,
+            controller,
+            ready: VecDeque::new(),
+            held: VecDeque::new(),
+        }
+    }
+}
+
+impl<EL> FaultyEventLoop<EL>
+where
+    EL: MqttEventLoop + Send,
+{
+    /// Drop the connection of the wrapped event loop, returning the error to report for it
+    fn inject_disconnect(&mut self) -> ConnectionError {
+        self.inner.drop_connection();
+        connection_aborted()
+    }
+
+    /// Poll the wrapped event loop, releasing any held back publishes whose hold expires first.
+    /// Returns None if a held back publish was released.
+    async fn poll_inner(&mut self) -> Option<Result<Event, ConnectionError>> {
+        let Some(release_at) = self.held.iter().map(|(_, release_at)| *release_at).min() else {
+            return Some(self.inner.poll().await);
+        };
+        let remaining = release_at.saturating_duration_since(Instant::now());
+        if let Ok(result) = crate::runtime::timeout(remaining, self.inner.poll()).await {
+            return Some(result);
+        }
+        let now = Instant::now();
+        let (expired, held): (VecDeque<_>, VecDeque<_>) = self
+            .held
+            .drain(..)
+            .partition(|(_, release_at)| *release_at <= now);
+        log::debug!("Releasing {} held back incoming publish(es)", expired.len());
+        self.held = held;
+        self.ready.extend(
+            expired
+                .into_iter()
+                .map(|(held, _)| Event::Incoming(Incoming::Publish(held))),
+        );
+        None
+    }
+}
+
+#[async_trait]
+impl<EL> MqttEventLoop for FaultyEventLoop<EL>
+where
+    EL: MqttEventLoop + Send,
+{
+    async fn poll(&mut self) -> Result<Event, ConnectionError> {
+        loop {
+            if std::mem::take(&mut self.controller.state.lock().unwrap().pending_disconnect) {
+                return Err(self.inject_disconnect());
+            }
+            if let Some(event) = self.ready.pop_front() {
+                return Ok(event);
+            }
+
+            let Some(result) = self.poll_inner().await else {
+                continue;
+            };
+            let event = result?;
+            let publish = match event {
+                Event::Incoming(Incoming::ConnAck(ref connack)) => {
+                    if connack.code == ConnectReturnCode::Success {
+                        let refusal = self
+                            .controller
+                            .state
+                            .lock()
+                            .unwrap()
+                            .connack_refusals
+                            .pop_front();
+                        if let Some(code) = refusal {
+                            self.inner.drop_connection();
+                            return Err(ConnectionError::ConnectionRefused(code));
+                        }
+                    }
+                    return Ok(event);
+                }
+                Event::Incoming(Incoming::Publish(publish)) => publish,
+                _ => return Ok(event),
+            };
+
+            let topic = String::from_utf8_lossy(&publish.topic).to_string();
+            let fault =
+                self.controller
+                    .state
+                    .lock()
+                    .unwrap()
+                    .take_fault(&topic, |fault| match fault {
+                        Fault::DropIncoming
+                        | Fault::DelayIncoming(_)
+                        | Fault::DuplicateIncoming
+                        | Fault::ReorderIncoming(_)
+                        | Fault::DisconnectOnIncoming => Some(fault.clone()),
+                        _ => None,
+                    });
+
+            match fault {
+                Some(Fault::DropIncoming) => {
+                    log::debug!("Dropping incoming publish on {topic}");
+                    continue;
+                }
+                Some(Fault::DelayIncoming(delay)) => {
+                    log::debug!("Delaying incoming publish on {topic} by {delay:?}");
+                    crate::runtime::sleep(delay).await;
+                }
+                Some(Fault::DuplicateIncoming) => {
+                    log::debug!("Duplicating incoming publish on {topic}");
+                    let mut duplicate = publish.clone();
+                    duplicate.dup = true;
+                    self.ready
+                        .push_back(Event::Incoming(Incoming::Publish(duplicate)));
+                }
+                Some(Fault::ReorderIncoming(max_hold)) => {
+                    log::debug!("Holding back incoming publish on {topic}");
+                    self.held.push_back((publish, Instant::now() + max_hold));
+                    continue;
+                }
+                Some(Fault::DisconnectOnIncoming) => {
+                    log::debug!("Disconnecting on incoming publish on {topic}");
+                    return Err(self.inject_disconnect());
+                }
+                _ => {}
+            }
+
+            // Release any held back publishes after this one
+            self.ready.extend(
+                self.held
+                    .drain(..)
+                    .map(|(held, _)| Event::Incoming(Incoming::Publish(held))),
+            );
+            return Ok(Event::Incoming(Incoming::Publish(publish)));
+        }
+    }
+
+    fn set_clean_start(&mut self, clean_start: bool) {
+        self.inner.set_clean_start(clean_start);
+    }
+
+    fn set_authentication_method(&mut self, authentication_method: Option<String>) {
+        self.inner.set_authentication_method(authentication_method);
+    }
+
+    fn set_authentication_data(&mut self, authentication_data: Option<Bytes>) {
+        self.inner.set_authentication_data(authentication_data);
+    }
+
+    fn set_connection_target(
+        &mut self,
+        target: &ConnectionTarget,
+    ) -> Result<(), ConnectionTargetError> {
+        self.inner.set_connection_target(target)
+    }
+
+    fn set_broker(&mut self, hostname: String, tcp_port: Option<u16>) {
+        self.inner.set_broker(hostname, tcp_port);
+    }
+
+    fn take_server_reference(&mut self) -> Option<String> {
+        self.inner.take_server_reference()
+    }
+
+    fn drop_connection(&mut self) {
+        self.inner.drop_connection();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rumqttc::v5::mqttbytes::v5::ConnAck;
+
+    use super::*;
+    use crate::interface_mocks::{EventInjector, MockClient, MockEventLoop};
+
+    fn publish_event(topic: &str, pkid: u16) -> Event {
+        let mut publish = Publish::new(topic, QoS::AtLeastOnce, "payload", None);
+        publish.pkid = pkid;
+        Event::Incoming(Incoming::Publish(publish))
+    }
+
+    fn setup() -> (
+        FaultyEventLoop<MockEventLoop>,
+        EventInjector,
+        FaultController,
+    ) {
+        let controller = FaultController::new();
+        let (event_loop, injector) = MockEventLoop::new();
+        (
+            FaultyEventLoop::new(event_loop, controller.clone()),
+            injector,
+            controller,
+        )
+    }
+
+    async fn poll_pkid(event_loop: &mut FaultyEventLoop<MockEventLoop>) -> u16 {
+        match event_loop.poll().await.unwrap() {
+            Event::Incoming(Incoming::Publish(publish)) => publish.pkid,
+            _ => panic!("expected incoming publish"),
+        }
+    }
+
+    #[tokio::test]
+    async fn drop_incoming_on_matching_topic() {
+        let (mut event_loop, injector, controller) = setup();
+        controller.add_rule(FaultRule::new("faulty/+", Fault::DropIncoming).unwrap());
+
+        injector.inject(publish_event("faulty/topic", 1)).unwrap();
+        injector.inject(publish_event("healthy/topic", 2)).unwrap();
+        assert_eq!(poll_pkid(&mut event_loop).await, 2);
+    }
+
+    #[tokio::test]
+    async fn duplicate_incoming_limited_times() {
+        let (mut event_loop, injector, controller) = setup();
+        controller.add_rule(
+            FaultRule::new("#", Fault::DuplicateIncoming)
+                .unwrap()
+                .times(1),
+        );
+
+        injector.inject(publish_event("topic", 1)).unwrap();
+        injector.inject(publish_event("topic", 2)).unwrap();
+        injector.inject(publish_event("topic", 3)).unwrap();
+        assert_eq!(poll_pkid(&mut event_loop).await, 1);
+        assert_eq!(poll_pkid(&mut event_loop).await, 1);
+        assert_eq!(poll_pkid(&mut event_loop).await, 2);
+        assert_eq!(poll_pkid(&mut event_loop).await, 3);
+    }
+
+    #[tokio::test]
+    async fn reorder_incoming() {
+        let (mut event_loop, injector, controller) = setup();
+        controller.add_rule(
+            FaultRule::new("topic", Fault::ReorderIncoming(Duration::from_secs(10)))
+                .unwrap()
+                .times(1),
+        );
+
+        injector.inject(publish_event("topic", 1)).unwrap();
+        injector.inject(publish_event("topic", 2)).unwrap();
+        assert_eq!(poll_pkid(&mut event_loop).await, 2);
+        assert_eq!(poll_pkid(&mut event_loop).await, 1);
+    }
+
+    #[tokio::test]
+    async fn reorder_incoming_released_after_max_hold() {
+        let (mut event_loop, injector, controller) = setup();
+        let max_hold = Duration::from_millis(100);
+        controller.add_rule(FaultRule::new("topic", Fault::ReorderIncoming(max_hold)).unwrap());
+
+        let start = Instant::now();
+        injector.inject(publish_event("topic", 1)).unwrap();
+        // No other publish arrives, so the held back publish is released once the hold expires
+        assert_eq!(poll_pkid(&mut event_loop).await, 1);
+        assert!(start.elapsed() >= max_hold);
+    }
+
+    #[tokio::test]
+    async fn delay_incoming() {
+        let (mut event_loop, injector, controller) = setup();
+        let delay = Duration::from_millis(100);
+        controller.add_rule(
+            FaultRule::new("faulty/+", Fault::DelayIncoming(delay))
+                .unwrap()
+                .times(1),
+        );
+
+        let start = Instant::now();
+        injector.inject(publish_event("faulty/topic", 1)).unwrap();
+        assert_eq!(poll_pkid(&mut event_loop).await, 1);
+        assert!(start.elapsed() >= delay);
+
+        let start = Instant::now();
+        injector.inject(publish_event("faulty/topic", 2)).unwrap();
+        assert_eq!(poll_pkid(&mut event_loop).await, 2);
+        assert!(start.elapsed() < delay);
+    }
+
+    #[tokio::test]
+    async fn disconnect_on_incoming() {
+        let (mut event_loop, injector, controller) = setup();
+        controller.add_rule(
+            FaultRule::new("faulty/+", Fault::DisconnectOnIncoming)
+                .unwrap()
+                .times(1),
+        );
+
+        injector.inject(publish_event("healthy/topic", 1)).unwrap();
+        assert_eq!(poll_pkid(&mut event_loop).await, 1);
+        assert_eq!(injector.dropped_connections(), 0);
+
+        injector.inject(publish_event("faulty/topic", 2)).unwrap();
+        injector.inject(publish_event("faulty/topic", 3)).unwrap();
+        assert!(matches!(
+            event_loop.poll().await,
+            Err(ConnectionError::Io(e)) if e.kind() == io::ErrorKind::ConnectionAborted
+        ));
+        assert_eq!(injector.dropped_connections(), 1);
+        // The publish that triggered the disconnect is not delivered
+        assert_eq!(poll_pkid(&mut event_loop).await, 3);
+    }
+
+    #[tokio::test]
+    async fn refuse_connack_and_disconnect() {
+        let (mut event_loop, injector, controller) = setup();
+        let connack = Event::Incoming(Incoming::ConnAck(ConnAck {
+            session_present: false,
+            code: ConnectReturnCode::Success,
+            properties: None,
+        }));
+        controller.refuse_connack(ConnectReturnCode::NotAuthorized);
+
+        injector.inject(connack.clone()).unwrap();
+        assert!(matches!(
+            event_loop.poll().await,
+            Err(ConnectionError::ConnectionRefused(
+                ConnectReturnCode::NotAuthorized
+            ))
+        ));
+        assert_eq!(injector.dropped_connections(), 1);
+        injector.inject(connack.clone()).unwrap();
+        assert_eq!(event_loop.poll().await.unwrap(), connack);
+        assert_eq!(injector.dropped_connections(), 1);
+
+        controller.disconnect();
+        assert!(matches!(
+            event_loop.poll().await,
+            Err(ConnectionError::Io(e)) if e.kind() == io::ErrorKind::ConnectionAborted
+        ));
+        assert_eq!(injector.dropped_connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn fail_publish_and_subscribe() {
+        let controller = FaultController::new();
+        let client = FaultyClient::new(MockClient::new(), controller.clone());
+        controller.add_rule(
+            FaultRule::new(
+                "faulty/#",
+                Fault::FailPublish(PublishErrorKind::DetachedClient),
+            )
+            .unwrap(),
+        );
+        controller.add_rule(
+            FaultRule::new(
+                "faulty/#",
+                Fault::FailSubscribe(SubscribeErrorKind::InvalidTopicFilter),
+            )
+            .unwrap(),
+        );
+
+        let Err(err) = client
+            .publish("faulty/topic", QoS::AtLeastOnce, false, "payload")
+            .await
+        else {
+            panic!("expected publish to fail");
+        };
+        assert_eq!(*err.kind(), PublishErrorKind::DetachedClient);
+        assert!(
+            client
+                .publish("healthy/topic", QoS::AtLeastOnce, false, "payload")
+                .await
+                .is_ok()
+        );
+        let Err(err) = client.subscribe("faulty/#", QoS::AtLeastOnce).await else {
+            panic!("expected subscribe to fail");
+        };
+        assert_eq!(*err.kind(), SubscribeErrorKind::InvalidTopicFilter);
+
+        controller.clear_rules();
+        assert!(
+            client
+                .publish("faulty/topic", QoS::AtLeastOnce, false, "payload")
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -7,17 +7,37 @@ use async_trait::async_trait;
 use bytes::Bytes;
 
 use crate::control_packet::{
-    AuthProperties, Publish, PublishProperties, QoS, SubscribeProperties, UnsubscribeProperties,
+    AuthProperties, NackReasonCode, Publish, PublishProperties, QoS, SubscribeProperties,
+    SubscriptionOptions, UnsubscribeProperties,
 };
 use crate::error::{
-    AckError, CompletionError, ConnectionError, DisconnectError, PublishError, ReauthError,
-    SubscribeError, UnsubscribeError,
+    AckError, CompletionError, ConnectionError, ConnectionTargetError, DisconnectError,
+    PublishError, ReauthError, SubscribeError, UnsubscribeError,
 };
 pub use crate::session::receiver::AckToken; // TODO: remove this pub re-export after concretized receivers / managed clients
 use crate::topic::TopicParseError;
 
 // ---------- Concrete Types ----------
 
+/// Broker address and TLS configuration used by an [`MqttEventLoop`] to connect to the MQTT broker.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionTarget {
+    /// FQDN of the host to connect to
+    pub hostname: String,
+    /// TCP port to connect to the host on
+    pub tcp_port: u16,
+    /// Use TLS when connecting
+    pub use_tls: bool,
+    /// Path to a PEM file used to validate server identity
+    pub ca_file: Option<String>,
+    /// Path to PEM file used to establish X509 client authentication
+    pub cert_file: Option<String>,
+    /// Path to a file containing a key used to establish X509 client authentication
+    pub key_file: Option<String>,
+    /// Path to a file containing the password used to decrypt the Key
+    pub key_password_file: Option<String>,
+}
+
 /// Awaitable token indicating completion of MQTT message delivery.
 pub struct CompletionToken(
     pub Box<dyn std::future::Future<Output = Result<(), CompletionError>> + Send>,
@@ -108,6 +128,17 @@ pub trait MqttPubSub {
         properties: SubscribeProperties,
     ) -> Result<CompletionToken, SubscribeError>;
 
+    /// MQTT Subscribe with MQTT 5.0 subscription options
+    ///
+    /// If connection is unavailable, subscribe will be queued and delivered when connection is re-established.
+    /// Blocks if at capacity for queueing.
+    async fn subscribe_with_options(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        options: SubscriptionOptions,
+    ) -> Result<CompletionToken, SubscribeError>;
+
     /// MQTT Unsubscribe
     ///
     /// If connection is unavailable, unsubscribe will be queued and delivered when connection is re-established.
@@ -133,6 +164,15 @@ pub trait MqttPubSub {
 pub trait MqttAck {
     /// Acknowledge a received Publish.
     async fn ack(&self, publish: &Publish) -> Result<CompletionToken, AckError>;
+
+    /// Negatively acknowledge a received Publish, reporting a failure reason code and an
+    /// optional human readable reason string to the broker.
+    async fn nack(
+        &self,
+        publish: &Publish,
+        reason_code: NackReasonCode,
+        reason_string: Option<String>,
+    ) -> Result<CompletionToken, AckError>;
 }
 
 // TODO: consider scoping this to also include a `connect`. Not currently needed, but would be more flexible,
@@ -166,6 +206,27 @@ pub trait MqttEventLoop {
 
     /// Set the authentication data
     fn set_authentication_data(&mut self, authentication_data: Option<Bytes>);
+
+    /// Set the broker address and TLS configuration for subsequent MQTT connection attempts
+    ///
+    /// # Errors
+    /// Returns a [`ConnectionTargetError`] if the target cannot be applied
+    fn set_connection_target(
+        &mut self,
+        target: &ConnectionTarget,
+    ) -> Result<(), ConnectionTargetError>;
+
+    /// Set the broker address for subsequent MQTT connection attempts.
+    /// If `tcp_port` is `None`, the current TCP port is retained.
+    fn set_broker(&mut self, hostname: String, tcp_port: Option<u16>);
+
+    /// Take the Server Reference provided by the broker in the CONNACK or DISCONNECT that caused
+    /// the most recent [`ConnectionError`] returned by [`poll`](MqttEventLoop::poll), if any.
+    fn take_server_reference(&mut self) -> Option<String>;
+
+    /// Drop the current MQTT connection, if any, without sending a DISCONNECT.
+    /// The next poll will attempt to connect again.
+    fn drop_connection(&mut self);
 }
 
 // ---------- Higher level MQTT abstractions ----------
@@ -191,6 +252,12 @@ pub trait ManagedClient: MqttPubSub {
     /// Creates a new [`PubReceiver`] that receives all messages not sent to other
     /// filtered receivers.
     fn create_unfiltered_pub_receiver(&self) -> Self::PubReceiver;
+
+    /// Creates a new [`PubReceiver`] that receives messages delivered because of the subscription
+    /// with the specified subscription identifier (see [`SubscriptionOptions`]), regardless of
+    /// their topic.
+    fn create_subscription_pub_receiver(&self, subscription_identifier: usize)
+    -> Self::PubReceiver;
 }
 
 #[async_trait]
@@ -3,6 +3,7 @@
 
 //! Bespoke mocks for relevant traits defined in the interface module.
 #![allow(unused_variables)]
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
@@ -10,14 +11,16 @@ use bytes::Bytes;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, error::SendError, unbounded_channel};
 
 use crate::control_packet::{
-    AuthProperties, Publish, PublishProperties, QoS, SubscribeProperties, UnsubscribeProperties,
+    AuthProperties, NackReasonCode, Publish, PublishProperties, QoS, SubscribeProperties,
+    SubscriptionOptions, UnsubscribeProperties,
 };
 use crate::error::{
-    AckError, CompletionError, ConnectionError, DisconnectError, PublishError, ReauthError,
-    SubscribeError, UnsubscribeError,
+    AckError, CompletionError, ConnectionError, ConnectionTargetError, DisconnectError,
+    PublishError, ReauthError, SubscribeError, UnsubscribeError,
 };
 use crate::interface::{
-    CompletionToken, Event, MqttAck, MqttClient, MqttDisconnect, MqttEventLoop, MqttPubSub,
+    CompletionToken, ConnectionTarget, Event, MqttAck, MqttClient, MqttDisconnect, MqttEventLoop,
+    MqttPubSub,
 };
 
 /// Stand-in for the inner future of a [`CompletionToken`].
@@ -42,6 +45,7 @@ pub enum MockClientCall {
     Subscribe(SubscribeCall),
     Unsubscribe(UnsubscribeCall),
     Ack(AckCall),
+    Nack(NackCall),
 }
 
 #[derive(Clone)]
@@ -60,6 +64,7 @@ pub struct SubscribeCall {
     pub topic: String,
     pub qos: QoS,
     pub properties: Option<SubscribeProperties>,
+    pub options: Option<SubscriptionOptions>,
 }
 
 #[derive(Clone)]
@@ -75,6 +80,14 @@ pub struct AckCall {
     pub publish: Publish,
 }
 
+#[derive(Clone)]
+#[allow(missing_docs)]
+pub struct NackCall {
+    pub publish: Publish,
+    pub reason_code: NackReasonCode,
+    pub reason_string: Option<String>,
+}
+
 /// Call data for [`MockClient`]
 #[derive(Default)]
 struct SharedCallTracker {
@@ -139,6 +152,19 @@ impl MockClientController {
             .count()
     }
 
+    /// Return the number of `.nack()` calls made to the client.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn nack_count(&self) -> usize {
+        self.shared_tracker
+            .lock()
+            .unwrap()
+            .call_sequence
+            .iter()
+            .filter(|call| matches!(call, MockClientCall::Nack(_)))
+            .count()
+    }
+
     /// Return a snapshot of the sequence of calls made to the mocked client so far
     #[must_use]
     #[allow(clippy::missing_panics_doc)]
@@ -246,6 +272,7 @@ impl MqttPubSub for MockClient {
             topic: topic.into(),
             qos,
             properties: None,
+            options: None,
         };
         self.shared_tracker
             .lock()
@@ -265,6 +292,27 @@ impl MqttPubSub for MockClient {
             topic: topic.into(),
             qos,
             properties: Some(properties),
+            options: None,
+        };
+        self.shared_tracker
+            .lock()
+            .unwrap()
+            .call_sequence
+            .push(MockClientCall::Subscribe(call));
+        Ok(CompletionToken(Box::new(CompletedAckFuture {})))
+    }
+
+    async fn subscribe_with_options(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        options: SubscriptionOptions,
+    ) -> Result<CompletionToken, SubscribeError> {
+        let call = SubscribeCall {
+            topic: topic.into(),
+            qos,
+            properties: None,
+            options: Some(options),
         };
         self.shared_tracker
             .lock()
@@ -321,6 +369,25 @@ impl MqttAck for MockClient {
             .push(MockClientCall::Ack(call));
         Ok(CompletionToken(Box::new(CompletedAckFuture {})))
     }
+
+    async fn nack(
+        &self,
+        publish: &Publish,
+        reason_code: NackReasonCode,
+        reason_string: Option<String>,
+    ) -> Result<CompletionToken, AckError> {
+        let call = NackCall {
+            publish: publish.clone(),
+            reason_code,
+            reason_string,
+        };
+        self.shared_tracker
+            .lock()
+            .unwrap()
+            .call_sequence
+            .push(MockClientCall::Nack(call));
+        Ok(CompletionToken(Box::new(CompletedAckFuture {})))
+    }
 }
 
 #[async_trait]
@@ -340,6 +407,7 @@ impl MqttClient for MockClient {
 /// Mock implementation of an MQTT event loop
 pub struct MockEventLoop {
     rx: UnboundedReceiver<Event>,
+    dropped_connections: Arc<AtomicUsize>,
 }
 
 impl MockEventLoop {
@@ -347,7 +415,17 @@ impl MockEventLoop {
     #[must_use]
     pub fn new() -> (Self, EventInjector) {
         let (tx, rx) = unbounded_channel();
-        (Self { rx }, EventInjector { tx })
+        let dropped_connections = Arc::new(AtomicUsize::new(0));
+        (
+            Self {
+                rx,
+                dropped_connections: dropped_connections.clone(),
+            },
+            EventInjector {
+                tx,
+                dropped_connections,
+            },
+        )
     }
 }
 
@@ -365,12 +443,30 @@ impl MqttEventLoop for MockEventLoop {
     fn set_authentication_method(&mut self, authentication_method: Option<String>) {}
 
     fn set_authentication_data(&mut self, authentication_data: Option<Bytes>) {}
+
+    fn set_connection_target(
+        &mut self,
+        _target: &ConnectionTarget,
+    ) -> Result<(), ConnectionTargetError> {
+        Ok(())
+    }
+
+    fn set_broker(&mut self, _hostname: String, _tcp_port: Option<u16>) {}
+
+    fn take_server_reference(&mut self) -> Option<String> {
+        None
+    }
+
+    fn drop_connection(&mut self) {
+        self.dropped_connections.fetch_add(1, Ordering::SeqCst);
+    }
 }
 
 /// Used to inject events into the [`MockEventLoop`].
 #[derive(Clone)]
 pub struct EventInjector {
     tx: UnboundedSender<Event>,
+    dropped_connections: Arc<AtomicUsize>,
 }
 
 impl EventInjector {
@@ -382,6 +478,12 @@ impl EventInjector {
     pub fn inject(&self, event: Event) -> Result<(), SendError<Event>> {
         self.tx.send(event)
     }
+
+    /// Get the number of times the connection of the [`MockEventLoop`] has been dropped
+    #[must_use]
+    pub fn dropped_connections(&self) -> usize {
+        self.dropped_connections.load(Ordering::SeqCst)
+    }
 }
 
 #[cfg(test)]
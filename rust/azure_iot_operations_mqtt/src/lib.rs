@@ -11,20 +11,29 @@
 pub use crate::connection_settings::{
     MqttConnectionSettings, MqttConnectionSettingsBuilder, MqttConnectionSettingsBuilderError,
 };
+pub use crate::settings_watcher::{
+    MqttConnectionSettingsWatcher, MqttConnectionSettingsWatcherError,
+};
 
 mod auth;
 mod connection_settings;
 pub mod control_packet;
 pub mod error;
 pub mod interface;
+pub mod runtime;
 pub mod session;
+mod settings_watcher;
 pub mod topic;
 
 // TODO: put behind `use-rumqttc` feature flag
 mod rumqttc_adapter;
 
+#[cfg(feature = "test-utils")]
+pub mod fault_injection;
 #[cfg(feature = "test-utils")]
 pub mod interface_mocks;
+#[cfg(feature = "test-utils")]
+pub mod recording;
 
 #[macro_use]
 extern crate derive_builder;
@@ -0,0 +1,1009 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Record and replay of MQTT traffic for deterministic tests.
+//!
+//! A [`RecordingClient`] and [`RecordingEventLoop`] can be layered over any [`MqttClient`] and
+//! [`MqttEventLoop`] implementation to capture the outgoing requests and incoming events of a
+//! [`Session`](session::session::Session) to a capture file. A [`ReplayEventLoop`] can later feed the
+//! captured incoming events back into a [`Session`](session::session::Session) created via
+//! [`Session::new_from_injection`](session::session::Session::new_from_injection), so that traffic captured
+//! once against a real broker can be replayed in unit tests without a broker.
+//!
+//! # Capture format
+//! Captures are written in a versioned binary format, consisting of the magic bytes `AIOMQCAP`
+//! and a big-endian `u16` format version, followed by any number of records. Each record is:
+//! * a `u8` record kind
+//! * a big-endian `u64` offset (in milliseconds) from the start of the capture
+//! * a big-endian `u32` length, followed by that many bytes of record data
+//!
+//! Incoming packets and outgoing requests are stored as encoded MQTT v5 control packets, so all
+//! properties are preserved.
+
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use rumqttc::v5::mqttbytes::v5::{
+    Disconnect, DisconnectReasonCode, Filter, Packet, PubAck, PubAckProperties, PubAckReason,
+    PubRec, PubRecProperties, PubRecReason, Subscribe, Unsubscribe,
+};
+use thiserror::Error;
+
+use crate::control_packet::{
+    AuthProperties, NackReasonCode, Publish, PublishProperties, QoS, SubscribeProperties,
+    SubscriptionOptions, UnsubscribeProperties,
+};
+use crate::error::{
+    AckError, ConnectionError, ConnectionTargetError, DisconnectError, PublishError, ReauthError,
+    SubscribeError, UnsubscribeError,
+};
+use crate::interface::{
+    CompletionToken, ConnectionTarget, Event, Incoming, MqttAck, MqttClient, MqttDisconnect,
+    MqttEventLoop, MqttPubSub, Outgoing,
+};
+use crate::rumqttc_adapter as adapter;
+use crate::session::{self, SessionConfigError, SessionOptions};
+
+/// Magic bytes at the start of every capture file
+const CAPTURE_MAGIC: &[u8; 8] = b"AIOMQCAP";
+/// Version of the capture format written by this crate
+pub const CAPTURE_FORMAT_VERSION: u16 = 1;
+
+const KIND_INCOMING: u8 = 1;
+const KIND_OUTGOING_EVENT: u8 = 2;
+const KIND_OUTGOING_REQUEST: u8 = 3;
+const KIND_CONNECTION_ERROR: u8 = 4;
+
+/// Error reading or writing a capture
+#[derive(Error, Debug)]
+pub enum CaptureError {
+    /// I/O error on the underlying capture file
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The capture was written with an unsupported format version
+    #[error("unsupported capture format version: {0}")]
+    UnsupportedVersion(u16),
+    /// The capture is malformed
+    #[error("invalid capture: {0}")]
+    InvalidFormat(String),
+}
+
+/// A single record of MQTT traffic in a capture
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureRecord {
+    /// Incoming packet yielded by the event loop
+    Incoming(Incoming),
+    /// Outgoing notification yielded by the event loop
+    OutgoingEvent(Outgoing),
+    /// Outgoing request made via the client, represented as the control packet it produces.
+    ///
+    /// Packet identifiers are not known at request time, and are thus always 0 for
+    /// SUBSCRIBE and UNSUBSCRIBE requests. `QoS` 1 and 2 PUBLISH requests are recorded with their
+    /// packet identifier once the event loop sends them.
+    OutgoingRequest(Packet),
+    /// Connection error yielded by the event loop
+    ConnectionError(String),
+}
+
+/// A [`CaptureRecord`] along with the time it was captured
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureEntry {
+    /// Time since the start of the capture
+    pub offset: Duration,
+    /// The captured record
+    pub record: CaptureRecord,
+}
+
+/// Shared writer for a capture. Cloned instances write to the same capture.
+#[derive(Clone)]
+pub struct CaptureWriter {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    start: Instant,
+    publishes: Arc<Mutex<PendingPublishes>>,
+}
+
+/// `QoS` 1 and 2 publishes that cannot be recorded until their packet identifier is known
+#[derive(Default)]
+struct PendingPublishes {
+    /// Publishes requested via the client but not yet sent by the event loop, in request order
+    unsent: VecDeque<Publish>,
+    /// Packet identifiers of recorded publishes that have not been acknowledged yet, so that
+    /// retransmissions are not mistaken for new publishes
+    unacked: HashSet<u16>,
+}
+
+impl CaptureWriter {
+    /// Create a new capture file at the provided path, replacing any existing file.
+    ///
+    /// # Errors
+    /// Returns a [`CaptureError`] if the file cannot be created or the header cannot be written.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, CaptureError> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Create a new capture written to the provided writer.
+    ///
+    /// # Errors
+    /// Returns a [`CaptureError`] if the header cannot be written.
+    pub fn new(mut writer: impl Write + Send + 'static) -> Result<Self, CaptureError> {
+        writer.write_all(CAPTURE_MAGIC)?;
+        writer.write_all(&CAPTURE_FORMAT_VERSION.to_be_bytes())?;
+        Ok(Self {
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+            start: Instant::now(),
+            publishes: Arc::new(Mutex::new(PendingPublishes::default())),
+        })
+    }
+
+    /// Write a record to the capture.
+    ///
+    /// # Errors
+    /// Returns a [`CaptureError`] if the record cannot be encoded or written.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn record(&self, record: &CaptureRecord) -> Result<(), CaptureError> {
+        let (kind, data) = encode_record(record)?;
+        let offset = u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX);
+        let len = u32::try_from(data.len())
+            .map_err(|_| CaptureError::InvalidFormat("record too large".to_string()))?;
+
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(&[kind])?;
+        writer.write_all(&offset.to_be_bytes())?;
+        writer.write_all(&len.to_be_bytes())?;
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    /// Flush all records written so far to the underlying writer.
+    ///
+    /// # Errors
+    /// Returns a [`CaptureError`] if the flush fails.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn flush(&self) -> Result<(), CaptureError> {
+        self.writer.lock().unwrap().flush()?;
+        Ok(())
+    }
+
+    /// Record, logging rather than returning any failure, so that recording never
+    /// interferes with the traffic being recorded.
+    fn record_or_log(&self, record: &CaptureRecord) {
+        if let Err(e) = self.record(record).and_then(|()| self.flush()) {
+            log::error!("Failed to record MQTT traffic: {e}");
+        }
+    }
+
+    /// Record a publish requested via the client. `QoS` 1 and 2 publishes are held until the
+    /// event loop sends them, as a PUBLISH packet cannot be encoded without a packet identifier.
+    fn record_publish_request(&self, publish: Publish) {
+        if publish.qos == QoS::AtMostOnce {
+            self.record_or_log(&CaptureRecord::OutgoingRequest(Packet::Publish(publish)));
+        } else {
+            self.publishes.lock().unwrap().unsent.push_back(publish);
+        }
+    }
+
+    /// Record the oldest unsent publish, now that the event loop has sent it with `pkid`
+    fn record_publish_sent(&self, pkid: u16) {
+        let publish = {
+            let mut publishes = self.publishes.lock().unwrap();
+            if pkid == 0 || !publishes.unacked.insert(pkid) {
+                // QoS 0, or a retransmission of an already recorded publish
+                return;
+            }
+            publishes.unsent.pop_front()
+        };
+        if let Some(mut publish) = publish {
+            publish.pkid = pkid;
+            self.record_or_log(&CaptureRecord::OutgoingRequest(Packet::Publish(publish)));
+        }
+    }
+
+    /// Forget a publish acknowledged by the server, so that its packet identifier can be reused
+    fn record_publish_acked(&self, pkid: u16) {
+        self.publishes.lock().unwrap().unacked.remove(&pkid);
+    }
+}
+
+/// Read all entries of a capture file at the provided path.
+///
+/// # Errors
+/// Returns a [`CaptureError`] if the file cannot be read or is not a valid capture.
+pub fn read_capture(path: impl AsRef<Path>) -> Result<Vec<CaptureEntry>, CaptureError> {
+    read_capture_from(BufReader::new(File::open(path)?))
+}
+
+/// Read all entries of a capture from the provided reader.
+///
+/// # Errors
+/// Returns a [`CaptureError`] if the capture cannot be read or is not valid.
+pub fn read_capture_from(mut reader: impl Read) -> Result<Vec<CaptureEntry>, CaptureError> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != CAPTURE_MAGIC {
+        return Err(CaptureError::InvalidFormat(
+            "missing capture header".to_string(),
+        ));
+    }
+    let mut version = [0u8; 2];
+    reader.read_exact(&mut version)?;
+    let version = u16::from_be_bytes(version);
+    if version != CAPTURE_FORMAT_VERSION {
+        return Err(CaptureError::UnsupportedVersion(version));
+    }
+
+    let mut entries = vec![];
+    loop {
+        let mut kind = [0u8; 1];
+        match reader.read_exact(&mut kind) {
+            Ok(()) => {}
+            // Clean end of capture
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let mut offset = [0u8; 8];
+        reader.read_exact(&mut offset)?;
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        let mut data = vec![0u8; u32::from_be_bytes(len) as usize];
+        reader.read_exact(&mut data)?;
+
+        entries.push(CaptureEntry {
+            offset: Duration::from_millis(u64::from_be_bytes(offset)),
+            record: decode_record(kind[0], data)?,
+        });
+    }
+    Ok(entries)
+}
+
+fn encode_record(record: &CaptureRecord) -> Result<(u8, Vec<u8>), CaptureError> {
+    match record {
+        CaptureRecord::Incoming(packet) => Ok((KIND_INCOMING, encode_packet(packet)?)),
+        CaptureRecord::OutgoingEvent(outgoing) => {
+            let (tag, pkid) = match outgoing {
+                Outgoing::Publish(pkid) => (1, *pkid),
+                Outgoing::Subscribe(pkid) => (2, *pkid),
+                Outgoing::Unsubscribe(pkid) => (3, *pkid),
+                Outgoing::PubAck(pkid) => (4, *pkid),
+                Outgoing::PubRec(pkid) => (5, *pkid),
+                Outgoing::PubRel(pkid) => (6, *pkid),
+                Outgoing::PubComp(pkid) => (7, *pkid),
+                Outgoing::PingReq => (8, 0),
+                Outgoing::PingResp => (9, 0),
+                Outgoing::Disconnect => (10, 0),
+                Outgoing::AwaitAck(pkid) => (11, *pkid),
+            };
+            let mut data = vec![tag];
+            data.extend_from_slice(&pkid.to_be_bytes());
+            Ok((KIND_OUTGOING_EVENT, data))
+        }
+        CaptureRecord::OutgoingRequest(packet) => {
+            Ok((KIND_OUTGOING_REQUEST, encode_packet(packet)?))
+        }
+        CaptureRecord::ConnectionError(description) => {
+            Ok((KIND_CONNECTION_ERROR, description.as_bytes().to_vec()))
+        }
+    }
+}
+
+fn decode_record(kind: u8, data: Vec<u8>) -> Result<CaptureRecord, CaptureError> {
+    match kind {
+        KIND_INCOMING => Ok(CaptureRecord::Incoming(decode_packet(&data)?)),
+        KIND_OUTGOING_EVENT => {
+            let [tag, pkid_hi, pkid_lo] = data[..] else {
+                return Err(CaptureError::InvalidFormat(
+                    "malformed outgoing event record".to_string(),
+                ));
+            };
+            let pkid = u16::from_be_bytes([pkid_hi, pkid_lo]);
+            let outgoing = match tag {
+                1 => Outgoing::Publish(pkid),
+                2 => Outgoing::Subscribe(pkid),
+                3 => Outgoing::Unsubscribe(pkid),
+                4 => Outgoing::PubAck(pkid),
+                5 => Outgoing::PubRec(pkid),
+                6 => Outgoing::PubRel(pkid),
+                7 => Outgoing::PubComp(pkid),
+                8 => Outgoing::PingReq,
+                9 => Outgoing::PingResp,
+                10 => Outgoing::Disconnect,
+                11 => Outgoing::AwaitAck(pkid),
+                _ => {
+                    return Err(CaptureError::InvalidFormat(format!(
+                        "unknown outgoing event tag: {tag}"
+                    )));
+                }
+            };
+            Ok(CaptureRecord::OutgoingEvent(outgoing))
+        }
+        KIND_OUTGOING_REQUEST => Ok(CaptureRecord::OutgoingRequest(decode_packet(&data)?)),
+        KIND_CONNECTION_ERROR => Ok(CaptureRecord::ConnectionError(
+            String::from_utf8(data).map_err(|e| CaptureError::InvalidFormat(e.to_string()))?,
+        )),
+        _ => Err(CaptureError::InvalidFormat(format!(
+            "unknown record kind: {kind}"
+        ))),
+    }
+}
+
+fn encode_packet(packet: &Packet) -> Result<Vec<u8>, CaptureError> {
+    let mut buf = BytesMut::new();
+    packet
+        .write(&mut buf)
+        .map_err(|e| CaptureError::InvalidFormat(format!("cannot encode packet: {e}")))?;
+    Ok(buf.to_vec())
+}
+
+fn decode_packet(data: &[u8]) -> Result<Packet, CaptureError> {
+    let mut buf = BytesMut::from(data);
+    Packet::read(&mut buf, None)
+        .map_err(|e| CaptureError::InvalidFormat(format!("cannot decode packet: {e}")))
+}
+
+/// Build the control packet that acknowledges the provided publish
+fn ack_packet(
+    publish: &Publish,
+    reason_code: Option<NackReasonCode>,
+    reason_string: Option<String>,
+) -> Packet {
+    if publish.qos == QoS::ExactlyOnce {
+        let mut pubrec = PubRec::new(
+            publish.pkid,
+            reason_string.map(|reason_string| PubRecProperties {
+                reason_string: Some(reason_string),
+                user_properties: vec![],
+            }),
+        );
+        if let Some(reason_code) = reason_code {
+            pubrec.reason = match reason_code {
+                NackReasonCode::UnspecifiedError => PubRecReason::UnspecifiedError,
+                NackReasonCode::ImplementationSpecificError => {
+                    PubRecReason::ImplementationSpecificError
+                }
+                NackReasonCode::NotAuthorized => PubRecReason::NotAuthorized,
+                NackReasonCode::TopicNameInvalid => PubRecReason::TopicNameInvalid,
+                NackReasonCode::QuotaExceeded => PubRecReason::QuotaExceeded,
+                NackReasonCode::PayloadFormatInvalid => PubRecReason::PayloadFormatInvalid,
+            };
+        }
+        Packet::PubRec(pubrec)
+    } else {
+        let mut puback = PubAck::new(
+            publish.pkid,
+            reason_string.map(|reason_string| PubAckProperties {
+                reason_string: Some(reason_string),
+                user_properties: vec![],
+            }),
+        );
+        if let Some(reason_code) = reason_code {
+            puback.reason = match reason_code {
+                NackReasonCode::UnspecifiedError => PubAckReason::UnspecifiedError,
+                NackReasonCode::ImplementationSpecificError => {
+                    PubAckReason::ImplementationSpecificError
+                }
+                NackReasonCode::NotAuthorized => PubAckReason::NotAuthorized,
+                NackReasonCode::TopicNameInvalid => PubAckReason::TopicNameInvalid,
+                NackReasonCode::QuotaExceeded => PubAckReason::QuotaExceeded,
+                NackReasonCode::PayloadFormatInvalid => PubAckReason::PayloadFormatInvalid,
+            };
+        }
+        Packet::PubAck(puback)
+    }
+}
+
+// ---------- Recording ----------
+
+/// [`MqttClient`] wrapper that records all outgoing requests made through it to a capture
+/// before forwarding them to the wrapped client.
+#[derive(Clone)]
+pub struct RecordingClient<C> {
+    inner: C,
+    writer: CaptureWriter,
+}
+
+impl<C> RecordingClient<C> {
+    /// Wrap the provided client, recording to the provided [`CaptureWriter`]
+    pub fn new(inner: C, writer: CaptureWriter) -> Self {
+        Self { inner, writer }
+    }
+
+    fn record_publish(
+        &self,
+        topic: &str,
+        qos: QoS,
+        retain: bool,
+        payload: &Bytes,
+        properties: Option<PublishProperties>,
+    ) {
+        let mut publish = Publish::new(topic, qos, payload.clone(), properties);
+        publish.retain = retain;
+        self.writer.record_publish_request(publish);
+    }
+
+    fn record_subscribe(&self, topic: &str, qos: QoS, properties: Option<SubscribeProperties>) {
+        self.record_subscribe_filter(Filter::new(topic, qos), properties);
+    }
+
+    fn record_subscribe_filter(&self, filter: Filter, properties: Option<SubscribeProperties>) {
+        let subscribe = Subscribe::new(filter, properties);
+        self.writer
+            .record_or_log(&CaptureRecord::OutgoingRequest(Packet::Subscribe(
+                subscribe,
+            )));
+    }
+
+    fn record_unsubscribe(&self, topic: &str, properties: Option<UnsubscribeProperties>) {
+        let unsubscribe = Unsubscribe::new(topic, properties);
+        self.writer
+            .record_or_log(&CaptureRecord::OutgoingRequest(Packet::Unsubscribe(
+                unsubscribe,
+            )));
+    }
+}
+
+#[async_trait]
+impl<C> MqttPubSub for RecordingClient<C>
+where
+    C: MqttPubSub + Send + Sync,
+{
+    async fn publish(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Bytes> + Send,
+    ) -> Result<CompletionToken, PublishError> {
+        let topic = topic.into();
+        let payload = payload.into();
+        self.record_publish(&topic, qos, retain, &payload, None);
+        self.inner.publish(topic, qos, retain, payload).await
+    }
+
+    async fn publish_with_properties(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Bytes> + Send,
+        properties: PublishProperties,
+    ) -> Result<CompletionToken, PublishError> {
+        let topic = topic.into();
+        let payload = payload.into();
+        self.record_publish(&topic, qos, retain, &payload, Some(properties.clone()));
+        self.inner
+            .publish_with_properties(topic, qos, retain, payload, properties)
+            .await
+    }
+
+    async fn subscribe(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+    ) -> Result<CompletionToken, SubscribeError> {
+        let topic = topic.into();
+        self.record_subscribe(&topic, qos, None);
+        self.inner.subscribe(topic, qos).await
+    }
+
+    async fn subscribe_with_properties(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        properties: SubscribeProperties,
+    ) -> Result<CompletionToken, SubscribeError> {
+        let topic = topic.into();
+        self.record_subscribe(&topic, qos, Some(properties.clone()));
+        self.inner
+            .subscribe_with_properties(topic, qos, properties)
+            .await
+    }
+
+    async fn subscribe_with_options(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        options: SubscriptionOptions,
+    ) -> Result<CompletionToken, SubscribeError> {
+        let topic = topic.into();
+        let filter = Filter {
+            path: topic.clone(),
+            qos,
+            nolocal: options.no_local,
+            preserve_retain: options.retain_as_published,
+            retain_forward_rule: options.retain_handling.into(),
+        };
+        let properties = options
+            .subscription_identifier
+            .map(|id| SubscribeProperties {
+                id: Some(id),
+                user_properties: vec![],
+            });
+        self.record_subscribe_filter(filter, properties);
+        self.inner.subscribe_with_options(topic, qos, options).await
+    }
+
+    async fn unsubscribe(
+        &self,
+        topic: impl Into<String> + Send,
+    ) -> Result<CompletionToken, UnsubscribeError> {
+        let topic = topic.into();
+        self.record_unsubscribe(&topic, None);
+        self.inner.unsubscribe(topic).await
+    }
+
+    async fn unsubscribe_with_properties(
+        &self,
+        topic: impl Into<String> + Send,
+        properties: UnsubscribeProperties,
+    ) -> Result<CompletionToken, UnsubscribeError> {
+        let topic = topic.into();
+        self.record_unsubscribe(&topic, Some(properties.clone()));
+        self.inner
+            .unsubscribe_with_properties(topic, properties)
+            .await
+    }
+}
+
+#[async_trait]
+impl<C> MqttAck for RecordingClient<C>
+where
+    C: MqttAck + Send + Sync,
+{
+    async fn ack(&self, publish: &Publish) -> Result<CompletionToken, AckError> {
+        self.writer
+            .record_or_log(&CaptureRecord::OutgoingRequest(ack_packet(
+                publish, None, None,
+            )));
+        self.inner.ack(publish).await
+    }
+
+    async fn nack(
+        &self,
+        publish: &Publish,
+        reason_code: NackReasonCode,
+        reason_string: Option<String>,
+    ) -> Result<CompletionToken, AckError> {
+        self.writer
+            .record_or_log(&CaptureRecord::OutgoingRequest(ack_packet(
+                publish,
+                Some(reason_code),
+                reason_string.clone(),
+            )));
+        self.inner.nack(publish, reason_code, reason_string).await
+    }
+}
+
+#[async_trait]
+impl<C> MqttDisconnect for RecordingClient<C>
+where
+    C: MqttDisconnect + Send + Sync,
+{
+    async fn disconnect(&self) -> Result<(), DisconnectError> {
+        self.writer
+            .record_or_log(&CaptureRecord::OutgoingRequest(Packet::Disconnect(
+                Disconnect::new(DisconnectReasonCode::NormalDisconnection),
+            )));
+        self.inner.disconnect().await
+    }
+}
+
+#[async_trait]
+impl<C> MqttClient for RecordingClient<C>
+where
+    C: MqttClient + Send + Sync,
+{
+    // NOTE: Reauthentication is not recorded, as the AUTH exchange contains credentials
+    async fn reauth(&self, auth_props: AuthProperties) -> Result<(), ReauthError> {
+        self.inner.reauth(auth_props).await
+    }
+}
+
+/// [`MqttEventLoop`] wrapper that records all events and connection errors yielded by the
+/// wrapped event loop to a capture.
+pub struct RecordingEventLoop<EL> {
+    inner: EL,
+    writer: CaptureWriter,
+}
+
+impl<EL> RecordingEventLoop<EL> {
+    /// Wrap the provided event loop, recording to the provided [`CaptureWriter`]
+    pub fn new(inner: EL, writer: CaptureWriter) -> Self {
+        Self { inner, writer }
+    }
+}
+
+#[async_trait]
+impl<EL> MqttEventLoop for RecordingEventLoop<EL>
+where
+    EL: MqttEventLoop + Send,
+{
+    async fn poll(&mut self) -> Result<Event, ConnectionError> {
+        let result = self.inner.poll().await;
+        match &result {
+            Ok(Event::Outgoing(Outgoing::Publish(pkid))) => self.writer.record_publish_sent(*pkid),
+            Ok(Event::Incoming(Incoming::PubAck(ack))) => {
+                self.writer.record_publish_acked(ack.pkid);
+            }
+            Ok(Event::Incoming(Incoming::PubComp(comp))) => {
+                self.writer.record_publish_acked(comp.pkid);
+            }
+            _ => {}
+        }
+        let record = match &result {
+            Ok(Event::Incoming(packet)) => CaptureRecord::Incoming(packet.clone()),
+            Ok(Event::Outgoing(outgoing)) => CaptureRecord::OutgoingEvent(outgoing.clone()),
+            Err(e) => CaptureRecord::ConnectionError(e.to_string()),
+        };
+        self.writer.record_or_log(&record);
+        result
+    }
+
+    fn set_clean_start(&mut self, clean_start: bool) {
+        self.inner.set_clean_start(clean_start);
+    }
+
+    fn set_authentication_method(&mut self, authentication_method: Option<String>) {
+        self.inner.set_authentication_method(authentication_method);
+    }
+
+    fn set_authentication_data(&mut self, authentication_data: Option<Bytes>) {
+        self.inner.set_authentication_data(authentication_data);
+    }
+
+    fn set_connection_target(
+        &mut self,
+        target: &ConnectionTarget,
+    ) -> Result<(), ConnectionTargetError> {
+        self.inner.set_connection_target(target)
+    }
+
+    fn set_broker(&mut self, hostname: String, tcp_port: Option<u16>) {
+        self.inner.set_broker(hostname, tcp_port);
+    }
+
+    fn take_server_reference(&mut self) -> Option<String> {
+        self.inner.take_server_reference()
+    }
+
+    fn drop_connection(&mut self) {
+        self.inner.drop_connection();
+    }
+}
+
+/// Create a new [`Session`](session::session::Session) connecting to a real MQTT broker with the
+/// provided options, recording all of its traffic to the provided [`CaptureWriter`].
+///
+/// # Errors
+/// Returns a [`SessionConfigError`] if there are errors using the session options.
+#[allow(clippy::type_complexity)]
+pub fn new_recording_session(
+    options: SessionOptions,
+    writer: CaptureWriter,
+) -> Result<
+    session::session::Session<
+        RecordingClient<adapter::ClientAlias>,
+        RecordingEventLoop<adapter::EventLoopAlias>,
+    >,
+    SessionConfigError,
+> {
+    session::new_session_with(options, |client, event_loop| {
+        (
+            RecordingClient::new(client, writer.clone()),
+            RecordingEventLoop::new(event_loop, writer),
+        )
+    })
+}
+
+// ---------- Replay ----------
+
+/// [`MqttEventLoop`] implementation that replays the incoming events and connection errors
+/// of a capture, in order.
+///
+/// Outgoing requests in the capture are not replayed, as they are expected to be made by the
+/// code under test. They remain accessible via [`ReplayEventLoop::outgoing_requests`] for
+/// comparison against the requests actually made.
+///
+/// Connection errors are replayed as [`ConnectionError::Io`] errors with the captured
+/// description. Once the capture is exhausted, [`ConnectionError::RequestsDone`] is returned.
+pub struct ReplayEventLoop {
+    entries: VecDeque<CaptureEntry>,
+    outgoing_requests: Vec<Packet>,
+    realtime: bool,
+    last_offset: Duration,
+}
+
+impl ReplayEventLoop {
+    /// Create a new [`ReplayEventLoop`] from previously read capture entries
+    #[must_use]
+    pub fn new(entries: Vec<CaptureEntry>) -> Self {
+        let outgoing_requests = entries
+            .iter()
+            .filter_map(|entry| match &entry.record {
+                CaptureRecord::OutgoingRequest(packet) => Some(packet.clone()),
+                _ => None,
+            })
+            .collect();
+        Self {
+            entries: entries.into(),
+            outgoing_requests,
+            realtime: false,
+            last_offset: Duration::ZERO,
+        }
+    }
+
+    /// Create a new [`ReplayEventLoop`] from the capture file at the provided path
+    ///
+    /// # Errors
+    /// Returns a [`CaptureError`] if the file cannot be read or is not a valid capture.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, CaptureError> {
+        Ok(Self::new(read_capture(path)?))
+    }
+
+    /// Replay events with the same relative timing as when they were captured.
+    /// By default, events are replayed as fast as they are polled.
+    #[must_use]
+    pub fn with_realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    /// The outgoing requests that were captured, in order
+    #[must_use]
+    pub fn outgoing_requests(&self) -> &[Packet] {
+        &self.outgoing_requests
+    }
+}
+
+#[async_trait]
+impl MqttEventLoop for ReplayEventLoop {
+    async fn poll(&mut self) -> Result<Event, ConnectionError> {
+        while let Some(entry) = self.entries.pop_front() {
+            let result = match entry.record {
+                CaptureRecord::Incoming(packet) => Ok(Event::Incoming(packet)),
+                CaptureRecord::OutgoingEvent(outgoing) => Ok(Event::Outgoing(outgoing)),
+                CaptureRecord::OutgoingRequest(_) => continue,
+                CaptureRecord::ConnectionError(description) => {
+                    Err(ConnectionError::Io(io::Error::other(description)))
+                }
+            };
+            if self.realtime {
+                crate::runtime::sleep(entry.offset.saturating_sub(self.last_offset)).await;
+            }
+            self.last_offset = entry.offset;
+            return result;
+        }
+        Err(ConnectionError::RequestsDone)
+    }
+
+    fn set_clean_start(&mut self, _clean_start: bool) {}
+
+    fn set_authentication_method(&mut self, _authentication_method: Option<String>) {}
+
+    fn set_authentication_data(&mut self, _authentication_data: Option<Bytes>) {}
+
+    fn set_connection_target(
+        &mut self,
+        _target: &ConnectionTarget,
+    ) -> Result<(), ConnectionTargetError> {
+        Ok(())
+    }
+
+    fn set_broker(&mut self, _hostname: String, _tcp_port: Option<u16>) {}
+
+    fn take_server_reference(&mut self) -> Option<String> {
+        None
+    }
+
+    fn drop_connection(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use rumqttc::v5::mqttbytes::v5::{ConnAck, ConnectReturnCode, SubAck, SubscribeReasonCode};
+
+    use super::*;
+    use crate::interface::ManagedClient;
+    use crate::interface::PubReceiver;
+    use crate::interface_mocks::{MockClient, MockEventLoop};
+    use crate::session::reconnect_policy::ExponentialBackoffWithJitter;
+
+    fn incoming_publish(pkid: u16) -> Publish {
+        let mut publish = Publish::new(
+            "test/topic",
+            QoS::AtLeastOnce,
+            "payload",
+            Some(PublishProperties {
+                content_type: Some("text/plain".to_string()),
+                user_properties: vec![("key".to_string(), "value".to_string())],
+                ..Default::default()
+            }),
+        );
+        publish.pkid = pkid;
+        publish
+    }
+
+    #[tokio::test]
+    async fn record_and_read_roundtrip() {
+        let capture_file = tempfile::NamedTempFile::new().unwrap();
+        let writer = CaptureWriter::create(capture_file.path()).unwrap();
+
+        let (event_loop, injector) = MockEventLoop::new();
+        let mut event_loop = RecordingEventLoop::new(event_loop, writer.clone());
+        let client = RecordingClient::new(MockClient::new(), writer.clone());
+
+        let connack = Incoming::ConnAck(ConnAck {
+            session_present: false,
+            code: ConnectReturnCode::Success,
+            properties: None,
+        });
+        let suback = Incoming::SubAck(SubAck {
+            pkid: 1,
+            return_codes: vec![SubscribeReasonCode::Success(QoS::AtLeastOnce)],
+            properties: None,
+        });
+        let publish = incoming_publish(2);
+
+        client
+            .subscribe("test/topic", QoS::AtLeastOnce)
+            .await
+            .unwrap();
+        for event in [
+            Event::Incoming(connack.clone()),
+            Event::Outgoing(Outgoing::Subscribe(1)),
+            Event::Incoming(suback.clone()),
+            Event::Incoming(Incoming::Publish(publish.clone())),
+        ] {
+            injector.inject(event).unwrap();
+            event_loop.poll().await.unwrap();
+        }
+        client
+            .nack(
+                &publish,
+                NackReasonCode::QuotaExceeded,
+                Some("busy".to_string()),
+            )
+            .await
+            .unwrap();
+        drop(injector);
+        assert!(event_loop.poll().await.is_err());
+
+        let records: Vec<CaptureRecord> = read_capture(capture_file.path())
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.record)
+            .collect();
+        assert_eq!(records.len(), 7);
+        assert!(matches!(
+            &records[0],
+            CaptureRecord::OutgoingRequest(Packet::Subscribe(s)) if s.filters[0].path == "test/topic"
+        ));
+        assert_eq!(records[1], CaptureRecord::Incoming(connack));
+        assert_eq!(
+            records[2],
+            CaptureRecord::OutgoingEvent(Outgoing::Subscribe(1))
+        );
+        assert_eq!(records[3], CaptureRecord::Incoming(suback));
+        assert_eq!(
+            records[4],
+            CaptureRecord::Incoming(Incoming::Publish(publish))
+        );
+        assert!(matches!(
+            &records[5],
+            CaptureRecord::OutgoingRequest(Packet::PubAck(p))
+                if p.pkid == 2 && p.reason == PubAckReason::QuotaExceeded
+        ));
+        assert!(matches!(records[6], CaptureRecord::ConnectionError(_)));
+    }
+
+    #[tokio::test]
+    async fn record_qos1_publish_roundtrip() {
+        let capture_file = tempfile::NamedTempFile::new().unwrap();
+        let writer = CaptureWriter::create(capture_file.path()).unwrap();
+
+        let (event_loop, injector) = MockEventLoop::new();
+        let mut event_loop = RecordingEventLoop::new(event_loop, writer.clone());
+        let client = RecordingClient::new(MockClient::new(), writer.clone());
+
+        client
+            .publish("test/topic", QoS::AtLeastOnce, false, "payload")
+            .await
+            .unwrap();
+        for event in [
+            Event::Outgoing(Outgoing::Publish(1)),
+            // Retransmission of the same publish after a reconnect
+            Event::Outgoing(Outgoing::Publish(1)),
+            Event::Incoming(Incoming::PubAck(PubAck::new(1, None))),
+        ] {
+            injector.inject(event).unwrap();
+            event_loop.poll().await.unwrap();
+        }
+
+        let records: Vec<CaptureRecord> = read_capture(capture_file.path())
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.record)
+            .collect();
+        assert_eq!(records.len(), 4);
+        assert!(matches!(
+            &records[0],
+            CaptureRecord::OutgoingRequest(Packet::Publish(p))
+                if p.pkid == 1 && p.qos == QoS::AtLeastOnce && p.topic == "test/topic" && p.payload == "payload"
+        ));
+        assert_eq!(
+            records[1],
+            CaptureRecord::OutgoingEvent(Outgoing::Publish(1))
+        );
+        assert_eq!(
+            records[2],
+            CaptureRecord::OutgoingEvent(Outgoing::Publish(1))
+        );
+        assert!(matches!(
+            &records[3],
+            CaptureRecord::Incoming(Incoming::PubAck(p)) if p.pkid == 1
+        ));
+    }
+
+    #[test]
+    fn read_unsupported_version() {
+        let mut capture = CAPTURE_MAGIC.to_vec();
+        capture.extend_from_slice(&(CAPTURE_FORMAT_VERSION + 1).to_be_bytes());
+        assert!(matches!(
+            read_capture_from(&capture[..]),
+            Err(CaptureError::UnsupportedVersion(v)) if v == CAPTURE_FORMAT_VERSION + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn replay_into_session() {
+        let publish = incoming_publish(1);
+        let entries = vec![
+            CaptureEntry {
+                offset: Duration::ZERO,
+                record: CaptureRecord::Incoming(Incoming::ConnAck(ConnAck {
+                    session_present: false,
+                    code: ConnectReturnCode::Success,
+                    properties: None,
+                })),
+            },
+            CaptureEntry {
+                offset: Duration::from_millis(1),
+                record: CaptureRecord::OutgoingRequest(Packet::Subscribe(Subscribe::new(
+                    Filter::new("test/topic", QoS::AtLeastOnce),
+                    None,
+                ))),
+            },
+            CaptureEntry {
+                offset: Duration::from_millis(2),
+                record: CaptureRecord::Incoming(Incoming::Publish(publish.clone())),
+            },
+        ];
+        let event_loop = ReplayEventLoop::new(entries);
+        assert_eq!(event_loop.outgoing_requests().len(), 1);
+
+        let session = session::session::Session::new_from_injection(
+            MockClient::new(),
+            event_loop,
+            Box::new(ExponentialBackoffWithJitter::default()),
+            "test_client_id".to_string(),
+            None,
+        );
+        let mut pub_receiver = session
+            .create_managed_client()
+            .create_filtered_pub_receiver("test/topic")
+            .unwrap();
+
+        tokio::select! {
+            received = pub_receiver.recv() => {
+                let received = received.unwrap();
+                assert_eq!(received.payload, publish.payload);
+                assert_eq!(received.properties, publish.properties);
+            }
+            _ = session.run() => panic!("session ended before replay was received"),
+        }
+    }
+}
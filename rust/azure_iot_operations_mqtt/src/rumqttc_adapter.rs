@@ -13,15 +13,17 @@ use thiserror::Error;
 
 use crate::connection_settings::MqttConnectionSettings;
 use crate::control_packet::{
-    AuthProperties, Publish, PublishProperties, QoS, SubscribeProperties, UnsubscribeProperties,
+    AuthProperties, MAX_SUBSCRIPTION_IDENTIFIER, NackReasonCode, Publish, PublishProperties, QoS,
+    RetainHandling, SubscribeProperties, SubscriptionOptions, UnsubscribeProperties,
 };
 use crate::error::{
-    AckError, AckErrorKind, ConnectionError, DisconnectError, DisconnectErrorKind, PublishError,
-    PublishErrorKind, ReauthError, ReauthErrorKind, SubscribeError, SubscribeErrorKind,
-    UnsubscribeError, UnsubscribeErrorKind,
+    AckError, AckErrorKind, ConnectionError, ConnectionTargetError, DisconnectError,
+    DisconnectErrorKind, PublishError, PublishErrorKind, ReauthError, ReauthErrorKind,
+    SubscribeError, SubscribeErrorKind, UnsubscribeError, UnsubscribeErrorKind,
 };
 use crate::interface::{
-    CompletionToken, Event, MqttAck, MqttClient, MqttDisconnect, MqttEventLoop, MqttPubSub,
+    CompletionToken, ConnectionTarget, Event, MqttAck, MqttClient, MqttDisconnect, MqttEventLoop,
+    MqttPubSub,
 };
 use crate::topic::{TopicFilter, TopicName};
 
@@ -175,6 +177,42 @@ impl MqttPubSub for rumqttc::v5::AsyncClient {
         Ok(CompletionToken(Box::new(nf.wait_async())))
     }
 
+    async fn subscribe_with_options(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        options: SubscriptionOptions,
+    ) -> Result<CompletionToken, SubscribeError> {
+        let topic = topic.into();
+        if !TopicFilter::is_valid_topic_filter(&topic) {
+            return Err(SubscribeError::new(SubscribeErrorKind::InvalidTopicFilter));
+        }
+        let filter = rumqttc::v5::mqttbytes::v5::Filter {
+            path: topic,
+            qos,
+            nolocal: options.no_local,
+            preserve_retain: options.retain_as_published,
+            retain_forward_rule: options.retain_handling.into(),
+        };
+        let nf = match options.subscription_identifier {
+            Some(id) => {
+                if !(1..=MAX_SUBSCRIPTION_IDENTIFIER).contains(&id) {
+                    return Err(SubscribeError::new(
+                        SubscribeErrorKind::InvalidSubscriptionIdentifier,
+                    ));
+                }
+                let properties = SubscribeProperties {
+                    id: Some(id),
+                    user_properties: vec![],
+                };
+                self.subscribe_many_with_properties(vec![filter], properties)
+                    .await?
+            }
+            None => self.subscribe_many(vec![filter]).await?,
+        };
+        Ok(CompletionToken(Box::new(nf.wait_async())))
+    }
+
     async fn unsubscribe(
         &self,
         topic: impl Into<String> + Send,
@@ -225,6 +263,49 @@ impl MqttAck for rumqttc::v5::AsyncClient {
         // correctness in QoS2 especially, but also QoS1 connection loss scenarios.
         Ok(CompletionToken(Box::new(async { Ok(()) })))
     }
+
+    async fn nack(
+        &self,
+        publish: &Publish,
+        reason_code: NackReasonCode,
+        reason_string: Option<String>,
+    ) -> Result<CompletionToken, AckError> {
+        let mut manual_ack = self.get_manual_ack(publish);
+        manual_ack.set_reason(reason_code.into());
+        if let Some(reason_string) = reason_string {
+            manual_ack.set_reason_string(reason_string);
+        }
+        self.manual_ack(manual_ack).await?;
+        // NOTE: As with ack, the CompletionToken is simulated, as rumqttc does not provide one.
+        Ok(CompletionToken(Box::new(async { Ok(()) })))
+    }
+}
+
+impl From<NackReasonCode> for rumqttc::v5::ManualAckReason {
+    fn from(reason_code: NackReasonCode) -> Self {
+        match reason_code {
+            NackReasonCode::UnspecifiedError => rumqttc::v5::ManualAckReason::UnspecifiedError,
+            NackReasonCode::ImplementationSpecificError => {
+                rumqttc::v5::ManualAckReason::ImplementationSpecificError
+            }
+            NackReasonCode::NotAuthorized => rumqttc::v5::ManualAckReason::NotAuthorized,
+            NackReasonCode::TopicNameInvalid => rumqttc::v5::ManualAckReason::TopicNameInvalid,
+            NackReasonCode::QuotaExceeded => rumqttc::v5::ManualAckReason::QuotaExceeded,
+            NackReasonCode::PayloadFormatInvalid => {
+                rumqttc::v5::ManualAckReason::PayloadFormatInvalid
+            }
+        }
+    }
+}
+
+impl From<RetainHandling> for rumqttc::v5::mqttbytes::v5::RetainForwardRule {
+    fn from(retain_handling: RetainHandling) -> Self {
+        match retain_handling {
+            RetainHandling::SendOnSubscribe => Self::OnEverySubscribe,
+            RetainHandling::SendOnNewSubscribe => Self::OnNewSubscribe,
+            RetainHandling::DoNotSend => Self::Never,
+        }
+    }
 }
 
 #[async_trait]
@@ -247,6 +328,11 @@ impl MqttEventLoop for rumqttc::v5::EventLoop {
         self.poll().await
     }
 
+    fn drop_connection(&mut self) {
+        // NOTE: Unacknowledged and unsent requests are retained, and sent after reconnecting
+        self.clean();
+    }
+
     fn set_clean_start(&mut self, clean_start: bool) {
         self.options.set_clean_start(clean_start);
     }
@@ -259,6 +345,81 @@ impl MqttEventLoop for rumqttc::v5::EventLoop {
     fn set_authentication_data(&mut self, authentication_data: Option<Bytes>) {
         self.options.set_authentication_data(authentication_data);
     }
+
+    fn set_connection_target(
+        &mut self,
+        target: &ConnectionTarget,
+    ) -> Result<(), ConnectionTargetError> {
+        // Build the transport first so that the current target is retained on error
+        let transport = if target.use_tls {
+            tls_config(
+                target.ca_file.clone(),
+                target.cert_file.clone(),
+                target.key_file.clone(),
+                target.key_password_file.clone(),
+            )
+            .map_err(|e| ConnectionTargetError::new(format!("tls config error: {e}")))?
+        } else {
+            Transport::Tcp
+        };
+        self.set_broker(target.hostname.clone(), Some(target.tcp_port));
+        self.options.set_transport(transport);
+        Ok(())
+    }
+
+    fn set_broker(&mut self, hostname: String, tcp_port: Option<u16>) {
+        // NOTE: rumqttc does not allow changing the broker address of existing MqttOptions, so
+        // new MqttOptions are created, copying all other values from the existing MqttOptions.
+        let tcp_port = tcp_port.unwrap_or_else(|| self.options.broker_address().1);
+        let mut mqtt_options =
+            rumqttc::v5::MqttOptions::new(self.options.client_id(), hostname, tcp_port);
+        mqtt_options
+            .set_transport(self.options.transport())
+            .set_clean_start(self.options.clean_start())
+            .set_request_channel_capacity(self.options.request_channel_capacity())
+            .set_pending_throttle(self.options.pending_throttle())
+            .set_connection_timeout(self.options.connection_timeout())
+            .set_manual_acks(self.options.manual_acks())
+            .set_network_options(self.options.network_options());
+        // NOTE: The keep alive may have been overridden by the broker with a value below the
+        // minimum that rumqttc accepts from the user, in which case the default is used until
+        // the broker overrides it again on connect.
+        if self.options.keep_alive() >= Duration::from_secs(5) {
+            mqtt_options.set_keep_alive(self.options.keep_alive());
+        }
+        if let Some((username, password)) = self.options.credentials() {
+            mqtt_options.set_credentials(username, password);
+        }
+        if let Some(last_will) = self.options.last_will() {
+            mqtt_options.set_last_will(last_will);
+        }
+        if let Some(connect_properties) = self.options.connect_properties() {
+            mqtt_options.set_connect_properties(connect_properties);
+        }
+        if let Some(limit) = self.options.get_outgoing_inflight_upper_limit() {
+            mqtt_options.set_outgoing_inflight_upper_limit(limit);
+        }
+        self.options = mqtt_options;
+    }
+
+    fn take_server_reference(&mut self) -> Option<String> {
+        // NOTE: rumqttc buffers a DISCONNECT received from the broker as an event before reporting
+        // the disconnect as an error. It is removed here so that it is not reported after
+        // reconnecting. rumqttc does not retain a CONNACK that refuses the connection, so the
+        // Server Reference of a refused connection is not available.
+        let index = self.state.events.iter().rposition(|event| {
+            matches!(
+                event,
+                rumqttc::v5::Event::Incoming(rumqttc::v5::Incoming::Disconnect(_))
+            )
+        })?;
+        match self.state.events.remove(index) {
+            Some(rumqttc::v5::Event::Incoming(rumqttc::v5::Incoming::Disconnect(disconnect))) => {
+                disconnect.properties.and_then(|p| p.server_reference)
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Client constructors + TLS
@@ -0,0 +1,171 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Abstraction over the async runtime used for spawning tasks and timers.
+//!
+//! The runtime is selected with cargo features:
+//! * `tokio-runtime` (default) - tasks are spawned on, and timers are driven by, the current
+//!   [tokio](https://docs.rs/tokio) runtime.
+//! * `smol-runtime` - tasks are spawned on the global [smol](https://docs.rs/smol) executor, and
+//!   timers are driven by [`async-io`](https://docs.rs/async-io). Only used when `tokio-runtime`
+//!   is not enabled.
+//!
+//! Synchronization primitives do not depend on a runtime, and neither does file watching, which
+//! is performed on dedicated threads.
+//!
+//! Only task spawning and timers are abstracted. See the crate README for the limitations of
+//! `smol-runtime`.
+
+use std::future::Future;
+use std::pin::{Pin, pin};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use thiserror::Error;
+
+#[cfg(not(any(feature = "tokio-runtime", feature = "smol-runtime")))]
+compile_error!("one of the `tokio-runtime` or `smol-runtime` features must be enabled");
+
+/// Error returned by [`timeout`] when the deadline elapses before the future completes.
+#[derive(Debug, Error, Clone, Copy, Eq, PartialEq)]
+#[error("deadline has elapsed")]
+pub struct Elapsed(());
+
+/// Handle to a task spawned with [`spawn`].
+///
+/// Awaiting the handle returns the output of the task. Dropping the handle detaches the task,
+/// which continues to run to completion.
+pub struct JoinHandle<T> {
+    #[cfg(feature = "tokio-runtime")]
+    inner: tokio::task::JoinHandle<T>,
+    #[cfg(all(feature = "smol-runtime", not(feature = "tokio-runtime")))]
+    inner: Option<smol::Task<T>>,
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    #[cfg(feature = "tokio-runtime")]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        Pin::new(&mut self.inner)
+            .poll(cx)
+            .map(|result| match result {
+                Ok(output) => output,
+                // Propagate panics of the task to the awaiting task, as other runtimes do
+                Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                Err(e) => panic!("Task was cancelled: {e}"),
+            })
+    }
+
+    #[cfg(all(feature = "smol-runtime", not(feature = "tokio-runtime")))]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let task = self
+            .inner
+            .as_mut()
+            .expect("Task is only taken when the handle is dropped");
+        Pin::new(task).poll(cx)
+    }
+}
+
+#[cfg(all(feature = "smol-runtime", not(feature = "tokio-runtime")))]
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        // smol cancels tasks when their handle is dropped, so detach to match tokio behavior
+        if let Some(task) = self.inner.take() {
+            task.detach();
+        }
+    }
+}
+
+/// Spawn a new task on the selected runtime.
+///
+/// # Panics
+/// With `tokio-runtime`, panics if called outside of a tokio runtime.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(feature = "tokio-runtime")]
+    {
+        JoinHandle {
+            inner: tokio::task::spawn(future),
+        }
+    }
+    #[cfg(all(feature = "smol-runtime", not(feature = "tokio-runtime")))]
+    {
+        JoinHandle {
+            inner: Some(smol::spawn(future)),
+        }
+    }
+}
+
+/// Run a blocking function on a thread where blocking is acceptable.
+///
+/// # Panics
+/// With `tokio-runtime`, panics if called outside of a tokio runtime.
+pub fn spawn_blocking<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    #[cfg(feature = "tokio-runtime")]
+    {
+        JoinHandle {
+            inner: tokio::task::spawn_blocking(f),
+        }
+    }
+    #[cfg(all(feature = "smol-runtime", not(feature = "tokio-runtime")))]
+    {
+        JoinHandle {
+            inner: Some(smol::spawn(smol::unblock(f))),
+        }
+    }
+}
+
+/// Wait until the provided duration has elapsed.
+pub async fn sleep(duration: Duration) {
+    #[cfg(feature = "tokio-runtime")]
+    tokio::time::sleep(duration).await;
+    #[cfg(all(feature = "smol-runtime", not(feature = "tokio-runtime")))]
+    smol::Timer::after(duration).await;
+}
+
+/// Require the provided future to complete before the provided duration has elapsed.
+///
+/// # Errors
+/// Returns [`Elapsed`] if the duration elapses before the future completes.
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    match future::select(pin!(future), pin!(sleep(duration))).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(((), _)) => Err(Elapsed(())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn spawn_and_join() {
+        assert_eq!(spawn(async { 1 + 1 }).await, 2);
+    }
+
+    #[tokio::test]
+    async fn spawn_blocking_and_join() {
+        assert_eq!(spawn_blocking(|| 1 + 1).await, 2);
+    }
+
+    #[tokio::test]
+    async fn timeout_completes() {
+        let result = timeout(Duration::from_secs(1), async { "done" }).await;
+        assert_eq!(result, Ok("done"));
+    }
+
+    #[tokio::test]
+    async fn timeout_elapses() {
+        let result = timeout(Duration::from_millis(10), future::pending::<()>()).await;
+        assert_eq!(result, Err(Elapsed(())));
+    }
+}
@@ -8,7 +8,11 @@
 //! * [`SessionManagedClient`] - Sends MQTT messages to the broker
 //! * [`SessionPubReceiver`] - Receives MQTT messages from the broker
 //! * [`SessionConnectionMonitor`] - Provides information about MQTT connection state
+//! * [`SessionAckMonitor`] - Provides information about stalled acknowledgements of received messages
+//! * [`SessionDuplicateMonitor`] - Provides information about suppressed duplicate deliveries
+//! * [`SessionPriorityMonitor`] - Provides information about the priority lanes of outgoing publishes
 //! * [`SessionExitHandle`] - Allows the user to exit the session gracefully
+//! * [`SessionPool`] - Manages multiple sessions to spread outgoing publishes across connections
 //!
 //! # [`Session`] lifespan
 //! Each instance of [`Session`] is single use - after configuring a [`Session`], and creating any
@@ -43,8 +47,12 @@
 //! [`SessionPubReceiver`] *before* subscribing to the topic filter.
 
 pub mod managed_client; // TODO: This really ought be private, but we need it public for testing
+mod pool;
+mod priority;
+mod rate_limit;
 pub(crate) mod receiver;
 pub mod reconnect_policy;
+mod redirect;
 #[doc(hidden)]
 #[allow(clippy::module_inception)]
 // This isn't ideal naming, but it'd be inconsistent otherwise.
@@ -59,6 +67,16 @@ use thiserror::Error;
 use crate::auth::SatAuthContextInitError;
 use crate::error::{ConnectionError, DisconnectError};
 use crate::rumqttc_adapter as adapter;
+use crate::settings_watcher::MqttConnectionSettingsWatcherError;
+pub use pool::{
+    HashByTopic, PublishDistribution, ReconnectPolicyFactory, RoundRobin, SessionPool,
+    SessionPoolConnectionMonitor, SessionPoolExitHandle, SessionPoolManagedClient,
+    SessionPoolOptions, SessionPoolOptionsBuilder, SessionPoolOptionsBuilderError,
+};
+pub use priority::{PriorityClassifier, PriorityLaneStats, PriorityLanesConfig, PublishPriority};
+pub use rate_limit::{RateLimit, RateLimitBehavior, RateLimitConfig};
+pub use receiver::{DeduplicationConfig, DeduplicationKey, StalledAck};
+pub use redirect::ServerRedirect;
 pub use wrapper::*;
 
 /// Error describing why a [`Session`] ended prematurely
@@ -66,12 +84,57 @@ pub use wrapper::*;
 #[error(transparent)]
 pub struct SessionError(#[from] SessionErrorRepr);
 
+impl SessionError {
+    /// Return the corresponding [`SessionErrorKind`] for this error
+    #[must_use]
+    pub fn kind(&self) -> SessionErrorKind {
+        match &self.0 {
+            SessionErrorRepr::SessionLost => SessionErrorKind::SessionLost,
+            SessionErrorRepr::SessionTakenOver => SessionErrorKind::SessionTakenOver,
+            SessionErrorRepr::RedirectLimitExceeded(_) => SessionErrorKind::RedirectLimitExceeded,
+            SessionErrorRepr::ConnectionError(_) => SessionErrorKind::ConnectionError,
+            SessionErrorRepr::ReconnectHalted => SessionErrorKind::ReconnectHalted,
+            SessionErrorRepr::ForceExit => SessionErrorKind::ForceExit,
+            SessionErrorRepr::IoError(_) => SessionErrorKind::IoError,
+            SessionErrorRepr::SatAuthError(_) => SessionErrorKind::SatAuthError,
+        }
+    }
+}
+
+/// An enumeration of categories of [`SessionError`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SessionErrorKind {
+    /// MQTT session was lost due to a connection error
+    SessionLost,
+    /// MQTT session was taken over by another client using the same client ID
+    SessionTakenOver,
+    /// The broker redirected to other brokers more times than allowed
+    RedirectLimitExceeded,
+    /// MQTT session was ended due to an unrecoverable connection error
+    ConnectionError,
+    /// Reconnect attempts were halted by the reconnect policy
+    ReconnectHalted,
+    /// The [`Session`] was ended by a user-initiated force exit
+    ForceExit,
+    /// The [`Session`] was ended by an IO error
+    IoError,
+    /// The [`Session`] was ended by an error in the SAT auth context
+    SatAuthError,
+}
+
 /// Internal error for [`Session`] runs.
 #[derive(Error, Debug)]
 enum SessionErrorRepr {
     /// MQTT session was lost due to a connection error.
     #[error("session state not present on broker after reconnect")]
     SessionLost,
+    /// MQTT session was taken over by another client using the same client ID.
+    /// Reconnecting would take the MQTT session back, so no reconnect is attempted.
+    #[error("session taken over by another client with the same client ID")]
+    SessionTakenOver,
+    /// The broker redirected to other brokers more times than allowed without a successful connection.
+    #[error("redirect limit exceeded ({0} redirects)")]
+    RedirectLimitExceeded(u32),
     /// MQTT session was ended due to an unrecoverable connection error
     #[error(transparent)]
     ConnectionError(#[from] ConnectionError),
@@ -92,7 +155,18 @@ enum SessionErrorRepr {
 /// Error configuring a [`Session`].
 #[derive(Error, Debug)]
 #[error(transparent)]
-pub struct SessionConfigError(#[from] adapter::MqttAdapterError);
+pub struct SessionConfigError(#[from] SessionConfigErrorRepr);
+
+/// Internal error for [`Session`] configuration.
+#[derive(Error, Debug)]
+enum SessionConfigErrorRepr {
+    /// The underlying MQTT client could not be created from the options.
+    #[error(transparent)]
+    AdapterError(#[from] adapter::MqttAdapterError),
+    /// The connection settings file mounts could not be watched.
+    #[error(transparent)]
+    SettingsWatcherError(#[from] MqttConnectionSettingsWatcherError),
+}
 
 /// Error type for exiting a [`Session`] using the [`SessionExitHandle`].
 #[derive(Error, Debug)]
@@ -8,12 +8,16 @@ use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use tokio::sync::OwnedSemaphorePermit;
 
 use crate::control_packet::{
-    Publish, PublishProperties, QoS, SubscribeProperties, UnsubscribeProperties,
+    Publish, PublishProperties, QoS, SubscribeProperties, SubscriptionOptions,
+    UnsubscribeProperties,
 };
 use crate::error::{PublishError, SubscribeError, UnsubscribeError};
 use crate::interface::{CompletionToken, ManagedClient, MqttPubSub, PubReceiver};
+use crate::session::priority::PriorityLanes;
+use crate::session::rate_limit::RateLimiter;
 use crate::session::receiver::{AckToken, PublishReceiverManager, PublishRx};
 use crate::topic::{TopicFilter, TopicParseError};
 
@@ -30,6 +34,10 @@ where
     pub(crate) pub_sub: PS,
     /// Manager for receivers
     pub(crate) receiver_manager: Arc<Mutex<PublishReceiverManager>>,
+    /// Priority lanes for outgoing publishes, if configured
+    pub(crate) priority_lanes: Option<Arc<PriorityLanes>>,
+    /// Rate limiter for outgoing publishes, if configured
+    pub(crate) rate_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl<PS> ManagedClient for SessionManagedClient<PS>
@@ -63,6 +71,18 @@ where
             .create_unfiltered_receiver();
         SessionPubReceiver { pub_rx }
     }
+
+    fn create_subscription_pub_receiver(
+        &self,
+        subscription_identifier: usize,
+    ) -> SessionPubReceiver {
+        let pub_rx = self
+            .receiver_manager
+            .lock()
+            .unwrap()
+            .create_subscription_receiver(subscription_identifier);
+        SessionPubReceiver { pub_rx }
+    }
 }
 
 #[async_trait]
@@ -77,7 +97,19 @@ where
         retain: bool,
         payload: impl Into<Bytes> + Send,
     ) -> Result<CompletionToken, PublishError> {
-        self.pub_sub.publish(topic, qos, retain, payload).await
+        let topic = topic.into();
+        let payload = payload.into();
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(&topic, payload.len()).await?;
+        }
+        let Some(priority_lanes) = &self.priority_lanes else {
+            return self.pub_sub.publish(topic, qos, retain, payload).await;
+        };
+        let priority = priority_lanes.classify(&topic, &PublishProperties::default());
+        // The permit is released once the publish has been handed off to the event loop
+        let mut permit = priority_lanes.acquire(priority).await;
+        let ct = self.pub_sub.publish(topic, qos, retain, payload).await?;
+        Ok(hold_until_complete(ct, permit.take_slot()))
     }
 
     async fn publish_with_properties(
@@ -88,9 +120,25 @@ where
         payload: impl Into<Bytes> + Send,
         properties: PublishProperties,
     ) -> Result<CompletionToken, PublishError> {
-        self.pub_sub
+        let topic = topic.into();
+        let payload = payload.into();
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(&topic, payload.len()).await?;
+        }
+        let Some(priority_lanes) = &self.priority_lanes else {
+            return self
+                .pub_sub
+                .publish_with_properties(topic, qos, retain, payload, properties)
+                .await;
+        };
+        let priority = priority_lanes.classify(&topic, &properties);
+        // The permit is released once the publish has been handed off to the event loop
+        let mut permit = priority_lanes.acquire(priority).await;
+        let ct = self
+            .pub_sub
             .publish_with_properties(topic, qos, retain, payload, properties)
-            .await
+            .await?;
+        Ok(hold_until_complete(ct, permit.take_slot()))
     }
 
     async fn subscribe(
@@ -112,6 +160,17 @@ where
             .await
     }
 
+    async fn subscribe_with_options(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        options: SubscriptionOptions,
+    ) -> Result<CompletionToken, SubscribeError> {
+        self.pub_sub
+            .subscribe_with_options(topic, qos, options)
+            .await
+    }
+
     async fn unsubscribe(
         &self,
         topic: impl Into<String> + Send,
@@ -130,6 +189,19 @@ where
     }
 }
 
+/// Hold the slot a publish occupies in its priority lane, if any, until the publish completes
+/// (or its [`CompletionToken`] is dropped).
+fn hold_until_complete(ct: CompletionToken, slot: Option<OwnedSemaphorePermit>) -> CompletionToken {
+    match slot {
+        Some(slot) => CompletionToken(Box::new(async move {
+            let result = ct.await;
+            drop(slot);
+            result
+        })),
+        None => ct,
+    }
+}
+
 /// Receive and acknowledge incoming MQTT messages.
 pub struct SessionPubReceiver {
     /// Receiver for incoming publishes
@@ -0,0 +1,565 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Pool of [`Session`]s for spreading outgoing publishes across multiple MQTT connections.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::future::{self, FutureExt};
+
+use crate::control_packet::{
+    PublishProperties, QoS, SubscribeProperties, SubscriptionOptions, UnsubscribeProperties,
+};
+use crate::error::{PublishError, SubscribeError, UnsubscribeError};
+use crate::interface::{CompletionToken, ManagedClient, MqttPubSub};
+use crate::session::reconnect_policy::{ExponentialBackoffWithJitter, ReconnectPolicy};
+use crate::session::session;
+use crate::session::{
+    DeduplicationConfig, PriorityLanesConfig, RateLimitConfig, Session, SessionConfigError,
+    SessionConfigErrorRepr, SessionConnectionMonitor, SessionError, SessionExitError,
+    SessionExitHandle, SessionManagedClient, SessionOptions, SessionPubReceiver,
+};
+use crate::topic::TopicParseError;
+use crate::{MqttConnectionSettings, MqttConnectionSettingsWatcher};
+
+/// Maximum length of a client ID that brokers are required to accept.
+/// See: MQTT 5.0 spec, 3.1.3.1
+const MAX_GUARANTEED_CLIENT_ID_LENGTH: usize = 23;
+
+/// Number of hexadecimal digits of the hash of the configured client ID used in a shortened
+/// derived client ID
+const CLIENT_ID_HASH_LENGTH: usize = 8;
+
+/// Strategy for selecting which [`Session`] of a [`SessionPool`] an outgoing publish is sent on.
+pub trait PublishDistribution: Send + Sync {
+    /// Select the index of the [`Session`] to send a publish on the provided topic on.
+    /// Must be less than `pool_size`.
+    fn select(&self, topic: &str, pool_size: usize) -> usize;
+}
+
+/// Distributes publishes evenly across all sessions in turn.
+///
+/// Publishes on the same topic may be sent on different connections, and thus may not be
+/// delivered in the order they were sent.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl PublishDistribution for RoundRobin {
+    fn select(&self, _topic: &str, pool_size: usize) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % pool_size
+    }
+}
+
+/// Distributes publishes across sessions by a hash of the topic.
+///
+/// All publishes on the same topic are sent on the same connection, preserving their order.
+#[derive(Debug, Default)]
+pub struct HashByTopic;
+
+impl PublishDistribution for HashByTopic {
+    fn select(&self, topic: &str, pool_size: usize) -> usize {
+        let mut hasher = DefaultHasher::new();
+        topic.hash(&mut hasher);
+        // NOTE: Truncation is fine, as this is only used to select an index
+        #[allow(clippy::cast_possible_truncation)]
+        let hash = hasher.finish() as usize;
+        hash % pool_size
+    }
+}
+
+/// Factory for the [`ReconnectPolicy`] of each [`Session`] in a [`SessionPool`]
+pub type ReconnectPolicyFactory = Arc<dyn Fn() -> Box<dyn ReconnectPolicy> + Send + Sync>;
+
+/// Options for configuring a new [`SessionPool`]
+#[derive(Builder)]
+#[builder(pattern = "owned", build_fn(validate = "Self::validate"))]
+pub struct SessionPoolOptions {
+    /// MQTT Connection Settings for configuring the sessions of the [`SessionPool`].
+    /// The client ID of each session is derived from the client ID of these settings.
+    pub connection_settings: MqttConnectionSettings,
+    /// Number of sessions (and thus MQTT connections) in the [`SessionPool`]
+    #[builder(default = "2")]
+    pub pool_size: usize,
+    /// Strategy for distributing outgoing publishes across the sessions
+    #[builder(default = "Arc::new(RoundRobin::default())")]
+    pub distribution: Arc<dyn PublishDistribution>,
+    /// Factory for the Reconnect Policy to be used by each session
+    #[builder(
+        default = "Arc::new(|| Box::new(ExponentialBackoffWithJitter::default()) as Box<dyn ReconnectPolicy>)"
+    )]
+    pub reconnect_policy: ReconnectPolicyFactory,
+    /// Maximum number of queued outgoing messages not yet accepted by each MQTT session
+    #[builder(default = "100")]
+    pub outgoing_max: usize,
+    /// Indicates if the sessions should use features specific for use with the AIO MQTT Broker
+    #[builder(default = "true")]
+    pub aio_broker_features: bool,
+    /// Maximum duration a message received by a session may go unacknowledged before it is
+    /// reported as stalled. If `None`, stalled acknowledgements are not monitored.
+    /// See [`SessionOptions::ack_timeout`].
+    #[builder(default = "None")]
+    pub ack_timeout: Option<Duration>,
+    /// Indicates if a received message whose acknowledgement has stalled beyond the `ack_timeout`
+    /// should be forcibly acknowledged. Has no effect if `ack_timeout` is `None`.
+    #[builder(default = "false")]
+    pub force_ack_on_timeout: bool,
+    /// Configuration for suppressing redelivered duplicates of received messages.
+    /// If `None`, all received messages are delivered.
+    #[builder(default = "None")]
+    pub deduplication: Option<DeduplicationConfig>,
+    /// Indicates if each session should watch the connection settings file mounts, and gracefully
+    /// reconnect using the updated settings when they change.
+    /// See [`MqttConnectionSettingsWatcher`].
+    #[builder(default = "false")]
+    pub watch_connection_settings: bool,
+    /// Maximum number of consecutive broker redirects that each session will follow without a
+    /// successful connection before it ends.
+    #[builder(default = "session::DEFAULT_MAX_REDIRECTS")]
+    pub max_redirects: u32,
+    /// Configuration for sending the outgoing messages of each session in priority lanes.
+    /// If `None`, outgoing messages are queued in the order they are sent.
+    #[builder(default = "None")]
+    pub priority_lanes: Option<PriorityLanesConfig>,
+    /// Configuration for rate limiting the outgoing messages of each session.
+    /// Each session is rate limited separately, so the rate limits of the whole [`SessionPool`]
+    /// are `pool_size` times those configured.
+    /// If `None`, outgoing messages are not rate limited.
+    #[builder(default = "None")]
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+impl SessionPoolOptionsBuilder {
+    /// Validate the Session Pool Options.
+    ///
+    /// # Errors
+    /// Returns a `String` describing the error if the fields contain invalid values
+    fn validate(&self) -> Result<(), String> {
+        if self.pool_size == Some(0) {
+            return Err("pool_size must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Pool of [`Session`]s, each with its own MQTT connection, that can be used to send a higher
+/// throughput of publishes than a single [`Session`] allows.
+///
+/// Each session uses a client ID derived from the configured client ID by appending the index of
+/// the session (e.g. `myclient-0`, `myclient-1`). If the derived client ID would be longer than
+/// brokers are required to accept, the configured client ID is shortened and a hash of it is
+/// included instead (e.g. `myveryveryver9f3c02a1-0`). Outgoing publishes are spread across the
+/// sessions according to the configured [`PublishDistribution`]. Subscriptions and incoming
+/// publishes are handled solely by the first session of the pool.
+pub struct SessionPool {
+    sessions: Vec<Session>,
+    distribution: Arc<dyn PublishDistribution>,
+}
+
+impl SessionPool {
+    /// Create a new [`SessionPool`] with the provided options structure.
+    ///
+    /// # Errors
+    /// Returns a [`SessionConfigError`] if there are errors using the session pool options.
+    pub fn new(options: SessionPoolOptions) -> Result<Self, SessionConfigError> {
+        let sessions = (0..options.pool_size)
+            .map(|i| {
+                let mut connection_settings = options.connection_settings.clone();
+                connection_settings.client_id = pooled_client_id(&connection_settings.client_id, i);
+                let connection_settings_watcher = if options.watch_connection_settings {
+                    Some(
+                        MqttConnectionSettingsWatcher::new(connection_settings.clone())
+                            .map_err(SessionConfigErrorRepr::from)?,
+                    )
+                } else {
+                    None
+                };
+                Session::new(SessionOptions {
+                    connection_settings,
+                    reconnect_policy: (options.reconnect_policy)(),
+                    outgoing_max: options.outgoing_max,
+                    aio_broker_features: options.aio_broker_features,
+                    ack_timeout: options.ack_timeout,
+                    force_ack_on_timeout: options.force_ack_on_timeout,
+                    deduplication: options.deduplication.clone(),
+                    connection_settings_watcher,
+                    max_redirects: options.max_redirects,
+                    priority_lanes: options.priority_lanes.clone(),
+                    rate_limit: options.rate_limit.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            sessions,
+            distribution: options.distribution,
+        })
+    }
+
+    /// Return a new instance of [`SessionPoolExitHandle`] that can be used to end all sessions
+    /// of this [`SessionPool`]
+    pub fn create_exit_handle(&self) -> SessionPoolExitHandle {
+        SessionPoolExitHandle(
+            self.sessions
+                .iter()
+                .map(Session::create_exit_handle)
+                .collect(),
+        )
+    }
+
+    /// Return a new instance of [`SessionPoolConnectionMonitor`] that can be used to monitor the
+    /// aggregate connection state of all sessions of this [`SessionPool`]
+    pub fn create_connection_monitor(&self) -> SessionPoolConnectionMonitor {
+        SessionPoolConnectionMonitor(
+            self.sessions
+                .iter()
+                .map(Session::create_connection_monitor)
+                .collect(),
+        )
+    }
+
+    /// Return a new instance of [`SessionPoolManagedClient`] that can be used to send and
+    /// receive messages
+    pub fn create_managed_client(&self) -> SessionPoolManagedClient {
+        SessionPoolManagedClient {
+            clients: self
+                .sessions
+                .iter()
+                .map(Session::create_managed_client)
+                .collect::<Vec<_>>()
+                .into(),
+            distribution: self.distribution.clone(),
+        }
+    }
+
+    /// Begin running all sessions of the [`SessionPool`].
+    ///
+    /// Blocks until all sessions have exited, or until any session encounters a fatal error,
+    /// in which case the remaining sessions are ended without a graceful exit.
+    ///
+    /// # Errors
+    /// Returns a [`SessionError`] if any session encounters a fatal error and ends.
+    pub async fn run(self) -> Result<(), SessionError> {
+        future::try_join_all(self.sessions.into_iter().map(Session::run)).await?;
+        Ok(())
+    }
+}
+
+/// Derive the client ID of the session at the provided index of a [`SessionPool`].
+///
+/// If appending the index would make the client ID longer than brokers are required to accept,
+/// the configured client ID is truncated and a hash of it is inserted before the index, so that
+/// pools with configured client IDs that share a prefix do not use the same client IDs.
+fn pooled_client_id(client_id: &str, index: usize) -> String {
+    let suffix = format!("-{index}");
+    if client_id.len() + suffix.len() <= MAX_GUARANTEED_CLIENT_ID_LENGTH {
+        return format!("{client_id}{suffix}");
+    }
+    // NOTE: FNV-1a is used rather than the std hasher, as the derived client ID must be stable
+    // across releases in order for the MQTT sessions to be resumed.
+    let hash = client_id
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+    let hash = format!("{hash:016x}");
+    let prefix_len =
+        MAX_GUARANTEED_CLIENT_ID_LENGTH.saturating_sub(CLIENT_ID_HASH_LENGTH + suffix.len());
+    let prefix: String = client_id
+        .char_indices()
+        .take_while(|(i, c)| i + c.len_utf8() <= prefix_len)
+        .map(|(_, c)| c)
+        .collect();
+    format!("{prefix}{}{suffix}", &hash[..CLIENT_ID_HASH_LENGTH])
+}
+
+/// Handle used to end all sessions of a [`SessionPool`].
+#[derive(Clone)]
+pub struct SessionPoolExitHandle(Vec<SessionExitHandle>);
+
+impl SessionPoolExitHandle {
+    /// Attempt to gracefully end all sessions of the [`SessionPool`] that created this handle.
+    ///
+    /// An attempt is made for every session, even if an attempt for another session fails.
+    /// See [`SessionExitHandle::try_exit`] for more details.
+    ///
+    /// # Errors
+    /// Returns the first [`SessionExitError`] encountered, if any attempt fails.
+    pub async fn try_exit(&self) -> Result<(), SessionExitError> {
+        let results = future::join_all(self.0.iter().map(SessionExitHandle::try_exit)).await;
+        results.into_iter().collect()
+    }
+
+    /// Forcefully end all sessions of the [`SessionPool`] that created this handle.
+    /// See [`SessionExitHandle::exit_force`] for more details.
+    ///
+    /// Returns true if all exits were graceful, and false if any exit was forced.
+    pub async fn exit_force(&self) -> bool {
+        future::join_all(self.0.iter().map(SessionExitHandle::exit_force))
+            .await
+            .into_iter()
+            .all(|graceful| graceful)
+    }
+}
+
+/// Monitor for the aggregate connection state of all sessions of a [`SessionPool`].
+#[derive(Clone)]
+pub struct SessionPoolConnectionMonitor(Vec<SessionConnectionMonitor>);
+
+impl SessionPoolConnectionMonitor {
+    /// Returns the number of sessions that are currently connected.
+    /// Note that this may not be accurate if connection has been recently lost.
+    #[must_use]
+    pub fn connected_count(&self) -> usize {
+        self.0.iter().filter(|m| m.is_connected()).count()
+    }
+
+    /// Returns true if all sessions are currently connected.
+    /// Note that this may not be accurate if connection has been recently lost.
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.0.iter().all(SessionConnectionMonitor::is_connected)
+    }
+
+    /// Wait until all sessions are connected.
+    /// Returns immediately if already connected.
+    pub async fn connected(&self) {
+        future::join_all(self.0.iter().map(SessionConnectionMonitor::connected)).await;
+    }
+
+    /// Wait until any session is disconnected.
+    /// Returns immediately if any is already disconnected.
+    pub async fn disconnected(&self) {
+        future::select_all(self.0.iter().map(|m| m.disconnected().boxed())).await;
+    }
+}
+
+/// An MQTT client that spreads outgoing publishes across the sessions of a [`SessionPool`].
+/// Can be used to send messages and create receivers for incoming messages.
+#[derive(Clone)]
+pub struct SessionPoolManagedClient {
+    clients: Arc<[SessionManagedClient]>,
+    distribution: Arc<dyn PublishDistribution>,
+}
+
+impl SessionPoolManagedClient {
+    /// Get the client used for publishes on the provided topic
+    fn publish_client(&self, topic: &str) -> &SessionManagedClient {
+        let index = self.distribution.select(topic, self.clients.len());
+        &self.clients[index % self.clients.len()]
+    }
+
+    /// Get the client used for subscriptions and incoming publishes
+    fn primary_client(&self) -> &SessionManagedClient {
+        &self.clients[0]
+    }
+}
+
+impl ManagedClient for SessionPoolManagedClient {
+    type PubReceiver = SessionPubReceiver;
+
+    /// Get the client id of the first session of the pool, which handles subscriptions
+    fn client_id(&self) -> &str {
+        self.primary_client().client_id()
+    }
+
+    fn create_filtered_pub_receiver(
+        &self,
+        topic_filter: &str,
+    ) -> Result<SessionPubReceiver, TopicParseError> {
+        self.primary_client()
+            .create_filtered_pub_receiver(topic_filter)
+    }
+
+    fn create_unfiltered_pub_receiver(&self) -> SessionPubReceiver {
+        self.primary_client().create_unfiltered_pub_receiver()
+    }
+
+    fn create_subscription_pub_receiver(
+        &self,
+        subscription_identifier: usize,
+    ) -> SessionPubReceiver {
+        self.primary_client()
+            .create_subscription_pub_receiver(subscription_identifier)
+    }
+}
+
+#[async_trait]
+impl MqttPubSub for SessionPoolManagedClient {
+    async fn publish(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Bytes> + Send,
+    ) -> Result<CompletionToken, PublishError> {
+        let topic = topic.into();
+        self.publish_client(&topic)
+            .publish(topic, qos, retain, payload)
+            .await
+    }
+
+    async fn publish_with_properties(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Bytes> + Send,
+        properties: PublishProperties,
+    ) -> Result<CompletionToken, PublishError> {
+        let topic = topic.into();
+        self.publish_client(&topic)
+            .publish_with_properties(topic, qos, retain, payload, properties)
+            .await
+    }
+
+    async fn subscribe(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+    ) -> Result<CompletionToken, SubscribeError> {
+        self.primary_client().subscribe(topic, qos).await
+    }
+
+    async fn subscribe_with_properties(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        properties: SubscribeProperties,
+    ) -> Result<CompletionToken, SubscribeError> {
+        self.primary_client()
+            .subscribe_with_properties(topic, qos, properties)
+            .await
+    }
+
+    async fn subscribe_with_options(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        options: SubscriptionOptions,
+    ) -> Result<CompletionToken, SubscribeError> {
+        self.primary_client()
+            .subscribe_with_options(topic, qos, options)
+            .await
+    }
+
+    async fn unsubscribe(
+        &self,
+        topic: impl Into<String> + Send,
+    ) -> Result<CompletionToken, UnsubscribeError> {
+        self.primary_client().unsubscribe(topic).await
+    }
+
+    async fn unsubscribe_with_properties(
+        &self,
+        topic: impl Into<String> + Send,
+        properties: UnsubscribeProperties,
+    ) -> Result<CompletionToken, UnsubscribeError> {
+        self.primary_client()
+            .unsubscribe_with_properties(topic, properties)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::MqttConnectionSettingsBuilder;
+
+    #[test]
+    fn round_robin_distribution() {
+        let distribution = RoundRobin::default();
+        let selected: Vec<usize> = (0..6)
+            .map(|_| distribution.select("some/topic", 3))
+            .collect();
+        assert_eq!(selected, vec![0, 1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn hash_by_topic_distribution() {
+        let distribution = HashByTopic;
+        // Same topic is always sent on the same session
+        let first = distribution.select("some/topic", 4);
+        assert!(first < 4);
+        for _ in 0..10 {
+            assert_eq!(distribution.select("some/topic", 4), first);
+        }
+        // Different topics are spread across sessions
+        let selected: std::collections::HashSet<usize> = (0..100)
+            .map(|i| distribution.select(&format!("topic/{i}"), 4))
+            .collect();
+        assert!(selected.len() > 1);
+    }
+
+    // NOTE: Sessions must be created and dropped within a tokio runtime
+    #[tokio::test]
+    async fn derived_client_ids() {
+        let connection_settings = MqttConnectionSettingsBuilder::default()
+            .client_id("test_client_id")
+            .hostname("test_host")
+            .use_tls(false)
+            .build()
+            .unwrap();
+        let options = SessionPoolOptionsBuilder::default()
+            .connection_settings(connection_settings)
+            .pool_size(3usize)
+            .build()
+            .unwrap();
+        let pool = SessionPool::new(options).unwrap();
+        let client = pool.create_managed_client();
+        let client_ids: Vec<&str> = client
+            .clients
+            .iter()
+            .map(ManagedClient::client_id)
+            .collect();
+        assert_eq!(
+            client_ids,
+            vec!["test_client_id-0", "test_client_id-1", "test_client_id-2"]
+        );
+        assert_eq!(client.client_id(), "test_client_id-0");
+    }
+
+    #[test_case("test_client_id", 0, "test_client_id-0"; "short")]
+    #[test_case("test_client_id_123456", 9, "test_client_id_123456-9"; "longest unshortened")]
+    #[test_case("test_client_id_1234567", 0, "test_client_ia32471a5-0"; "shortened")]
+    #[test_case("test_client_id_1234567", 12, "test_client_a32471a5-12"; "shortened two digit index")]
+    fn pooled_client_id_length(client_id: &str, index: usize, expected: &str) {
+        let pooled = pooled_client_id(client_id, index);
+        assert_eq!(pooled, expected);
+        assert!(pooled.len() <= MAX_GUARANTEED_CLIENT_ID_LENGTH);
+    }
+
+    #[test]
+    fn shortened_client_ids_differ() {
+        // Configured client IDs that share a truncated prefix still derive different client IDs
+        assert_ne!(
+            pooled_client_id("test_client_id_12345678", 0),
+            pooled_client_id("test_client_id_87654321", 0)
+        );
+    }
+
+    #[test]
+    fn zero_pool_size() {
+        let connection_settings = MqttConnectionSettingsBuilder::default()
+            .client_id("test_client_id")
+            .hostname("test_host")
+            .use_tls(false)
+            .build()
+            .unwrap();
+        assert!(
+            SessionPoolOptionsBuilder::default()
+                .connection_settings(connection_settings)
+                .pool_size(0usize)
+                .build()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,466 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Priority lanes for outgoing publishes.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, oneshot};
+
+use crate::control_packet::PublishProperties;
+
+/// Priority class of an outgoing publish
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum PublishPriority {
+    /// Latency sensitive traffic (e.g. RPC requests and responses)
+    Control,
+    /// Throughput oriented traffic (e.g. telemetry)
+    Bulk,
+}
+
+/// Classification of outgoing publishes into a [`PublishPriority`]
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub enum PriorityClassifier {
+    /// RPC requests (publishes with a response topic) and RPC responses (publishes with any of
+    /// the provided user properties) are [`PublishPriority::Control`].
+    /// All other publishes are [`PublishPriority::Bulk`].
+    Rpc {
+        /// Names of the user properties that identify RPC responses
+        response_properties: Vec<String>,
+    },
+    /// Publishes are classified by an application provided function of the topic name and
+    /// properties of the publish.
+    Custom(Arc<dyn Fn(&str, &PublishProperties) -> PublishPriority + Send + Sync>),
+}
+
+/// Configuration for sending outgoing publishes in priority lanes.
+///
+/// Publishes are handed to the event loop of the [`Session`](crate::session::Session) one at a
+/// time. While a publish is being handed off (e.g. waiting for space in the outgoing queue), other
+/// publishes wait in the lane of their [`PublishPriority`], and waiting
+/// [`PublishPriority::Control`] publishes are handed off before any waiting
+/// [`PublishPriority::Bulk`] publishes. Publishes of the same priority are handed off in the order
+/// they are sent. Once handed off, a publish is sent in queue order, so ordering only applies
+/// while the outgoing queue is full.
+///
+/// To keep room in the outgoing queue for [`PublishPriority::Control`] publishes, at most
+/// `bulk_max` [`PublishPriority::Bulk`] publishes may be handed off and not yet completed at a
+/// time. Further [`PublishPriority::Bulk`] publishes wait in their lane until an earlier one
+/// completes, or its completion token is dropped.
+///
+/// Only publishes are ordered. Other packets (e.g. SUBSCRIBE or AUTH) do not go through the lanes.
+#[derive(Clone)]
+pub struct PriorityLanesConfig {
+    /// Classification of outgoing publishes
+    pub classifier: PriorityClassifier,
+    /// Maximum number of [`PublishPriority::Bulk`] publishes handed off and not yet completed.
+    /// Should be less than the `outgoing_max` of the [`Session`](crate::session::Session), so that
+    /// the rest of the outgoing queue is reserved for [`PublishPriority::Control`] publishes.
+    /// A value of 0 is treated as 1.
+    pub bulk_max: usize,
+}
+
+/// Snapshot of the metrics of a priority lane
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PriorityLaneStats {
+    /// Number of publishes waiting in the lane to be handed off
+    pub waiting: usize,
+    /// Total number of publishes handed off through the lane
+    pub published: u64,
+}
+
+/// Single priority lane
+#[derive(Default)]
+struct Lane {
+    waiting: AtomicUsize,
+    published: AtomicU64,
+}
+
+/// Decrements the waiting count of a lane when dropped, so that cancelled waits are accounted for
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// State of the handoff of publishes to the event loop
+#[derive(Default)]
+struct HandoffState {
+    /// Indicates if a publish is currently being handed off
+    busy: bool,
+    /// Publishes waiting to be handed off, by priority
+    control: VecDeque<oneshot::Sender<()>>,
+    bulk: VecDeque<oneshot::Sender<()>>,
+}
+
+impl HandoffState {
+    /// Pass the handoff to the next waiting publish, in priority order, if any.
+    fn release(&mut self) {
+        while let Some(next) = self.control.pop_front().or_else(|| self.bulk.pop_front()) {
+            // Waits that have been cancelled have dropped their receiver
+            if next.send(()).is_ok() {
+                return;
+            }
+        }
+        self.busy = false;
+    }
+}
+
+/// Exclusive permission to hand a publish off to the event loop.
+/// The next waiting publish is permitted when dropped.
+pub(crate) struct LanePermit {
+    handoff: Arc<Mutex<HandoffState>>,
+    slot: Option<OwnedSemaphorePermit>,
+}
+
+impl LanePermit {
+    /// Take the slot the publish occupies in its lane, if the lane is bounded.
+    /// The slot should be held until the publish completes.
+    pub(crate) fn take_slot(&mut self) -> Option<OwnedSemaphorePermit> {
+        self.slot.take()
+    }
+}
+
+impl Drop for LanePermit {
+    fn drop(&mut self) {
+        self.handoff.lock().unwrap().release();
+    }
+}
+
+/// Wait for a [`LanePermit`] that passes the handoff on if cancelled after being permitted
+struct PermitWait {
+    rx: oneshot::Receiver<()>,
+    handoff: Arc<Mutex<HandoffState>>,
+}
+
+impl Drop for PermitWait {
+    fn drop(&mut self) {
+        self.rx.close();
+        // If the handoff was passed to this wait, but the wait was cancelled before it completed,
+        // pass the handoff on
+        if self.rx.try_recv().is_ok() {
+            self.handoff.lock().unwrap().release();
+        }
+    }
+}
+
+/// Priority lanes that outgoing publishes are handed off to the event loop through
+pub(crate) struct PriorityLanes {
+    classifier: PriorityClassifier,
+    handoff: Arc<Mutex<HandoffState>>,
+    control: Lane,
+    bulk: Lane,
+    /// Slots for bulk publishes that have not yet completed
+    bulk_slots: Arc<Semaphore>,
+}
+
+impl PriorityLanes {
+    pub(crate) fn new(config: PriorityLanesConfig) -> Self {
+        Self {
+            classifier: config.classifier,
+            handoff: Arc::new(Mutex::new(HandoffState::default())),
+            control: Lane::default(),
+            bulk: Lane::default(),
+            bulk_slots: Arc::new(Semaphore::new(config.bulk_max.max(1))),
+        }
+    }
+
+    /// Return the [`PublishPriority`] of a publish with the provided topic name and properties.
+    pub(crate) fn classify(&self, topic: &str, properties: &PublishProperties) -> PublishPriority {
+        match &self.classifier {
+            PriorityClassifier::Rpc {
+                response_properties,
+            } => {
+                if properties.response_topic.is_some()
+                    || properties
+                        .user_properties
+                        .iter()
+                        .any(|(k, _)| response_properties.contains(k))
+                {
+                    PublishPriority::Control
+                } else {
+                    PublishPriority::Bulk
+                }
+            }
+            PriorityClassifier::Custom(classify) => classify(topic, properties),
+        }
+    }
+
+    /// Wait for permission to hand a publish of the provided [`PublishPriority`] off to the event
+    /// loop. The returned [`LanePermit`] should be dropped once the publish has been handed off.
+    pub(crate) async fn acquire(&self, priority: PublishPriority) -> LanePermit {
+        let lane = self.lane(priority);
+        // Wait for a slot before waiting for the handoff, so that bulk publishes waiting for a
+        // slot do not hold up control publishes
+        let slot = match priority {
+            PublishPriority::Control => None,
+            PublishPriority::Bulk => {
+                if let Ok(slot) = self.bulk_slots.clone().try_acquire_owned() {
+                    Some(slot)
+                } else {
+                    lane.waiting.fetch_add(1, Ordering::Relaxed);
+                    let _waiting = WaitingGuard(&lane.waiting);
+                    Some(
+                        self.bulk_slots
+                            .clone()
+                            .acquire_owned()
+                            .await
+                            .expect("Bulk slots are never closed"),
+                    )
+                }
+            }
+        };
+        let wait = {
+            let mut handoff = self.handoff.lock().unwrap();
+            if handoff.busy {
+                let (tx, rx) = oneshot::channel();
+                match priority {
+                    PublishPriority::Control => handoff.control.push_back(tx),
+                    PublishPriority::Bulk => handoff.bulk.push_back(tx),
+                }
+                Some(PermitWait {
+                    rx,
+                    handoff: self.handoff.clone(),
+                })
+            } else {
+                handoff.busy = true;
+                None
+            }
+        };
+        if let Some(mut wait) = wait {
+            lane.waiting.fetch_add(1, Ordering::Relaxed);
+            let _waiting = WaitingGuard(&lane.waiting);
+            (&mut wait.rx)
+                .await
+                .expect("Waiting publishes are only removed when permitted");
+        }
+        lane.published.fetch_add(1, Ordering::Relaxed);
+        LanePermit {
+            handoff: self.handoff.clone(),
+            slot,
+        }
+    }
+
+    /// Return the current metrics of the lane of the provided [`PublishPriority`].
+    pub(crate) fn stats(&self, priority: PublishPriority) -> PriorityLaneStats {
+        let lane = self.lane(priority);
+        PriorityLaneStats {
+            waiting: lane.waiting.load(Ordering::Relaxed),
+            published: lane.published.load(Ordering::Relaxed),
+        }
+    }
+
+    fn lane(&self, priority: PublishPriority) -> &Lane {
+        match priority {
+            PublishPriority::Control => &self.control,
+            PublishPriority::Bulk => &self.bulk,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::FutureExt;
+    use test_case::test_case;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn lanes() -> PriorityLanes {
+        PriorityLanes::new(PriorityLanesConfig {
+            classifier: PriorityClassifier::Rpc {
+                response_properties: vec!["__stat".to_string()],
+            },
+            bulk_max: 10,
+        })
+    }
+
+    /// Spawn a task that acquires a permit of the provided priority, reports the acquisition,
+    /// and then immediately releases the permit
+    fn spawn_acquire(
+        lanes: &Arc<PriorityLanes>,
+        priority: PublishPriority,
+        acquired_tx: &mpsc::UnboundedSender<PublishPriority>,
+    ) -> tokio::task::JoinHandle<()> {
+        let lanes = lanes.clone();
+        let acquired_tx = acquired_tx.clone();
+        tokio::task::spawn(async move {
+            let _permit = lanes.acquire(priority).await;
+            acquired_tx.send(priority).unwrap();
+        })
+    }
+
+    #[test_case(Some("response/topic".to_string()), vec![], PublishPriority::Control; "request")]
+    #[test_case(None, vec![("__stat".to_string(), "200".to_string())], PublishPriority::Control; "response")]
+    #[test_case(None, vec![("__protVer".to_string(), "1.0".to_string())], PublishPriority::Bulk; "telemetry")]
+    fn rpc_classification(
+        response_topic: Option<String>,
+        user_properties: Vec<(String, String)>,
+        expected: PublishPriority,
+    ) {
+        let properties = PublishProperties {
+            response_topic,
+            user_properties,
+            ..Default::default()
+        };
+        assert_eq!(lanes().classify("some/topic", &properties), expected);
+    }
+
+    #[test]
+    fn custom_classification() {
+        let lanes = PriorityLanes::new(PriorityLanesConfig {
+            classifier: PriorityClassifier::Custom(Arc::new(|topic, _| {
+                if topic.starts_with("alarms/") {
+                    PublishPriority::Control
+                } else {
+                    PublishPriority::Bulk
+                }
+            })),
+            bulk_max: 10,
+        });
+        let properties = PublishProperties::default();
+        assert_eq!(
+            lanes.classify("alarms/fire", &properties),
+            PublishPriority::Control
+        );
+        assert_eq!(
+            lanes.classify("telemetry/temperature", &properties),
+            PublishPriority::Bulk
+        );
+    }
+
+    #[tokio::test]
+    async fn control_handed_off_before_bulk() {
+        let lanes = Arc::new(lanes());
+        let (acquired_tx, mut acquired_rx) = mpsc::unbounded_channel();
+
+        // Hold the handoff while publishes of both priorities queue up, bulk first
+        let permit = lanes.acquire(PublishPriority::Bulk).await;
+        let bulk = spawn_acquire(&lanes, PublishPriority::Bulk, &acquired_tx);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let control = spawn_acquire(&lanes, PublishPriority::Control, &acquired_tx);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(lanes.stats(PublishPriority::Bulk).waiting, 1);
+        assert_eq!(lanes.stats(PublishPriority::Control).waiting, 1);
+        assert!(acquired_rx.try_recv().is_err());
+
+        // Releasing the handoff permits the control publish first
+        drop(permit);
+        bulk.await.unwrap();
+        control.await.unwrap();
+        assert_eq!(acquired_rx.recv().await, Some(PublishPriority::Control));
+        assert_eq!(acquired_rx.recv().await, Some(PublishPriority::Bulk));
+
+        assert_eq!(
+            lanes.stats(PublishPriority::Bulk),
+            PriorityLaneStats {
+                waiting: 0,
+                published: 2,
+            }
+        );
+        assert_eq!(
+            lanes.stats(PublishPriority::Control),
+            PriorityLaneStats {
+                waiting: 0,
+                published: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn bulk_bounded_by_slots() {
+        let lanes = PriorityLanes::new(PriorityLanesConfig {
+            classifier: PriorityClassifier::Custom(Arc::new(|_, _| PublishPriority::Bulk)),
+            bulk_max: 2,
+        });
+
+        // Bulk publishes that have been handed off but not completed hold their slots
+        let mut first = lanes.acquire(PublishPriority::Bulk).await;
+        let first_slot = first.take_slot();
+        drop(first);
+        let mut second = lanes.acquire(PublishPriority::Bulk).await;
+        let _second_slot = second.take_slot();
+        drop(second);
+
+        // No slots remain for further bulk publishes, but control publishes are not bounded
+        let mut third = Box::pin(lanes.acquire(PublishPriority::Bulk));
+        assert!((&mut third).now_or_never().is_none());
+        assert_eq!(lanes.stats(PublishPriority::Bulk).waiting, 1);
+        assert!(
+            lanes
+                .acquire(PublishPriority::Control)
+                .now_or_never()
+                .is_some()
+        );
+
+        // Completion of a bulk publish frees its slot
+        drop(first_slot);
+        assert!(third.now_or_never().is_some());
+        assert_eq!(lanes.stats(PublishPriority::Bulk).waiting, 0);
+    }
+
+    #[tokio::test]
+    async fn permit_released_on_drop() {
+        let lanes = lanes();
+        let permit = lanes.acquire(PublishPriority::Bulk).await;
+        assert!(
+            lanes
+                .acquire(PublishPriority::Control)
+                .now_or_never()
+                .is_none()
+        );
+        drop(permit);
+        assert!(
+            lanes
+                .acquire(PublishPriority::Control)
+                .now_or_never()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn waiting_count() {
+        let lanes = Arc::new(lanes());
+        let _permit = lanes.acquire(PublishPriority::Bulk).await;
+
+        let waiter = tokio::task::spawn({
+            let lanes = lanes.clone();
+            async move {
+                lanes.acquire(PublishPriority::Bulk).await;
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(lanes.stats(PublishPriority::Bulk).waiting, 1);
+
+        // Cancelled waits are no longer counted
+        waiter.abort();
+        let _ = waiter.await;
+        assert_eq!(lanes.stats(PublishPriority::Bulk).waiting, 0);
+    }
+
+    #[tokio::test]
+    async fn cancelled_wait_passes_handoff_on() {
+        let lanes = lanes();
+        let permit = lanes.acquire(PublishPriority::Bulk).await;
+
+        // Permitted while waiting, but cancelled before the wait completes
+        let mut cancelled = Box::pin(lanes.acquire(PublishPriority::Control));
+        assert!((&mut cancelled).now_or_never().is_none());
+        drop(permit);
+        drop(cancelled);
+
+        assert!(
+            lanes
+                .acquire(PublishPriority::Bulk)
+                .now_or_never()
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Client-side rate limiting of outgoing publishes.
+
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::error::{PublishError, PublishErrorKind};
+use crate::runtime;
+use crate::topic::{TopicFilter, TopicName};
+
+/// Rate at which outgoing publishes may be sent.
+///
+/// Limits are enforced with token buckets that hold up to one second of their rate, allowing
+/// bursts of up to one second's worth of publishes after a period of inactivity.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RateLimit {
+    /// Maximum number of publishes per second. If `None`, the number of publishes is not limited.
+    pub messages_per_second: Option<u32>,
+    /// Maximum number of payload bytes per second. If `None`, the payload bytes are not limited.
+    /// A single publish with a payload larger than this limit is allowed once the bucket is full,
+    /// and delays subsequent publishes accordingly.
+    pub bytes_per_second: Option<u64>,
+}
+
+/// Behavior of a publish that exceeds a [`RateLimit`]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RateLimitBehavior {
+    /// Wait until the publish is allowed by the rate limits
+    #[default]
+    Wait,
+    /// Fail the publish with a [`PublishErrorKind::RateLimited`] error
+    FailFast,
+}
+
+/// Configuration for rate limiting outgoing publishes
+#[derive(Clone, Debug, Default)]
+pub struct RateLimitConfig {
+    /// Rate limit applied to all outgoing publishes of the [`Session`](crate::session::Session)
+    pub session: Option<RateLimit>,
+    /// Rate limits applied to outgoing publishes with a topic name matching the topic filter.
+    /// A publish matching multiple topic filters must be allowed by all of their rate limits.
+    pub topics: Vec<(TopicFilter, RateLimit)>,
+    /// Behavior of a publish that exceeds a rate limit
+    pub behavior: RateLimitBehavior,
+}
+
+/// Token bucket holding up to one second of its rate
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, now: Instant) -> Self {
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        self.last_refill = now;
+    }
+
+    /// Duration until the bucket allows the provided cost. A cost larger than the capacity of the
+    /// bucket is allowed once the bucket is full.
+    fn wait_time(&self, cost: f64) -> Duration {
+        let needed = cost.min(self.rate) - self.tokens;
+        if needed <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(needed / self.rate)
+        }
+    }
+
+    fn consume(&mut self, cost: f64) {
+        self.tokens -= cost;
+    }
+}
+
+/// Token buckets enforcing a single [`RateLimit`]
+struct Limiter {
+    messages: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl Limiter {
+    // NOTE: Precision loss in the conversion of byte counts to f64 is irrelevant at any
+    // realistic rate
+    #[allow(clippy::cast_precision_loss)]
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            messages: limit
+                .messages_per_second
+                .filter(|r| *r > 0)
+                .map(|r| TokenBucket::new(f64::from(r), now)),
+            bytes: limit
+                .bytes_per_second
+                .filter(|r| *r > 0)
+                .map(|r| TokenBucket::new(r as f64, now)),
+        }
+    }
+
+    /// Refill the buckets and return the duration until a publish with the provided payload size
+    /// is allowed
+    fn wait_time(&mut self, now: Instant, bytes: f64) -> Duration {
+        let mut wait = Duration::ZERO;
+        if let Some(messages) = &mut self.messages {
+            messages.refill(now);
+            wait = wait.max(messages.wait_time(1.0));
+        }
+        if let Some(bytes_bucket) = &mut self.bytes {
+            bytes_bucket.refill(now);
+            wait = wait.max(bytes_bucket.wait_time(bytes));
+        }
+        wait
+    }
+
+    fn consume(&mut self, bytes: f64) {
+        if let Some(messages) = &mut self.messages {
+            messages.consume(1.0);
+        }
+        if let Some(bytes_bucket) = &mut self.bytes {
+            bytes_bucket.consume(bytes);
+        }
+    }
+}
+
+/// Rate limiter for the outgoing publishes of a [`Session`](crate::session::Session)
+pub(crate) struct RateLimiter {
+    session: Option<Mutex<Limiter>>,
+    topics: Vec<(TopicFilter, Mutex<Limiter>)>,
+    behavior: RateLimitBehavior,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: RateLimitConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            session: config
+                .session
+                .map(|limit| Mutex::new(Limiter::new(limit, now))),
+            topics: config
+                .topics
+                .into_iter()
+                .map(|(filter, limit)| (filter, Mutex::new(Limiter::new(limit, now))))
+                .collect(),
+            behavior: config.behavior,
+        }
+    }
+
+    /// Wait until a publish to the provided topic name with the provided payload size is allowed
+    /// by all applicable rate limits.
+    ///
+    /// # Errors
+    /// Returns a [`PublishError`] of kind [`PublishErrorKind::RateLimited`] if the publish is not
+    /// currently allowed and the [`RateLimitBehavior`] is [`RateLimitBehavior::FailFast`].
+    pub(crate) async fn acquire(
+        &self,
+        topic: &str,
+        payload_size: usize,
+    ) -> Result<(), PublishError> {
+        // Topic limits are not applied to invalid topic names, as the publish will be rejected
+        let topic_name = TopicName::from_string(topic.to_string()).ok();
+        let limiters = self
+            .session
+            .iter()
+            .chain(
+                self.topics
+                    .iter()
+                    .filter(|(filter, _)| {
+                        topic_name
+                            .as_ref()
+                            .is_some_and(|name| name.matches_topic_filter(filter))
+                    })
+                    .map(|(_, limiter)| limiter),
+            )
+            .collect::<Vec<_>>();
+        if limiters.is_empty() {
+            return Ok(());
+        }
+        loop {
+            let wait = Self::try_acquire(&limiters, payload_size);
+            if wait.is_zero() {
+                return Ok(());
+            }
+            match self.behavior {
+                RateLimitBehavior::Wait => runtime::sleep(wait).await,
+                RateLimitBehavior::FailFast => {
+                    return Err(PublishError::new(PublishErrorKind::RateLimited));
+                }
+            }
+        }
+    }
+
+    /// Consume from all provided limiters if all of them allow the publish. Otherwise, return the
+    /// duration until all of them are expected to allow it.
+    #[allow(clippy::cast_precision_loss)]
+    fn try_acquire(limiters: &[&Mutex<Limiter>], payload_size: usize) -> Duration {
+        let now = Instant::now();
+        let bytes = payload_size as f64;
+        // Limiters are always locked in the same order, so concurrent publishes cannot deadlock
+        let mut guards = limiters
+            .iter()
+            .map(|limiter| limiter.lock().unwrap())
+            .collect::<Vec<MutexGuard<'_, Limiter>>>();
+        let wait = guards
+            .iter_mut()
+            .map(|limiter| limiter.wait_time(now, bytes))
+            .max()
+            .unwrap_or_default();
+        if wait.is_zero() {
+            for limiter in &mut guards {
+                limiter.consume(bytes);
+            }
+        }
+        wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn filter(topic_filter: &str) -> TopicFilter {
+        TopicFilter::from_string(topic_filter.to_string()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn session_message_limit_fail_fast() {
+        let rate_limiter = RateLimiter::new(RateLimitConfig {
+            session: Some(RateLimit {
+                messages_per_second: Some(2),
+                bytes_per_second: None,
+            }),
+            topics: vec![],
+            behavior: RateLimitBehavior::FailFast,
+        });
+        assert!(rate_limiter.acquire("a/b", 10).await.is_ok());
+        assert!(rate_limiter.acquire("c/d", 10).await.is_ok());
+        let err = rate_limiter.acquire("a/b", 10).await.unwrap_err();
+        assert_eq!(*err.kind(), PublishErrorKind::RateLimited);
+    }
+
+    #[test_case(60, 60, false; "second publish exceeds limit")]
+    #[test_case(40, 60, true; "both publishes within limit")]
+    #[test_case(500, 0, false; "oversized publish allowed when bucket full")]
+    #[tokio::test]
+    async fn session_byte_limit_fail_fast(first: usize, second: usize, second_allowed: bool) {
+        let rate_limiter = RateLimiter::new(RateLimitConfig {
+            session: Some(RateLimit {
+                messages_per_second: None,
+                bytes_per_second: Some(100),
+            }),
+            topics: vec![],
+            behavior: RateLimitBehavior::FailFast,
+        });
+        assert!(rate_limiter.acquire("a/b", first).await.is_ok());
+        assert_eq!(
+            rate_limiter.acquire("a/b", second).await.is_ok(),
+            second_allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn topic_limit_only_applies_to_matching_topics() {
+        let rate_limiter = RateLimiter::new(RateLimitConfig {
+            session: None,
+            topics: vec![(
+                filter("telemetry/#"),
+                RateLimit {
+                    messages_per_second: Some(1),
+                    bytes_per_second: None,
+                },
+            )],
+            behavior: RateLimitBehavior::FailFast,
+        });
+        assert!(rate_limiter.acquire("telemetry/a", 10).await.is_ok());
+        assert!(rate_limiter.acquire("telemetry/b", 10).await.is_err());
+        assert!(rate_limiter.acquire("commands/a", 10).await.is_ok());
+        assert!(rate_limiter.acquire("commands/a", 10).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn exceeding_limit_waits() {
+        let rate_limiter = RateLimiter::new(RateLimitConfig {
+            session: Some(RateLimit {
+                messages_per_second: Some(10),
+                bytes_per_second: None,
+            }),
+            topics: vec![],
+            behavior: RateLimitBehavior::Wait,
+        });
+        for _ in 0..10 {
+            rate_limiter.acquire("a/b", 10).await.unwrap();
+        }
+        let start = Instant::now();
+        rate_limiter.acquire("a/b", 10).await.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50));
+        assert!(elapsed < Duration::from_secs(1));
+    }
+}
@@ -6,37 +6,248 @@
 mod ordered_acker;
 mod plenary_ack;
 
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
 use std::string::FromUtf8Error;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use thiserror::Error;
+use tokio::sync::broadcast;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
 
-use crate::control_packet::{Publish, QoS};
+use crate::control_packet::{NackReasonCode, Publish, QoS};
 use crate::error::AckError;
 use crate::interface::{CompletionToken, MqttAck};
+use crate::runtime;
 use crate::session::receiver::{
     ordered_acker::{OrderedAcker, PkidAckQueue, PkidError},
-    plenary_ack::{PlenaryAck, PlenaryAckMember},
+    plenary_ack::{PlenaryAck, PlenaryAckMember, PlenaryAckMonitor},
 };
 use crate::topic::{TopicFilter, TopicName, TopicParseError};
 
 /// Token that can be used to acknowledge a received MQTT publish.
 #[derive(Debug)]
-pub struct AckToken(PlenaryAckMember);
+pub struct AckToken {
+    member: PlenaryAckMember,
+    /// Tokens of other publishes, acknowledged along with this one
+    linked: Vec<AckToken>,
+}
 
 impl AckToken {
     /// Acknowledge the received Publish message and return a [`CompletionToken`] for the completion
     /// of the acknowledgement process.
     ///
+    /// Publishes linked to this token with [`AckToken::link`] are acknowledged first, and the
+    /// returned [`CompletionToken`] completes once all acknowledgements have completed.
+    ///
     /// # Errors
     /// Returns an [`AckError`] if the Publish message could not be acknowledged.
     pub async fn ack(self) -> Result<CompletionToken, AckError> {
-        self.0.ack().await
+        self.complete(AckKind::Ack).await
+    }
+
+    /// Negatively acknowledge the received Publish message with the provided reason code and
+    /// optional reason string, and return a [`CompletionToken`] for the completion of the
+    /// acknowledgement process.
+    ///
+    /// Note that if the Publish message was dispatched to multiple receivers, a single
+    /// acknowledgement is sent to the broker once all receivers have acknowledged. If any of them
+    /// negatively acknowledged, the first reported reason will be sent.
+    ///
+    /// Publishes linked to this token with [`AckToken::link`] are negatively acknowledged with
+    /// the same reason.
+    ///
+    /// # Errors
+    /// Returns an [`AckError`] if the Publish message could not be acknowledged.
+    pub async fn nack(
+        self,
+        reason_code: NackReasonCode,
+        reason_string: Option<String>,
+    ) -> Result<CompletionToken, AckError> {
+        self.complete(AckKind::Nack(reason_code, reason_string))
+            .await
+    }
+
+    /// Link the [`AckToken`]s of other received Publish messages to this one, so that they are
+    /// acknowledged along with it. This is useful when several Publish messages together make up
+    /// a single message, e.g. the chunks of a chunked message, which should only be acknowledged
+    /// once the whole message has been processed.
+    ///
+    /// Dropping this token drops the linked tokens as well, acknowledging them.
+    #[must_use]
+    pub fn link(mut self, tokens: impl IntoIterator<Item = AckToken>) -> Self {
+        for mut token in tokens {
+            // Keep the links flat, so that tokens are acknowledged in the order they were linked
+            self.linked.append(&mut token.linked);
+            self.linked.push(token);
+        }
+        self
+    }
+
+    async fn complete(self, ack_kind: AckKind) -> Result<CompletionToken, AckError> {
+        let mut completion_tokens = Vec::with_capacity(self.linked.len());
+        for token in self.linked {
+            completion_tokens.push(token.member.ack(ack_kind.clone()).await?);
+        }
+        let completion_token = self.member.ack(ack_kind).await?;
+        if completion_tokens.is_empty() {
+            return Ok(completion_token);
+        }
+        completion_tokens.push(completion_token);
+        Ok(CompletionToken(Box::new(async move {
+            for completion_token in completion_tokens {
+                completion_token.await?;
+            }
+            Ok(())
+        })))
+    }
+}
+
+/// The kind of acknowledgement to be sent for a received publish
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AckKind {
+    /// Positive acknowledgement
+    #[default]
+    Ack,
+    /// Negative acknowledgement with reason code and optional reason string
+    Nack(NackReasonCode, Option<String>),
+}
+
+/// Information about a received publish whose acknowledgement has stalled beyond the configured
+/// ack timeout, blocking the acknowledgement of all subsequently received publishes.
+#[derive(Clone, Debug)]
+pub struct StalledAck {
+    /// Packet identifier of the stalled publish
+    pub pkid: u16,
+    /// Topic name of the stalled publish
+    pub topic: String,
+    /// Topic filters of the receivers still holding an [`AckToken`] for the publish.
+    /// `None` indicates an unfiltered receiver or a receiver for a subscription identifier.
+    pub pending_receivers: Vec<Option<TopicFilter>>,
+    /// Indicates if the publish was forcibly acknowledged after the timeout elapsed
+    pub force_acked: bool,
+}
+
+/// Configuration for monitoring stalled acknowledgements
+#[derive(Clone, Copy, Debug)]
+pub struct AckWatchdogConfig {
+    /// Duration after dispatch after which an unacknowledged publish is considered stalled
+    pub timeout: Duration,
+    /// Indicates if a stalled publish should be forcibly acknowledged
+    pub force_ack: bool,
+}
+
+/// Identity used to recognize a received publish as a duplicate of a previously received publish
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub enum DeduplicationKey {
+    /// Only publishes with the DUP flag set are considered, and are identified by their packet
+    /// identifier, topic name and payload. This suppresses redeliveries of publishes whose
+    /// acknowledgement was lost (e.g. due to a reconnect with `clean_start=false`).
+    PacketId,
+    /// Publishes are identified by their correlation data property along with their user
+    /// properties. Publishes without correlation data are never suppressed.
+    ///
+    /// Correlation data alone does not identify a message, as distinct messages may share it:
+    /// the chunks of a chunked payload, the responses of a streamed command response, and the
+    /// attempts and cancellation of a command invocation. These differ in their user properties
+    /// (e.g. a chunk or stream index, or a timestamp), so they are not mistaken for duplicates.
+    CorrelationData,
+    /// Publishes are identified by the key returned by an application provided extractor.
+    /// Publishes for which the extractor returns `None` are never suppressed.
+    Custom(Arc<dyn Fn(&Publish) -> Option<Vec<u8>> + Send + Sync>),
+}
+
+/// Configuration for suppressing duplicate deliveries of received publishes
+#[derive(Clone)]
+pub struct DeduplicationConfig {
+    /// Number of most recently received publish identities remembered
+    pub window_size: usize,
+    /// Identity used to recognize duplicates
+    pub key: DeduplicationKey,
+}
+
+/// Bounded window of recently received publish identities
+struct DuplicateFilter {
+    config: DeduplicationConfig,
+    window: VecDeque<Vec<u8>>,
+    seen: HashSet<Vec<u8>>,
+    suppressed_count: Arc<AtomicU64>,
+}
+
+impl DuplicateFilter {
+    fn new(config: DeduplicationConfig, suppressed_count: Arc<AtomicU64>) -> Self {
+        Self {
+            config,
+            window: VecDeque::new(),
+            seen: HashSet::new(),
+            suppressed_count,
+        }
+    }
+
+    /// Returns true if the publish is a duplicate of a publish in the window.
+    /// Otherwise, remembers the publish, and returns false.
+    fn is_duplicate(&mut self, publish: &Publish) -> bool {
+        let Some(key) = self.extract_key(publish) else {
+            return false;
+        };
+        if self.seen.contains(&key) {
+            // With the PacketId key, only redeliveries (DUP flag set) can be duplicates
+            if publish.dup || !matches!(self.config.key, DeduplicationKey::PacketId) {
+                self.suppressed_count.fetch_add(1, Ordering::Relaxed);
+                return true;
+            }
+            return false;
+        }
+        if self.config.window_size == 0 {
+            return false;
+        }
+        // Evict the oldest identity to make room, if necessary
+        while self.window.len() >= self.config.window_size {
+            let oldest = self.window.pop_front().expect("window is not empty");
+            self.seen.remove(&oldest);
+        }
+        self.seen.insert(key.clone());
+        self.window.push_back(key);
+        false
+    }
+
+    fn extract_key(&self, publish: &Publish) -> Option<Vec<u8>> {
+        match &self.config.key {
+            DeduplicationKey::PacketId => {
+                // NOTE: The PKID alone is insufficient, as the broker may re-use a PKID once the
+                // previous publish with it was acknowledged. Only the first delivery of a publish
+                // has the DUP flag unset, so the original is remembered using the same identity.
+                if publish.qos == QoS::AtMostOnce {
+                    return None;
+                }
+                let mut hasher = DefaultHasher::new();
+                publish.topic.hash(&mut hasher);
+                publish.payload.hash(&mut hasher);
+                let mut key = publish.pkid.to_be_bytes().to_vec();
+                key.extend_from_slice(&hasher.finish().to_be_bytes());
+                Some(key)
+            }
+            DeduplicationKey::CorrelationData => {
+                let properties = publish.properties.as_ref()?;
+                let mut key = properties.correlation_data.as_ref()?.to_vec();
+                let mut hasher = DefaultHasher::new();
+                properties.user_properties.hash(&mut hasher);
+                key.extend_from_slice(&hasher.finish().to_be_bytes());
+                Some(key)
+            }
+            DeduplicationKey::Custom(extractor) => extractor(publish),
+        }
     }
 }
 
+// NOTE: Stalled ack notifications are purely informational, so a bounded broadcast channel is
+// used. Slow or absent listeners will simply miss older notifications.
+const STALLED_ACK_CHANNEL_CAPACITY: usize = 100;
+
 // NOTE: We need to use unbounded channels, because there is no way to know how many
 // publishes may be in-flight. The MQTT client can specify a receive_maximum, yes,
 // but that only applies to QoS1 and QoS2. There is no limit on QoS0.
@@ -67,8 +278,10 @@ pub enum InvalidPublish {
 }
 
 #[derive(Default)]
+#[allow(clippy::struct_field_names)]
 pub struct PublishReceiverManager {
     filtered_txs: HashMap<TopicFilter, Vec<PublishTx>>,
+    subscription_txs: HashMap<usize, Vec<PublishTx>>,
     unfiltered_txs: Vec<PublishTx>,
 }
 
@@ -104,6 +317,27 @@ impl PublishReceiverManager {
         rx
     }
 
+    /// Create a new [`PublishRx`] that will receive dispatched [`Publish`]es that carry the
+    /// provided subscription identifier for as long as it is open.
+    ///
+    /// Multiple receivers can be created for the same subscription identifier. Each receiver will
+    /// receive all publishes that carry the subscription identifier, in addition to any filtered
+    /// receivers with a topic filter matching the topic name of the publish.
+    ///
+    /// # Arguments
+    /// * `subscription_identifier` - The subscription identifier to match incoming publishes against
+    pub fn create_subscription_receiver(&mut self, subscription_identifier: usize) -> PublishRx {
+        // NOTE: Prune for the same reason as when creating a filtered receiver
+        self.prune_subscription_txs();
+
+        let (tx, rx) = unbounded_channel();
+        self.subscription_txs
+            .entry(subscription_identifier)
+            .or_default()
+            .push(tx);
+        rx
+    }
+
     /// Create a new [`PublishRx`] that will receive all dispatched [`Publish`]es that do not
     /// match the topic filters for any other filtered [`PublishRx`]s, for as long as it
     /// is open.
@@ -131,6 +365,14 @@ impl PublishReceiverManager {
             !v.is_empty()
         });
     }
+
+    /// Remove any closed subscription identifier receivers.
+    fn prune_subscription_txs(&mut self) {
+        self.subscription_txs.retain(|_, v| {
+            v.retain(|tx| !tx.is_closed());
+            !v.is_empty()
+        });
+    }
 }
 
 /// Manager for creating and dispatching messages to [`PublishRx`]s.
@@ -141,6 +383,10 @@ where
     acker: OrderedAcker<A>,
     pkid_ack_queue: Arc<Mutex<PkidAckQueue>>,
     receiver_manager: Arc<Mutex<PublishReceiverManager>>,
+    ack_watchdog: Option<AckWatchdogConfig>,
+    stalled_ack_tx: broadcast::Sender<StalledAck>,
+    duplicate_filter: Option<DuplicateFilter>,
+    suppressed_count: Arc<AtomicU64>,
 }
 
 impl<A> IncomingPublishDispatcher<A>
@@ -154,10 +400,15 @@ where
     pub fn new(acker: A) -> Self {
         let pkid_ack_queue = Arc::new(Mutex::new(PkidAckQueue::default()));
         let acker = OrderedAcker::new(acker, pkid_ack_queue.clone());
+        let (stalled_ack_tx, _) = broadcast::channel(STALLED_ACK_CHANNEL_CAPACITY);
         Self {
             acker,
             pkid_ack_queue,
             receiver_manager: Arc::new(Mutex::new(PublishReceiverManager::default())),
+            ack_watchdog: None,
+            stalled_ack_tx,
+            duplicate_filter: None,
+            suppressed_count: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -166,11 +417,33 @@ where
         self.receiver_manager.clone()
     }
 
+    /// Monitor dispatched publishes for acknowledgements that have stalled beyond the timeout
+    /// specified in the provided [`AckWatchdogConfig`].
+    pub fn set_ack_watchdog(&mut self, config: AckWatchdogConfig) {
+        self.ack_watchdog = Some(config);
+    }
+
+    /// Suppress dispatch of received publishes that are duplicates according to the provided
+    /// [`DeduplicationConfig`]. Suppressed duplicates are still acknowledged.
+    pub fn set_deduplication(&mut self, config: DeduplicationConfig) {
+        self.duplicate_filter = Some(DuplicateFilter::new(config, self.suppressed_count.clone()));
+    }
+
+    /// Get a shared counter of the number of suppressed duplicate publishes.
+    pub fn get_suppressed_count(&self) -> Arc<AtomicU64> {
+        self.suppressed_count.clone()
+    }
+
+    /// Get a receiver for notifications of [`StalledAck`]s.
+    pub fn subscribe_stalled_acks(&self) -> broadcast::Receiver<StalledAck> {
+        self.stalled_ack_tx.subscribe()
+    }
+
     /// Dispatch a [`Publish`] to all relevant receivers.
     ///
-    /// The [`Publish`] will be sent to any filtered receivers that correspond to the topic name.
-    /// If no filtered receivers that match the [`Publish`] topic name are present, the [`Publish`]
-    /// will be sent to all unfiltered receivers.
+    /// The [`Publish`] will be sent to any receivers for the subscription identifiers it carries,
+    /// and to any filtered receivers that correspond to the topic name.
+    /// If no such receivers are present, the [`Publish`] will be sent to all unfiltered receivers.
     ///
     /// Returns the number of receivers that the [`Publish`] was dispatched to.
     ///
@@ -210,6 +483,19 @@ where
             return Ok(0);
         }
 
+        // Check if the incoming publish is a duplicate of a recently received publish that was
+        // already acked. If so, it will still be acked, but not dispatched to any receivers.
+        let suppress = self
+            .duplicate_filter
+            .as_mut()
+            .is_some_and(|filter| filter.is_duplicate(publish));
+        if suppress {
+            log::debug!(
+                "Duplicate PUB received (PKID {}). Suppressing dispatch",
+                publish.pkid
+            );
+        }
+
         // Prepare the PlenaryAck for distributed acking
         let plenary_ack = {
             if publish.qos == QoS::AtMostOnce {
@@ -217,12 +503,19 @@ where
             } else {
                 // Insert the PKID into the PKID queue for ordered acking
                 self.pkid_ack_queue.lock().unwrap().insert(publish.pkid)?;
-                // Create an acking future for use with a PlenaryAck
-                let ack_f = {
+                // Create an acking function for use with a PlenaryAck
+                let ack_fn = {
                     let acker = self.acker.clone();
                     let publish = publish.clone();
-                    async move {
-                        let result = acker.ordered_ack(&publish).await;
+                    move |ack_kind: AckKind| async move {
+                        let result = match ack_kind {
+                            AckKind::Ack => acker.ordered_ack(&publish).await,
+                            AckKind::Nack(reason_code, reason_string) => {
+                                acker
+                                    .ordered_nack(&publish, reason_code, reason_string)
+                                    .await
+                            }
+                        };
                         if result.is_ok() {
                             log::debug!("Sent ACK for PKID {}", publish.pkid);
                         } else {
@@ -233,17 +526,26 @@ where
                         result
                     }
                 };
-                Some(PlenaryAck::new(ack_f))
+                Some(PlenaryAck::new(ack_fn))
             }
         };
 
         // Dispatch the publish to all relevant receivers
         let mut num_dispatches = 0;
-        // First, dispatch to all filtered receivers that match the topic name
-        num_dispatches += self.dispatch_filtered(&topic_name, publish, plenary_ack.as_ref());
-        // Then, if no filters matched, dispatch to all unfiltered receivers (if present)
-        if num_dispatches == 0 {
-            num_dispatches += self.dispatch_unfiltered(publish, plenary_ack.as_ref());
+        // Topic filters of the receivers that were issued an AckToken, in order of issue
+        let mut receivers = vec![];
+        if !suppress {
+            // First, dispatch to all receivers for the subscription identifiers of the publish
+            num_dispatches +=
+                self.dispatch_subscription(publish, plenary_ack.as_ref(), &mut receivers);
+            // Then, dispatch to all filtered receivers that match the topic name
+            num_dispatches +=
+                self.dispatch_filtered(&topic_name, publish, plenary_ack.as_ref(), &mut receivers);
+            // Then, if no filters matched, dispatch to all unfiltered receivers (if present)
+            if num_dispatches == 0 {
+                num_dispatches +=
+                    self.dispatch_unfiltered(publish, plenary_ack.as_ref(), &mut receivers);
+            }
         }
 
         log::debug!(
@@ -253,18 +555,76 @@ where
 
         // Once all dispatches have been made, commence the plenary ack
         if let Some(plenary_ack) = plenary_ack {
+            if let Some(config) = self.ack_watchdog {
+                spawn_ack_watchdog(
+                    config,
+                    publish.pkid,
+                    topic_name,
+                    receivers,
+                    plenary_ack.create_monitor(),
+                    self.stalled_ack_tx.clone(),
+                );
+            }
             plenary_ack.commence();
         }
 
         Ok(num_dispatches)
     }
 
+    /// Dispatch to receivers for the subscription identifiers of the publish
+    fn dispatch_subscription(
+        &mut self,
+        publish: &Publish,
+        plenary_ack: Option<&PlenaryAck>,
+        receivers: &mut Vec<Option<TopicFilter>>,
+    ) -> usize {
+        let Some(properties) = &publish.properties else {
+            return 0;
+        };
+
+        let mut num_dispatches = 0;
+        let mut closed = vec![]; // (subscription identifier, position in vector)
+
+        let mut receiver_manager = self.receiver_manager.lock().unwrap();
+
+        for id in &properties.subscription_identifiers {
+            let Some(v) = receiver_manager.subscription_txs.get(id) else {
+                continue;
+            };
+            for (pos, tx) in v.iter().enumerate() {
+                // Send the publish to the receiver, along with an ack token
+                // If the receiver is closed, add it to the list of closed receivers to remove after iteration.
+                if plenary_ack.is_some() && self.ack_watchdog.is_some() {
+                    receivers.push(None);
+                }
+                match tx.send((publish.clone(), create_ack_token(plenary_ack))) {
+                    Ok(()) => num_dispatches += 1,
+                    Err(_) => closed.push((*id, pos)),
+                }
+            }
+        }
+
+        // Remove any closed receivers.
+        // NOTE: Do this in reverse order to avoid index issues.
+        for (id, pos) in closed.iter().rev() {
+            if let Some(v) = receiver_manager.subscription_txs.get_mut(id) {
+                v.remove(*pos);
+                if v.is_empty() {
+                    receiver_manager.subscription_txs.remove(id);
+                }
+            }
+        }
+
+        num_dispatches
+    }
+
     /// Dispatch to filtered receivers
     fn dispatch_filtered(
         &mut self,
         topic_name: &TopicName,
         publish: &Publish,
         plenary_ack: Option<&PlenaryAck>,
+        receivers: &mut Vec<Option<TopicFilter>>,
     ) -> usize {
         let mut num_dispatches = 0;
         let mut closed = vec![]; // (topic filter, position in vector)
@@ -282,6 +642,9 @@ where
                 // NOTE: Removing closed receivers must be done dynamically because the awaitable send allows
                 // for a channel to be closed sometime during the execution of this loop. You cannot simply
                 // use .prune() before the loop.
+                if plenary_ack.is_some() && self.ack_watchdog.is_some() {
+                    receivers.push(Some(topic_filter.clone()));
+                }
                 match tx.send((publish.clone(), create_ack_token(plenary_ack))) {
                     Ok(()) => num_dispatches += 1,
                     Err(_) => closed.push((topic_filter.clone(), pos)),
@@ -308,6 +671,7 @@ where
         &mut self,
         publish: &Publish,
         plenary_ack: Option<&PlenaryAck>,
+        receivers: &mut Vec<Option<TopicFilter>>,
     ) -> usize {
         let mut num_dispatches = 0;
         let mut closed = vec![];
@@ -319,6 +683,9 @@ where
             // If the receiver is closed, add it to the list of closed receivers to remove after iteration.
             // NOTE: Removing closed receivers must be done dynamically because the awaitable send allows
             // for a channel to be closed sometime during the execution of this loop
+            if plenary_ack.is_some() && self.ack_watchdog.is_some() {
+                receivers.push(None);
+            }
             match tx.send((publish.clone(), create_ack_token(plenary_ack))) {
                 Ok(()) => num_dispatches += 1,
                 Err(_) => closed.push(pos),
@@ -342,13 +709,61 @@ fn extract_publish_topic_name(publish: &Publish) -> Result<TopicName, InvalidPub
 }
 
 fn create_ack_token(plenary_ack: Option<&PlenaryAck>) -> Option<AckToken> {
-    plenary_ack.map(|plenary_ack| AckToken(plenary_ack.create_member()))
+    plenary_ack.map(|plenary_ack| AckToken {
+        member: plenary_ack.create_member(),
+        linked: Vec::new(),
+    })
+}
+
+/// Spawn a task that reports (and optionally forcibly acks) a dispatched publish if any of the
+/// [`AckToken`]s issued for it have not been used within the configured timeout.
+fn spawn_ack_watchdog(
+    config: AckWatchdogConfig,
+    pkid: u16,
+    topic_name: TopicName,
+    receivers: Vec<Option<TopicFilter>>,
+    monitor: PlenaryAckMonitor,
+    stalled_ack_tx: broadcast::Sender<StalledAck>,
+) {
+    runtime::spawn(async move {
+        runtime::sleep(config.timeout).await;
+        let pending_members = monitor.pending_members();
+        if pending_members.is_empty() {
+            return;
+        }
+        // NOTE: Members are created in the same order receivers are recorded, so the member
+        // index can be used to identify the receiver.
+        let pending_receivers: Vec<Option<TopicFilter>> = pending_members
+            .iter()
+            .filter_map(|i| receivers.get(*i).cloned())
+            .collect();
+        let receiver_names: Vec<&str> = pending_receivers
+            .iter()
+            .map(|r| r.as_ref().map_or("<unfiltered>", TopicFilter::as_str))
+            .collect();
+        log::warn!(
+            "ACK for PKID {pkid} on topic {} stalled for {:?}. Subsequent ACKs are blocked. Pending receivers: {receiver_names:?}",
+            topic_name.as_str(),
+            config.timeout,
+        );
+        let force_acked = config.force_ack && monitor.force();
+        if force_acked {
+            log::warn!("Forcing ACK for stalled PKID {pkid}");
+        }
+        // NOTE: An error here only indicates there are no listeners
+        let _ = stalled_ack_tx.send(StalledAck {
+            pkid,
+            topic: topic_name.as_str().to_string(),
+            pending_receivers,
+            force_acked,
+        });
+    });
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::control_packet::QoS;
+    use crate::control_packet::{PublishProperties, QoS};
     use crate::interface_mocks::{MockClient, MockClientCall};
     use std::str::FromStr;
     use std::time::Duration;
@@ -573,6 +988,46 @@ mod tests {
         assert_eq!(filtered_rx2.try_recv().unwrap_err(), TryRecvError::Empty);
     }
 
+    #[test_case(QoS::AtMostOnce; "QoS 0")]
+    #[test_case(QoS::AtLeastOnce; "QoS 1")]
+    #[test_case(QoS::ExactlyOnce; "QoS 2")]
+    #[tokio::test]
+    async fn dispatch_subscription_identifier(qos: QoS) {
+        let client = MockClient::new();
+        let mut dispatcher = IncomingPublishDispatcher::new(client);
+        let manager = dispatcher.get_receiver_manager();
+        let topic_name = TopicName::from_str("sport/tennis/player1").unwrap();
+
+        // Create receivers for two different subscription identifiers, and an unfiltered receiver
+        let mut subscription_rx1 = manager.lock().unwrap().create_subscription_receiver(1);
+        let mut subscription_rx2 = manager.lock().unwrap().create_subscription_receiver(2);
+        let mut unfiltered_rx = manager.lock().unwrap().create_unfiltered_receiver();
+
+        // Dispatched publish is received by only the receiver for its subscription identifier,
+        // even though no topic filter matches
+        let mut publish = create_publish_qos(&topic_name, "publish 1", 1, qos);
+        publish.properties = Some(PublishProperties {
+            subscription_identifiers: vec![1],
+            ..Default::default()
+        });
+        assert_eq!(dispatcher.dispatch_publish(&publish).unwrap(), 1);
+        assert_expected_recv_value(&subscription_rx1.try_recv().unwrap(), &publish);
+        assert_eq!(
+            subscription_rx2.try_recv().unwrap_err(),
+            TryRecvError::Empty
+        );
+        assert_eq!(unfiltered_rx.try_recv().unwrap_err(), TryRecvError::Empty);
+
+        // Publish without a subscription identifier goes to the unfiltered receiver
+        let publish = create_publish_qos(&topic_name, "publish 2", 2, qos);
+        assert_eq!(dispatcher.dispatch_publish(&publish).unwrap(), 1);
+        assert_eq!(
+            subscription_rx1.try_recv().unwrap_err(),
+            TryRecvError::Empty
+        );
+        assert_expected_recv_value(&unfiltered_rx.try_recv().unwrap(), &publish);
+    }
+
     #[test_case(QoS::AtMostOnce; "QoS 0")]
     #[test_case(QoS::AtLeastOnce; "QoS 1")]
     #[test_case(QoS::ExactlyOnce; "QoS 2")]
@@ -1693,6 +2148,224 @@ mod tests {
         }
     }
 
+    #[test_case(QoS::AtLeastOnce; "QoS 1")]
+    #[test_case(QoS::ExactlyOnce; "QoS 2")]
+    #[tokio::test]
+    async fn ack_token_nack_multi_receiver(qos: QoS) {
+        let client = MockClient::new();
+        let mock_controller = client.mock_controller();
+        let mut dispatcher = IncomingPublishDispatcher::new(client);
+        let manager = dispatcher.get_receiver_manager();
+
+        // Create unfiltered receivers
+        let mut unfiltered_rx1 = manager.lock().unwrap().create_unfiltered_receiver();
+        let mut unfiltered_rx2 = manager.lock().unwrap().create_unfiltered_receiver();
+
+        // Dispatched publish is received by the unfiltered receivers
+        let topic_name = TopicName::from_str("sport/tennis/player1").unwrap();
+        let publish = create_publish_qos(&topic_name, "payload", 1, qos);
+        assert_eq!(dispatcher.dispatch_publish(&publish).unwrap(), 2);
+        let (r_publish1, ack_token1) = unfiltered_rx1.try_recv().unwrap();
+        let (_, ack_token2) = unfiltered_rx2.try_recv().unwrap();
+
+        // Negatively acknowledge with one of the ack tokens, and positively with the other
+        let jh1 = tokio::task::spawn(
+            ack_token1
+                .unwrap()
+                .nack(NackReasonCode::NotAuthorized, Some("denied".to_string())),
+        );
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(!jh1.is_finished());
+        assert_eq!(mock_controller.nack_count(), 0);
+        ack_token2.unwrap().ack().await.unwrap();
+        jh1.await.unwrap().unwrap();
+
+        // A single nack has been sent for the publish, and no ack
+        assert_eq!(mock_controller.ack_count(), 0);
+        assert_eq!(mock_controller.nack_count(), 1);
+        let calls = mock_controller.call_sequence();
+        assert_eq!(calls.len(), 1);
+        match &calls[0] {
+            MockClientCall::Nack(call) => {
+                assert_eq!(call.publish, r_publish1);
+                assert_eq!(call.reason_code, NackReasonCode::NotAuthorized);
+                assert_eq!(call.reason_string, Some("denied".to_string()));
+            }
+            _ => panic!("Expected Nack"),
+        }
+    }
+
+    #[test_case(QoS::AtLeastOnce; "QoS 1")]
+    #[test_case(QoS::ExactlyOnce; "QoS 2")]
+    #[tokio::test]
+    async fn ack_token_linked_ack(qos: QoS) {
+        let client = MockClient::new();
+        let mock_controller = client.mock_controller();
+        let mut dispatcher = IncomingPublishDispatcher::new(client);
+        let manager = dispatcher.get_receiver_manager();
+        let mut unfiltered_rx = manager.lock().unwrap().create_unfiltered_receiver();
+
+        // Dispatch three publishes, receiving them on the unfiltered receiver
+        let topic_name = TopicName::from_str("sport/tennis/player1").unwrap();
+        let mut r_publishes = vec![];
+        let mut ack_tokens = vec![];
+        for pkid in 1..=3 {
+            let publish = create_publish_qos(&topic_name, "chunk", pkid, qos);
+            assert_eq!(dispatcher.dispatch_publish(&publish).unwrap(), 1);
+            let (r_publish, ack_token) = unfiltered_rx.try_recv().unwrap();
+            r_publishes.push(r_publish);
+            ack_tokens.push(ack_token.unwrap());
+        }
+
+        // Link the tokens of the first two publishes to the token of the last one
+        let ack_token = ack_tokens.pop().unwrap().link(ack_tokens);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(mock_controller.ack_count(), 0);
+
+        // Acknowledging the last publish acknowledges all of them, in order
+        ack_token.ack().await.unwrap().await.unwrap();
+        assert_eq!(mock_controller.ack_count(), 3);
+        let calls = mock_controller.call_sequence();
+        for (call, r_publish) in calls.iter().zip(&r_publishes) {
+            match call {
+                MockClientCall::Ack(call) => assert_eq!(&call.publish, r_publish),
+                _ => panic!("Expected AcknowledgePublish"),
+            }
+        }
+    }
+
+    #[test_case(QoS::AtLeastOnce; "QoS 1")]
+    #[test_case(QoS::ExactlyOnce; "QoS 2")]
+    #[tokio::test]
+    async fn ack_token_linked_nack(qos: QoS) {
+        let client = MockClient::new();
+        let mock_controller = client.mock_controller();
+        let mut dispatcher = IncomingPublishDispatcher::new(client);
+        let manager = dispatcher.get_receiver_manager();
+        let mut unfiltered_rx = manager.lock().unwrap().create_unfiltered_receiver();
+
+        let topic_name = TopicName::from_str("sport/tennis/player1").unwrap();
+        let mut ack_tokens = vec![];
+        for pkid in 1..=2 {
+            let publish = create_publish_qos(&topic_name, "chunk", pkid, qos);
+            assert_eq!(dispatcher.dispatch_publish(&publish).unwrap(), 1);
+            ack_tokens.push(unfiltered_rx.try_recv().unwrap().1.unwrap());
+        }
+
+        // Negatively acknowledging the last publish negatively acknowledges the linked one too
+        let ack_token = ack_tokens.pop().unwrap().link(ack_tokens);
+        ack_token
+            .nack(NackReasonCode::NotAuthorized, Some("denied".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(mock_controller.ack_count(), 0);
+        assert_eq!(mock_controller.nack_count(), 2);
+        for call in mock_controller.call_sequence() {
+            match call {
+                MockClientCall::Nack(call) => {
+                    assert_eq!(call.reason_code, NackReasonCode::NotAuthorized);
+                    assert_eq!(call.reason_string, Some("denied".to_string()));
+                }
+                _ => panic!("Expected Nack"),
+            }
+        }
+    }
+
+    #[test_case(QoS::AtLeastOnce; "QoS 1")]
+    #[test_case(QoS::ExactlyOnce; "QoS 2")]
+    #[tokio::test]
+    async fn ack_token_linked_drop(qos: QoS) {
+        let client = MockClient::new();
+        let mock_controller = client.mock_controller();
+        let mut dispatcher = IncomingPublishDispatcher::new(client);
+        let manager = dispatcher.get_receiver_manager();
+        let mut unfiltered_rx = manager.lock().unwrap().create_unfiltered_receiver();
+
+        let topic_name = TopicName::from_str("sport/tennis/player1").unwrap();
+        let mut ack_tokens = vec![];
+        for pkid in 1..=2 {
+            let publish = create_publish_qos(&topic_name, "chunk", pkid, qos);
+            assert_eq!(dispatcher.dispatch_publish(&publish).unwrap(), 1);
+            ack_tokens.push(unfiltered_rx.try_recv().unwrap().1.unwrap());
+        }
+
+        // Dropping the last token acknowledges the linked one too
+        let ack_token = ack_tokens.pop().unwrap().link(ack_tokens);
+        drop(ack_token);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(mock_controller.ack_count(), 2);
+    }
+
+    #[test_case(QoS::AtLeastOnce, false; "QoS 1")]
+    #[test_case(QoS::ExactlyOnce, false; "QoS 2")]
+    #[test_case(QoS::AtLeastOnce, true; "QoS 1 force ack")]
+    #[test_case(QoS::ExactlyOnce, true; "QoS 2 force ack")]
+    #[tokio::test]
+    async fn ack_watchdog_stalled_ack(qos: QoS, force_ack: bool) {
+        let client = MockClient::new();
+        let mock_controller = client.mock_controller();
+        let mut dispatcher = IncomingPublishDispatcher::new(client);
+        dispatcher.set_ack_watchdog(AckWatchdogConfig {
+            timeout: Duration::from_secs(1),
+            force_ack,
+        });
+        let mut stalled_ack_rx = dispatcher.subscribe_stalled_acks();
+        let manager = dispatcher.get_receiver_manager();
+
+        // Create a filtered receiver and an unfiltered receiver
+        let topic_filter = TopicFilter::from_str("sport/tennis/+").unwrap();
+        let mut filtered_rx = manager
+            .lock()
+            .unwrap()
+            .create_filtered_receiver(&topic_filter);
+        let mut unfiltered_rx = manager.lock().unwrap().create_unfiltered_receiver();
+
+        // Dispatch two publishes to the filtered receiver
+        let topic_name = TopicName::from_str("sport/tennis/player1").unwrap();
+        let publish1 = create_publish_qos(&topic_name, "publish 1", 1, qos);
+        let publish2 = create_publish_qos(&topic_name, "publish 2", 2, qos);
+        assert_eq!(dispatcher.dispatch_publish(&publish1).unwrap(), 1);
+        assert_eq!(dispatcher.dispatch_publish(&publish2).unwrap(), 1);
+        let (_, ack_token1) = filtered_rx.try_recv().unwrap();
+        let (_, ack_token2) = filtered_rx.try_recv().unwrap();
+
+        // Ack the second publish only, which is blocked behind the first
+        let jh2 = tokio::task::spawn(ack_token2.unwrap().ack());
+
+        // Only the first publish is reported as stalled, since it holds the pending ack token
+        let stalled_ack = stalled_ack_rx.recv().await.unwrap();
+        assert_eq!(stalled_ack.pkid, 1);
+        assert_eq!(stalled_ack.topic, "sport/tennis/player1");
+        assert_eq!(stalled_ack.pending_receivers.len(), 1);
+        assert_eq!(
+            stalled_ack.pending_receivers[0].as_ref().unwrap().as_str(),
+            "sport/tennis/+"
+        );
+        assert_eq!(stalled_ack.force_acked, force_ack);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(
+            stalled_ack_rx.try_recv().unwrap_err(),
+            tokio::sync::broadcast::error::TryRecvError::Empty
+        );
+        assert_eq!(unfiltered_rx.try_recv().unwrap_err(), TryRecvError::Empty);
+
+        if force_ack {
+            // Both publishes were acked, since the stalled ack was forced
+            assert!(jh2.is_finished());
+            assert_eq!(mock_controller.ack_count(), 2);
+            // The stalled ack token can still be used
+            ack_token1.unwrap().ack().await.unwrap();
+            assert_eq!(mock_controller.ack_count(), 2);
+        } else {
+            // Neither publish has been acked
+            assert!(!jh2.is_finished());
+            assert_eq!(mock_controller.ack_count(), 0);
+            ack_token1.unwrap().ack().await.unwrap();
+            jh2.await.unwrap().unwrap();
+            assert_eq!(mock_controller.ack_count(), 2);
+        }
+    }
+
     #[test_case(QoS::AtLeastOnce; "QoS 1")]
     #[test_case(QoS::ExactlyOnce; "QoS 2")]
     #[tokio::test]
@@ -2158,4 +2831,91 @@ mod tests {
         let r_result = unfiltered_rx.recv().await;
         assert!(r_result.is_none());
     }
+
+    #[test_case(QoS::AtLeastOnce; "QoS 1")]
+    #[test_case(QoS::ExactlyOnce; "QoS 2")]
+    #[tokio::test]
+    async fn duplicate_suppressed_and_acked(qos: QoS) {
+        let client = MockClient::new();
+        let mock_controller = client.mock_controller();
+        let mut dispatcher = IncomingPublishDispatcher::new(client);
+        dispatcher.set_deduplication(DeduplicationConfig {
+            window_size: 10,
+            key: DeduplicationKey::PacketId,
+        });
+        let suppressed_count = dispatcher.get_suppressed_count();
+        let manager = dispatcher.get_receiver_manager();
+
+        // Create an unfiltered receiver
+        let mut unfiltered_rx = manager.lock().unwrap().create_unfiltered_receiver();
+
+        // Dispatch the original publish, and ack it
+        let topic_name = TopicName::from_str("sport/tennis/player1").unwrap();
+        let publish = create_publish_qos(&topic_name, "publish 1", 1, qos);
+        assert_eq!(dispatcher.dispatch_publish(&publish).unwrap(), 1);
+        let (r_publish, ack_token) = unfiltered_rx.try_recv().unwrap();
+        assert_eq!(r_publish, publish);
+        ack_token.unwrap().ack().await.unwrap();
+        assert_eq!(mock_controller.ack_count(), 1);
+
+        // Redelivery of the already acked publish is not dispatched, but is still acked
+        let mut duplicate = publish.clone();
+        duplicate.dup = true;
+        assert_eq!(dispatcher.dispatch_publish(&duplicate).unwrap(), 0);
+        assert!(unfiltered_rx.try_recv().is_err());
+        assert_eq!(suppressed_count.load(Ordering::Relaxed), 1);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(mock_controller.ack_count(), 2);
+
+        // A new publish re-using the PKID without the DUP flag is dispatched as normal
+        let publish2 = create_publish_qos(&topic_name, "publish 2", 1, qos);
+        assert_eq!(dispatcher.dispatch_publish(&publish2).unwrap(), 1);
+        let (r_publish, _) = unfiltered_rx.try_recv().unwrap();
+        assert_eq!(r_publish, publish2);
+        assert_eq!(suppressed_count.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn duplicate_correlation_data_distinguished_by_user_properties() {
+        let client = MockClient::new();
+        let mut dispatcher = IncomingPublishDispatcher::new(client);
+        dispatcher.set_deduplication(DeduplicationConfig {
+            window_size: 10,
+            key: DeduplicationKey::CorrelationData,
+        });
+        let suppressed_count = dispatcher.get_suppressed_count();
+        let manager = dispatcher.get_receiver_manager();
+        let mut unfiltered_rx = manager.lock().unwrap().create_unfiltered_receiver();
+
+        let topic_name = TopicName::from_str("sport/tennis/player1").unwrap();
+        let create_chunk = |pkid: u16, chunk_index: &str| {
+            let mut publish = create_publish_qos(&topic_name, "chunk", pkid, QoS::AtLeastOnce);
+            publish.properties = Some(PublishProperties {
+                correlation_data: Some("correlation".into()),
+                user_properties: vec![("__chunkIdx".to_string(), chunk_index.to_string())],
+                ..Default::default()
+            });
+            publish
+        };
+
+        // Chunks sharing correlation data are all dispatched
+        assert_eq!(
+            dispatcher.dispatch_publish(&create_chunk(1, "0")).unwrap(),
+            1
+        );
+        assert_eq!(
+            dispatcher.dispatch_publish(&create_chunk(2, "1")).unwrap(),
+            1
+        );
+        assert!(unfiltered_rx.try_recv().is_ok());
+        assert!(unfiltered_rx.try_recv().is_ok());
+
+        // A repeat of a chunk is suppressed
+        assert_eq!(
+            dispatcher.dispatch_publish(&create_chunk(3, "1")).unwrap(),
+            0
+        );
+        assert!(unfiltered_rx.try_recv().is_err());
+        assert_eq!(suppressed_count.load(Ordering::Relaxed), 1);
+    }
 }
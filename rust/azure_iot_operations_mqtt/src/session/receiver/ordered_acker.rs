@@ -9,9 +9,10 @@ use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use tokio::sync::Notify;
 
-use crate::control_packet::Publish;
+use crate::control_packet::{NackReasonCode, Publish};
 use crate::error::{AckError, AckErrorKind};
 use crate::interface::{CompletionToken, MqttAck};
+use crate::session::receiver::AckKind;
 
 /// Error related to PKID
 #[derive(Error, Debug, PartialEq)]
@@ -67,6 +68,31 @@ where
     /// Returns an [`AckError`] if the publish cannot be acknowledged. Note that if ack fails,
     /// its position the queue will be relinquished.
     pub async fn ordered_ack(&self, publish: &Publish) -> Result<CompletionToken, AckError> {
+        self.ordered_ack_kind(publish, AckKind::Ack).await
+    }
+
+    /// Negatively acknowledge a received publish, when it is this publish's turn to be acked.
+    ///
+    /// # Errors
+    /// Returns an [`AckError`] if the publish cannot be acknowledged. Note that if nack fails,
+    /// its position the queue will be relinquished.
+    pub async fn ordered_nack(
+        &self,
+        publish: &Publish,
+        reason_code: NackReasonCode,
+        reason_string: Option<String>,
+    ) -> Result<CompletionToken, AckError> {
+        self.ordered_ack_kind(publish, AckKind::Nack(reason_code, reason_string))
+            .await
+    }
+
+    /// Send the specified kind of acknowledgement for a received publish, when it is this
+    /// publish's turn to be acked.
+    async fn ordered_ack_kind(
+        &self,
+        publish: &Publish,
+        ack_kind: AckKind,
+    ) -> Result<CompletionToken, AckError> {
         // No need to ack QoS0 publishes. Skip.
         if publish.pkid == 0 {
             return Ok(CompletionToken(Box::new(async { Ok(()) })));
@@ -118,7 +144,12 @@ where
 
             // Ack the publish if it is this publish's turn to be acked
             if should_ack {
-                let ct = self.acker.ack(publish).await?;
+                let ct = match ack_kind {
+                    AckKind::Ack => self.acker.ack(publish).await?,
+                    AckKind::Nack(reason_code, reason_string) => {
+                        self.acker.nack(publish, reason_code, reason_string).await?
+                    }
+                };
                 // NOTE: Only notify the waiters AFTER the ack is completed to ensure that no scheduling
                 // shenanigans allow ack order to be altered.
                 self.notify.notify_waiters();
@@ -364,6 +395,71 @@ mod tests {
         }
     }
 
+    #[test_case(QoS::AtLeastOnce; "QoS 1")]
+    #[test_case(QoS::ExactlyOnce; "QoS 2")]
+    #[tokio::test]
+    async fn nack_unordered_invokes(qos: QoS) {
+        let mut pkid_queue = PkidAckQueue::default();
+        pkid_queue.insert(1).unwrap();
+        pkid_queue.insert(2).unwrap();
+
+        let mock_client = MockClient::new();
+        let mock_client_controller = mock_client.mock_controller();
+        let acker = OrderedAcker::new(mock_client, Arc::new(Mutex::new(pkid_queue)));
+
+        let topic_name = TopicName::from_str("test").unwrap();
+        let publish1 = create_publish_qos(&topic_name, "publish 1", 1, qos);
+        let publish2 = create_publish_qos(&topic_name, "publish 2", 2, qos);
+
+        // Nacking a publish out of order will be delayed the same way an ack would be
+        let jh2 = tokio::task::spawn({
+            let acker = acker.clone();
+            async move {
+                acker
+                    .ordered_nack(
+                        &publish2,
+                        NackReasonCode::ImplementationSpecificError,
+                        Some("poison message".to_string()),
+                    )
+                    .await
+                    .unwrap();
+            }
+        });
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(!jh2.is_finished());
+        assert_eq!(mock_client_controller.nack_count(), 0);
+
+        // Only after acking the first publish in the PKID queue will the nack trigger
+        acker.ordered_ack(&publish1).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(jh2.is_finished());
+        assert_eq!(mock_client_controller.ack_count(), 1);
+        assert_eq!(mock_client_controller.nack_count(), 1);
+
+        // Validate order
+        let calls = mock_client_controller.call_sequence();
+        assert_eq!(calls.len(), 2);
+
+        match &calls[0] {
+            MockClientCall::Ack(call) => {
+                assert_eq!(call.publish.pkid, 1);
+            }
+            _ => panic!("Unexpected call"),
+        }
+
+        match &calls[1] {
+            MockClientCall::Nack(call) => {
+                assert_eq!(call.publish.pkid, 2);
+                assert_eq!(
+                    call.reason_code,
+                    NackReasonCode::ImplementationSpecificError
+                );
+                assert_eq!(call.reason_string, Some("poison message".to_string()));
+            }
+            _ => panic!("Unexpected call"),
+        }
+    }
+
     #[tokio::test]
     async fn qos0() {
         let mock_client = MockClient::new();
@@ -15,6 +15,8 @@ use tokio::sync::Notify;
 use crate::{
     error::{AckError, CompletionError},
     interface::CompletionToken,
+    runtime,
+    session::receiver::AckKind,
 };
 
 // NOTE: It could be argued this module should not have the Ack semantics at all, and just let this
@@ -45,6 +47,11 @@ struct PlenaryState {
     commenced: bool,
     /// Notify to trigger when all signals have been reported and the plenary has commenced
     approved: Arc<Notify>,
+    /// The kind of ack to perform, as determined by the signals reported by members.
+    /// A negative ack from any member takes precedence - the first one reported is used.
+    ack_kind: AckKind,
+    /// Indicates, for each member (in order of creation), whether it has signalled
+    signaled_members: Vec<bool>,
 }
 
 // NOTE: Some of these methods if used at the wrong time could lead to a broken state,
@@ -56,13 +63,19 @@ impl PlenaryState {
         self.members
     }
 
-    /// Increment the number of members
-    fn add_member(&mut self) {
+    /// Increment the number of members, returning the index of the new member
+    fn add_member(&mut self) -> usize {
         self.members += 1;
+        self.signaled_members.push(false);
+        self.signaled_members.len() - 1
     }
 
-    /// Indicate a member has signalled
-    fn signal(&mut self) {
+    /// Indicate a member has signalled with the kind of ack it wishes to perform
+    fn signal(&mut self, member_index: usize, ack_kind: AckKind) {
+        if self.ack_kind == AckKind::Ack {
+            self.ack_kind = ack_kind;
+        }
+        self.signaled_members[member_index] = true;
         self.signals += 1;
         if self.signals == self.members && self.commenced {
             self.approved.notify_one();
@@ -81,6 +94,32 @@ impl PlenaryState {
     fn get_approved_notify(&self) -> Arc<Notify> {
         self.approved.clone()
     }
+
+    /// Get the kind of ack to perform based on the signals reported so far
+    fn ack_kind(&self) -> AckKind {
+        self.ack_kind.clone()
+    }
+
+    /// Get the indexes of the members that have not yet signalled
+    fn pending_members(&self) -> Vec<usize> {
+        self.signaled_members
+            .iter()
+            .enumerate()
+            .filter_map(|(i, signaled)| if *signaled { None } else { Some(i) })
+            .collect()
+    }
+
+    /// Approve the plenary regardless of any members that have not yet signalled.
+    /// Returns false if the plenary was already approved.
+    fn force_approve(&mut self) -> bool {
+        if self.commenced && self.signals >= self.members {
+            return false;
+        }
+        self.commenced = true;
+        self.signals = self.members;
+        self.approved.notify_one();
+        true
+    }
 }
 
 /// A member of a [`PlenaryAck`] that is required to issue an ack before the operation managed by the
@@ -89,13 +128,14 @@ impl PlenaryState {
 pub struct PlenaryAckMember {
     state: Arc<Mutex<PlenaryState>>,
     plenary_op_f: PlenaryAckOpFuture,
+    index: usize,
     signaled: bool,
 }
 
 impl PlenaryAckMember {
-    pub async fn ack(mut self) -> Result<CompletionToken, AckError> {
+    pub async fn ack(mut self, ack_kind: AckKind) -> Result<CompletionToken, AckError> {
         // Signal the member has arrived
-        self.state.lock().unwrap().signal();
+        self.state.lock().unwrap().signal(self.index, ack_kind);
         self.signaled = true;
         // Wait for the ack to be completed
         // NOTE: Cloning the future here isn't ideal, but is necessary under the current
@@ -114,11 +154,11 @@ impl Drop for PlenaryAckMember {
     fn drop(&mut self) {
         // If the member is dropped before signalling, signal it now
         if !self.signaled {
-            self.state.lock().unwrap().signal();
+            self.state.lock().unwrap().signal(self.index, AckKind::Ack);
             // We also have to spawn a task for the plenary future op here to ensure it will
             // execute. If there were multiple members, this doesn't matter, but if this was the
             // only member, the plenary future would never execute.
-            tokio::task::spawn({
+            runtime::spawn({
                 let plenary_op_f = self.plenary_op_f.clone();
                 async move {
                     match plenary_op_f.await {
@@ -144,25 +184,31 @@ pub struct PlenaryAck {
 }
 
 impl PlenaryAck {
-    /// Create a new [`PlenaryAck`] with the given ack future that will be triggered once all members ack
-    pub fn new(
-        ack_future: impl Future<Output = Result<CompletionToken, AckError>> + Send + 'static,
-    ) -> Self {
-        let state = PlenaryState::default();
-        let approved = state.get_approved_notify();
-
-        let ack_op_f = async move {
-            // Wait for the ack to be approved by all members signal
-            approved.notified().await;
-            // Trigger the ack operation
-            let ct = ack_future.await?;
-            // Return the completion token as a boxed shared future so that it can be propagated to
-            // the multiple members that may be waiting for it.
-            Ok(ct.boxed().shared())
+    /// Create a new [`PlenaryAck`] with the given ack function that will be invoked with the
+    /// resulting [`AckKind`] once all members ack
+    pub fn new<F>(ack_fn: impl FnOnce(AckKind) -> F + Send + 'static) -> Self
+    where
+        F: Future<Output = Result<CompletionToken, AckError>> + Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(PlenaryState::default()));
+        let approved = state.lock().unwrap().get_approved_notify();
+
+        let ack_op_f = {
+            let state = state.clone();
+            async move {
+                // Wait for the ack to be approved by all members signal
+                approved.notified().await;
+                // Trigger the ack operation
+                let ack_kind = state.lock().unwrap().ack_kind();
+                let ct = ack_fn(ack_kind).await?;
+                // Return the completion token as a boxed shared future so that it can be propagated to
+                // the multiple members that may be waiting for it.
+                Ok(ct.boxed().shared())
+            }
         };
 
         Self {
-            state: Arc::new(Mutex::new(state)),
+            state,
             plenary_op_f: ack_op_f.boxed().shared(),
         }
     }
@@ -172,15 +218,25 @@ impl PlenaryAck {
     pub fn create_member(&self) -> PlenaryAckMember {
         // NOTE: no need to worry about the case where a member is added after the plenary has
         // commenced because .commence() consumes the PlenaryAck
-        self.state.lock().unwrap().add_member();
+        let index = self.state.lock().unwrap().add_member();
 
         PlenaryAckMember {
             state: self.state.clone(),
             plenary_op_f: self.plenary_op_f.clone(),
+            index,
             signaled: false,
         }
     }
 
+    /// Create a [`PlenaryAckMonitor`] that can be used to inspect and intervene in the progress
+    /// of the [`PlenaryAck`] after it has commenced
+    pub fn create_monitor(&self) -> PlenaryAckMonitor {
+        PlenaryAckMonitor {
+            state: self.state.clone(),
+            plenary_op_f: self.plenary_op_f.clone(),
+        }
+    }
+
     /// Indicate the ack operation should begin when all associated [`PlenaryAckMember`]s have acked.
     /// This consumes the [`PlenaryAck`].
     pub fn commence(self) {
@@ -199,7 +255,7 @@ impl PlenaryAck {
         // plenary_op_f will not run until awaited, and if there are no members, there is nobody
         // to await it.
         if self.state.lock().unwrap().members() == 0 {
-            tokio::task::spawn({
+            runtime::spawn({
                 let plenary_op_f = self.plenary_op_f.clone();
                 async move {
                     match plenary_op_f.await {
@@ -227,9 +283,50 @@ impl Drop for PlenaryAck {
     }
 }
 
+/// Monitor for the progress of a [`PlenaryAck`]
+pub struct PlenaryAckMonitor {
+    /// Shared state among members of the plenary
+    state: Arc<Mutex<PlenaryState>>,
+    /// The operation that will be triggered once all members have acked
+    plenary_op_f: PlenaryAckOpFuture,
+}
+
+impl PlenaryAckMonitor {
+    /// Return the indexes (in order of creation) of the [`PlenaryAckMember`]s that have not yet
+    /// acked or been dropped
+    pub fn pending_members(&self) -> Vec<usize> {
+        self.state.lock().unwrap().pending_members()
+    }
+
+    /// Trigger the plenary operation without waiting for any pending [`PlenaryAckMember`]s.
+    /// Any pending members that later ack will receive the result of the forced operation.
+    ///
+    /// Returns false if the plenary operation had already been approved by all members.
+    pub fn force(&self) -> bool {
+        if !self.state.lock().unwrap().force_approve() {
+            return false;
+        }
+        // Pending members may never ack, so the plenary future op must be driven here to
+        // ensure it will execute.
+        runtime::spawn({
+            let plenary_op_f = self.plenary_op_f.clone();
+            async move {
+                match plenary_op_f.await {
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::error!("Forced plenary ack reported failure: {e:?}");
+                    }
+                }
+            }
+        });
+        true
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::control_packet::NackReasonCode;
     use tokio::sync::oneshot;
 
     struct CompletionTokenTrigger(oneshot::Sender<()>);
@@ -271,7 +368,7 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
 
         // Commence without creating any members
         plenary_ack.commence();
@@ -294,12 +391,12 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
         let member1 = plenary_ack.create_member();
 
         // Commence, and then ack w/ plenary member
         plenary_ack.commence();
-        let ct = member1.ack().await.unwrap();
+        let ct = member1.ack(AckKind::Ack).await.unwrap();
         // The mock ack was triggered
         assert!(*mock_ack_triggered.lock().unwrap());
         // Returned completion token has not yet returned
@@ -324,10 +421,10 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
         let member1 = plenary_ack.create_member();
 
-        let m1_ack = tokio::task::spawn(member1.ack());
+        let m1_ack = tokio::task::spawn(member1.ack(AckKind::Ack));
         // Even after a second, the mock ack has not triggered, nor has the member ack returned
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         assert!(!m1_ack.is_finished());
@@ -358,7 +455,7 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
         let member1 = plenary_ack.create_member();
         let member2 = plenary_ack.create_member();
         let member3 = plenary_ack.create_member();
@@ -366,17 +463,17 @@ mod test {
         // Commence before any members ack
         plenary_ack.commence();
         // Mock ack has not triggered after the first plenary member acks, nor has the plenary ack task returned
-        let m1_ack = tokio::task::spawn(member1.ack());
+        let m1_ack = tokio::task::spawn(member1.ack(AckKind::Ack));
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         assert!(!*mock_ack_triggered.lock().unwrap());
         assert!(!m1_ack.is_finished());
         // Mock ack has not triggered after the second plenary member acks, nor has the plenary ack task returned
-        let m2_ack = tokio::task::spawn(member2.ack());
+        let m2_ack = tokio::task::spawn(member2.ack(AckKind::Ack));
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         assert!(!*mock_ack_triggered.lock().unwrap());
         assert!(!m2_ack.is_finished());
         // After the third plenary member acks, the mock ack will trigger, and all plenary ack tasks return
-        let m3_ack = tokio::task::spawn(member3.ack());
+        let m3_ack = tokio::task::spawn(member3.ack(AckKind::Ack));
         let ct1 = m1_ack.await.unwrap().unwrap();
         let ct2 = m2_ack.await.unwrap().unwrap();
         let ct3 = m3_ack.await.unwrap().unwrap();
@@ -409,15 +506,15 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
         let member1 = plenary_ack.create_member();
         let member2 = plenary_ack.create_member();
         let member3 = plenary_ack.create_member();
 
         // Trigger all members to ack before commencing
-        let m1_ack = tokio::task::spawn(member1.ack());
-        let m2_ack = tokio::task::spawn(member2.ack());
-        let m3_ack = tokio::task::spawn(member3.ack());
+        let m1_ack = tokio::task::spawn(member1.ack(AckKind::Ack));
+        let m2_ack = tokio::task::spawn(member2.ack(AckKind::Ack));
+        let m3_ack = tokio::task::spawn(member3.ack(AckKind::Ack));
         // Even after a second, the mock ack has not triggered, nor have the member acks returned
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         assert!(!*mock_ack_triggered.lock().unwrap());
@@ -458,14 +555,14 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
         let member1 = plenary_ack.create_member();
         let member2 = plenary_ack.create_member();
         let member3 = plenary_ack.create_member();
 
         // Trigger two of the members before commencing
-        let m1_ack = tokio::task::spawn(member1.ack());
-        let m2_ack = tokio::task::spawn(member2.ack());
+        let m1_ack = tokio::task::spawn(member1.ack(AckKind::Ack));
+        let m2_ack = tokio::task::spawn(member2.ack(AckKind::Ack));
         // Even after a second, the mock ack has not triggered, nor have the member acks returned
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         assert!(!*mock_ack_triggered.lock().unwrap());
@@ -478,7 +575,7 @@ mod test {
         assert!(!m1_ack.is_finished());
         assert!(!m2_ack.is_finished());
         // Trigger the third member to ack, and then the mock ack will trigger, with all member acks returning
-        let m3_ack = tokio::task::spawn(member3.ack());
+        let m3_ack = tokio::task::spawn(member3.ack(AckKind::Ack));
         let ct1 = m1_ack.await.unwrap().unwrap();
         let ct2 = m2_ack.await.unwrap().unwrap();
         let ct3 = m3_ack.await.unwrap().unwrap();
@@ -498,6 +595,83 @@ mod test {
         jh3.await.unwrap().unwrap();
     }
 
+    #[tokio::test]
+    async fn multiple_member_nack() {
+        let mock_ack_kind = Arc::new(Mutex::new(None));
+
+        let plenary_ack = PlenaryAck::new({
+            let mock_ack_kind = mock_ack_kind.clone();
+            move |ack_kind| async move {
+                *mock_ack_kind.lock().unwrap() = Some(ack_kind);
+                Ok(create_completion_token())
+            }
+        });
+        let member1 = plenary_ack.create_member();
+        let member2 = plenary_ack.create_member();
+        let member3 = plenary_ack.create_member();
+        plenary_ack.commence();
+
+        // Ack and nack with the members. The first nack reported is the one that is used.
+        let m1_ack = tokio::task::spawn(member1.ack(AckKind::Ack));
+        let m2_ack = tokio::task::spawn(member2.ack(AckKind::Nack(
+            NackReasonCode::QuotaExceeded,
+            Some("quota".to_string()),
+        )));
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        assert!(mock_ack_kind.lock().unwrap().is_none());
+        let m3_ack =
+            tokio::task::spawn(member3.ack(AckKind::Nack(NackReasonCode::UnspecifiedError, None)));
+        m1_ack.await.unwrap().unwrap();
+        m2_ack.await.unwrap().unwrap();
+        m3_ack.await.unwrap().unwrap();
+
+        assert_eq!(
+            *mock_ack_kind.lock().unwrap(),
+            Some(AckKind::Nack(
+                NackReasonCode::QuotaExceeded,
+                Some("quota".to_string())
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn monitor_pending_members_and_force() {
+        let mock_ack_triggered = Arc::new(Mutex::new(false));
+
+        let mock_ack_f = {
+            let mock_ack_triggered = mock_ack_triggered.clone();
+            async move {
+                *mock_ack_triggered.lock().unwrap() = true;
+                Ok(create_completion_token())
+            }
+        };
+
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
+        let member1 = plenary_ack.create_member();
+        let member2 = plenary_ack.create_member();
+        let member3 = plenary_ack.create_member();
+        let monitor = plenary_ack.create_monitor();
+        plenary_ack.commence();
+        assert_eq!(monitor.pending_members(), vec![0, 1, 2]);
+
+        // Ack with one member and drop another. Only the remaining one is pending.
+        let m1_ack = tokio::task::spawn(member1.ack(AckKind::Ack));
+        drop(member3);
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        assert_eq!(monitor.pending_members(), vec![1]);
+        assert!(!*mock_ack_triggered.lock().unwrap());
+        assert!(!m1_ack.is_finished());
+
+        // Force the plenary. The mock ack is triggered without the pending member
+        assert!(monitor.force());
+        m1_ack.await.unwrap().unwrap();
+        assert!(*mock_ack_triggered.lock().unwrap());
+
+        // Forcing again has no effect, and the pending member can still ack
+        assert!(!monitor.force());
+        member2.ack(AckKind::Ack).await.unwrap();
+    }
+
     #[tokio::test]
     async fn member_drop_before_ack() {
         let mock_ack_triggered = Arc::new(Mutex::new(false));
@@ -510,7 +684,7 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
         let member1 = plenary_ack.create_member();
 
         // Commence, and then drop the member before it acks
@@ -533,12 +707,12 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
         let member1 = plenary_ack.create_member();
 
         // Commence, and then ack w/ plenary member
         plenary_ack.commence();
-        member1.ack().await.unwrap();
+        member1.ack(AckKind::Ack).await.unwrap();
         // Mock ack was triggered once
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         assert_eq!(*mock_ack_trigger_count.lock().unwrap(), 1);
@@ -563,7 +737,7 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
         let member1 = plenary_ack.create_member();
 
         // Drop the plenary ack
@@ -572,7 +746,7 @@ mod test {
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         assert!(!*mock_ack_triggered.lock().unwrap());
         // Now ack with the member, and the mock ack will trigger
-        member1.ack().await.unwrap();
+        member1.ack(AckKind::Ack).await.unwrap();
         assert!(*mock_ack_triggered.lock().unwrap());
     }
 
@@ -588,7 +762,7 @@ mod test {
             }
         };
 
-        let plenary_ack = PlenaryAck::new(mock_ack_f);
+        let plenary_ack = PlenaryAck::new(|_| mock_ack_f);
 
         // Drop the plenary ack
         drop(plenary_ack);
@@ -0,0 +1,124 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Types for following MQTT server redirection.
+
+use crate::control_packet::{ConnectReturnCode, DisconnectReasonCode};
+use crate::error::{ConnectionError, StateError};
+
+/// A broker that a [`Session`](crate::session::Session) has been redirected to by the broker it
+/// was connecting or connected to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ServerRedirect {
+    /// Host name of the broker redirected to
+    pub hostname: String,
+    /// TCP port of the broker redirected to. If `None`, the current port is used.
+    pub tcp_port: Option<u16>,
+    /// Indicates if the redirect is permanent (Server Moved), rather than temporary (Use Another
+    /// Server)
+    pub permanent: bool,
+}
+
+impl ServerRedirect {
+    /// Parse a [`ServerRedirect`] from the value of an MQTT Server Reference property.
+    ///
+    /// The format of the Server Reference is not defined by the MQTT specification. It is
+    /// expected to be a space separated list of references in the form `<host>[:<port>]`, where
+    /// `<host>` may be a bracketed IPv6 address. Only the first reference is used.
+    ///
+    /// Returns `None` if the Server Reference cannot be parsed.
+    pub(crate) fn from_server_reference(server_reference: &str, permanent: bool) -> Option<Self> {
+        let reference = server_reference.split_whitespace().next()?;
+        let (hostname, tcp_port) = if let Some(ipv6) = reference.strip_prefix('[') {
+            let (hostname, rest) = ipv6.split_once(']')?;
+            match rest {
+                "" => (hostname, None),
+                _ => (hostname, Some(rest.strip_prefix(':')?.parse().ok()?)),
+            }
+        } else {
+            match reference.split_once(':') {
+                Some((hostname, port)) => (hostname, Some(port.parse().ok()?)),
+                None => (reference, None),
+            }
+        };
+        if hostname.is_empty() {
+            return None;
+        }
+        Some(Self {
+            hostname: hostname.to_string(),
+            tcp_port,
+            permanent,
+        })
+    }
+}
+
+/// Returns `Some(permanent)` if the CONNACK reason code indicates a redirect, otherwise `None`.
+pub(crate) fn connack_redirect(code: ConnectReturnCode) -> Option<bool> {
+    match code {
+        ConnectReturnCode::UseAnotherServer => Some(false),
+        ConnectReturnCode::ServerMoved => Some(true),
+        _ => None,
+    }
+}
+
+/// Returns `Some(permanent)` if the DISCONNECT reason code indicates a redirect, otherwise `None`.
+pub(crate) fn disconnect_redirect(reason_code: DisconnectReasonCode) -> Option<bool> {
+    match reason_code {
+        DisconnectReasonCode::UseAnotherServer => Some(false),
+        DisconnectReasonCode::ServerMoved => Some(true),
+        _ => None,
+    }
+}
+
+/// Returns `Some(permanent)` if the connection error is a refused connection or a disconnect by
+/// the broker that indicates a redirect, otherwise `None`.
+pub(crate) fn error_redirect(error: &ConnectionError) -> Option<bool> {
+    match error {
+        ConnectionError::ConnectionRefused(rc) => connack_redirect(*rc),
+        ConnectionError::MqttState(StateError::ServerDisconnect { reason_code, .. }) => {
+            disconnect_redirect(*reason_code)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("broker.example.com", "broker.example.com", None; "hostname")]
+    #[test_case("broker.example.com:1883", "broker.example.com", Some(1883); "hostname and port")]
+    #[test_case("10.0.0.1:8883", "10.0.0.1", Some(8883); "ipv4 and port")]
+    #[test_case("[::1]", "::1", None; "ipv6")]
+    #[test_case("[::1]:8883", "::1", Some(8883); "ipv6 and port")]
+    #[test_case("first.example.com:1883 second.example.com:1883", "first.example.com", Some(1883); "multiple references")]
+    fn parse_server_reference(reference: &str, hostname: &str, tcp_port: Option<u16>) {
+        let redirect = ServerRedirect::from_server_reference(reference, true).unwrap();
+        assert_eq!(redirect.hostname, hostname);
+        assert_eq!(redirect.tcp_port, tcp_port);
+        assert!(redirect.permanent);
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case(":1883"; "missing hostname")]
+    #[test_case("broker.example.com:port"; "invalid port")]
+    #[test_case("[::1"; "unterminated ipv6")]
+    #[test_case("[::1]8883"; "ipv6 missing port separator")]
+    fn parse_invalid_server_reference(reference: &str) {
+        assert!(ServerRedirect::from_server_reference(reference, false).is_none());
+    }
+
+    #[test_case(ConnectionError::ConnectionRefused(ConnectReturnCode::UseAnotherServer), Some(false); "refused use another server")]
+    #[test_case(ConnectionError::ConnectionRefused(ConnectReturnCode::ServerMoved), Some(true); "refused server moved")]
+    #[test_case(ConnectionError::ConnectionRefused(ConnectReturnCode::ServerBusy), None; "refused server busy")]
+    #[test_case(ConnectionError::MqttState(StateError::ServerDisconnect { reason_code: DisconnectReasonCode::UseAnotherServer, reason_string: None }), Some(false); "disconnect use another server")]
+    #[test_case(ConnectionError::MqttState(StateError::ServerDisconnect { reason_code: DisconnectReasonCode::ServerMoved, reason_string: None }), Some(true); "disconnect server moved")]
+    #[test_case(ConnectionError::MqttState(StateError::ServerDisconnect { reason_code: DisconnectReasonCode::ServerShuttingDown, reason_string: None }), None; "disconnect server shutting down")]
+    #[test_case(ConnectionError::RequestsDone, None; "requests done")]
+    #[allow(clippy::needless_pass_by_value)]
+    fn redirect_from_error(error: ConnectionError, expected: Option<bool>) {
+        assert_eq!(error_redirect(&error), expected);
+    }
+}
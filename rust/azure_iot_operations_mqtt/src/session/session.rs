@@ -4,21 +4,39 @@
 //! Internal implementation of [`Session`] and [`SessionExitHandle`].
 
 use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use tokio::sync::Notify;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::{Notify, broadcast};
 use tokio_util::sync::CancellationToken;
 
 use crate::auth::{self, SatAuthContext};
-use crate::control_packet::QoS;
-use crate::error::ConnectionError;
+use crate::connection_settings::MqttConnectionSettings;
+use crate::control_packet::{DisconnectReasonCode, QoS};
+use crate::error::{ConnectionError, StateError};
 use crate::interface::{Event, Incoming, MqttClient, MqttDisconnect, MqttEventLoop};
+use crate::runtime;
 use crate::session::managed_client::SessionManagedClient;
-use crate::session::receiver::{IncomingPublishDispatcher, PublishReceiverManager};
+use crate::session::priority::{
+    PriorityLaneStats, PriorityLanes, PriorityLanesConfig, PublishPriority,
+};
+use crate::session::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::session::receiver::{
+    AckWatchdogConfig, DeduplicationConfig, IncomingPublishDispatcher, PublishReceiverManager,
+    StalledAck,
+};
 use crate::session::reconnect_policy::ReconnectPolicy;
+use crate::session::redirect::{
+    ServerRedirect, connack_redirect, disconnect_redirect, error_redirect,
+};
 use crate::session::state::SessionState;
 use crate::session::{SessionError, SessionErrorRepr, SessionExitError, SessionExitErrorKind};
+use crate::settings_watcher::{MqttConnectionSettingsWatcher, MqttConnectionSettingsWatcherError};
+
+/// Default maximum number of consecutive redirects followed without a successful connection
+pub(crate) const DEFAULT_MAX_REDIRECTS: u32 = 3;
 
 /// Client that manages connections over a single MQTT session.
 ///
@@ -47,6 +65,14 @@ where
     state: Arc<SessionState>,
     /// Notifier for a force exit signal
     notify_force_exit: Arc<Notify>,
+    /// Watcher for changes to the connection settings
+    settings_watcher: Option<MqttConnectionSettingsWatcher>,
+    /// Maximum number of consecutive redirects to follow without a successful connection
+    max_redirects: u32,
+    /// Priority lanes for outgoing publishes
+    priority_lanes: Option<Arc<PriorityLanes>>,
+    /// Rate limiter for outgoing publishes
+    rate_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl<C, EL> Session<C, EL>
@@ -77,9 +103,56 @@ where
             reconnect_policy,
             state: Arc::new(SessionState::default()),
             notify_force_exit: Arc::new(Notify::new()),
+            settings_watcher: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            priority_lanes: None,
+            rate_limiter: None,
         }
     }
 
+    /// Monitor received publishes for acknowledgements that have not been made within the
+    /// provided timeout. Stalled acknowledgements are logged and reported to any
+    /// [`SessionAckMonitor`]s. If `force_ack` is true, stalled publishes will also be acknowledged
+    /// so that they no longer block the acknowledgement of subsequent publishes.
+    pub fn set_ack_timeout(&mut self, timeout: Duration, force_ack: bool) {
+        self.incoming_pub_dispatcher
+            .set_ack_watchdog(AckWatchdogConfig { timeout, force_ack });
+    }
+
+    /// Suppress delivery of received publishes that are duplicates of recently received publishes,
+    /// as identified by the provided [`DeduplicationConfig`]. Suppressed duplicates are still
+    /// acknowledged, and counted by any [`SessionDuplicateMonitor`]s.
+    pub fn set_deduplication(&mut self, config: DeduplicationConfig) {
+        self.incoming_pub_dispatcher.set_deduplication(config);
+    }
+
+    /// Watch for changes to the connection settings using the provided
+    /// [`MqttConnectionSettingsWatcher`]. When a change is detected, the [`Session`] will
+    /// gracefully disconnect, and reconnect using the updated settings.
+    pub fn set_connection_settings_watcher(&mut self, watcher: MqttConnectionSettingsWatcher) {
+        self.settings_watcher = Some(watcher);
+    }
+
+    /// Set the maximum number of consecutive broker redirects (Use Another Server or Server Moved)
+    /// that will be followed without a successful connection before ending the [`Session`].
+    pub fn set_max_redirects(&mut self, max_redirects: u32) {
+        self.max_redirects = max_redirects;
+    }
+
+    /// Send outgoing publishes of [`SessionManagedClient`]s in priority lanes, as configured by the
+    /// provided [`PriorityLanesConfig`]. Only applies to [`SessionManagedClient`]s created after
+    /// this call.
+    pub fn set_priority_lanes(&mut self, config: PriorityLanesConfig) {
+        self.priority_lanes = Some(Arc::new(PriorityLanes::new(config)));
+    }
+
+    /// Limit the rate of outgoing publishes of [`SessionManagedClient`]s, as configured by the
+    /// provided [`RateLimitConfig`]. Only applies to [`SessionManagedClient`]s created after this
+    /// call.
+    pub fn set_rate_limit(&mut self, config: RateLimitConfig) {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(config)));
+    }
+
     /// Return a new instance of [`SessionExitHandle`] that can be used to end this [`Session`]
     pub fn create_exit_handle(&self) -> SessionExitHandle<C> {
         SessionExitHandle {
@@ -96,12 +169,35 @@ where
         }
     }
 
+    /// Return a new instance of [`SessionAckMonitor`] that can be used to monitor stalled acknowledgements
+    pub fn create_ack_monitor(&self) -> SessionAckMonitor {
+        SessionAckMonitor {
+            stalled_ack_rx: self.incoming_pub_dispatcher.subscribe_stalled_acks(),
+        }
+    }
+
+    /// Return a new instance of [`SessionDuplicateMonitor`] that can be used to monitor suppressed duplicates
+    pub fn create_duplicate_monitor(&self) -> SessionDuplicateMonitor {
+        SessionDuplicateMonitor {
+            suppressed_count: self.incoming_pub_dispatcher.get_suppressed_count(),
+        }
+    }
+
     /// Return a new instance of [`SessionManagedClient`] that can be used to send and receive messages
     pub fn create_managed_client(&self) -> SessionManagedClient<C> {
         SessionManagedClient {
             client_id: self.client_id.clone(),
             pub_sub: self.client.clone(),
             receiver_manager: self.receiver_manager.clone(),
+            priority_lanes: self.priority_lanes.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+        }
+    }
+
+    /// Return a new instance of [`SessionPriorityMonitor`] that can be used to monitor the priority lanes
+    pub fn create_priority_monitor(&self) -> SessionPriorityMonitor {
+        SessionPriorityMonitor {
+            priority_lanes: self.priority_lanes.clone(),
         }
     }
 
@@ -150,7 +246,7 @@ where
 
         // Background tasks
         let cancel_token = CancellationToken::new();
-        tokio::spawn({
+        runtime::spawn({
             let cancel_token = cancel_token.clone();
             let client = self.client.clone();
             run_background(client, sat_auth_context, cancel_token)
@@ -160,11 +256,39 @@ where
         let mut prev_connected = false;
         // Number of previous reconnect attempts
         let mut prev_reconnect_attempts = 0;
+        // Number of redirects followed since the last successful connection
+        let mut redirect_count = 0;
+        // Indicates whether a disconnect is expected in order to reconnect to an updated target
+        // (i.e. updated connection settings or a redirect)
+        let mut reconnect_pending = false;
+        // Indicates whether updated connection settings were applied without a disconnect, while
+        // a connection to the previous target may have been established
+        let mut disconnect_pending = false;
+        // Indicates whether the target has changed since the last successful connection, and thus
+        // the session state may not be present on the broker connected to next
+        let mut target_changed = false;
+        // Changes of connection settings, forwarded by the settings watcher task
+        let (settings_tx, mut settings_rx) = mpsc::unbounded_channel();
+        if let Some(settings_watcher) = self.settings_watcher.take() {
+            runtime::spawn(watch_connection_settings(
+                settings_watcher,
+                self.client.clone(),
+                self.state.clone(),
+                settings_tx,
+                cancel_token.clone(),
+            ));
+        }
         // Return value for the session indicating reason for exit
         let mut result = Ok(());
 
         // Handle events
         loop {
+            // Disconnect from the previous target in order to reconnect using updated connection
+            // settings, if the settings watcher task did not already do so
+            if std::mem::take(&mut disconnect_pending) && !reconnect_pending {
+                reconnect_pending = disconnect_to_reconnect(&self.client, &self.state).await;
+            }
+
             // Poll the next event/error unless a force exit occurs.
             let next = tokio::select! {
                 // Ensure that the force exit signal is checked first.
@@ -173,16 +297,57 @@ where
                 next = self.event_loop.poll() => { next },
             };
 
+            // Apply changes of connection settings between polls, so that a poll is never
+            // interrupted. The settings are applied before handling the result of the poll, as the
+            // result may be the disconnect triggered in order to apply them.
+            while let Ok(change) = settings_rx.try_recv() {
+                reconnect_pending |= change.disconnected;
+                if self.apply_connection_settings(change.settings) {
+                    target_changed = true;
+                    disconnect_pending |= !change.disconnected;
+                }
+            }
+
             match next {
+                // Connection refused by broker with a redirect to another broker
+                // NOTE: rumqttc reports refused connections as errors with only the reason code,
+                // so this only occurs if the event loop provides the CONNACK itself.
+                Ok(Event::Incoming(Incoming::ConnAck(connack)))
+                    if connack_redirect(connack.code).is_some() =>
+                {
+                    log::debug!("Incoming CONNACK: {connack:?}");
+                    let server_reference = connack
+                        .properties
+                        .as_ref()
+                        .and_then(|p| p.server_reference.as_deref());
+                    match self.follow_redirect(
+                        server_reference,
+                        connack_redirect(connack.code).unwrap_or_default(),
+                        &mut redirect_count,
+                    ) {
+                        Ok(followed) => {
+                            reconnect_pending |= followed;
+                            target_changed |= followed;
+                        }
+                        Err(e) => {
+                            result = Err(e);
+                            break;
+                        }
+                    }
+                }
+
                 Ok(Event::Incoming(Incoming::ConnAck(connack))) => {
                     // Update connection state
                     self.state.transition_connected();
-                    // Reset the counter on reconnect attempts
+                    // Reset the counters on reconnect attempts and redirects
                     prev_reconnect_attempts = 0;
+                    redirect_count = 0;
                     log::debug!("Incoming CONNACK: {connack:?}");
 
-                    // If the session is not present after a reconnect, end the session.
-                    if prev_connected && !connack.session_present {
+                    // If the session is not present after a reconnect to the same target, end the
+                    // session. A changed target may be a different broker, which does not have the
+                    // session state, so a new session is started on it.
+                    if prev_connected && !connack.session_present && !target_changed {
                         log::error!(
                             "Session state not present on broker after reconnect. Ending session."
                         );
@@ -203,6 +368,12 @@ where
                     }
                     // Otherwise, connection was successful
                     else {
+                        if prev_connected && !connack.session_present {
+                            log::warn!(
+                                "Session state not present on broker after reconnect to an updated target. A new session has started."
+                            );
+                        }
+                        target_changed = false;
                         prev_connected = true;
                         // Set clean start to false for subsequent connections
                         self.event_loop.set_clean_start(false);
@@ -246,7 +417,7 @@ where
                                         "Auto-ack of PKID {} may not be correctly ordered",
                                         publish.pkid
                                     );
-                                    tokio::spawn({
+                                    runtime::spawn({
                                         let acker = self.client.clone();
                                         async move {
                                             match acker.ack(&publish).await {
@@ -279,6 +450,40 @@ where
                     }
                 }
 
+                // Disconnected by broker
+                // NOTE: rumqttc reports disconnects by the broker as errors with only the reason
+                // code and reason string, so this only occurs if the event loop provides the
+                // DISCONNECT itself.
+                Ok(Event::Incoming(Incoming::Disconnect(disconnect))) => {
+                    log::debug!("Incoming DISCONNECT: {disconnect:?}");
+                    if disconnect.reason_code == DisconnectReasonCode::SessionTakenOver {
+                        self.state.transition_disconnected();
+                        log::error!(
+                            "Session taken over by another client with the same client ID. Ending session."
+                        );
+                        result = Err(SessionErrorRepr::SessionTakenOver);
+                        break;
+                    }
+                    if let Some(permanent) = disconnect_redirect(disconnect.reason_code) {
+                        let server_reference = disconnect
+                            .properties
+                            .as_ref()
+                            .and_then(|p| p.server_reference.as_deref());
+                        match self.follow_redirect(server_reference, permanent, &mut redirect_count)
+                        {
+                            Ok(followed) => {
+                                reconnect_pending |= followed;
+                                target_changed |= followed;
+                            }
+                            Err(e) => {
+                                self.state.transition_disconnected();
+                                result = Err(e);
+                                break;
+                            }
+                        }
+                    }
+                }
+
                 Ok(_e) => {
                     // There could be additional incoming and outgoing event responses here if
                     // more filters like the above one are applied
@@ -297,6 +502,55 @@ where
                     break;
                 }
 
+                // Session taken over by another client - reconnecting would take the MQTT session
+                // back from the other client, which would then do the same, so end the session.
+                Err(ConnectionError::MqttState(StateError::ServerDisconnect {
+                    reason_code: DisconnectReasonCode::SessionTakenOver,
+                    ..
+                })) => {
+                    self.state.transition_disconnected();
+                    log::error!(
+                        "Session taken over by another client with the same client ID. Ending session."
+                    );
+                    result = Err(SessionErrorRepr::SessionTakenOver);
+                    break;
+                }
+
+                // Disconnect in order to reconnect to an updated target completed
+                Err(e) if reconnect_pending => {
+                    reconnect_pending = false;
+                    self.state.transition_disconnected();
+                    log::info!("Reconnecting to updated target (disconnect: {e:?})");
+                }
+
+                // Connection refused or disconnected by broker with a redirect to another broker
+                Err(e) if error_redirect(&e).is_some() => {
+                    self.state.transition_disconnected();
+                    log::info!("Redirected by broker: {e:?}");
+                    let server_reference = self.event_loop.take_server_reference();
+                    match self.follow_redirect(
+                        server_reference.as_deref(),
+                        error_redirect(&e).unwrap_or_default(),
+                        &mut redirect_count,
+                    ) {
+                        // Reconnect to the redirected broker immediately
+                        Ok(true) => target_changed = true,
+                        // The redirect could not be followed, so defer to the reconnect policy
+                        Ok(false) => {
+                            if let Err(e) = self.delay_reconnect(prev_reconnect_attempts, &e).await
+                            {
+                                result = Err(e);
+                                break;
+                            }
+                            prev_reconnect_attempts += 1;
+                        }
+                        Err(e) => {
+                            result = Err(e);
+                            break;
+                        }
+                    }
+                }
+
                 // Connection refused by broker - unrecoverable
                 Err(ConnectionError::ConnectionRefused(rc)) => {
                     log::error!("Connection Refused: rc: {rc:?}");
@@ -311,24 +565,8 @@ where
                     // Always log the error itself at error level
                     log::error!("Error: {e:?}");
 
-                    // Defer decision to reconnect policy
-                    if let Some(delay) = self
-                        .reconnect_policy
-                        .next_reconnect_delay(prev_reconnect_attempts, &e)
-                    {
-                        log::info!("Attempting reconnect in {delay:?}");
-                        // Wait for either the reconnect delay time, or a force exit signal
-                        tokio::select! {
-                            () = tokio::time::sleep(delay) => {}
-                            () = self.notify_force_exit.notified() => {
-                                log::info!("Reconnect attempts halted by force exit");
-                                result = Err(SessionErrorRepr::ForceExit);
-                                break;
-                            }
-                        }
-                    } else {
-                        log::info!("Reconnect attempts halted by reconnect policy");
-                        result = Err(SessionErrorRepr::ReconnectHalted);
+                    if let Err(e) = self.delay_reconnect(prev_reconnect_attempts, &e).await {
+                        result = Err(e);
                         break;
                     }
                     prev_reconnect_attempts += 1;
@@ -340,6 +578,96 @@ where
         result.map_err(std::convert::Into::into)
     }
 
+    /// Apply updated connection settings to the event loop, to be used on the next connection
+    /// attempt.
+    ///
+    /// Returns true if the settings were applied.
+    fn apply_connection_settings(
+        &mut self,
+        change: Result<MqttConnectionSettings, MqttConnectionSettingsWatcherError>,
+    ) -> bool {
+        let target = match change {
+            Ok(settings) => settings.connection_target(),
+            Err(e) => {
+                log::error!("Invalid connection settings change ignored: {e}");
+                return false;
+            }
+        };
+        if let Err(e) = self.event_loop.set_connection_target(&target) {
+            log::error!("Cannot apply connection settings change: {e}");
+            return false;
+        }
+        log::info!(
+            "Connection settings changed. Target is now {}:{}",
+            target.hostname,
+            target.tcp_port
+        );
+        true
+    }
+
+    /// Follow a redirect to another broker for subsequent connection attempts.
+    ///
+    /// Returns true if the redirect was followed, or false if it could not be followed due to
+    /// a missing or invalid server reference, in which case the current broker continues to be
+    /// used. Returns an error if following the redirect would exceed the redirect limit.
+    fn follow_redirect(
+        &mut self,
+        server_reference: Option<&str>,
+        permanent: bool,
+        redirect_count: &mut u32,
+    ) -> Result<bool, SessionErrorRepr> {
+        let Some(redirect) =
+            server_reference.and_then(|r| ServerRedirect::from_server_reference(r, permanent))
+        else {
+            log::warn!(
+                "Redirected by broker without a valid server reference ({server_reference:?}). Continuing with current broker."
+            );
+            return Ok(false);
+        };
+        if *redirect_count >= self.max_redirects {
+            log::error!(
+                "Redirect limit of {} exceeded. Ending session.",
+                self.max_redirects
+            );
+            return Err(SessionErrorRepr::RedirectLimitExceeded(self.max_redirects));
+        }
+        *redirect_count += 1;
+        self.event_loop
+            .set_broker(redirect.hostname.clone(), redirect.tcp_port);
+        // The broker redirected to does not have the session state, so start a new session on it
+        self.event_loop.set_clean_start(true);
+        self.state.transition_redirected(redirect);
+        Ok(true)
+    }
+
+    /// Wait before the next reconnect attempt as determined by the reconnect policy.
+    ///
+    /// Returns an error if reconnect attempts are halted by the reconnect policy or a force exit.
+    async fn delay_reconnect(
+        &self,
+        prev_reconnect_attempts: u32,
+        error: &ConnectionError,
+    ) -> Result<(), SessionErrorRepr> {
+        // Defer decision to reconnect policy
+        if let Some(delay) = self
+            .reconnect_policy
+            .next_reconnect_delay(prev_reconnect_attempts, error)
+        {
+            log::info!("Attempting reconnect in {delay:?}");
+            // Wait for either the reconnect delay time, or a force exit signal
+            tokio::select! {
+                () = runtime::sleep(delay) => Ok(()),
+                () = self.notify_force_exit.notified() => {
+                    log::info!("Reconnect attempts halted by force exit");
+                    Err(SessionErrorRepr::ForceExit)
+                }
+            }
+        } else {
+            log::info!("Reconnect attempts halted by reconnect policy");
+            Err(SessionErrorRepr::ReconnectHalted)
+        }
+    }
+
     /// Helper for triggering a session exit and logging the result
     async fn trigger_session_exit(&self) {
         let exit_handle = self.create_exit_handle();
@@ -350,6 +678,66 @@ where
     }
 }
 
+/// Change of connection settings to be applied by [`Session.run()`] between polls of the event loop
+struct SettingsChange {
+    /// Updated connection settings, or the error reading them
+    settings: Result<MqttConnectionSettings, MqttConnectionSettingsWatcherError>,
+    /// Indicates whether a disconnect was triggered in order to reconnect using the settings
+    disconnected: bool,
+}
+
+/// Forward changes of connection settings to [`Session.run()`], triggering a disconnect if
+/// connected, so that the poll of the event loop returns and the settings are applied before the
+/// next poll.
+async fn watch_connection_settings(
+    mut watcher: MqttConnectionSettingsWatcher,
+    client: impl MqttClient,
+    state: Arc<SessionState>,
+    changes: UnboundedSender<SettingsChange>,
+    cancel_token: CancellationToken,
+) {
+    loop {
+        let settings = tokio::select! {
+            () = cancel_token.cancelled() => {
+                log::debug!("Connection settings watcher task cancelled");
+                return;
+            }
+            settings = watcher.changed() => settings,
+        };
+        let disconnect = settings.is_ok() && state.is_connected() && !state.desire_exit();
+        // NOTE: The change must be sent before disconnecting, so that it is available to be
+        // applied when the poll of the event loop returns due to the disconnect.
+        let change = SettingsChange {
+            settings,
+            disconnected: disconnect,
+        };
+        if changes.send(change).is_err() {
+            // The session has ended
+            return;
+        }
+        if disconnect && !disconnect_to_reconnect(&client, &state).await {
+            log::warn!("Updated connection settings will be used on the next reconnect");
+        }
+    }
+}
+
+/// Disconnect in order to reconnect to an updated target, if connected.
+///
+/// Returns true if a disconnect was triggered.
+async fn disconnect_to_reconnect(client: &impl MqttClient, state: &SessionState) -> bool {
+    // If not connected, the updated target will be used on the next connection attempt
+    if !state.is_connected() || state.desire_exit() {
+        return false;
+    }
+    match client.disconnect().await {
+        Ok(()) => true,
+        Err(e) => {
+            log::error!("Cannot disconnect to reconnect to updated target: {e:?}");
+            false
+        }
+    }
+}
+
 /// Run background tasks for [`Session.run()`]
 async fn run_background(
     client: impl MqttClient + Clone,
@@ -380,7 +768,7 @@ async fn run_background(
             log::error!("Error renewing SAT token, retrying...");
             retrying = true;
             // Wait before retrying
-            tokio::time::sleep(Duration::from_secs(10)).await;
+            runtime::sleep(Duration::from_secs(10)).await;
         }
     }
 
@@ -489,7 +877,7 @@ where
     /// * [`SessionExitError`] of kind [`SessionExitErrorKind::BrokerUnavailable`] if the Session is not connected to the broker within the specified timeout interval.
     ///   within the timeout interval.
     pub async fn try_exit_timeout(&self, timeout: Duration) -> Result<(), SessionExitError> {
-        tokio::time::timeout(timeout, self.try_exit())
+        runtime::timeout(timeout, self.try_exit())
             .await
             .map_err(|_| SessionExitError {
                 attempted: true,
@@ -513,7 +901,7 @@ where
         let _ = self.trigger_exit_user().await;
         // 1 second grace period to gracefully complete
         tokio::select! {
-            () = tokio::time::sleep(Duration::from_secs(1)) => {
+            () = runtime::sleep(Duration::from_secs(1)) => {
                 log::debug!("Grace period for graceful session exit expired. Force exiting session");
                 // NOTE: There is only one waiter on this Notify at any time.
                 self.force_exit.notify_one();
@@ -572,4 +960,329 @@ impl SessionConnectionMonitor {
     pub async fn disconnected(&self) {
         self.state.condition_disconnected().await;
     }
+
+    /// Returns the broker the [`Session`] was most recently redirected to, if any.
+    #[must_use]
+    pub fn redirect(&self) -> Option<ServerRedirect> {
+        self.state.redirect()
+    }
+}
+
+/// Monitor for stalled acknowledgements of publishes received by the [`Session`].
+///
+/// Only reports stalls if an ack timeout has been configured on the [`Session`].
+pub struct SessionAckMonitor {
+    stalled_ack_rx: broadcast::Receiver<StalledAck>,
+}
+
+impl SessionAckMonitor {
+    /// Wait until the acknowledgement of a received publish is reported as stalled.
+    ///
+    /// Returns [`None`] if the [`Session`] has been dropped.
+    pub async fn stalled(&mut self) -> Option<StalledAck> {
+        loop {
+            match self.stalled_ack_rx.recv().await {
+                Ok(stalled_ack) => return Some(stalled_ack),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    log::warn!("{n} stalled ack notifications were missed");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// Monitor for duplicate publishes suppressed by the [`Session`].
+///
+/// Only reports suppressions if deduplication has been configured on the [`Session`].
+#[derive(Clone)]
+pub struct SessionDuplicateMonitor {
+    suppressed_count: Arc<AtomicU64>,
+}
+
+impl SessionDuplicateMonitor {
+    /// Returns the number of received publishes that have been suppressed as duplicates.
+    #[must_use]
+    pub fn suppressed_count(&self) -> u64 {
+        self.suppressed_count.load(Ordering::Relaxed)
+    }
+}
+
+/// Monitor for the priority lanes of outgoing publishes of the [`Session`].
+///
+/// Only reports metrics if priority lanes have been configured on the [`Session`].
+#[derive(Clone)]
+pub struct SessionPriorityMonitor {
+    priority_lanes: Option<Arc<PriorityLanes>>,
+}
+
+impl SessionPriorityMonitor {
+    /// Returns the current metrics of the lane of the provided [`PublishPriority`].
+    #[must_use]
+    pub fn lane_stats(&self, priority: PublishPriority) -> PriorityLaneStats {
+        self.priority_lanes
+            .as_ref()
+            .map(|lanes| lanes.stats(priority))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use async_trait::async_trait;
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::control_packet::ConnectReturnCode;
+    use crate::error::{ConnectionTargetError, ErrorClassification};
+    use crate::interface::ConnectionTarget;
+    use crate::interface_mocks::MockClient;
+    use crate::session::SessionErrorKind;
+
+    /// Brokers set on a [`ScriptedEventLoop`], in order
+    type Brokers = Arc<Mutex<Vec<(String, Option<u16>)>>>;
+
+    /// Clean start values set on a [`ScriptedEventLoop`], in order
+    type CleanStarts = Arc<Mutex<Vec<bool>>>;
+
+    /// Event loop that returns a scripted sequence of events and errors, each optionally
+    /// accompanied by a Server Reference.
+    struct ScriptedEventLoop {
+        script: VecDeque<(Result<Event, ConnectionError>, Option<&'static str>)>,
+        server_reference: Option<String>,
+        brokers: Brokers,
+        clean_starts: CleanStarts,
+    }
+
+    impl ScriptedEventLoop {
+        fn new(
+            script: Vec<(Result<Event, ConnectionError>, Option<&'static str>)>,
+        ) -> (Self, Brokers) {
+            let brokers = Arc::new(Mutex::new(vec![]));
+            (
+                Self {
+                    script: script.into(),
+                    server_reference: None,
+                    brokers: brokers.clone(),
+                    clean_starts: Arc::new(Mutex::new(vec![])),
+                },
+                brokers,
+            )
+        }
+
+        fn clean_starts(&self) -> CleanStarts {
+            self.clean_starts.clone()
+        }
+    }
+
+    #[async_trait]
+    impl MqttEventLoop for ScriptedEventLoop {
+        async fn poll(&mut self) -> Result<Event, ConnectionError> {
+            match self.script.pop_front() {
+                Some((next, server_reference)) => {
+                    self.server_reference = server_reference.map(ToString::to_string);
+                    next
+                }
+                None => Err(ConnectionError::RequestsDone),
+            }
+        }
+
+        fn set_clean_start(&mut self, clean_start: bool) {
+            self.clean_starts.lock().unwrap().push(clean_start);
+        }
+
+        fn set_authentication_method(&mut self, _authentication_method: Option<String>) {}
+
+        fn set_authentication_data(&mut self, _authentication_data: Option<Bytes>) {}
+
+        fn set_connection_target(
+            &mut self,
+            _target: &ConnectionTarget,
+        ) -> Result<(), ConnectionTargetError> {
+            Ok(())
+        }
+
+        fn set_broker(&mut self, hostname: String, tcp_port: Option<u16>) {
+            self.brokers.lock().unwrap().push((hostname, tcp_port));
+        }
+
+        fn take_server_reference(&mut self) -> Option<String> {
+            self.server_reference.take()
+        }
+
+        fn drop_connection(&mut self) {}
+    }
+
+    /// Reconnect policy that reconnects immediately after retriable errors
+    struct ImmediateReconnect;
+
+    impl ReconnectPolicy for ImmediateReconnect {
+        fn next_reconnect_delay(
+            &self,
+            _prev_attempts: u32,
+            error: &ConnectionError,
+        ) -> Option<Duration> {
+            error.is_retriable().then_some(Duration::ZERO)
+        }
+    }
+
+    fn session(event_loop: ScriptedEventLoop) -> Session<MockClient, ScriptedEventLoop> {
+        Session::new_from_injection(
+            MockClient::new(),
+            event_loop,
+            Box::new(ImmediateReconnect),
+            "test-client-id".to_string(),
+            None,
+        )
+    }
+
+    fn connack(session_present: bool) -> Event {
+        Event::Incoming(Incoming::ConnAck(rumqttc::v5::mqttbytes::v5::ConnAck {
+            session_present,
+            code: ConnectReturnCode::Success,
+            properties: None,
+        }))
+    }
+
+    fn server_disconnect(reason_code: DisconnectReasonCode) -> Result<Event, ConnectionError> {
+        Err(ConnectionError::MqttState(StateError::ServerDisconnect {
+            reason_code,
+            reason_string: None,
+        }))
+    }
+
+    #[tokio::test]
+    async fn connack_redirect() {
+        let (event_loop, brokers) = ScriptedEventLoop::new(vec![
+            (
+                Err(ConnectionError::ConnectionRefused(
+                    ConnectReturnCode::UseAnotherServer,
+                )),
+                Some("other.example.com:1883"),
+            ),
+            (Ok(connack(false)), None),
+        ]);
+        let session = session(event_loop);
+        let monitor = session.create_connection_monitor();
+
+        let err = session.run().await.unwrap_err();
+        assert_eq!(err.kind(), SessionErrorKind::ReconnectHalted);
+        assert_eq!(
+            *brokers.lock().unwrap(),
+            vec![("other.example.com".to_string(), Some(1883))]
+        );
+        assert_eq!(
+            monitor.redirect(),
+            Some(ServerRedirect {
+                hostname: "other.example.com".to_string(),
+                tcp_port: Some(1883),
+                permanent: false,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn disconnect_redirect() {
+        let (event_loop, brokers) = ScriptedEventLoop::new(vec![
+            (Ok(connack(false)), None),
+            (
+                server_disconnect(DisconnectReasonCode::ServerMoved),
+                Some("moved.example.com"),
+            ),
+            (Ok(connack(false)), None),
+        ]);
+        let clean_starts = event_loop.clean_starts();
+        let session = session(event_loop);
+        let monitor = session.create_connection_monitor();
+
+        let err = session.run().await.unwrap_err();
+        // A new session was started on the broker redirected to, rather than the session being
+        // reported as lost
+        assert_eq!(err.kind(), SessionErrorKind::ReconnectHalted);
+        assert_eq!(*clean_starts.lock().unwrap(), vec![false, true, false]);
+        assert_eq!(
+            *brokers.lock().unwrap(),
+            vec![("moved.example.com".to_string(), None)]
+        );
+        assert_eq!(
+            monitor.redirect(),
+            Some(ServerRedirect {
+                hostname: "moved.example.com".to_string(),
+                tcp_port: None,
+                permanent: true,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn session_lost_without_redirect() {
+        let (event_loop, _) = ScriptedEventLoop::new(vec![
+            (Ok(connack(false)), None),
+            (
+                server_disconnect(DisconnectReasonCode::ServerShuttingDown),
+                None,
+            ),
+            (Ok(connack(false)), None),
+            // Completion of the disconnect triggered by the session ending
+            (
+                Err(ConnectionError::MqttState(StateError::ConnectionAborted)),
+                None,
+            ),
+        ]);
+        let clean_starts = event_loop.clean_starts();
+        let session = session(event_loop);
+
+        // Reconnecting to the same broker without the session state ends the session
+        let err = session.run().await.unwrap_err();
+        assert_eq!(err.kind(), SessionErrorKind::SessionLost);
+        assert_eq!(*clean_starts.lock().unwrap(), vec![false]);
+    }
+
+    #[tokio::test]
+    async fn redirect_without_server_reference() {
+        let (event_loop, brokers) = ScriptedEventLoop::new(vec![
+            (Ok(connack(false)), None),
+            (
+                server_disconnect(DisconnectReasonCode::UseAnotherServer),
+                None,
+            ),
+            (Ok(connack(true)), None),
+        ]);
+        let session = session(event_loop);
+        let monitor = session.create_connection_monitor();
+
+        // The redirect is not followed, and the reconnect policy reconnects to the current broker
+        let err = session.run().await.unwrap_err();
+        assert_eq!(err.kind(), SessionErrorKind::ReconnectHalted);
+        assert!(brokers.lock().unwrap().is_empty());
+        assert_eq!(monitor.redirect(), None);
+    }
+
+    #[tokio::test]
+    async fn max_redirects_exceeded() {
+        let refused = || {
+            Err(ConnectionError::ConnectionRefused(
+                ConnectReturnCode::ServerMoved,
+            ))
+        };
+        let (event_loop, brokers) = ScriptedEventLoop::new(vec![
+            (refused(), Some("first.example.com")),
+            (refused(), Some("second.example.com")),
+            (refused(), Some("third.example.com")),
+        ]);
+        let mut session = session(event_loop);
+        session.set_max_redirects(2);
+
+        let err = session.run().await.unwrap_err();
+        assert_eq!(err.kind(), SessionErrorKind::RedirectLimitExceeded);
+        assert_eq!(
+            *brokers.lock().unwrap(),
+            vec![
+                ("first.example.com".to_string(), None),
+                ("second.example.com".to_string(), None),
+            ]
+        );
+    }
 }
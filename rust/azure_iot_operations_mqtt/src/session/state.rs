@@ -8,6 +8,8 @@ use std::sync::RwLock;
 
 use tokio::sync::Notify;
 
+use crate::session::ServerRedirect;
+
 /// Information used to track the state of the Session.
 pub struct SessionState {
     /// State information locked for concurrency protection
@@ -25,6 +27,8 @@ struct InnerSessionState {
     connected: bool,
     /// Indicates if a Session exit is desired, and if so, by whom.
     desire_exit: DesireExit,
+    /// The most recent broker the Session was redirected to, if any.
+    redirect: Option<ServerRedirect>,
 }
 
 // NOTE: There could be more methods implemented here, but they would not be used yet,
@@ -48,6 +52,11 @@ impl SessionState {
         !matches!(self.state.read().unwrap().desire_exit, DesireExit::No)
     }
 
+    /// Return the most recent broker the Session was redirected to, if any
+    pub fn redirect(&self) -> Option<ServerRedirect> {
+        self.state.read().unwrap().redirect.clone()
+    }
+
     /// Wait until the Session is connected.
     /// Returns immediately if the Session is already connected.
     #[allow(dead_code)]
@@ -117,6 +126,22 @@ impl SessionState {
         log::debug!("{state:?}");
     }
 
+    /// Update the state to reflect a redirection to another broker
+    pub fn transition_redirected(&self, redirect: ServerRedirect) {
+        let mut state = self.state.write().unwrap();
+        log::info!(
+            "Redirected to {}:{} (permanent: {})",
+            redirect.hostname,
+            redirect
+                .tcp_port
+                .map_or_else(|| "<current port>".to_string(), |p| p.to_string()),
+            redirect.permanent
+        );
+        state.redirect = Some(redirect);
+        self.state_change.notify_waiters();
+        log::debug!("{state:?}");
+    }
+
     /// Update the state to reflect the Session is running
     pub fn transition_running(&self) {
         let mut state = self.state.write().unwrap();
@@ -170,6 +195,7 @@ impl Default for InnerSessionState {
             lifecycle_status: LifecycleStatus::NotStarted,
             connected: false,
             desire_exit: DesireExit::No,
+            redirect: None,
         }
     }
 }
@@ -192,6 +218,7 @@ impl fmt::Debug for InnerSessionState {
             .field("lifecycle_status", &self.lifecycle_status)
             .field("connected", &self.connected)
             .field("desire_exit", &self.desire_exit)
+            .field("redirect", &self.redirect)
             .finish()
     }
 }
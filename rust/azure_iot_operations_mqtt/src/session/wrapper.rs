@@ -6,16 +6,24 @@ use async_trait::async_trait;
 use bytes::Bytes;
 
 use crate::MqttConnectionSettings;
+use crate::MqttConnectionSettingsWatcher;
 use crate::control_packet::{
-    Publish, PublishProperties, QoS, SubscribeProperties, UnsubscribeProperties,
+    Publish, PublishProperties, QoS, SubscribeProperties, SubscriptionOptions,
+    UnsubscribeProperties,
 };
 use crate::error::{PublishError, SubscribeError, UnsubscribeError};
-use crate::interface::{AckToken, CompletionToken, ManagedClient, MqttPubSub, PubReceiver};
+use crate::interface::{
+    AckToken, CompletionToken, ManagedClient, MqttClient, MqttEventLoop, MqttPubSub, PubReceiver,
+};
 use crate::rumqttc_adapter as adapter;
 use crate::session::managed_client;
 use crate::session::reconnect_policy::{ExponentialBackoffWithJitter, ReconnectPolicy};
 use crate::session::session;
-use crate::session::{SessionConfigError, SessionError, SessionExitError};
+use crate::session::{
+    DeduplicationConfig, PriorityLaneStats, PriorityLanesConfig, PublishPriority, RateLimitConfig,
+    ServerRedirect, SessionConfigError, SessionConfigErrorRepr, SessionError, SessionExitError,
+    StalledAck,
+};
 use crate::topic::TopicParseError;
 
 /// Client that manages connections over a single MQTT session.
@@ -38,6 +46,23 @@ pub struct SessionExitHandle(session::SessionExitHandle<adapter::ClientAlias>);
 #[derive(Clone)]
 pub struct SessionConnectionMonitor(session::SessionConnectionMonitor);
 
+/// Monitor for stalled acknowledgements of messages received by the [`Session`].
+///
+/// Only reports stalls if an `ack_timeout` has been configured in the [`SessionOptions`].
+pub struct SessionAckMonitor(session::SessionAckMonitor);
+
+/// Monitor for duplicate messages suppressed by the [`Session`].
+///
+/// Only reports suppressions if `deduplication` has been configured in the [`SessionOptions`].
+#[derive(Clone)]
+pub struct SessionDuplicateMonitor(session::SessionDuplicateMonitor);
+
+/// Monitor for the priority lanes of messages sent by the [`Session`].
+///
+/// Only reports metrics if `priority_lanes` have been configured in the [`SessionOptions`].
+#[derive(Clone)]
+pub struct SessionPriorityMonitor(session::SessionPriorityMonitor);
+
 /// An MQTT client that has it's connection state externally managed by a [`Session`].
 /// Can be used to send messages and create receivers for incoming messages.
 #[derive(Clone)]
@@ -61,6 +86,41 @@ pub struct SessionOptions {
     /// Indicates if the Session should use features specific for use with the AIO MQTT Broker
     #[builder(default = "true")]
     pub aio_broker_features: bool,
+    /// Maximum duration a received message may go unacknowledged before it is reported as stalled.
+    /// Since messages are acknowledged in the order they were received, a stalled acknowledgement
+    /// blocks the acknowledgement of all subsequently received messages.
+    /// If `None`, stalled acknowledgements are not monitored.
+    #[builder(default = "None")]
+    pub ack_timeout: Option<Duration>,
+    /// Indicates if a received message whose acknowledgement has stalled beyond the `ack_timeout`
+    /// should be forcibly acknowledged. Has no effect if `ack_timeout` is `None`.
+    #[builder(default = "false")]
+    pub force_ack_on_timeout: bool,
+    /// Configuration for suppressing redelivered duplicates of received messages.
+    /// Suppressed duplicates are still acknowledged.
+    /// If `None`, all received messages are delivered.
+    #[builder(default = "None")]
+    pub deduplication: Option<DeduplicationConfig>,
+    /// Watcher for changes to the connection settings provided via file mounts.
+    /// When a change is detected, the [`Session`] gracefully reconnects using the updated settings.
+    /// If `None`, the connection settings are not updated after the [`Session`] is created.
+    #[builder(default = "None")]
+    pub connection_settings_watcher: Option<MqttConnectionSettingsWatcher>,
+    /// Maximum number of consecutive broker redirects (Use Another Server or Server Moved) that
+    /// will be followed without a successful connection before the [`Session`] ends.
+    #[builder(default = "session::DEFAULT_MAX_REDIRECTS")]
+    pub max_redirects: u32,
+    /// Configuration for sending outgoing messages in priority lanes, so that control traffic
+    /// (e.g. RPC requests and responses) is queued ahead of waiting bulk traffic (e.g. telemetry)
+    /// when the outgoing queue is full.
+    /// If `None`, outgoing messages are queued in the order they are sent.
+    #[builder(default = "None")]
+    pub priority_lanes: Option<PriorityLanesConfig>,
+    /// Configuration for rate limiting outgoing messages, for the whole [`Session`] and/or per
+    /// topic filter, in messages and/or payload bytes per second.
+    /// If `None`, outgoing messages are not rate limited.
+    #[builder(default = "None")]
+    pub rate_limit: Option<RateLimitConfig>,
 }
 
 impl Session {
@@ -69,30 +129,9 @@ impl Session {
     /// # Errors
     /// Returns a [`SessionConfigError`] if there are errors using the session options.
     pub fn new(options: SessionOptions) -> Result<Self, SessionConfigError> {
-        let client_id = options.connection_settings.client_id.clone();
-        let sat_file = options.connection_settings.sat_file.clone();
-
-        // Add AIO metric to user properties when using AIO MQTT broker features
-        // TODO: consider user properties from being supported on SessionOptions or ConnectionSettings
-        let user_properties = if options.aio_broker_features {
-            vec![("metriccategory".into(), "aiosdk-rust".into())]
-        } else {
-            vec![]
-        };
-
-        let (client, event_loop) = adapter::client(
-            options.connection_settings,
-            options.outgoing_max,
-            true,
-            user_properties,
-        )?;
-        Ok(Session(session::Session::new_from_injection(
-            client,
-            event_loop,
-            options.reconnect_policy,
-            client_id,
-            sat_file,
-        )))
+        Ok(Session(new_session_with(options, |client, event_loop| {
+            (client, event_loop)
+        })?))
     }
 
     /// Return a new instance of [`SessionExitHandle`] that can be used to end this [`Session`]
@@ -105,6 +144,21 @@ impl Session {
         SessionConnectionMonitor(self.0.create_connection_monitor())
     }
 
+    /// Return a new instance of [`SessionAckMonitor`] that can be used to monitor stalled acknowledgements
+    pub fn create_ack_monitor(&self) -> SessionAckMonitor {
+        SessionAckMonitor(self.0.create_ack_monitor())
+    }
+
+    /// Return a new instance of [`SessionDuplicateMonitor`] that can be used to monitor suppressed duplicates
+    pub fn create_duplicate_monitor(&self) -> SessionDuplicateMonitor {
+        SessionDuplicateMonitor(self.0.create_duplicate_monitor())
+    }
+
+    /// Return a new instance of [`SessionPriorityMonitor`] that can be used to monitor the priority lanes
+    pub fn create_priority_monitor(&self) -> SessionPriorityMonitor {
+        SessionPriorityMonitor(self.0.create_priority_monitor())
+    }
+
     /// Return a new instance of [`SessionManagedClient`] that can be used to send and receive messages
     pub fn create_managed_client(&self) -> SessionManagedClient {
         SessionManagedClient(self.0.create_managed_client())
@@ -121,6 +175,61 @@ impl Session {
     }
 }
 
+/// Create a new internal [`session::Session`] with the provided options structure, allowing the
+/// underlying client and event loop to be wrapped (e.g. for recording) before use.
+pub(crate) fn new_session_with<C, EL>(
+    options: SessionOptions,
+    wrap: impl FnOnce(adapter::ClientAlias, adapter::EventLoopAlias) -> (C, EL),
+) -> Result<session::Session<C, EL>, SessionConfigError>
+where
+    C: MqttClient + Clone + Send + Sync + 'static,
+    EL: MqttEventLoop,
+{
+    let client_id = options.connection_settings.client_id.clone();
+    let sat_file = options.connection_settings.sat_file.clone();
+
+    // Add AIO metric to user properties when using AIO MQTT broker features
+    // TODO: consider user properties from being supported on SessionOptions or ConnectionSettings
+    let user_properties = if options.aio_broker_features {
+        vec![("metriccategory".into(), "aiosdk-rust".into())]
+    } else {
+        vec![]
+    };
+
+    let (client, event_loop) = adapter::client(
+        options.connection_settings,
+        options.outgoing_max,
+        true,
+        user_properties,
+    )
+    .map_err(SessionConfigErrorRepr::from)?;
+    let (client, event_loop) = wrap(client, event_loop);
+    let mut session = session::Session::new_from_injection(
+        client,
+        event_loop,
+        options.reconnect_policy,
+        client_id,
+        sat_file,
+    );
+    if let Some(ack_timeout) = options.ack_timeout {
+        session.set_ack_timeout(ack_timeout, options.force_ack_on_timeout);
+    }
+    if let Some(deduplication) = options.deduplication {
+        session.set_deduplication(deduplication);
+    }
+    if let Some(watcher) = options.connection_settings_watcher {
+        session.set_connection_settings_watcher(watcher);
+    }
+    session.set_max_redirects(options.max_redirects);
+    if let Some(priority_lanes) = options.priority_lanes {
+        session.set_priority_lanes(priority_lanes);
+    }
+    if let Some(rate_limit) = options.rate_limit {
+        session.set_rate_limit(rate_limit);
+    }
+    Ok(session)
+}
+
 impl ManagedClient for SessionManagedClient {
     type PubReceiver = SessionPubReceiver;
 
@@ -140,6 +249,16 @@ impl ManagedClient for SessionManagedClient {
     fn create_unfiltered_pub_receiver(&self) -> SessionPubReceiver {
         SessionPubReceiver(self.0.create_unfiltered_pub_receiver())
     }
+
+    fn create_subscription_pub_receiver(
+        &self,
+        subscription_identifier: usize,
+    ) -> SessionPubReceiver {
+        SessionPubReceiver(
+            self.0
+                .create_subscription_pub_receiver(subscription_identifier),
+        )
+    }
 }
 
 #[async_trait]
@@ -186,6 +305,15 @@ impl MqttPubSub for SessionManagedClient {
             .await
     }
 
+    async fn subscribe_with_options(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        options: SubscriptionOptions,
+    ) -> Result<CompletionToken, SubscribeError> {
+        self.0.subscribe_with_options(topic, qos, options).await
+    }
+
     async fn unsubscribe(
         &self,
         topic: impl Into<String> + Send,
@@ -289,4 +417,35 @@ impl SessionConnectionMonitor {
     pub async fn disconnected(&self) {
         self.0.disconnected().await;
     }
+
+    /// Returns the broker the [`Session`] was most recently redirected to, if any.
+    #[must_use]
+    pub fn redirect(&self) -> Option<ServerRedirect> {
+        self.0.redirect()
+    }
+}
+
+impl SessionAckMonitor {
+    /// Wait until the acknowledgement of a received message is reported as stalled.
+    ///
+    /// Returns [`None`] if the [`Session`] has ended.
+    pub async fn stalled(&mut self) -> Option<StalledAck> {
+        self.0.stalled().await
+    }
+}
+
+impl SessionDuplicateMonitor {
+    /// Returns the number of received messages that have been suppressed as duplicates.
+    #[must_use]
+    pub fn suppressed_count(&self) -> u64 {
+        self.0.suppressed_count()
+    }
+}
+
+impl SessionPriorityMonitor {
+    /// Returns the current metrics of the lane of the provided [`PublishPriority`].
+    #[must_use]
+    pub fn lane_stats(&self, priority: PublishPriority) -> PriorityLaneStats {
+        self.0.lane_stats(priority)
+    }
 }
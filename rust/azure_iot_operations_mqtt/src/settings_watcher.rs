@@ -0,0 +1,175 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Watcher for changes to MQTT connection settings provided via file mounts.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify::RecommendedWatcher;
+use notify_debouncer_full::{Debouncer, RecommendedCache, new_debouncer};
+use thiserror::Error;
+use tokio::sync::Notify;
+
+use crate::connection_settings::MqttConnectionSettings;
+use crate::runtime;
+
+/// Error type for the [`MqttConnectionSettingsWatcher`].
+#[derive(Debug, Error)]
+pub enum MqttConnectionSettingsWatcherError {
+    /// None of the file mounts that can be watched are configured in the environment.
+    #[error("no connection settings file mounts are configured in the environment")]
+    NoFileMounts,
+    /// Error occurred while watching the file mounts.
+    #[error("{0}")]
+    WatcherError(#[from] notify::Error),
+    /// The file mounts contain invalid connection settings.
+    #[error("invalid connection settings in file mounts: {0}")]
+    InvalidSettings(String),
+}
+
+/// Watches the file mounts used by [`MqttConnectionSettingsBuilder::from_file_mount`](crate::MqttConnectionSettingsBuilder::from_file_mount)
+/// for changes to the broker target address (`BROKER_TARGET_ADDRESS`), TLS usage
+/// (`BROKER_USE_TLS`) and TLS trust bundle.
+///
+/// Can be used directly to be notified of changes, or provided to a
+/// [`Session`](crate::session::Session) via the
+/// [`SessionOptions`](crate::session::SessionOptions) in order to have the
+/// [`Session`](crate::session::Session) gracefully reconnect using the updated settings.
+pub struct MqttConnectionSettingsWatcher {
+    /// Current connection settings
+    settings: MqttConnectionSettings,
+    /// Contents of the current TLS trust bundle
+    ca_bundle: Option<Vec<u8>>,
+    /// File mount watchers, held to keep the watchers alive
+    #[allow(dead_code)]
+    watchers: Vec<Debouncer<RecommendedWatcher, RecommendedCache>>,
+    /// Notifier for changes in the file mounts
+    notify: Arc<Notify>,
+}
+
+impl MqttConnectionSettingsWatcher {
+    /// Create a new [`MqttConnectionSettingsWatcher`] that reports changes in the file mounts
+    /// relative to the provided [`MqttConnectionSettings`].
+    ///
+    /// # Errors
+    /// Returns a [`MqttConnectionSettingsWatcherError`] if no file mounts are configured in the
+    /// environment, or if the file mounts cannot be watched.
+    pub fn new(
+        settings: MqttConnectionSettings,
+    ) -> Result<Self, MqttConnectionSettingsWatcherError> {
+        let mut watch_paths = vec![];
+        // The AEP config map is a directory
+        if let Ok(aep_path) = env::var("AEP_CONFIGMAP_MOUNT_PATH") {
+            watch_paths.push(PathBuf::from(aep_path));
+        }
+        // The TLS trust bundle is a file, so watch its directory, as mounts are updated by
+        // replacing symlinks rather than modifying the file itself
+        let ca_file = env::var("BROKER_TLS_TRUST_BUNDLE_CACERT_MOUNT_PATH").ok();
+        if let Some(parent_path) = ca_file.as_deref().and_then(|f| Path::new(f).parent()) {
+            watch_paths.push(parent_path.to_path_buf());
+        }
+        if watch_paths.is_empty() {
+            return Err(MqttConnectionSettingsWatcherError::NoFileMounts);
+        }
+
+        let notify = Arc::new(Notify::new());
+        let watchers = watch_paths
+            .iter()
+            .map(|path| watch_directory(path, notify.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let ca_bundle = read_ca_bundle(&settings);
+        Ok(Self {
+            settings,
+            ca_bundle,
+            watchers,
+            notify,
+        })
+    }
+
+    /// Wait until the connection settings in the file mounts change, and return the updated
+    /// [`MqttConnectionSettings`].
+    ///
+    /// Only the broker target address and TLS settings are updated from the file mounts.
+    /// Changes to the file mounts that do not affect these settings are ignored.
+    ///
+    /// # Errors
+    /// Returns a [`MqttConnectionSettingsWatcherError`] if the changed file mounts contain
+    /// invalid settings. Watching for further changes may continue after an error.
+    pub async fn changed(
+        &mut self,
+    ) -> Result<MqttConnectionSettings, MqttConnectionSettingsWatcherError> {
+        loop {
+            self.notify.notified().await;
+            // Reading the file mounts is blocking I/O, so do it off of the async runtime
+            let current = self.settings.clone();
+            let (settings, ca_bundle) = runtime::spawn_blocking(move || {
+                let settings = current.with_file_mount_target()?;
+                let ca_bundle = read_ca_bundle(&settings);
+                Ok((settings, ca_bundle))
+            })
+            .await
+            .map_err(MqttConnectionSettingsWatcherError::InvalidSettings)?;
+            if settings.same_target(&self.settings) && ca_bundle == self.ca_bundle {
+                log::debug!("File mounts changed, but connection settings are unchanged");
+                continue;
+            }
+            self.settings = settings.clone();
+            self.ca_bundle = ca_bundle;
+            return Ok(settings);
+        }
+    }
+
+    /// Return the current [`MqttConnectionSettings`].
+    #[must_use]
+    pub fn settings(&self) -> &MqttConnectionSettings {
+        &self.settings
+    }
+}
+
+/// Watch a directory for changes, notifying the provided [`Notify`] on any change.
+fn watch_directory(
+    path: &Path,
+    notify: Arc<Notify>,
+) -> Result<Debouncer<RecommendedWatcher, RecommendedCache>, notify::Error> {
+    let mut debouncer = new_debouncer(
+        Duration::from_secs(5),
+        None,
+        move |res: Result<Vec<notify_debouncer_full::DebouncedEvent>, Vec<notify::Error>>| {
+            match res {
+                Ok(events) => {
+                    if events.iter().any(|e| {
+                        // Only notify on non-open events
+                        !matches!(
+                            e.event.kind,
+                            notify::EventKind::Access(notify::event::AccessKind::Open(_))
+                        )
+                    }) {
+                        notify.notify_one();
+                    }
+                }
+                Err(err) => {
+                    log::error!("Error watching connection settings file mount: {err:?}");
+                }
+            }
+        },
+    )?;
+    debouncer.watch(path, notify::RecursiveMode::NonRecursive)?;
+    Ok(debouncer)
+}
+
+/// Read the contents of the TLS trust bundle of the provided settings, if present.
+fn read_ca_bundle(settings: &MqttConnectionSettings) -> Option<Vec<u8>> {
+    let ca_file = settings.ca_file.as_ref()?;
+    match fs::read(ca_file) {
+        Ok(contents) => Some(contents),
+        Err(e) => {
+            log::warn!("Cannot read CA file {ca_file}: {e}");
+            None
+        }
+    }
+}
@@ -9,6 +9,29 @@ pub mod hybrid_logical_clock;
 /// This module contains a trait that payload structs should implement to be serializable.
 pub mod payload_serialize;
 
+/// This module contains serde-backed implementations of the payload serialization trait.
+#[cfg(any(feature = "json", feature = "cbor", feature = "msgpack"))]
+pub mod serde_payload;
+
+/// This module contains Protobuf implementations of the payload serialization trait.
+#[cfg(feature = "protobuf")]
+pub mod protobuf_payload;
+
+/// This module contains the payload compression applied by senders and invokers.
+pub mod compression;
+
+/// This module contains the splitting of large payloads into chunks and their reassembly.
+pub mod chunking;
+
+/// This module contains the end-to-end payload encryption applied by senders, invokers and executors.
+pub mod encryption;
+
+/// This module contains the signing of telemetry messages and the verification of their signatures.
+pub mod signing;
+
+/// This module contains helpers shared by the encryption, signing and chunking of messages.
+pub(crate) mod message_properties;
+
 /// This module contains the error type for the Azure IoT Operations Protocol.
 pub mod aio_protocol_error;
 
@@ -5,6 +5,10 @@ use std::error::Error;
 use std::fmt;
 use std::time::Duration;
 
+use azure_iot_operations_mqtt::error::{
+    ConnectionError, ErrorClassification, PublishError, SubscribeError, UnsubscribeError,
+};
+
 use crate::common::{
     hybrid_logical_clock::{HLCError, HLCErrorKind, ParseHLCError},
     topic_processor::{TopicPatternError, TopicPatternErrorKind},
@@ -86,6 +90,9 @@ pub struct AIOProtocolError {
     /// The acceptable major protocol versions for the command executor if it rejected the
     /// command request, or for the command invoker if it rejected the command response.
     pub supported_protocol_major_versions: Option<Vec<u16>>,
+    /// The number of attempts made by a command invoker with a retry policy before it gave up
+    /// on the command invocation.
+    pub attempt_count: Option<u32>,
 }
 
 impl fmt::Display for AIOProtocolError {
@@ -252,6 +259,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -281,6 +289,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -310,6 +319,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -340,6 +350,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -368,6 +379,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -397,6 +409,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -425,6 +438,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -457,6 +471,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -486,6 +501,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -514,6 +530,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -541,6 +558,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -571,6 +589,7 @@ impl AIOProtocolError {
             command_name,
             protocol_version: Some(protocol_version),
             supported_protocol_major_versions: Some(supported_protocol_major_versions),
+            attempt_count: None,
         };
         e.ensure_error_message();
         e
@@ -584,6 +603,71 @@ impl AIOProtocolError {
     }
 }
 
+impl ErrorClassification for AIOProtocolErrorKind {
+    fn is_retriable(&self) -> bool {
+        match self {
+            AIOProtocolErrorKind::Timeout | AIOProtocolErrorKind::ClientError => true,
+            AIOProtocolErrorKind::HeaderMissing
+            | AIOProtocolErrorKind::HeaderInvalid
+            | AIOProtocolErrorKind::PayloadInvalid
+            | AIOProtocolErrorKind::Cancellation
+            | AIOProtocolErrorKind::ConfigurationInvalid
+            | AIOProtocolErrorKind::StateInvalid
+            | AIOProtocolErrorKind::InternalLogicError
+            | AIOProtocolErrorKind::UnknownError
+            | AIOProtocolErrorKind::ExecutionException
+            | AIOProtocolErrorKind::UnsupportedVersion => false,
+        }
+    }
+}
+
+impl ErrorClassification for AIOProtocolError {
+    fn is_retriable(&self) -> bool {
+        // Shallow errors are detected prior to any network communication, and thus are caused by
+        // invalid arguments or configuration that will not change on retry
+        if self.is_shallow {
+            return false;
+        }
+        if let Some(nested) = self.nested_classification() {
+            return nested.is_retriable();
+        }
+        match self.kind {
+            // A remote component may recover from a condition it considered unexpected, so
+            // the operation may succeed if retried
+            AIOProtocolErrorKind::StateInvalid
+            | AIOProtocolErrorKind::InternalLogicError
+            | AIOProtocolErrorKind::UnknownError => self.is_remote,
+            _ => self.kind.is_retriable(),
+        }
+    }
+
+    fn is_auth_related(&self) -> bool {
+        self.nested_classification()
+            .is_some_and(ErrorClassification::is_auth_related)
+    }
+}
+
+impl AIOProtocolError {
+    /// Returns the classification of the MQTT error that caused this error, if present
+    fn nested_classification(&self) -> Option<&dyn ErrorClassification> {
+        if self.kind != AIOProtocolErrorKind::ClientError {
+            return None;
+        }
+        let nested = self.nested_error.as_deref()?;
+        if let Some(e) = nested.downcast_ref::<PublishError>() {
+            Some(e)
+        } else if let Some(e) = nested.downcast_ref::<SubscribeError>() {
+            Some(e)
+        } else if let Some(e) = nested.downcast_ref::<UnsubscribeError>() {
+            Some(e)
+        } else if let Some(e) = nested.downcast_ref::<ConnectionError>() {
+            Some(e)
+        } else {
+            None
+        }
+    }
+}
+
 impl From<HLCError> for AIOProtocolError {
     fn from(error: HLCError) -> Self {
         let (property_name, message) = match error.kind() {
@@ -616,3 +700,56 @@ impl From<ParseHLCError> for AIOProtocolError {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use azure_iot_operations_mqtt::control_packet::ConnectReturnCode;
+    use azure_iot_operations_mqtt::error::PublishErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn classification() {
+        let timeout = AIOProtocolError::new_timeout_error(
+            false,
+            None,
+            "timeout",
+            Duration::from_secs(1),
+            None,
+            None,
+        );
+        assert!(timeout.is_retriable());
+
+        let config = AIOProtocolError::new_configuration_invalid_error(
+            None,
+            "property",
+            Value::Integer(0),
+            None,
+            None,
+        );
+        assert!(config.is_fatal());
+
+        let local_unknown = AIOProtocolError::new_unknown_error(false, false, None, None, None);
+        assert!(local_unknown.is_fatal());
+        let remote_unknown = AIOProtocolError::new_unknown_error(true, false, None, None, None);
+        assert!(remote_unknown.is_retriable());
+
+        let detached = AIOProtocolError::new_mqtt_error(
+            None,
+            Box::new(PublishError::new(PublishErrorKind::DetachedClient)),
+            None,
+        );
+        assert!(detached.is_fatal());
+        assert!(!detached.is_auth_related());
+
+        let not_authorized = AIOProtocolError::new_mqtt_error(
+            None,
+            Box::new(ConnectionError::ConnectionRefused(
+                ConnectReturnCode::NotAuthorized,
+            )),
+            None,
+        );
+        assert!(not_authorized.is_fatal());
+        assert!(not_authorized.is_auth_related());
+    }
+}
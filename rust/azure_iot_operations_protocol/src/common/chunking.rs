@@ -0,0 +1,1082 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Transparent chunking of payloads larger than the maximum packet size of the broker.
+//!
+//! A [`telemetry::Sender`](crate::telemetry::Sender), [`rpc_command::Invoker`](crate::rpc_command::Invoker)
+//! or [`rpc_command::Executor`](crate::rpc_command::Executor) configured with a [`Chunking`]
+//! option splits each payload larger than [`max_chunk_size`](Chunking::max_chunk_size) into
+//! sequenced chunks, published as separate messages. Each chunk carries the properties of the
+//! original message, along with its index in the [`UserProperty::ChunkIndex`] user property, the
+//! number of chunks in the [`UserProperty::ChunkCount`] user property, and the checksum of the
+//! complete payload in the [`UserProperty::ChunkChecksum`] user property. Chunks of a message share
+//! its correlation data, which identifies them as belonging together. Since a chunk of a UTF-8
+//! payload is not necessarily valid UTF-8, chunks are sent as unspecified bytes, and the payload
+//! format indicator of the message is carried in the [`UserProperty::ChunkFormatIndicator`] user
+//! property.
+//!
+//! Telemetry receivers, command executors and command invokers reassemble chunked messages before
+//! processing them, within the [`ReassemblyLimits`] they are configured with. Chunking is applied
+//! last when sending, so payloads are compressed, encrypted and signed as a whole.
+//!
+//! Chunks received with Quality of Service 1 are only acknowledged once their message is: the
+//! [`AckToken`]s of the buffered chunks are linked to the token of the last chunk, which is the
+//! one returned with the reassembled message. The receive maximum of the MQTT connection must
+//! therefore allow for all chunks of a message to be in flight at once, and be greater than the
+//! [`max_held_chunks`](ReassemblyLimits::max_held_chunks) of the receiver so that other messages
+//! can still be received. The tokens of a message that is not reassembled within the
+//! [timeout](ReassemblyLimits::timeout) are acknowledged when it elapses, so that they do not block
+//! the acknowledgement of later messages.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use azure_iot_operations_mqtt::control_packet::{Publish, PublishProperties};
+use azure_iot_operations_mqtt::interface::AckToken;
+use azure_iot_operations_mqtt::runtime;
+use bytes::{Bytes, BytesMut};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use super::aio_protocol_error::AIOProtocolError;
+use super::message_properties::{find_property, received_message_error};
+use super::payload_serialize::FormatIndicator;
+use super::user_properties::UserProperty;
+
+/// Default time allowed for all chunks of a message to be received
+pub const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default maximum size, in bytes, of a reassembled payload
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default maximum size, in bytes, of the chunks buffered for all messages being reassembled
+pub const DEFAULT_MAX_BUFFERED_SIZE: usize = 256 * 1024 * 1024;
+
+/// Default minimum size, in bytes, assumed for the chunks of a message
+pub const DEFAULT_MIN_CHUNK_SIZE: usize = 1024;
+
+/// Default maximum number of chunks whose acknowledgement is held for all messages being reassembled
+pub const DEFAULT_MAX_HELD_CHUNKS: usize = 1024;
+
+/// Chunking configuration of a telemetry sender, command invoker or command executor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chunking {
+    /// Maximum size, in bytes, of the payload of each chunk. Must be greater than zero, and small
+    /// enough for a chunk and its properties to fit in the maximum packet size of the broker.
+    pub max_chunk_size: usize,
+}
+
+impl Chunking {
+    /// Creates a new [`Chunking`] splitting payloads into chunks of at most `max_chunk_size` bytes.
+    #[must_use]
+    pub fn new(max_chunk_size: usize) -> Self {
+        Self { max_chunk_size }
+    }
+
+    /// Validates the configuration, returning an [`AIOProtocolError`] of kind
+    /// [`ConfigurationInvalid`](super::aio_protocol_error::AIOProtocolErrorKind::ConfigurationInvalid)
+    /// if [`max_chunk_size`](Chunking::max_chunk_size) is zero.
+    pub(crate) fn validate(self, command_name: Option<String>) -> Result<(), AIOProtocolError> {
+        if self.max_chunk_size == 0 {
+            return Err(AIOProtocolError::new_configuration_invalid_error(
+                None,
+                "chunking.max_chunk_size",
+                super::aio_protocol_error::Value::Integer(0),
+                Some("Maximum chunk size must be greater than zero".to_string()),
+                command_name,
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Limits applied by a telemetry receiver, command executor or command invoker when reassembling
+/// chunked messages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReassemblyLimits {
+    /// Time allowed for all chunks of a message to be received, from the reception of its first
+    /// chunk. Incomplete messages are discarded after this time.
+    pub timeout: Duration,
+    /// Maximum size, in bytes, of a reassembled payload
+    pub max_message_size: usize,
+    /// Maximum size, in bytes, of the chunks buffered for all messages being reassembled
+    pub max_buffered_size: usize,
+    /// Minimum size, in bytes, assumed for the chunks of a message. Messages with more chunks than
+    /// fit in [`max_message_size`](ReassemblyLimits::max_message_size) at this size are rejected
+    /// on their first chunk. Must not be greater than the chunk size of the senders.
+    pub min_chunk_size: usize,
+    /// Maximum number of chunks received with Quality of Service 1 whose acknowledgement is held
+    /// for all messages being reassembled, which must be lower than the receive maximum of the
+    /// MQTT connection. Messages with more chunks are rejected, as they could never be
+    /// acknowledged.
+    pub max_held_chunks: usize,
+}
+
+impl Default for ReassemblyLimits {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_REASSEMBLY_TIMEOUT,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            max_buffered_size: DEFAULT_MAX_BUFFERED_SIZE,
+            min_chunk_size: DEFAULT_MIN_CHUNK_SIZE,
+            max_held_chunks: DEFAULT_MAX_HELD_CHUNKS,
+        }
+    }
+}
+
+/// Error reassembling a chunked message
+#[derive(thiserror::Error, Debug)]
+pub enum ChunkingError {
+    /// A user property required to reassemble the message is missing
+    #[error("Chunked message is missing the '{0}' user property")]
+    PropertyMissing(UserProperty),
+    /// A user property required to reassemble the message is invalid
+    #[error("Invalid value '{value}' for the '{property}' user property of a chunked message")]
+    PropertyInvalid {
+        /// The invalid user property
+        property: UserProperty,
+        /// Value of the user property
+        value: String,
+    },
+    /// The chunk has no correlation data identifying the message it belongs to
+    #[error("Chunked message is missing correlation data")]
+    CorrelationDataMissing,
+    /// The chunks of the message disagree on the number of chunks or the checksum
+    #[error("Chunks of the message have inconsistent chunk counts or checksums")]
+    Inconsistent,
+    /// The reassembled payload would be larger than [`ReassemblyLimits::max_message_size`]
+    #[error("Chunked message exceeds the maximum size of {0} bytes")]
+    MessageTooLarge(usize),
+    /// Buffering the chunk would exceed [`ReassemblyLimits::max_buffered_size`]
+    #[error("Buffered chunks exceed the maximum size of {0} bytes")]
+    BufferFull(usize),
+    /// The message has, or holding the chunk would exceed, more than
+    /// [`ReassemblyLimits::max_held_chunks`] chunks
+    #[error("Chunks held for reassembly exceed the maximum of {0} chunks")]
+    TooManyChunks(usize),
+    /// The checksum of the reassembled payload does not match the checksum of the message
+    #[error("Checksum of the reassembled payload does not match")]
+    ChecksumMismatch,
+}
+
+impl ChunkingError {
+    /// The name and, if present, the value of the user property causing the error
+    pub(crate) fn invalid_property(&self) -> Option<(String, Option<String>)> {
+        match self {
+            ChunkingError::PropertyMissing(property) => Some((property.to_string(), None)),
+            ChunkingError::PropertyInvalid { property, value } => {
+                Some((property.to_string(), Some(value.clone())))
+            }
+            ChunkingError::CorrelationDataMissing => Some(("Correlation Data".to_string(), None)),
+            _ => None,
+        }
+    }
+
+    /// Converts an error reassembling a received message into an [`AIOProtocolError`]
+    pub(crate) fn into_reassembly_error(self, command_name: Option<String>) -> AIOProtocolError {
+        let invalid_property = self.invalid_property();
+        received_message_error(self, invalid_property, command_name)
+    }
+}
+
+/// User properties added to each chunk by [`split`]
+const CHUNK_PROPERTIES: [UserProperty; 3] = [
+    UserProperty::ChunkIndex,
+    UserProperty::ChunkCount,
+    UserProperty::ChunkChecksum,
+];
+
+/// Splits `payload` into chunks if `chunking` is configured and the payload is larger than its
+/// [`max_chunk_size`](Chunking::max_chunk_size).
+///
+/// Returns the payload and properties of each message to publish, in order. A payload that does
+/// not need to be chunked is returned as is, with `properties`. Chunks of a payload that is not
+/// unspecified bytes are sent as unspecified bytes, carrying the payload format indicator in the
+/// [`UserProperty::ChunkFormatIndicator`].
+pub(crate) fn split(
+    chunking: Option<Chunking>,
+    payload: Vec<u8>,
+    mut properties: PublishProperties,
+) -> Vec<(Bytes, PublishProperties)> {
+    let Some(chunking) = chunking.filter(|c| payload.len() > c.max_chunk_size) else {
+        return vec![(payload.into(), properties)];
+    };
+
+    let checksum = format!("{:08x}", crc32(&payload));
+    if let Some(format_indicator) = properties
+        .payload_format_indicator
+        .filter(|format_indicator| *format_indicator != FormatIndicator::UnspecifiedBytes as u8)
+    {
+        properties.user_properties.push((
+            UserProperty::ChunkFormatIndicator.to_string(),
+            format_indicator.to_string(),
+        ));
+        properties.payload_format_indicator = Some(FormatIndicator::UnspecifiedBytes as u8);
+    }
+    let payload = Bytes::from(payload);
+    let count = payload.len().div_ceil(chunking.max_chunk_size);
+    (0..count)
+        .map(|index| {
+            let start = index * chunking.max_chunk_size;
+            let end = payload.len().min(start + chunking.max_chunk_size);
+            let mut chunk_properties = properties.clone();
+            chunk_properties.user_properties.extend([
+                (UserProperty::ChunkIndex.to_string(), index.to_string()),
+                (UserProperty::ChunkCount.to_string(), count.to_string()),
+                (UserProperty::ChunkChecksum.to_string(), checksum.clone()),
+            ]);
+            (payload.slice(start..end), chunk_properties)
+        })
+        .collect()
+}
+
+/// Identifies the chunks of one message
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct ChunkKey {
+    topic: Bytes,
+    correlation_data: Bytes,
+}
+
+/// Chunks received so far for a message
+struct PendingMessage {
+    /// First chunk received, without its payload, providing the properties of the message
+    template: Publish,
+    chunks: Vec<Option<Bytes>>,
+    /// Tokens of the chunks received so far, held until the message is acknowledged
+    ack_tokens: HeldAckTokens,
+    received: usize,
+    size: usize,
+    checksum: String,
+    format_indicator: Option<u8>,
+    deadline: Instant,
+}
+
+/// Ack tokens held for the chunks of a message being reassembled.
+///
+/// A timer acknowledges the tokens when the reassembly timeout elapses, so that the chunks of a
+/// message that is never completed do not block the acknowledgement of later messages. The timer
+/// is cancelled when the tokens are dropped.
+#[derive(Default)]
+struct HeldAckTokens {
+    tokens: Arc<Mutex<Vec<AckToken>>>,
+    count: usize,
+    timer: Option<DropGuard>,
+}
+
+impl HeldAckTokens {
+    /// Holds `token`, starting the timer acknowledging the held tokens at `deadline` if needed.
+    fn hold(&mut self, token: AckToken, deadline: Instant) {
+        self.lock().push(token);
+        self.count += 1;
+        if self.timer.is_some() {
+            return;
+        }
+        let cancellation_token = CancellationToken::new();
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        runtime::spawn({
+            let cancellation_token = cancellation_token.clone();
+            let tokens = self.tokens.clone();
+            async move {
+                tokio::select! {
+                    () = cancellation_token.cancelled() => { /* Message reassembled or discarded */ },
+                    () = runtime::sleep(timeout) => {
+                        let mut tokens =
+                            std::mem::take(&mut *tokens.lock().unwrap_or_else(PoisonError::into_inner));
+                        if let Some(token) = tokens.pop() {
+                            log::warn!(
+                                "Acknowledging {} chunks of a chunked message not reassembled before the timeout",
+                                tokens.len() + 1
+                            );
+                            if let Err(e) = token.link(tokens).ack().await {
+                                log::error!("Ack error for the chunks of an expired chunked message: {e}");
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        self.timer = Some(cancellation_token.drop_guard());
+    }
+
+    /// Takes the held tokens, which the timer no longer acknowledges.
+    fn take(&mut self) -> Vec<AckToken> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<AckToken>> {
+        self.tokens.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for HeldAckTokens {
+    fn drop(&mut self) {
+        // Acknowledge the tokens of a discarded message now rather than when the timer task exits
+        drop(self.take());
+    }
+}
+
+/// Chunk metadata parsed from the user properties of a received message
+struct ChunkInfo {
+    index: usize,
+    count: usize,
+    checksum: String,
+    /// Payload format indicator of the message, if it was not unspecified bytes
+    format_indicator: Option<u8>,
+}
+
+impl ChunkInfo {
+    /// Parses the chunk user properties, returning [`None`] if the message is not chunked.
+    fn parse(user_properties: &[(String, String)]) -> Result<Option<Self>, ChunkingError> {
+        let [index, count, checksum] = CHUNK_PROPERTIES.map(|property| {
+            find_property(user_properties, property).map(|value| (property, value))
+        });
+        if index.is_none() && count.is_none() && checksum.is_none() {
+            return Ok(None);
+        }
+
+        let parse_number = |property: Option<(UserProperty, &str)>, missing: UserProperty| {
+            let (property, value) = property.ok_or(ChunkingError::PropertyMissing(missing))?;
+            value
+                .parse::<usize>()
+                .map_err(|_| ChunkingError::PropertyInvalid {
+                    property,
+                    value: value.to_string(),
+                })
+        };
+        let count = parse_number(count, UserProperty::ChunkCount)?;
+        if count == 0 {
+            return Err(ChunkingError::PropertyInvalid {
+                property: UserProperty::ChunkCount,
+                value: count.to_string(),
+            });
+        }
+        let index = parse_number(index, UserProperty::ChunkIndex)?;
+        if index >= count {
+            return Err(ChunkingError::PropertyInvalid {
+                property: UserProperty::ChunkIndex,
+                value: index.to_string(),
+            });
+        }
+        let (_, checksum) =
+            checksum.ok_or(ChunkingError::PropertyMissing(UserProperty::ChunkChecksum))?;
+        if checksum.len() != 8 || !checksum.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ChunkingError::PropertyInvalid {
+                property: UserProperty::ChunkChecksum,
+                value: checksum.to_string(),
+            });
+        }
+
+        let format_indicator = find_property(user_properties, UserProperty::ChunkFormatIndicator)
+            .map(|value| {
+                FormatIndicator::from_user_property(value)
+                    .map(|format_indicator| format_indicator as u8)
+                    .map_err(|_| ChunkingError::PropertyInvalid {
+                        property: UserProperty::ChunkFormatIndicator,
+                        value: value.to_string(),
+                    })
+            })
+            .transpose()?;
+
+        Ok(Some(Self {
+            index,
+            count,
+            checksum: checksum.to_ascii_lowercase(),
+            format_indicator,
+        }))
+    }
+}
+
+/// Reassembles chunked messages received by a telemetry receiver, command executor or command
+/// invoker.
+pub(crate) struct ChunkBuffer {
+    limits: ReassemblyLimits,
+    pending: HashMap<ChunkKey, PendingMessage>,
+    buffered_size: usize,
+    /// Number of ack tokens held for all pending messages
+    held_tokens: usize,
+}
+
+impl ChunkBuffer {
+    /// Creates a new, empty [`ChunkBuffer`] enforcing `limits`.
+    pub(crate) fn new(limits: ReassemblyLimits) -> Self {
+        Self {
+            limits,
+            pending: HashMap::new(),
+            buffered_size: 0,
+            held_tokens: 0,
+        }
+    }
+
+    /// Adds a received message to the buffer, along with its `ack_token` if any.
+    ///
+    /// Returns the message as is if it is not chunked, the reassembled message if `publish` is
+    /// its last missing chunk, or [`None`] if chunks of the message are still missing. Duplicate
+    /// chunks are ignored.
+    ///
+    /// The `ack_token` of a buffered or duplicate chunk is taken and held until its message is reassembled,
+    /// at which point the tokens of all its chunks are linked to the `ack_token` of the last one,
+    /// so that they are acknowledged together with the message. The tokens of a discarded message
+    /// are dropped, which acknowledges its chunks, and the tokens of an incomplete message are
+    /// acknowledged once its [timeout](ReassemblyLimits::timeout) elapses.
+    ///
+    /// Incomplete messages older than the timeout are discarded.
+    ///
+    /// # Errors
+    /// Returns a [`ChunkingError`] if the chunk is invalid, inconsistent with previous chunks of
+    /// its message, exceeds the [`ReassemblyLimits`], or completes a message whose checksum does
+    /// not match. The chunks buffered for the message are discarded, and `ack_token` is left to
+    /// the caller.
+    pub(crate) fn insert(
+        &mut self,
+        mut publish: Publish,
+        ack_token: &mut Option<AckToken>,
+    ) -> Result<Option<Publish>, ChunkingError> {
+        self.discard_expired();
+
+        let Some(properties) = publish.properties.as_mut() else {
+            return Ok(Some(publish));
+        };
+        let Some(info) = ChunkInfo::parse(&properties.user_properties)? else {
+            return Ok(Some(publish));
+        };
+        // Bound the chunk count before allocating the chunks of a new message
+        if info.count > self.limits.max_held_chunks {
+            return Err(ChunkingError::TooManyChunks(self.limits.max_held_chunks));
+        }
+        if info.count.saturating_mul(self.limits.min_chunk_size) > self.limits.max_message_size {
+            return Err(ChunkingError::MessageTooLarge(self.limits.max_message_size));
+        }
+        let correlation_data = properties
+            .correlation_data
+            .clone()
+            .ok_or(ChunkingError::CorrelationDataMissing)?;
+        let chunk_properties = CHUNK_PROPERTIES
+            .iter()
+            .chain([&UserProperty::ChunkFormatIndicator])
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        properties
+            .user_properties
+            .retain(|(k, _)| !chunk_properties.contains(k));
+
+        let key = ChunkKey {
+            topic: publish.topic.clone(),
+            correlation_data,
+        };
+        let payload = std::mem::take(&mut publish.payload);
+        let limits = self.limits;
+        let pending = self
+            .pending
+            .entry(key.clone())
+            .or_insert_with(|| PendingMessage {
+                template: publish,
+                chunks: vec![None; info.count],
+                ack_tokens: HeldAckTokens::default(),
+                received: 0,
+                size: 0,
+                checksum: info.checksum.clone(),
+                format_indicator: info.format_indicator,
+                deadline: Instant::now() + limits.timeout,
+            });
+
+        if pending.chunks.len() != info.count
+            || pending.checksum != info.checksum
+            || pending.format_indicator != info.format_indicator
+        {
+            self.discard(&key);
+            return Err(ChunkingError::Inconsistent);
+        }
+        if pending.chunks[info.index].is_some() {
+            // Redelivered chunk, acknowledged along with the message
+            self.hold(&key, ack_token)?;
+            return Ok(None);
+        }
+        if pending.size + payload.len() > limits.max_message_size {
+            self.discard(&key);
+            return Err(ChunkingError::MessageTooLarge(limits.max_message_size));
+        }
+        if self.buffered_size + payload.len() > limits.max_buffered_size {
+            self.discard(&key);
+            return Err(ChunkingError::BufferFull(limits.max_buffered_size));
+        }
+
+        pending.size += payload.len();
+        pending.received += 1;
+        self.buffered_size += payload.len();
+        pending.chunks[info.index] = Some(payload);
+        if pending.received < pending.chunks.len() {
+            self.hold(&key, ack_token)?;
+            return Ok(None);
+        }
+
+        let Some(mut pending) = self.discard(&key) else {
+            unreachable!("The pending message was just updated");
+        };
+        let mut payload = BytesMut::with_capacity(pending.size);
+        for chunk in pending.chunks.into_iter().flatten() {
+            payload.extend_from_slice(&chunk);
+        }
+        if format!("{:08x}", crc32(&payload)) != pending.checksum {
+            return Err(ChunkingError::ChecksumMismatch);
+        }
+        let mut publish = pending.template;
+        publish.payload = payload.freeze();
+        if let (Some(properties), Some(format_indicator)) =
+            (publish.properties.as_mut(), pending.format_indicator)
+        {
+            properties.payload_format_indicator = Some(format_indicator);
+        }
+        *ack_token = ack_token
+            .take()
+            .map(|ack_token| ack_token.link(pending.ack_tokens.take()));
+        Ok(Some(publish))
+    }
+
+    /// Holds `ack_token`, if any, until the message identified by `key` is reassembled.
+    ///
+    /// # Errors
+    /// Returns [`ChunkingError::TooManyChunks`] if [`ReassemblyLimits::max_held_chunks`] tokens
+    /// are already held, after discarding the message. `ack_token` is left to the caller.
+    fn hold(
+        &mut self,
+        key: &ChunkKey,
+        ack_token: &mut Option<AckToken>,
+    ) -> Result<(), ChunkingError> {
+        if ack_token.is_none() {
+            return Ok(());
+        }
+        if self.held_tokens >= self.limits.max_held_chunks {
+            self.discard(key);
+            return Err(ChunkingError::TooManyChunks(self.limits.max_held_chunks));
+        }
+        if let (Some(pending), Some(ack_token)) = (self.pending.get_mut(key), ack_token.take()) {
+            pending.ack_tokens.hold(ack_token, pending.deadline);
+            self.held_tokens += 1;
+        }
+        Ok(())
+    }
+
+    /// Removes the chunks buffered for the message identified by `key`.
+    fn discard(&mut self, key: &ChunkKey) -> Option<PendingMessage> {
+        let pending = self.pending.remove(key)?;
+        self.buffered_size -= pending.size;
+        self.held_tokens -= pending.ack_tokens.count;
+        Some(pending)
+    }
+
+    /// Discards incomplete messages whose timeout has elapsed.
+    fn discard_expired(&mut self) {
+        let now = Instant::now();
+        let expired = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in expired {
+            if let Some(pending) = self.discard(&key) {
+                log::warn!(
+                    "Discarding chunked message on topic {:?}: {} of {} chunks received before the timeout",
+                    key.topic,
+                    pending.received,
+                    pending.chunks.len()
+                );
+            }
+        }
+    }
+}
+
+/// Lookup table of the CRC-32 (IEEE 802.3) checksum
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        #[allow(clippy::cast_possible_truncation)] // i < 256
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE 802.3) checksum of `data`
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(u32::MAX, |crc, &byte| {
+        CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    const TOPIC: &str = "test/chunked";
+
+    fn message_properties(correlation_data: &[u8]) -> PublishProperties {
+        PublishProperties {
+            correlation_data: Some(Bytes::copy_from_slice(correlation_data)),
+            content_type: Some("application/octet-stream".to_string()),
+            user_properties: vec![("custom".to_string(), "value".to_string())],
+            ..Default::default()
+        }
+    }
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| u8::try_from(i % 251).unwrap()).collect()
+    }
+
+    fn chunks(payload: Vec<u8>, max_chunk_size: usize, correlation_data: &[u8]) -> Vec<Publish> {
+        split(
+            Some(Chunking::new(max_chunk_size)),
+            payload,
+            message_properties(correlation_data),
+        )
+        .into_iter()
+        .map(|(payload, properties)| Publish {
+            topic: TOPIC.into(),
+            payload,
+            properties: Some(properties),
+            ..Default::default()
+        })
+        .collect()
+    }
+
+    fn set_property(publish: &mut Publish, property: UserProperty, value: &str) {
+        let key = property.to_string();
+        for (k, v) in &mut publish.properties.as_mut().unwrap().user_properties {
+            if *k == key {
+                *v = value.to_string();
+            }
+        }
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test_case(0; "empty")]
+    #[test_case(99; "smaller")]
+    #[test_case(100; "equal")]
+    fn split_small_payload(len: usize) {
+        let chunked = split(
+            Some(Chunking::new(100)),
+            payload(len),
+            message_properties(b"id"),
+        );
+        assert_eq!(chunked.len(), 1);
+        assert_eq!(chunked[0].0, payload(len));
+        assert_eq!(chunked[0].1, message_properties(b"id"));
+    }
+
+    #[test]
+    fn split_without_chunking() {
+        let chunked = split(None, payload(1000), message_properties(b"id"));
+        assert_eq!(chunked.len(), 1);
+        assert_eq!(chunked[0].1, message_properties(b"id"));
+    }
+
+    #[test_case(101, 2; "one_byte_over")]
+    #[test_case(1000, 10; "exact_multiple")]
+    #[test_case(1050, 11; "partial_last_chunk")]
+    fn split_large_payload(len: usize, expected_count: usize) {
+        let chunked = chunks(payload(len), 100, b"id");
+        assert_eq!(chunked.len(), expected_count);
+        for (index, chunk) in chunked.iter().enumerate() {
+            assert!(chunk.payload.len() <= 100);
+            let info = ChunkInfo::parse(&chunk.properties.as_ref().unwrap().user_properties)
+                .unwrap()
+                .unwrap();
+            assert_eq!(info.index, index);
+            assert_eq!(info.count, expected_count);
+        }
+    }
+
+    #[test_case(false; "in_order")]
+    #[test_case(true; "reversed")]
+    fn reassemble(reversed: bool) {
+        let mut chunked = chunks(payload(1050), 100, b"id");
+        if reversed {
+            chunked.reverse();
+        }
+        let count = chunked.len();
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        for (i, chunk) in chunked.into_iter().enumerate() {
+            let result = buffer.insert(chunk, &mut None).unwrap();
+            if i + 1 < count {
+                assert!(result.is_none());
+            } else {
+                let publish = result.unwrap();
+                assert_eq!(publish.payload, payload(1050));
+                assert_eq!(publish.topic, TOPIC);
+                assert_eq!(publish.properties.unwrap(), message_properties(b"id"));
+            }
+        }
+        assert!(buffer.pending.is_empty());
+        assert_eq!(buffer.buffered_size, 0);
+    }
+
+    fn utf8_chunks(payload: Vec<u8>, max_chunk_size: usize) -> Vec<Publish> {
+        let mut properties = message_properties(b"id");
+        properties.payload_format_indicator = Some(FormatIndicator::Utf8EncodedCharacterData as u8);
+        split(Some(Chunking::new(max_chunk_size)), payload, properties)
+            .into_iter()
+            .map(|(payload, properties)| Publish {
+                topic: TOPIC.into(),
+                payload,
+                properties: Some(properties),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn split_utf8_payload() {
+        for chunk in utf8_chunks(vec![b'a'; 250], 100) {
+            let properties = chunk.properties.unwrap();
+            assert_eq!(properties.payload_format_indicator, Some(0));
+            assert!(properties.user_properties.contains(&(
+                UserProperty::ChunkFormatIndicator.to_string(),
+                "1".to_string()
+            )));
+        }
+    }
+
+    #[test]
+    fn reassemble_utf8_payload() {
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        let mut completed = Vec::new();
+        for chunk in utf8_chunks(vec![b'a'; 250], 100) {
+            completed.extend(buffer.insert(chunk, &mut None).unwrap());
+        }
+        assert_eq!(completed.len(), 1);
+        let mut expected = message_properties(b"id");
+        expected.payload_format_indicator = Some(FormatIndicator::Utf8EncodedCharacterData as u8);
+        assert_eq!(completed[0].properties.as_ref().unwrap(), &expected);
+    }
+
+    #[test]
+    fn inconsistent_format_indicator() {
+        let mut chunked = utf8_chunks(vec![b'a'; 250], 100);
+        chunked[1]
+            .properties
+            .as_mut()
+            .unwrap()
+            .user_properties
+            .retain(|(k, _)| *k != UserProperty::ChunkFormatIndicator.to_string());
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        assert!(
+            buffer
+                .insert(chunked[0].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(matches!(
+            buffer.insert(chunked[1].clone(), &mut None),
+            Err(ChunkingError::Inconsistent)
+        ));
+        assert!(buffer.pending.is_empty());
+    }
+
+    #[test]
+    fn invalid_format_indicator() {
+        let mut chunked = utf8_chunks(vec![b'a'; 250], 100);
+        set_property(&mut chunked[0], UserProperty::ChunkFormatIndicator, "2");
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        let Err(e) = buffer.insert(chunked[0].clone(), &mut None) else {
+            panic!("Expected an invalid chunk");
+        };
+        assert_eq!(
+            e.invalid_property().unwrap().0,
+            UserProperty::ChunkFormatIndicator.to_string()
+        );
+    }
+
+    #[test]
+    fn interleaved_messages() {
+        let first = chunks(payload(250), 100, b"first");
+        let second = chunks(vec![7; 250], 100, b"second");
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        let mut completed = Vec::new();
+        for (a, b) in first.into_iter().zip(second) {
+            completed.extend(buffer.insert(a, &mut None).unwrap());
+            completed.extend(buffer.insert(b, &mut None).unwrap());
+        }
+        assert_eq!(completed.len(), 2);
+        assert_eq!(completed[0].payload, payload(250));
+        assert_eq!(completed[1].payload, vec![7; 250]);
+    }
+
+    #[test]
+    fn unchunked_message_passes_through() {
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        let publish = Publish {
+            topic: TOPIC.into(),
+            payload: payload(10).into(),
+            properties: Some(message_properties(b"id")),
+            ..Default::default()
+        };
+        assert_eq!(
+            buffer.insert(publish.clone(), &mut None).unwrap(),
+            Some(publish)
+        );
+    }
+
+    #[test]
+    fn duplicate_chunk_is_ignored() {
+        let chunked = chunks(payload(250), 100, b"id");
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        assert!(
+            buffer
+                .insert(chunked[0].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            buffer
+                .insert(chunked[0].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            buffer
+                .insert(chunked[1].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        let publish = buffer
+            .insert(chunked[2].clone(), &mut None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(publish.payload, payload(250));
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let mut chunked = chunks(payload(250), 100, b"id");
+        chunked[1].payload = vec![0; 100].into();
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        assert!(
+            buffer
+                .insert(chunked[0].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            buffer
+                .insert(chunked[1].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(matches!(
+            buffer.insert(chunked[2].clone(), &mut None),
+            Err(ChunkingError::ChecksumMismatch)
+        ));
+        assert!(buffer.pending.is_empty());
+        assert_eq!(buffer.buffered_size, 0);
+    }
+
+    #[test]
+    fn inconsistent_chunk_count() {
+        let mut chunked = chunks(payload(250), 100, b"id");
+        set_property(&mut chunked[1], UserProperty::ChunkCount, "4");
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        assert!(
+            buffer
+                .insert(chunked[0].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(matches!(
+            buffer.insert(chunked[1].clone(), &mut None),
+            Err(ChunkingError::Inconsistent)
+        ));
+        assert!(buffer.pending.is_empty());
+    }
+
+    #[test]
+    fn message_too_large() {
+        let chunked = chunks(payload(250), 100, b"id");
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits {
+            max_message_size: 200,
+            min_chunk_size: 1,
+            ..Default::default()
+        });
+        assert!(
+            buffer
+                .insert(chunked[0].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            buffer
+                .insert(chunked[1].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(matches!(
+            buffer.insert(chunked[2].clone(), &mut None),
+            Err(ChunkingError::MessageTooLarge(200))
+        ));
+        assert_eq!(buffer.buffered_size, 0);
+    }
+
+    #[test_case("1025"; "above_max_held_chunks")]
+    #[test_case("18446744073709551615"; "max_usize")]
+    fn chunk_count_above_max_held_chunks(count: &str) {
+        let mut chunked = chunks(payload(250), 100, b"id");
+        set_property(&mut chunked[0], UserProperty::ChunkCount, count);
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        assert!(matches!(
+            buffer.insert(chunked[0].clone(), &mut None),
+            Err(ChunkingError::TooManyChunks(DEFAULT_MAX_HELD_CHUNKS))
+        ));
+        assert!(buffer.pending.is_empty());
+    }
+
+    #[test]
+    fn chunk_count_above_max_message_size() {
+        let mut chunked = chunks(payload(250), 100, b"id");
+        set_property(&mut chunked[0], UserProperty::ChunkCount, "11");
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits {
+            max_message_size: 1000,
+            min_chunk_size: 100,
+            ..Default::default()
+        });
+        assert!(matches!(
+            buffer.insert(chunked[0].clone(), &mut None),
+            Err(ChunkingError::MessageTooLarge(1000))
+        ));
+        assert!(buffer.pending.is_empty());
+    }
+
+    #[test]
+    fn buffer_full() {
+        let first = chunks(payload(250), 100, b"first");
+        let second = chunks(payload(250), 100, b"second");
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits {
+            max_buffered_size: 300,
+            ..Default::default()
+        });
+        assert!(
+            buffer
+                .insert(first[0].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            buffer
+                .insert(first[1].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            buffer
+                .insert(second[0].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(matches!(
+            buffer.insert(second[1].clone(), &mut None),
+            Err(ChunkingError::BufferFull(300))
+        ));
+        // The first message can still complete
+        assert!(
+            buffer
+                .insert(first[2].clone(), &mut None)
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(buffer.buffered_size, 0);
+    }
+
+    #[test]
+    fn incomplete_message_expires() {
+        let chunked = chunks(payload(250), 100, b"id");
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits {
+            timeout: Duration::ZERO,
+            ..Default::default()
+        });
+        assert!(
+            buffer
+                .insert(chunked[0].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(buffer.buffered_size, 100);
+        // Each chunk starts a new incomplete message, as the previous one has expired
+        assert!(
+            buffer
+                .insert(chunked[1].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            buffer
+                .insert(chunked[2].clone(), &mut None)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(buffer.pending.len(), 1);
+        assert_eq!(buffer.buffered_size, 50);
+    }
+
+    #[test_case(UserProperty::ChunkIndex, "x"; "index_not_a_number")]
+    #[test_case(UserProperty::ChunkIndex, "3"; "index_out_of_range")]
+    #[test_case(UserProperty::ChunkCount, "0"; "count_zero")]
+    #[test_case(UserProperty::ChunkChecksum, "xyz"; "checksum_not_hex")]
+    fn invalid_property(property: UserProperty, value: &str) {
+        let mut chunked = chunks(payload(250), 100, b"id");
+        set_property(&mut chunked[0], property, value);
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        let Err(e) = buffer.insert(chunked[0].clone(), &mut None) else {
+            panic!("Expected an invalid chunk");
+        };
+        assert_eq!(e.invalid_property().unwrap().0, property.to_string());
+    }
+
+    #[test]
+    fn missing_property() {
+        let mut chunked = chunks(payload(250), 100, b"id");
+        chunked[0]
+            .properties
+            .as_mut()
+            .unwrap()
+            .user_properties
+            .retain(|(k, _)| *k != UserProperty::ChunkChecksum.to_string());
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        assert!(matches!(
+            buffer.insert(chunked[0].clone(), &mut None),
+            Err(ChunkingError::PropertyMissing(UserProperty::ChunkChecksum))
+        ));
+    }
+
+    #[test]
+    fn missing_correlation_data() {
+        let mut chunked = chunks(payload(250), 100, b"id");
+        chunked[0].properties.as_mut().unwrap().correlation_data = None;
+        let mut buffer = ChunkBuffer::new(ReassemblyLimits::default());
+        assert!(matches!(
+            buffer.insert(chunked[0].clone(), &mut None),
+            Err(ChunkingError::CorrelationDataMissing)
+        ));
+    }
+
+    #[test]
+    fn zero_max_chunk_size_is_invalid() {
+        assert!(Chunking::new(0).validate(None).is_err());
+        assert!(Chunking::new(1).validate(None).is_ok());
+    }
+}
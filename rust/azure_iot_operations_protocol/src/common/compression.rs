@@ -0,0 +1,254 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Transparent compression of serialized payloads.
+//!
+//! A [`telemetry::Sender`](crate::telemetry::Sender) or
+//! [`rpc_command::Invoker`](crate::rpc_command::Invoker) configured with a [`Compression`] option
+//! compresses the serialized payload of each message before publishing it, and advertises the
+//! codec in the [`UserProperty::ContentEncoding`](super::user_properties::UserProperty::ContentEncoding)
+//! user property. Payloads smaller than the configured threshold, and payloads that would not
+//! get smaller, are sent uncompressed and without the user property.
+//!
+//! Telemetry receivers, command executors and command invokers decompress received payloads
+//! carrying the user property before deserializing them, so [`PayloadSerialize`](super::payload_serialize::PayloadSerialize)
+//! implementations never see compressed bytes.
+//!
+//! Each codec is enabled by a cargo feature:
+//! - [`CompressionCodec::Gzip`] - `gzip`
+//! - [`CompressionCodec::Zstd`] - `zstd`
+//!
+//! Received payloads compressed with a codec whose feature is not enabled are rejected.
+
+use std::fmt::{self, Display, Formatter};
+use std::io::Read;
+use std::str::FromStr;
+
+/// Default minimum size, in bytes, of a serialized payload for it to be compressed
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Maximum size, in bytes, of a decompressed payload. Larger payloads are rejected, so that a
+/// small compressed payload cannot exhaust the memory of the receiver.
+pub const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+
+/// Compression codec applied to a payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompressionCodec {
+    /// gzip ([RFC 1952](https://www.rfc-editor.org/rfc/rfc1952)), content encoding `gzip`
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Zstandard ([RFC 8878](https://www.rfc-editor.org/rfc/rfc8878)), content encoding `zstd`
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Display for CompressionCodec {
+    /// Get the content encoding of the codec, as carried in the user property.
+    #[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unused_variables))]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            #[cfg(feature = "gzip")]
+            CompressionCodec::Gzip => write!(f, "gzip"),
+            #[cfg(feature = "zstd")]
+            CompressionCodec::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for CompressionCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            #[cfg(feature = "gzip")]
+            "gzip" => Ok(CompressionCodec::Gzip),
+            #[cfg(feature = "zstd")]
+            "zstd" => Ok(CompressionCodec::Zstd),
+            _ => Err(format!("Unsupported content encoding '{s}'")),
+        }
+    }
+}
+
+/// Compression configuration of a telemetry sender or command invoker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compression {
+    /// Codec used to compress payloads
+    pub codec: CompressionCodec,
+    /// Minimum size, in bytes, of a serialized payload for it to be compressed
+    pub threshold: usize,
+}
+
+impl Compression {
+    /// Creates a new [`Compression`] using `codec`, with the [`DEFAULT_COMPRESSION_THRESHOLD`].
+    #[must_use]
+    pub fn new(codec: CompressionCodec) -> Self {
+        Self {
+            codec,
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+        }
+    }
+
+    /// Sets the minimum size, in bytes, of a serialized payload for it to be compressed.
+    #[must_use]
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Compresses `payload` if it is at least [`threshold`](Compression::threshold) bytes long.
+    ///
+    /// Returns the payload to send, and the codec applied to it, if any. The original payload is
+    /// returned if it is below the threshold or if compressing it would not reduce its size.
+    ///
+    /// # Errors
+    /// Returns an [`std::io::Error`] if the payload could not be compressed
+    pub(crate) fn compress(
+        self,
+        payload: Vec<u8>,
+    ) -> Result<(Vec<u8>, Option<CompressionCodec>), std::io::Error> {
+        if payload.len() < self.threshold {
+            return Ok((payload, None));
+        }
+        let compressed = encode(self.codec, &payload)?;
+        if compressed.len() < payload.len() {
+            Ok((compressed, Some(self.codec)))
+        } else {
+            Ok((payload, None))
+        }
+    }
+}
+
+/// Decompresses a received `payload` compressed with `codec`.
+///
+/// # Errors
+/// Returns a `String` describing the error if the payload could not be decompressed, or if the
+/// decompressed payload is larger than [`MAX_DECOMPRESSED_SIZE`].
+pub(crate) fn decompress(codec: CompressionCodec, payload: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decompressed = Vec::new();
+    decoder(codec, payload)
+        .and_then(|reader| {
+            reader
+                .take(MAX_DECOMPRESSED_SIZE as u64 + 1)
+                .read_to_end(&mut decompressed)
+        })
+        .map_err(|e| format!("Invalid {codec} payload: {e}"))?;
+    if decompressed.len() > MAX_DECOMPRESSED_SIZE {
+        return Err(format!(
+            "Decompressed payload exceeds the maximum size of {MAX_DECOMPRESSED_SIZE} bytes"
+        ));
+    }
+    Ok(decompressed)
+}
+
+/// Compresses `payload` with `codec`.
+#[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unused_variables))]
+fn encode(codec: CompressionCodec, payload: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    match codec {
+        #[cfg(feature = "gzip")]
+        CompressionCodec::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            std::io::Write::write_all(&mut encoder, payload)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "zstd")]
+        CompressionCodec::Zstd => zstd::encode_all(payload, 0),
+    }
+}
+
+/// Creates a reader of the decompressed contents of `payload`, compressed with `codec`.
+#[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unused_variables))]
+#[allow(clippy::unnecessary_wraps)] // Only the zstd decoder can fail to be created
+fn decoder(codec: CompressionCodec, payload: &[u8]) -> Result<Box<dyn Read + '_>, std::io::Error> {
+    match codec {
+        #[cfg(feature = "gzip")]
+        CompressionCodec::Gzip => Ok(Box::new(flate2::read::GzDecoder::new(payload))),
+        #[cfg(feature = "zstd")]
+        CompressionCodec::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(payload)?)),
+    }
+}
+
+#[cfg(all(test, any(feature = "gzip", feature = "zstd")))]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    /// Codecs enabled by the cargo features of the build
+    fn enabled_codecs() -> Vec<CompressionCodec> {
+        vec![
+            #[cfg(feature = "gzip")]
+            CompressionCodec::Gzip,
+            #[cfg(feature = "zstd")]
+            CompressionCodec::Zstd,
+        ]
+    }
+
+    fn repetitive_payload(len: usize) -> Vec<u8> {
+        br#"{"temperature":21.5,"humidity":40}"#.iter().copied().cycle().take(len).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for codec in enabled_codecs() {
+            let payload = repetitive_payload(10 * DEFAULT_COMPRESSION_THRESHOLD);
+            let (compressed, applied) = Compression::new(codec).compress(payload.clone()).unwrap();
+            assert_eq!(applied, Some(codec));
+            assert!(compressed.len() < payload.len());
+            assert_eq!(decompress(codec, &compressed).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn below_threshold() {
+        for codec in enabled_codecs() {
+            let payload = repetitive_payload(100);
+            let (sent, applied) = Compression::new(codec)
+                .with_threshold(101)
+                .compress(payload.clone())
+                .unwrap();
+            assert_eq!(applied, None);
+            assert_eq!(sent, payload);
+
+            let (_, applied) = Compression::new(codec)
+                .with_threshold(100)
+                .compress(payload)
+                .unwrap();
+            assert_eq!(applied, Some(codec));
+        }
+    }
+
+    #[test]
+    fn incompressible_payload() {
+        for codec in enabled_codecs() {
+            let payload = vec![0x5A];
+            let (sent, applied) = Compression::new(codec)
+                .with_threshold(0)
+                .compress(payload.clone())
+                .unwrap();
+            assert_eq!(applied, None);
+            assert_eq!(sent, payload);
+        }
+    }
+
+    #[test]
+    fn codec_to_from_string() {
+        for codec in enabled_codecs() {
+            assert_eq!(CompressionCodec::from_str(&codec.to_string()), Ok(codec));
+        }
+    }
+
+    #[test]
+    fn invalid_payload() {
+        for codec in enabled_codecs() {
+            assert!(decompress(codec, b"not compressed").is_err());
+        }
+    }
+
+    #[test_case("br"; "unknown codec")]
+    #[test_case(""; "empty")]
+    #[test_case("GZIP"; "wrong case")]
+    fn unsupported_content_encoding(content_encoding: &str) {
+        assert!(CompressionCodec::from_str(content_encoding).is_err());
+    }
+}
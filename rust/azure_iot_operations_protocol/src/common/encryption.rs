@@ -0,0 +1,756 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! End-to-end encryption of serialized payloads, independent of broker access control.
+//!
+//! A [`telemetry::Sender`](crate::telemetry::Sender) or
+//! [`rpc_command::Invoker`](crate::rpc_command::Invoker) configured with a [`KeyProvider`]
+//! encrypts the serialized payload of each message with the current key of the provider, after
+//! compression if any. The ID of the key and the algorithm are carried in the
+//! [`UserProperty::EncryptionKeyId`] and [`UserProperty::EncryptionAlgorithm`] user properties, so
+//! that keys can be rotated while messages encrypted with the previous key are still in flight.
+//! The topic and the [`UserProperty::Timestamp`] of the message are bound to the ciphertext as
+//! associated data, so an encrypted payload cannot be replayed on another topic or with another
+//! timestamp.
+//!
+//! A [`telemetry::Receiver`](crate::telemetry::Receiver) or
+//! [`rpc_command::Executor`](crate::rpc_command::Executor) configured with a [`KeyProvider`]
+//! decrypts received payloads with the key identified in the message, and rejects messages that
+//! are not encrypted. An executor configured with a [`KeyProvider`] also encrypts its responses,
+//! which are then decrypted by the invoker. Receivers, executors and invokers without a
+//! [`KeyProvider`] reject encrypted messages.
+//!
+//! The content type, format indicator and other metadata of messages are not encrypted.
+//!
+//! The algorithms are enabled by the `encryption` cargo feature.
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use azure_iot_operations_mqtt::control_packet::Publish;
+
+use super::aio_protocol_error::AIOProtocolError;
+use super::message_properties::{
+    find_property, length_prefixed, outgoing_message_error, received_message_error,
+};
+use super::user_properties::UserProperty;
+
+/// Length, in bytes, of the keys of all supported algorithms
+pub const KEY_LEN: usize = 32;
+
+/// Length, in bytes, of the nonce prepended to each encrypted payload
+pub const NONCE_LEN: usize = 12;
+
+/// Error returned by a [`KeyProvider`]
+pub type KeyProviderError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Authenticated encryption algorithm applied to a payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EncryptionAlgorithm {
+    /// AES-GCM with a 256-bit key, carried as `A256GCM`
+    #[cfg(feature = "encryption")]
+    Aes256Gcm,
+    /// ChaCha20-Poly1305, carried as `C20P`
+    #[cfg(feature = "encryption")]
+    ChaCha20Poly1305,
+}
+
+impl Display for EncryptionAlgorithm {
+    /// Get the name of the algorithm, as carried in the user property.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            #[cfg(feature = "encryption")]
+            EncryptionAlgorithm::Aes256Gcm => write!(f, "A256GCM"),
+            #[cfg(feature = "encryption")]
+            EncryptionAlgorithm::ChaCha20Poly1305 => write!(f, "C20P"),
+        }
+    }
+}
+
+impl FromStr for EncryptionAlgorithm {
+    type Err = EncryptionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            #[cfg(feature = "encryption")]
+            "A256GCM" => Ok(EncryptionAlgorithm::Aes256Gcm),
+            #[cfg(feature = "encryption")]
+            "C20P" => Ok(EncryptionAlgorithm::ChaCha20Poly1305),
+            _ => Err(EncryptionError::UnsupportedAlgorithm(s.to_string())),
+        }
+    }
+}
+
+/// Symmetric key used to encrypt and decrypt payloads, identified by a key ID.
+///
+/// The key material is not included in the [`Debug`] representation.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey {
+    id: String,
+    algorithm: EncryptionAlgorithm,
+    material: Vec<u8>,
+}
+
+impl EncryptionKey {
+    /// Creates a new [`EncryptionKey`].
+    ///
+    /// # Errors
+    /// [`EncryptionError::InvalidKey`] if `id` is empty or not a valid user property value, or if
+    /// `material` is not [`KEY_LEN`] bytes long.
+    pub fn new(
+        id: impl Into<String>,
+        algorithm: EncryptionAlgorithm,
+        material: impl Into<Vec<u8>>,
+    ) -> Result<Self, EncryptionError> {
+        let id = id.into();
+        let material = material.into();
+        if id.trim().is_empty() || super::is_invalid_utf8(&id) {
+            return Err(EncryptionError::InvalidKey(format!(
+                "Invalid key ID '{id}'"
+            )));
+        }
+        if material.len() != KEY_LEN {
+            return Err(EncryptionError::InvalidKey(format!(
+                "Key '{id}' is {} bytes long, must be {KEY_LEN} bytes",
+                material.len()
+            )));
+        }
+        Ok(Self {
+            id,
+            algorithm,
+            material,
+        })
+    }
+
+    /// ID of the key
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Algorithm the key is used with
+    #[must_use]
+    pub fn algorithm(&self) -> EncryptionAlgorithm {
+        self.algorithm
+    }
+}
+
+impl Debug for EncryptionKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Source of the keys used to encrypt and decrypt payloads.
+///
+/// Implement this trait to integrate with a key management service. [`InMemoryKeyProvider`] can
+/// be used when keys are distributed to the application by other means.
+pub trait KeyProvider: Send + Sync {
+    /// Returns the key used to encrypt outgoing payloads.
+    ///
+    /// # Errors
+    /// Returns a [`KeyProviderError`] if the key cannot be retrieved
+    fn encryption_key(&self) -> Result<EncryptionKey, KeyProviderError>;
+
+    /// Returns the key with ID `key_id`, used to decrypt received payloads, or [`None`] if the key
+    /// is unknown.
+    ///
+    /// # Errors
+    /// Returns a [`KeyProviderError`] if the key cannot be retrieved
+    fn decryption_key(&self, key_id: &str) -> Result<Option<EncryptionKey>, KeyProviderError>;
+}
+
+/// [`KeyProvider`] holding its keys in memory.
+///
+/// Outgoing payloads are encrypted with the current key. Received payloads can be decrypted with
+/// any key held by the provider, so that keys can be rotated without disrupting messages
+/// encrypted with previous keys.
+#[derive(Debug)]
+pub struct InMemoryKeyProvider {
+    keys: RwLock<KeyRing>,
+}
+
+#[derive(Debug)]
+struct KeyRing {
+    current: String,
+    keys: HashMap<String, EncryptionKey>,
+}
+
+impl InMemoryKeyProvider {
+    /// Creates a new [`InMemoryKeyProvider`] with `key` as the current key.
+    #[must_use]
+    pub fn new(key: EncryptionKey) -> Self {
+        Self {
+            keys: RwLock::new(KeyRing {
+                current: key.id.clone(),
+                keys: HashMap::from([(key.id.clone(), key)]),
+            }),
+        }
+    }
+
+    /// Makes `key` the current key. Previous keys are kept to decrypt received payloads until
+    /// they are [removed](InMemoryKeyProvider::remove).
+    ///
+    /// # Panics
+    /// if the lock on the keys is poisoned, which should not be possible
+    pub fn rotate(&self, key: EncryptionKey) {
+        let mut keys = self.keys.write().unwrap();
+        keys.current.clone_from(&key.id);
+        keys.keys.insert(key.id.clone(), key);
+    }
+
+    /// Removes the key with ID `key_id`, so that received payloads encrypted with it are rejected.
+    /// The current key cannot be removed.
+    ///
+    /// Returns `true` if the key was removed.
+    ///
+    /// # Panics
+    /// if the lock on the keys is poisoned, which should not be possible
+    pub fn remove(&self, key_id: &str) -> bool {
+        let mut keys = self.keys.write().unwrap();
+        if keys.current == key_id {
+            return false;
+        }
+        keys.keys.remove(key_id).is_some()
+    }
+}
+
+impl KeyProvider for InMemoryKeyProvider {
+    fn encryption_key(&self) -> Result<EncryptionKey, KeyProviderError> {
+        let keys = self.keys.read().unwrap();
+        keys.keys
+            .get(&keys.current)
+            .cloned()
+            .ok_or_else(|| format!("Current key '{}' is missing", keys.current).into())
+    }
+
+    fn decryption_key(&self, key_id: &str) -> Result<Option<EncryptionKey>, KeyProviderError> {
+        Ok(self.keys.read().unwrap().keys.get(key_id).cloned())
+    }
+}
+
+/// Error encrypting or decrypting a payload
+#[derive(thiserror::Error, Debug)]
+pub enum EncryptionError {
+    /// The key material or key ID is invalid
+    #[error("{0}")]
+    InvalidKey(String),
+    /// The [`KeyProvider`] failed to provide a key
+    #[error("Key provider error: {0}")]
+    KeyProvider(#[source] KeyProviderError),
+    /// The payload could not be encrypted
+    #[error("Payload encryption failed")]
+    EncryptionFailed,
+    /// The message is not encrypted, but a [`KeyProvider`] is configured
+    #[error("Message is not encrypted, but encryption is required")]
+    NotEncrypted,
+    /// The message is encrypted, but no [`KeyProvider`] is configured
+    #[error("Message is encrypted, but no key provider is configured")]
+    NoKeyProvider,
+    /// A user property required to decrypt the message is missing
+    #[error("Encrypted message is missing the '{0}' user property")]
+    PropertyMissing(UserProperty),
+    /// The encryption algorithm of the message is not supported
+    #[error("Unsupported encryption algorithm '{0}'")]
+    UnsupportedAlgorithm(String),
+    /// The key the message was encrypted with is not known to the [`KeyProvider`]
+    #[error("Unknown encryption key '{0}'")]
+    UnknownKey(String),
+    /// The algorithm of the message does not match the algorithm of its key
+    #[error("Encryption key '{key_id}' is not used with algorithm '{algorithm}'")]
+    AlgorithmMismatch {
+        /// ID of the key
+        key_id: String,
+        /// Algorithm of the message
+        algorithm: EncryptionAlgorithm,
+    },
+    /// The payload could not be decrypted, because it, its topic or its timestamp were altered,
+    /// or it was not encrypted with the identified key
+    #[error(
+        "Payload decryption failed: the payload, topic or timestamp were altered, or the key is wrong"
+    )]
+    DecryptionFailed,
+}
+
+impl EncryptionError {
+    /// The name and, if present, the value of the user property causing the error
+    pub(crate) fn invalid_property(&self) -> Option<(String, Option<String>)> {
+        match self {
+            EncryptionError::NotEncrypted => {
+                Some((UserProperty::EncryptionKeyId.to_string(), None))
+            }
+            EncryptionError::PropertyMissing(property) => Some((property.to_string(), None)),
+            EncryptionError::UnknownKey(key_id) => Some((
+                UserProperty::EncryptionKeyId.to_string(),
+                Some(key_id.clone()),
+            )),
+            EncryptionError::UnsupportedAlgorithm(algorithm) => Some((
+                UserProperty::EncryptionAlgorithm.to_string(),
+                Some(algorithm.clone()),
+            )),
+            EncryptionError::AlgorithmMismatch { algorithm, .. } => Some((
+                UserProperty::EncryptionAlgorithm.to_string(),
+                Some(algorithm.to_string()),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Converts an error encrypting an outgoing payload into an [`AIOProtocolError`]
+    pub(crate) fn into_encrypt_error(self, command_name: Option<String>) -> AIOProtocolError {
+        outgoing_message_error(self, "Payload encryption error", command_name)
+    }
+
+    /// Converts an error decrypting a received message into an [`AIOProtocolError`]
+    pub(crate) fn into_decrypt_error(self, command_name: Option<String>) -> AIOProtocolError {
+        let invalid_property = self.invalid_property();
+        received_message_error(self, invalid_property, command_name)
+    }
+}
+
+/// Returns `true` if `user_properties` indicate an encrypted payload
+pub(crate) fn is_encrypted(user_properties: &[(String, String)]) -> bool {
+    find_property(user_properties, UserProperty::EncryptionKeyId).is_some()
+        || find_property(user_properties, UserProperty::EncryptionAlgorithm).is_some()
+}
+
+/// Associated data binding the algorithm, key ID, topic and timestamp of a message to its
+/// ciphertext.
+fn associated_data(
+    algorithm: EncryptionAlgorithm,
+    key_id: &str,
+    topic: &[u8],
+    timestamp: Option<&str>,
+) -> Vec<u8> {
+    let algorithm = algorithm.to_string();
+    length_prefixed(&[
+        algorithm.as_bytes(),
+        key_id.as_bytes(),
+        topic,
+        timestamp.unwrap_or_default().as_bytes(),
+    ])
+}
+
+/// Encrypts `payload` with the current key of `key_provider`, binding `topic` and the
+/// [`UserProperty::Timestamp`] in `user_properties`, if any, as associated data. Adds the
+/// encryption user properties to `user_properties`.
+///
+/// Returns the nonce followed by the ciphertext and its authentication tag.
+pub(crate) fn seal(
+    key_provider: &dyn KeyProvider,
+    topic: &[u8],
+    user_properties: &mut Vec<(String, String)>,
+    payload: &[u8],
+) -> Result<Vec<u8>, EncryptionError> {
+    let key = key_provider
+        .encryption_key()
+        .map_err(EncryptionError::KeyProvider)?;
+    let aad = associated_data(
+        key.algorithm,
+        &key.id,
+        topic,
+        find_property(user_properties, UserProperty::Timestamp),
+    );
+    let sealed = aead::seal(&key, &aad, payload)?;
+    user_properties.push((UserProperty::EncryptionKeyId.to_string(), key.id));
+    user_properties.push((
+        UserProperty::EncryptionAlgorithm.to_string(),
+        key.algorithm.to_string(),
+    ));
+    Ok(sealed)
+}
+
+/// Decrypts a received `payload` with the key identified in `user_properties`, and removes the
+/// encryption user properties from `user_properties`.
+///
+/// Returns [`None`] if the message is not encrypted and no `key_provider` is configured.
+pub(crate) fn open(
+    key_provider: Option<&dyn KeyProvider>,
+    topic: &[u8],
+    user_properties: &mut Vec<(String, String)>,
+    payload: &[u8],
+) -> Result<Option<Vec<u8>>, EncryptionError> {
+    let encrypted = is_encrypted(user_properties);
+    let key_provider = match (key_provider, encrypted) {
+        (None, false) => return Ok(None),
+        (None, true) => return Err(EncryptionError::NoKeyProvider),
+        (Some(_), false) => return Err(EncryptionError::NotEncrypted),
+        (Some(key_provider), true) => key_provider,
+    };
+
+    let key_id = find_property(user_properties, UserProperty::EncryptionKeyId)
+        .ok_or(EncryptionError::PropertyMissing(
+            UserProperty::EncryptionKeyId,
+        ))?
+        .to_string();
+    let algorithm = find_property(user_properties, UserProperty::EncryptionAlgorithm)
+        .ok_or(EncryptionError::PropertyMissing(
+            UserProperty::EncryptionAlgorithm,
+        ))?
+        .parse::<EncryptionAlgorithm>()?;
+    let key = key_provider
+        .decryption_key(&key_id)
+        .map_err(EncryptionError::KeyProvider)?
+        .ok_or_else(|| EncryptionError::UnknownKey(key_id.clone()))?;
+    if key.algorithm != algorithm {
+        return Err(EncryptionError::AlgorithmMismatch { key_id, algorithm });
+    }
+
+    let aad = associated_data(
+        algorithm,
+        &key_id,
+        topic,
+        find_property(user_properties, UserProperty::Timestamp),
+    );
+    let plaintext = aead::open(&key, &aad, payload)?;
+
+    let encryption_properties = [
+        UserProperty::EncryptionKeyId.to_string(),
+        UserProperty::EncryptionAlgorithm.to_string(),
+    ];
+    user_properties.retain(|(k, _)| !encryption_properties.contains(k));
+    Ok(Some(plaintext))
+}
+
+/// Decrypts the payload of a received `publish` in place. See [`open`].
+pub(crate) fn open_publish(
+    key_provider: Option<&dyn KeyProvider>,
+    publish: &mut Publish,
+) -> Result<(), EncryptionError> {
+    let Some(properties) = publish.properties.as_mut() else {
+        return match key_provider {
+            Some(_) => Err(EncryptionError::NotEncrypted),
+            None => Ok(()),
+        };
+    };
+    if let Some(plaintext) = open(
+        key_provider,
+        &publish.topic,
+        &mut properties.user_properties,
+        &publish.payload,
+    )? {
+        publish.payload = plaintext.into();
+    }
+    Ok(())
+}
+
+#[cfg(feature = "encryption")]
+mod aead {
+    use ring::aead::{AES_256_GCM, Aad, CHACHA20_POLY1305, LessSafeKey, Nonce, UnboundKey};
+    use ring::rand::{SecureRandom, SystemRandom};
+
+    use super::{EncryptionAlgorithm, EncryptionError, EncryptionKey, NONCE_LEN};
+
+    fn less_safe_key(key: &EncryptionKey) -> Result<LessSafeKey, EncryptionError> {
+        let algorithm = match key.algorithm {
+            EncryptionAlgorithm::Aes256Gcm => &AES_256_GCM,
+            EncryptionAlgorithm::ChaCha20Poly1305 => &CHACHA20_POLY1305,
+        };
+        UnboundKey::new(algorithm, &key.material)
+            .map(LessSafeKey::new)
+            .map_err(|_| EncryptionError::InvalidKey(format!("Invalid key '{}'", key.id)))
+    }
+
+    pub(super) fn seal(
+        key: &EncryptionKey,
+        aad: &[u8],
+        payload: &[u8],
+    ) -> Result<Vec<u8>, EncryptionError> {
+        let sealing_key = less_safe_key(key)?;
+        // Nonces are random, as keys are shared by many senders that cannot coordinate counters
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| EncryptionError::EncryptionFailed)?;
+        let mut sealed = Vec::with_capacity(NONCE_LEN + payload.len() + AES_256_GCM.tag_len());
+        sealed.extend_from_slice(&nonce);
+        let mut in_out = payload.to_vec();
+        sealing_key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut in_out,
+            )
+            .map_err(|_| EncryptionError::EncryptionFailed)?;
+        sealed.extend_from_slice(&in_out);
+        Ok(sealed)
+    }
+
+    pub(super) fn open(
+        key: &EncryptionKey,
+        aad: &[u8],
+        payload: &[u8],
+    ) -> Result<Vec<u8>, EncryptionError> {
+        let opening_key = less_safe_key(key)?;
+        if payload.len() < NONCE_LEN {
+            return Err(EncryptionError::DecryptionFailed);
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| EncryptionError::DecryptionFailed)?;
+        let mut in_out = ciphertext.to_vec();
+        let plaintext_len = opening_key
+            .open_in_place(nonce, Aad::from(aad), &mut in_out)
+            .map_err(|_| EncryptionError::DecryptionFailed)?
+            .len();
+        in_out.truncate(plaintext_len);
+        Ok(in_out)
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+mod aead {
+    use super::{EncryptionError, EncryptionKey};
+
+    pub(super) fn seal(
+        key: &EncryptionKey,
+        _aad: &[u8],
+        _payload: &[u8],
+    ) -> Result<Vec<u8>, EncryptionError> {
+        match key.algorithm {}
+    }
+
+    pub(super) fn open(
+        key: &EncryptionKey,
+        _aad: &[u8],
+        _payload: &[u8],
+    ) -> Result<Vec<u8>, EncryptionError> {
+        match key.algorithm {}
+    }
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    const TOPIC: &[u8] = b"test/telemetry";
+
+    fn key(id: &str, algorithm: EncryptionAlgorithm) -> EncryptionKey {
+        EncryptionKey::new(id, algorithm, [id.len().to_le_bytes()[0]; KEY_LEN]).unwrap()
+    }
+
+    fn timestamp_properties() -> Vec<(String, String)> {
+        vec![(
+            UserProperty::Timestamp.to_string(),
+            "1700000000000:0:node".to_string(),
+        )]
+    }
+
+    #[test_case(EncryptionAlgorithm::Aes256Gcm; "aes_256_gcm")]
+    #[test_case(EncryptionAlgorithm::ChaCha20Poly1305; "chacha20_poly1305")]
+    fn round_trip(algorithm: EncryptionAlgorithm) {
+        let provider = InMemoryKeyProvider::new(key("key1", algorithm));
+        let mut user_properties = timestamp_properties();
+        let sealed = seal(&provider, TOPIC, &mut user_properties, b"payload").unwrap();
+        assert_eq!(sealed.len(), NONCE_LEN + b"payload".len() + 16);
+        assert!(is_encrypted(&user_properties));
+        assert_eq!(
+            find_property(&user_properties, UserProperty::EncryptionKeyId),
+            Some("key1")
+        );
+        assert_eq!(
+            find_property(&user_properties, UserProperty::EncryptionAlgorithm),
+            Some(algorithm.to_string().as_str())
+        );
+
+        let plaintext = open(Some(&provider), TOPIC, &mut user_properties, &sealed).unwrap();
+        assert_eq!(plaintext, Some(b"payload".to_vec()));
+        // Encryption properties are removed once the payload is decrypted
+        assert_eq!(user_properties, timestamp_properties());
+    }
+
+    #[test]
+    fn nonces_are_unique() {
+        let provider = InMemoryKeyProvider::new(key("key1", EncryptionAlgorithm::Aes256Gcm));
+        let first = seal(&provider, TOPIC, &mut vec![], b"payload").unwrap();
+        let second = seal(&provider, TOPIC, &mut vec![], b"payload").unwrap();
+        assert_ne!(first[..NONCE_LEN], second[..NONCE_LEN]);
+    }
+
+    #[test_case(b"other/topic", timestamp_properties(); "other topic")]
+    #[test_case(TOPIC, vec![(UserProperty::Timestamp.to_string(), "1700000000001:0:node".to_string())]; "other timestamp")]
+    #[test_case(TOPIC, vec![]; "timestamp removed")]
+    fn associated_data_is_bound(topic: &[u8], mut received_properties: Vec<(String, String)>) {
+        let provider = InMemoryKeyProvider::new(key("key1", EncryptionAlgorithm::Aes256Gcm));
+        let mut user_properties = timestamp_properties();
+        let sealed = seal(&provider, TOPIC, &mut user_properties, b"payload").unwrap();
+        received_properties.extend(
+            user_properties
+                .into_iter()
+                .filter(|(k, _)| *k != UserProperty::Timestamp.to_string()),
+        );
+        assert!(matches!(
+            open(Some(&provider), topic, &mut received_properties, &sealed),
+            Err(EncryptionError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn tampered_payload() {
+        let provider = InMemoryKeyProvider::new(key("key1", EncryptionAlgorithm::Aes256Gcm));
+        let mut user_properties = timestamp_properties();
+        let mut sealed = seal(&provider, TOPIC, &mut user_properties, b"payload").unwrap();
+        *sealed.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            open(
+                Some(&provider),
+                TOPIC,
+                &mut user_properties.clone(),
+                &sealed
+            ),
+            Err(EncryptionError::DecryptionFailed)
+        ));
+        assert!(matches!(
+            open(Some(&provider), TOPIC, &mut user_properties, &sealed[..4]),
+            Err(EncryptionError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn key_rotation() {
+        let provider = InMemoryKeyProvider::new(key("key1", EncryptionAlgorithm::Aes256Gcm));
+        let mut old_properties = timestamp_properties();
+        let old_sealed = seal(&provider, TOPIC, &mut old_properties, b"old").unwrap();
+
+        provider.rotate(key("key2", EncryptionAlgorithm::ChaCha20Poly1305));
+        let mut new_properties = timestamp_properties();
+        let new_sealed = seal(&provider, TOPIC, &mut new_properties, b"new").unwrap();
+        assert_eq!(
+            find_property(&new_properties, UserProperty::EncryptionKeyId),
+            Some("key2")
+        );
+
+        // Payloads encrypted with the previous key can still be decrypted
+        assert_eq!(
+            open(
+                Some(&provider),
+                TOPIC,
+                &mut old_properties.clone(),
+                &old_sealed
+            )
+            .unwrap(),
+            Some(b"old".to_vec())
+        );
+        assert_eq!(
+            open(Some(&provider), TOPIC, &mut new_properties, &new_sealed).unwrap(),
+            Some(b"new".to_vec())
+        );
+
+        // Until the previous key is removed. The current key cannot be removed.
+        assert!(!provider.remove("key2"));
+        assert!(provider.remove("key1"));
+        assert!(matches!(
+            open(Some(&provider), TOPIC, &mut old_properties, &old_sealed),
+            Err(EncryptionError::UnknownKey(key_id)) if key_id == "key1"
+        ));
+    }
+
+    #[test]
+    fn encryption_required() {
+        let provider = InMemoryKeyProvider::new(key("key1", EncryptionAlgorithm::Aes256Gcm));
+        let error = open(
+            Some(&provider),
+            TOPIC,
+            &mut timestamp_properties(),
+            b"payload",
+        )
+        .unwrap_err();
+        assert!(matches!(error, EncryptionError::NotEncrypted));
+        assert_eq!(
+            error.invalid_property(),
+            Some((UserProperty::EncryptionKeyId.to_string(), None))
+        );
+    }
+
+    #[test]
+    fn no_key_provider() {
+        assert_eq!(
+            open(None, TOPIC, &mut timestamp_properties(), b"payload").unwrap(),
+            None
+        );
+
+        let provider = InMemoryKeyProvider::new(key("key1", EncryptionAlgorithm::Aes256Gcm));
+        let mut user_properties = timestamp_properties();
+        let sealed = seal(&provider, TOPIC, &mut user_properties, b"payload").unwrap();
+        assert!(matches!(
+            open(None, TOPIC, &mut user_properties, &sealed),
+            Err(EncryptionError::NoKeyProvider)
+        ));
+    }
+
+    #[test_case(&[(UserProperty::EncryptionKeyId, "key1"), (UserProperty::EncryptionAlgorithm, "A128CBC")], UserProperty::EncryptionAlgorithm, Some("A128CBC"); "unsupported algorithm")]
+    #[test_case(&[(UserProperty::EncryptionKeyId, "key1"), (UserProperty::EncryptionAlgorithm, "C20P")], UserProperty::EncryptionAlgorithm, Some("C20P"); "algorithm mismatch")]
+    #[test_case(&[(UserProperty::EncryptionKeyId, "key2"), (UserProperty::EncryptionAlgorithm, "A256GCM")], UserProperty::EncryptionKeyId, Some("key2"); "unknown key")]
+    #[test_case(&[(UserProperty::EncryptionAlgorithm, "A256GCM")], UserProperty::EncryptionKeyId, None; "key id missing")]
+    #[test_case(&[(UserProperty::EncryptionKeyId, "key1")], UserProperty::EncryptionAlgorithm, None; "algorithm missing")]
+    fn invalid_properties(
+        properties: &[(UserProperty, &str)],
+        invalid_property: UserProperty,
+        invalid_value: Option<&str>,
+    ) {
+        let provider = InMemoryKeyProvider::new(key("key1", EncryptionAlgorithm::Aes256Gcm));
+        let mut user_properties = properties
+            .iter()
+            .map(|(k, v)| (k.to_string(), (*v).to_string()))
+            .collect();
+        let error = open(Some(&provider), TOPIC, &mut user_properties, &[0; 32]).unwrap_err();
+        assert_eq!(
+            error.invalid_property(),
+            Some((
+                invalid_property.to_string(),
+                invalid_value.map(ToString::to_string)
+            ))
+        );
+    }
+
+    #[test_case("", KEY_LEN; "empty id")]
+    #[test_case("key\n1", KEY_LEN; "invalid id")]
+    #[test_case("key1", 16; "short key")]
+    fn invalid_key(id: &str, len: usize) {
+        assert!(matches!(
+            EncryptionKey::new(id, EncryptionAlgorithm::Aes256Gcm, vec![0; len]),
+            Err(EncryptionError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn key_material_is_not_debug_printed() {
+        let key =
+            EncryptionKey::new("key1", EncryptionAlgorithm::Aes256Gcm, [0xAB; KEY_LEN]).unwrap();
+        let debug = format!("{key:?}");
+        assert!(debug.contains("key1"));
+        assert!(!debug.contains("171"));
+    }
+
+    #[test]
+    fn open_publish_in_place() {
+        let provider = InMemoryKeyProvider::new(key("key1", EncryptionAlgorithm::Aes256Gcm));
+        let mut user_properties = timestamp_properties();
+        let sealed = seal(&provider, TOPIC, &mut user_properties, b"payload").unwrap();
+        let mut publish = Publish {
+            topic: TOPIC.into(),
+            payload: sealed.into(),
+            properties: Some(
+                azure_iot_operations_mqtt::control_packet::PublishProperties {
+                    user_properties,
+                    ..Default::default()
+                },
+            ),
+            ..Default::default()
+        };
+        open_publish(Some(&provider), &mut publish).unwrap();
+        assert_eq!(&publish.payload[..], b"payload");
+        assert_eq!(
+            publish.properties.unwrap().user_properties,
+            timestamp_properties()
+        );
+    }
+}
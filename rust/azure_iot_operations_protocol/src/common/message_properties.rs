@@ -0,0 +1,136 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Helpers shared by the encryption, signing and chunking of messages, which carry their
+//! parameters in user properties.
+
+use std::error::Error;
+
+use super::aio_protocol_error::AIOProtocolError;
+use super::user_properties::UserProperty;
+
+/// Returns the value of the `property` user property, if present
+pub(crate) fn find_property(
+    user_properties: &[(String, String)],
+    property: UserProperty,
+) -> Option<&str> {
+    let key = property.to_string();
+    user_properties
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+/// Concatenates `fields`, prefixing each with its length as a big-endian `u32` so that fields
+/// cannot be shifted.
+pub(crate) fn length_prefixed(fields: &[&[u8]]) -> Vec<u8> {
+    let mut content = Vec::with_capacity(fields.iter().map(|f| f.len() + 4).sum());
+    for field in fields {
+        // Fields are bounded by the maximum MQTT packet size, so their lengths fit in a u32
+        content.extend_from_slice(&u32::try_from(field.len()).unwrap_or(u32::MAX).to_be_bytes());
+        content.extend_from_slice(field);
+    }
+    content
+}
+
+/// Converts an error processing an outgoing message into an [`AIOProtocolError`] with `message`
+pub(crate) fn outgoing_message_error(
+    error: impl Error + Send + Sync + 'static,
+    message: &str,
+    command_name: Option<String>,
+) -> AIOProtocolError {
+    AIOProtocolError::new_payload_invalid_error(
+        true,
+        false,
+        Some(Box::new(error)),
+        Some(message.to_string()),
+        command_name,
+    )
+}
+
+/// Converts an error processing a received message into an [`AIOProtocolError`]. The error is a
+/// missing or invalid header if `invalid_property` names the user property causing it, and an
+/// invalid payload otherwise.
+pub(crate) fn received_message_error(
+    error: impl Error + Send + Sync + 'static,
+    invalid_property: Option<(String, Option<String>)>,
+    command_name: Option<String>,
+) -> AIOProtocolError {
+    let message = Some(error.to_string());
+    match invalid_property {
+        Some((name, Some(value))) => {
+            AIOProtocolError::new_header_invalid_error(&name, &value, false, message, command_name)
+        }
+        Some((name, None)) => {
+            AIOProtocolError::new_header_missing_error(&name, false, message, command_name)
+        }
+        None => AIOProtocolError::new_payload_invalid_error(
+            false,
+            false,
+            Some(Box::new(error)),
+            message,
+            command_name,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::common::aio_protocol_error::AIOProtocolErrorKind;
+
+    #[derive(thiserror::Error, Debug)]
+    #[error("test error")]
+    struct TestError;
+
+    #[test]
+    fn find_property_by_name() {
+        let user_properties = vec![
+            ("custom".to_string(), "value".to_string()),
+            (UserProperty::Timestamp.to_string(), "ts".to_string()),
+        ];
+        assert_eq!(
+            find_property(&user_properties, UserProperty::Timestamp),
+            Some("ts")
+        );
+        assert_eq!(
+            find_property(&user_properties, UserProperty::SourceId),
+            None
+        );
+    }
+
+    #[test]
+    fn length_prefixed_fields_cannot_be_shifted() {
+        assert_eq!(
+            length_prefixed(&[b"ab", b"", b"c"]),
+            b"\0\0\0\x02ab\0\0\0\0\0\0\0\x01c".to_vec()
+        );
+        assert_ne!(
+            length_prefixed(&[b"ab", b"c"]),
+            length_prefixed(&[b"a", b"bc"])
+        );
+    }
+
+    #[test]
+    fn outgoing_message_error_is_shallow() {
+        let e = outgoing_message_error(TestError, "Test error", None);
+        assert_eq!(e.kind, AIOProtocolErrorKind::PayloadInvalid);
+        assert!(e.is_shallow);
+        assert_eq!(e.message, Some("Test error".to_string()));
+    }
+
+    #[test_case(Some(("name".to_string(), Some("value".to_string()))), &AIOProtocolErrorKind::HeaderInvalid; "header_invalid")]
+    #[test_case(Some(("name".to_string(), None)), &AIOProtocolErrorKind::HeaderMissing; "header_missing")]
+    #[test_case(None, &AIOProtocolErrorKind::PayloadInvalid; "payload_invalid")]
+    fn received_message_error_kind(
+        invalid_property: Option<(String, Option<String>)>,
+        kind: &AIOProtocolErrorKind,
+    ) {
+        let e = received_message_error(TestError, invalid_property, None);
+        assert_eq!(&e.kind, kind);
+        assert!(!e.is_shallow);
+        assert_eq!(e.message, Some("test error".to_string()));
+    }
+}
@@ -3,6 +3,8 @@
 
 use std::fmt::Debug;
 
+use crate::common::user_properties::UserProperty;
+
 /// Format indicator for serialization and deserialization.
 #[repr(u8)]
 #[derive(Clone, PartialEq, Debug, Default)]
@@ -28,6 +30,33 @@ impl TryFrom<Option<u8>> for FormatIndicator {
     }
 }
 
+impl FormatIndicator {
+    /// Records the format indicator as the [`UserProperty::PayloadFormatIndicator`] in
+    /// `user_properties` and resets it to [`FormatIndicator::UnspecifiedBytes`], since a
+    /// compressed or encrypted payload is no longer in its original format. Does nothing if the
+    /// format indicator is already [`FormatIndicator::UnspecifiedBytes`] or was already recorded.
+    pub(crate) fn mark_transformed(&mut self, user_properties: &mut Vec<(String, String)>) {
+        if *self != FormatIndicator::UnspecifiedBytes {
+            user_properties.push((
+                UserProperty::PayloadFormatIndicator.to_string(),
+                (self.clone() as u8).to_string(),
+            ));
+            *self = FormatIndicator::UnspecifiedBytes;
+        }
+    }
+
+    /// Parses the value of a [`UserProperty::PayloadFormatIndicator`] user property.
+    ///
+    /// # Errors
+    /// Returns a `String` describing the error if `value` is not a valid format indicator.
+    pub(crate) fn from_user_property(value: &str) -> Result<Self, String> {
+        value
+            .parse::<u8>()
+            .map_err(|e| format!("Invalid format indicator value: {value}. {e}"))
+            .and_then(|value| FormatIndicator::try_from(Some(value)))
+    }
+}
+
 /// Struct that specifies the content type, format indicator, and payload for a serialized payload.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct SerializedPayload {
@@ -198,7 +227,7 @@ pub static DESERIALIZE_MTX: Mutex<()> = Mutex::new(());
 mod tests {
     use test_case::test_case;
 
-    use crate::common::payload_serialize::FormatIndicator;
+    use crate::common::{payload_serialize::FormatIndicator, user_properties::UserProperty};
 
     #[test_case(&FormatIndicator::UnspecifiedBytes; "UnspecifiedBytes")]
     #[test_case(&FormatIndicator::Utf8EncodedCharacterData; "Utf8EncodedCharacterData")]
@@ -223,4 +252,42 @@ mod tests {
     fn test_from_option_u8_failure(value: Option<u8>) {
         assert!(&FormatIndicator::try_from(value).is_err());
     }
+
+    #[test]
+    fn test_mark_transformed_round_trip() {
+        let mut format_indicator = FormatIndicator::Utf8EncodedCharacterData;
+        let mut user_properties = Vec::new();
+        format_indicator.mark_transformed(&mut user_properties);
+        assert_eq!(format_indicator, FormatIndicator::UnspecifiedBytes);
+        assert_eq!(
+            user_properties,
+            vec![(
+                UserProperty::PayloadFormatIndicator.to_string(),
+                "1".to_string()
+            )]
+        );
+        assert_eq!(
+            FormatIndicator::from_user_property(&user_properties[0].1).unwrap(),
+            FormatIndicator::Utf8EncodedCharacterData
+        );
+
+        // A second transformation must not overwrite the original format indicator
+        format_indicator.mark_transformed(&mut user_properties);
+        assert_eq!(user_properties.len(), 1);
+    }
+
+    #[test]
+    fn test_mark_transformed_unspecified_bytes() {
+        let mut format_indicator = FormatIndicator::UnspecifiedBytes;
+        let mut user_properties = Vec::new();
+        format_indicator.mark_transformed(&mut user_properties);
+        assert_eq!(format_indicator, FormatIndicator::UnspecifiedBytes);
+        assert!(user_properties.is_empty());
+    }
+
+    #[test_case("2"; "out_of_range")]
+    #[test_case("utf8"; "not_a_number")]
+    fn test_from_user_property_failure(value: &str) {
+        assert!(FormatIndicator::from_user_property(value).is_err());
+    }
 }
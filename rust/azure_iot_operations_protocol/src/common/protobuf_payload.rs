@@ -0,0 +1,319 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Wrappers implementing [`PayloadSerialize`] for [`prost::Message`] types, with content type
+//! `application/protobuf`.
+//!
+//! The fully-qualified name of the message type can be carried with the payload in two ways, so
+//! that receivers can reject payloads of a different message type:
+//! - In the content type, as the `proto` parameter (e.g. `application/protobuf; proto=sample.Car`),
+//!   by using the [`NamedProtobuf`] wrapper. Mismatches are rejected when deserializing.
+//! - In a custom user property, by adding [`message_type_property`] to the custom user data of the
+//!   message, and checking received messages with [`check_message_type_property`].
+//!
+//! Enabled by the `protobuf` cargo feature.
+//!
+//! # Example
+//! ```
+//! use azure_iot_operations_protocol::common::payload_serialize::PayloadSerialize;
+//! use azure_iot_operations_protocol::common::protobuf_payload::NamedProtobuf;
+//!
+//! #[derive(Clone, PartialEq, prost::Message)]
+//! struct Car {
+//!     #[prost(string, tag = "1")]
+//!     model: String,
+//! }
+//!
+//! impl prost::Name for Car {
+//!     const NAME: &'static str = "Car";
+//!     const PACKAGE: &'static str = "sample";
+//! }
+//!
+//! let serialized = NamedProtobuf(Car { model: "sedan".to_string() }).serialize().unwrap();
+//! assert_eq!(serialized.content_type, "application/protobuf; proto=sample.Car");
+//! ```
+
+use prost::{DecodeError, Message, Name};
+
+use crate::common::payload_serialize::{
+    DeserializationError, FormatIndicator, PayloadSerialize, SerializedPayload,
+};
+
+/// Content type of payloads serialized with [`Protobuf`] or [`NamedProtobuf`], excluding
+/// parameters
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/protobuf";
+
+/// Name of the content type parameter carrying the fully-qualified name of the message type
+const MESSAGE_TYPE_PARAMETER: &str = "proto";
+
+/// Name of the custom user property carrying the fully-qualified name of the message type
+pub const MESSAGE_TYPE_PROPERTY: &str = "protoMessageType";
+
+/// Split a content type into its media type and the value of its `proto` parameter, if any.
+fn parse_content_type(content_type: &str) -> (&str, Option<&str>) {
+    let mut parts = content_type.split(';');
+    let media_type = parts.next().unwrap_or_default().trim();
+    let message_type = parts.find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case(MESSAGE_TYPE_PARAMETER)
+            .then(|| value.trim().trim_matches('"'))
+    });
+    (media_type, message_type)
+}
+
+/// Check that the media type of a received payload, if present, is `application/protobuf`, and
+/// return the message type carried in its content type, if any.
+fn check_content_type(
+    content_type: Option<&String>,
+) -> Result<Option<&str>, DeserializationError<DecodeError>> {
+    let Some(content_type) = content_type else {
+        return Ok(None);
+    };
+    let (media_type, message_type) = parse_content_type(content_type);
+    if !media_type.eq_ignore_ascii_case(PROTOBUF_CONTENT_TYPE) {
+        return Err(DeserializationError::UnsupportedContentType(format!(
+            "Invalid content type: '{content_type}'. Must be '{PROTOBUF_CONTENT_TYPE}'"
+        )));
+    }
+    Ok(message_type)
+}
+
+/// Payload serialized as Protobuf, with content type `application/protobuf`.
+///
+/// The message type is not carried in the content type. Received payloads are accepted regardless
+/// of the message type in their content type, as it cannot be verified. Use [`NamedProtobuf`] to
+/// carry and verify the message type.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Protobuf<T>(pub T);
+
+impl<T> PayloadSerialize for Protobuf<T>
+where
+    T: Message + Default + Clone,
+{
+    type Error = DecodeError;
+
+    fn serialize(self) -> Result<SerializedPayload, Self::Error> {
+        Ok(SerializedPayload {
+            payload: self.0.encode_to_vec(),
+            content_type: PROTOBUF_CONTENT_TYPE.to_string(),
+            format_indicator: FormatIndicator::UnspecifiedBytes,
+        })
+    }
+
+    fn deserialize(
+        payload: &[u8],
+        content_type: Option<&String>,
+        _format_indicator: &FormatIndicator,
+    ) -> Result<Self, DeserializationError<Self::Error>> {
+        check_content_type(content_type)?;
+        Ok(Protobuf(T::decode(payload)?))
+    }
+}
+
+/// Payload serialized as Protobuf, with the fully-qualified name of the message type carried in
+/// the content type (e.g. `application/protobuf; proto=sample.Car`).
+///
+/// Received payloads carrying a different message type in their content type are rejected with
+/// [`DeserializationError::UnsupportedContentType`]. Received payloads that do not carry a message
+/// type are accepted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NamedProtobuf<T>(pub T);
+
+impl<T> PayloadSerialize for NamedProtobuf<T>
+where
+    T: Message + Name + Default + Clone,
+{
+    type Error = DecodeError;
+
+    fn serialize(self) -> Result<SerializedPayload, Self::Error> {
+        Ok(SerializedPayload {
+            payload: self.0.encode_to_vec(),
+            content_type: format!(
+                "{PROTOBUF_CONTENT_TYPE}; {MESSAGE_TYPE_PARAMETER}={}",
+                T::full_name()
+            ),
+            format_indicator: FormatIndicator::UnspecifiedBytes,
+        })
+    }
+
+    fn deserialize(
+        payload: &[u8],
+        content_type: Option<&String>,
+        _format_indicator: &FormatIndicator,
+    ) -> Result<Self, DeserializationError<Self::Error>> {
+        if let Some(message_type) = check_content_type(content_type)? {
+            let expected = T::full_name();
+            if message_type != expected {
+                return Err(DeserializationError::UnsupportedContentType(format!(
+                    "Invalid message type: '{message_type}'. Must be '{expected}'"
+                )));
+            }
+        }
+        Ok(NamedProtobuf(T::decode(payload)?))
+    }
+}
+
+/// Custom user property carrying the fully-qualified name of the message type `T`, to be added to
+/// the custom user data of a message.
+#[must_use]
+pub fn message_type_property<T: Name>() -> (String, String) {
+    (MESSAGE_TYPE_PROPERTY.to_string(), T::full_name())
+}
+
+/// Check the message type carried in the custom user data of a received message, if any, against
+/// the message type `T`.
+///
+/// # Errors
+/// Returns [`DeserializationError::UnsupportedContentType`] if the custom user data carries a
+/// different message type.
+pub fn check_message_type_property<T: Name>(
+    custom_user_data: &[(String, String)],
+) -> Result<(), DeserializationError<DecodeError>> {
+    let Some((_, message_type)) = custom_user_data
+        .iter()
+        .find(|(key, _)| key == MESSAGE_TYPE_PROPERTY)
+    else {
+        return Ok(());
+    };
+    let expected = T::full_name();
+    if *message_type != expected {
+        return Err(DeserializationError::UnsupportedContentType(format!(
+            "Invalid message type: '{message_type}'. Must be '{expected}'"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[derive(Clone, PartialEq, Message)]
+    struct Sample {
+        #[prost(string, tag = "1")]
+        name: String,
+        #[prost(uint32, tag = "2")]
+        count: u32,
+    }
+
+    impl Name for Sample {
+        const NAME: &'static str = "Sample";
+        const PACKAGE: &'static str = "test";
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            name: "sample".to_string(),
+            count: 3,
+        }
+    }
+
+    #[test]
+    fn protobuf_round_trip() {
+        let serialized = Protobuf(sample()).serialize().unwrap();
+        assert_eq!(serialized.content_type, PROTOBUF_CONTENT_TYPE);
+        assert_eq!(
+            serialized.format_indicator,
+            FormatIndicator::UnspecifiedBytes
+        );
+        let Protobuf(result) = Protobuf::<Sample>::deserialize(
+            &serialized.payload,
+            Some(&serialized.content_type),
+            &serialized.format_indicator,
+        )
+        .unwrap();
+        assert_eq!(result, sample());
+    }
+
+    #[test]
+    fn named_protobuf_round_trip() {
+        let serialized = NamedProtobuf(sample()).serialize().unwrap();
+        assert_eq!(
+            serialized.content_type,
+            "application/protobuf; proto=test.Sample"
+        );
+        let NamedProtobuf(result) = NamedProtobuf::<Sample>::deserialize(
+            &serialized.payload,
+            Some(&serialized.content_type),
+            &serialized.format_indicator,
+        )
+        .unwrap();
+        assert_eq!(result, sample());
+    }
+
+    #[test_case(None; "no content type")]
+    #[test_case(Some("application/protobuf"); "no message type")]
+    #[test_case(Some("application/protobuf; proto=test.Sample"); "matching message type")]
+    #[test_case(Some("Application/Protobuf;proto=\"test.Sample\""); "quoted message type")]
+    #[test_case(Some("application/protobuf; charset=binary; proto=test.Sample"); "other parameters")]
+    fn named_protobuf_accepted_content_type(content_type: Option<&str>) {
+        let payload = sample().encode_to_vec();
+        let result = NamedProtobuf::<Sample>::deserialize(
+            &payload,
+            content_type.map(ToString::to_string).as_ref(),
+            &FormatIndicator::UnspecifiedBytes,
+        );
+        assert_eq!(result.unwrap().0, sample());
+    }
+
+    #[test_case("application/json"; "other media type")]
+    #[test_case("application/protobuf; proto=test.Other"; "other message type")]
+    #[test_case("application/protobuf; proto=Sample"; "unqualified message type")]
+    fn named_protobuf_unsupported_content_type(content_type: &str) {
+        let result = NamedProtobuf::<Sample>::deserialize(
+            &sample().encode_to_vec(),
+            Some(&content_type.to_string()),
+            &FormatIndicator::UnspecifiedBytes,
+        );
+        assert!(matches!(
+            result,
+            Err(DeserializationError::UnsupportedContentType(_))
+        ));
+    }
+
+    #[test_case("application/protobuf; proto=test.Other", true; "other message type")]
+    #[test_case("application/json", false; "other media type")]
+    fn protobuf_content_type(content_type: &str, accepted: bool) {
+        let result = Protobuf::<Sample>::deserialize(
+            &sample().encode_to_vec(),
+            Some(&content_type.to_string()),
+            &FormatIndicator::UnspecifiedBytes,
+        );
+        assert_eq!(result.is_ok(), accepted);
+    }
+
+    #[test]
+    fn invalid_payload() {
+        let result = Protobuf::<Sample>::deserialize(
+            &[0xFF, 0xFF, 0xFF],
+            Some(&PROTOBUF_CONTENT_TYPE.to_string()),
+            &FormatIndicator::UnspecifiedBytes,
+        );
+        assert!(matches!(
+            result,
+            Err(DeserializationError::InvalidPayload(_))
+        ));
+    }
+
+    #[test]
+    fn message_type_property_check() {
+        let mut custom_user_data = vec![("other".to_string(), "value".to_string())];
+        assert!(check_message_type_property::<Sample>(&custom_user_data).is_ok());
+
+        custom_user_data.push(message_type_property::<Sample>());
+        assert_eq!(
+            custom_user_data[1],
+            (MESSAGE_TYPE_PROPERTY.to_string(), "test.Sample".to_string())
+        );
+        assert!(check_message_type_property::<Sample>(&custom_user_data).is_ok());
+
+        custom_user_data[1].1 = "test.Other".to_string();
+        assert!(matches!(
+            check_message_type_property::<Sample>(&custom_user_data),
+            Err(DeserializationError::UnsupportedContentType(_))
+        ));
+    }
+}
@@ -0,0 +1,350 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Wrappers implementing [`PayloadSerialize`] for any type implementing [`Serialize`] and
+//! [`DeserializeOwned`] (and [`Clone`], as required by [`PayloadSerialize`]), so that payloads do
+//! not need hand-written implementations.
+//!
+//! When deserializing, the content type of the received payload must match the content type of
+//! the wrapper if present, otherwise [`DeserializationError::UnsupportedContentType`] is returned.
+//!
+//! Each wrapper is enabled by a cargo feature:
+//! - [`Json`] - `json`
+//! - [`Cbor`] - `cbor`
+//! - [`MsgPack`] - `msgpack`
+//!
+//! # Example
+//! ```
+//! # #[cfg(feature = "json")]
+//! # {
+//! use azure_iot_operations_protocol::common::payload_serialize::PayloadSerialize;
+//! use azure_iot_operations_protocol::common::serde_payload::Json;
+//!
+//! #[derive(Clone, serde::Serialize, serde::Deserialize)]
+//! struct CarLocation {
+//!     latitude: f64,
+//!     longitude: f64,
+//! }
+//!
+//! let serialized = Json(CarLocation { latitude: 47.6, longitude: -122.1 }).serialize().unwrap();
+//! assert_eq!(serialized.content_type, "application/json");
+//! # }
+//! ```
+
+use std::fmt::Debug;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::common::payload_serialize::{
+    DeserializationError, FormatIndicator, PayloadSerialize, SerializedPayload,
+};
+
+/// Content type of payloads serialized with [`Json`]
+#[cfg(feature = "json")]
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+/// Content type of payloads serialized with [`Cbor`]
+#[cfg(feature = "cbor")]
+pub const CBOR_CONTENT_TYPE: &str = "application/cbor";
+/// Content type of payloads serialized with [`MsgPack`]
+#[cfg(feature = "msgpack")]
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Check that the content type of a received payload, if present, is the expected content type.
+fn check_content_type<E>(
+    content_type: Option<&String>,
+    expected: &str,
+) -> Result<(), DeserializationError<E>>
+where
+    E: Debug + Into<Box<dyn std::error::Error + Sync + Send + 'static>>,
+{
+    match content_type {
+        Some(content_type) if content_type != expected => {
+            Err(DeserializationError::UnsupportedContentType(format!(
+                "Invalid content type: '{content_type}'. Must be '{expected}'"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Payload serialized as JSON, with content type `application/json`.
+#[cfg(feature = "json")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "json")]
+impl<T> PayloadSerialize for Json<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    type Error = serde_json::Error;
+
+    fn serialize(self) -> Result<SerializedPayload, Self::Error> {
+        Ok(SerializedPayload {
+            payload: serde_json::to_vec(&self.0)?,
+            content_type: JSON_CONTENT_TYPE.to_string(),
+            format_indicator: FormatIndicator::Utf8EncodedCharacterData,
+        })
+    }
+
+    fn deserialize(
+        payload: &[u8],
+        content_type: Option<&String>,
+        _format_indicator: &FormatIndicator,
+    ) -> Result<Self, DeserializationError<Self::Error>> {
+        check_content_type(content_type, JSON_CONTENT_TYPE)?;
+        Ok(Json(serde_json::from_slice(payload)?))
+    }
+}
+
+/// Error serializing or deserializing a [`Cbor`] payload
+#[cfg(feature = "cbor")]
+#[derive(thiserror::Error, Debug)]
+pub enum CborError {
+    /// The value could not be serialized
+    #[error(transparent)]
+    Serialize(#[from] ciborium::ser::Error<std::io::Error>),
+    /// The payload could not be deserialized
+    #[error(transparent)]
+    Deserialize(#[from] ciborium::de::Error<std::io::Error>),
+}
+
+/// Payload serialized as CBOR, with content type `application/cbor`.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cbor<T>(pub T);
+
+#[cfg(feature = "cbor")]
+impl<T> PayloadSerialize for Cbor<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    type Error = CborError;
+
+    fn serialize(self) -> Result<SerializedPayload, Self::Error> {
+        let mut payload = Vec::new();
+        ciborium::into_writer(&self.0, &mut payload)?;
+        Ok(SerializedPayload {
+            payload,
+            content_type: CBOR_CONTENT_TYPE.to_string(),
+            format_indicator: FormatIndicator::UnspecifiedBytes,
+        })
+    }
+
+    fn deserialize(
+        payload: &[u8],
+        content_type: Option<&String>,
+        _format_indicator: &FormatIndicator,
+    ) -> Result<Self, DeserializationError<Self::Error>> {
+        check_content_type(content_type, CBOR_CONTENT_TYPE)?;
+        ciborium::from_reader(payload)
+            .map(Cbor)
+            .map_err(|e| DeserializationError::InvalidPayload(CborError::Deserialize(e)))
+    }
+}
+
+/// Error serializing or deserializing a [`MsgPack`] payload
+#[cfg(feature = "msgpack")]
+#[derive(thiserror::Error, Debug)]
+pub enum MsgPackError {
+    /// The value could not be serialized
+    #[error(transparent)]
+    Serialize(#[from] rmp_serde::encode::Error),
+    /// The payload could not be deserialized
+    #[error(transparent)]
+    Deserialize(#[from] rmp_serde::decode::Error),
+}
+
+/// Payload serialized as `MessagePack`, with content type `application/msgpack`.
+///
+/// Structs are serialized as maps keyed by field name, so that payloads remain readable by
+/// implementations that do not share the Rust definition of the struct.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MsgPack<T>(pub T);
+
+#[cfg(feature = "msgpack")]
+impl<T> PayloadSerialize for MsgPack<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    type Error = MsgPackError;
+
+    fn serialize(self) -> Result<SerializedPayload, Self::Error> {
+        Ok(SerializedPayload {
+            payload: rmp_serde::to_vec_named(&self.0)?,
+            content_type: MSGPACK_CONTENT_TYPE.to_string(),
+            format_indicator: FormatIndicator::UnspecifiedBytes,
+        })
+    }
+
+    fn deserialize(
+        payload: &[u8],
+        content_type: Option<&String>,
+        _format_indicator: &FormatIndicator,
+    ) -> Result<Self, DeserializationError<Self::Error>> {
+        check_content_type(content_type, MSGPACK_CONTENT_TYPE)?;
+        rmp_serde::from_slice(payload)
+            .map(MsgPack)
+            .map_err(|e| DeserializationError::InvalidPayload(MsgPackError::Deserialize(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use test_case::test_case;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        name: String,
+        count: u32,
+        values: Vec<f64>,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            name: "sample".to_string(),
+            count: 3,
+            values: vec![1.5, -2.0],
+        }
+    }
+
+    /// Serialize and deserialize a wrapped sample, checking the content type and format indicator
+    fn round_trip<P: PayloadSerialize>(
+        wrapped: P,
+        content_type: &str,
+        format_indicator: &FormatIndicator,
+    ) -> P {
+        let serialized = wrapped.serialize().unwrap();
+        assert_eq!(serialized.content_type, content_type);
+        assert_eq!(&serialized.format_indicator, format_indicator);
+        P::deserialize(
+            &serialized.payload,
+            Some(&serialized.content_type),
+            &serialized.format_indicator,
+        )
+        .unwrap()
+    }
+
+    /// Deserialize an empty payload with the provided content type
+    fn deserialize_with<P: PayloadSerialize>(
+        content_type: Option<&str>,
+    ) -> Result<P, DeserializationError<P::Error>> {
+        P::deserialize(
+            &[],
+            content_type.map(ToString::to_string).as_ref(),
+            &FormatIndicator::UnspecifiedBytes,
+        )
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let Json(result) = round_trip(
+            Json(sample()),
+            JSON_CONTENT_TYPE,
+            &FormatIndicator::Utf8EncodedCharacterData,
+        );
+        assert_eq!(result, sample());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trip() {
+        let Cbor(result) = round_trip(
+            Cbor(sample()),
+            CBOR_CONTENT_TYPE,
+            &FormatIndicator::UnspecifiedBytes,
+        );
+        assert_eq!(result, sample());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_round_trip() {
+        let MsgPack(result) = round_trip(
+            MsgPack(sample()),
+            MSGPACK_CONTENT_TYPE,
+            &FormatIndicator::UnspecifiedBytes,
+        );
+        assert_eq!(result, sample());
+    }
+
+    #[cfg(feature = "json")]
+    #[test_case(Some("application/cbor"); "other content type")]
+    #[test_case(Some("application/json; charset=utf-8"); "content type with parameters")]
+    #[test_case(Some(""); "empty content type")]
+    fn json_unsupported_content_type(content_type: Option<&str>) {
+        assert!(matches!(
+            deserialize_with::<Json<Sample>>(content_type),
+            Err(DeserializationError::UnsupportedContentType(_))
+        ));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test_case(Some("application/json"); "other content type")]
+    #[test_case(Some(""); "empty content type")]
+    fn cbor_unsupported_content_type(content_type: Option<&str>) {
+        assert!(matches!(
+            deserialize_with::<Cbor<Sample>>(content_type),
+            Err(DeserializationError::UnsupportedContentType(_))
+        ));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test_case(Some("application/json"); "other content type")]
+    #[test_case(Some(""); "empty content type")]
+    fn msgpack_unsupported_content_type(content_type: Option<&str>) {
+        assert!(matches!(
+            deserialize_with::<MsgPack<Sample>>(content_type),
+            Err(DeserializationError::UnsupportedContentType(_))
+        ));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn missing_content_type_is_accepted() {
+        let result = Json::<Sample>::deserialize(
+            &serde_json::to_vec(&sample()).unwrap(),
+            None,
+            &FormatIndicator::Utf8EncodedCharacterData,
+        )
+        .unwrap();
+        assert_eq!(result, Json(sample()));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn invalid_json_payload() {
+        assert!(matches!(
+            deserialize_with::<Json<Sample>>(Some(JSON_CONTENT_TYPE)),
+            Err(DeserializationError::InvalidPayload(_))
+        ));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn invalid_cbor_payload() {
+        assert!(matches!(
+            deserialize_with::<Cbor<Sample>>(Some(CBOR_CONTENT_TYPE)),
+            Err(DeserializationError::InvalidPayload(
+                CborError::Deserialize(_)
+            ))
+        ));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn invalid_msgpack_payload() {
+        assert!(matches!(
+            deserialize_with::<MsgPack<Sample>>(Some(MSGPACK_CONTENT_TYPE)),
+            Err(DeserializationError::InvalidPayload(
+                MsgPackError::Deserialize(_)
+            ))
+        ));
+    }
+}
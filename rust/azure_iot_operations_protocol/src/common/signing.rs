@@ -0,0 +1,1151 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Signing of telemetry messages, to prove that they were not modified and that they come from a
+//! known sender.
+//!
+//! A [`telemetry::Sender`](crate::telemetry::Sender) configured with a [`SigningKey`] signs each
+//! message as it is published, after compression and encryption if any. The signature covers the
+//! payload, the topic, the [`UserProperty::Timestamp`], the [`UserProperty::SourceId`], the content
+//! type and the payload format indicator of the message, as well as the
+//! [`UserProperty::ContentEncoding`] and [`UserProperty::PayloadFormatIndicator`] needed to
+//! restore the original payload, and is carried with the ID of the key and the algorithm in the
+//! [`UserProperty::Signature`], [`UserProperty::SignatureKeyId`] and
+//! [`UserProperty::SignatureAlgorithm`] user properties.
+//!
+//! A [`telemetry::Receiver`](crate::telemetry::Receiver) configured with a [`TrustStore`]
+//! verifies the signature of each received message against the [`VerificationKey`] identified in
+//! the message, and reports the result as a [`SignatureStatus`] on the received
+//! [`Message`](crate::telemetry::receiver::Message). A [`VerificationKey`] can be bound to the
+//! source ID of a sender, so that a message signed with it is only verified if it comes from that
+//! sender. The receiver can also be configured to reject messages whose signature is missing or
+//! invalid.
+//!
+//! Signing is independent of [encryption](super::encryption): a signed message can be encrypted
+//! or not, and the signature covers the payload as published.
+//!
+//! The algorithms are enabled by the `signing` cargo feature.
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display, Formatter, Write};
+use std::str::FromStr;
+use std::sync::{PoisonError, RwLock};
+
+use azure_iot_operations_mqtt::control_packet::Publish;
+
+use super::aio_protocol_error::AIOProtocolError;
+use super::message_properties::{
+    find_property, length_prefixed, outgoing_message_error, received_message_error,
+};
+use super::user_properties::UserProperty;
+
+/// Length, in bytes, of an Ed25519 public key
+pub const ED25519_PUBLIC_KEY_LEN: usize = 32;
+
+/// Minimum length, in bytes, of an HMAC-SHA256 secret
+pub const MIN_HMAC_KEY_LEN: usize = 32;
+
+/// Error returned by a [`TrustStore`]
+pub type TrustStoreError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Signature algorithm applied to a message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SignatureAlgorithm {
+    /// Ed25519 ([RFC 8032](https://www.rfc-editor.org/rfc/rfc8032)), carried as `Ed25519`
+    #[cfg(feature = "signing")]
+    Ed25519,
+    /// HMAC with SHA-256 ([RFC 2104](https://www.rfc-editor.org/rfc/rfc2104)), carried as `HS256`
+    #[cfg(feature = "signing")]
+    HmacSha256,
+}
+
+impl Display for SignatureAlgorithm {
+    #[cfg_attr(not(feature = "signing"), allow(unused_variables))]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            #[cfg(feature = "signing")]
+            SignatureAlgorithm::Ed25519 => write!(f, "Ed25519"),
+            #[cfg(feature = "signing")]
+            SignatureAlgorithm::HmacSha256 => write!(f, "HS256"),
+        }
+    }
+}
+
+impl FromStr for SignatureAlgorithm {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            #[cfg(feature = "signing")]
+            "Ed25519" => Ok(SignatureAlgorithm::Ed25519),
+            #[cfg(feature = "signing")]
+            "HS256" => Ok(SignatureAlgorithm::HmacSha256),
+            _ => Err(SignatureError::UnsupportedAlgorithm(s.to_string())),
+        }
+    }
+}
+
+/// Key used by a sender to sign messages, identified by a key ID.
+///
+/// The key material is not included in the [`Debug`] representation.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey {
+    id: String,
+    algorithm: SignatureAlgorithm,
+    material: Vec<u8>,
+}
+
+impl SigningKey {
+    /// Creates a new [`SigningKey`].
+    ///
+    /// `material` is a PKCS#8 v2 document containing the key pair for
+    /// [`Ed25519`](SignatureAlgorithm::Ed25519), or the shared secret for
+    /// [`HmacSha256`](SignatureAlgorithm::HmacSha256).
+    ///
+    /// # Errors
+    /// [`SignatureError::InvalidKey`] if `id` is empty or not a valid user property value, or if
+    /// `material` is not a valid key for `algorithm`.
+    pub fn new(
+        id: impl Into<String>,
+        algorithm: SignatureAlgorithm,
+        material: impl Into<Vec<u8>>,
+    ) -> Result<Self, SignatureError> {
+        let id = validate_key_id(id.into())?;
+        let material = material.into();
+        crypto::validate_signing_key(algorithm, &material)
+            .map_err(|e| SignatureError::InvalidKey(format!("Invalid key '{id}': {e}")))?;
+        Ok(Self {
+            id,
+            algorithm,
+            material,
+        })
+    }
+
+    /// ID of the key
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Algorithm the key is used with
+    #[must_use]
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the [`VerificationKey`] verifying signatures made with this key: the public key for
+    /// [`Ed25519`](SignatureAlgorithm::Ed25519), or the shared secret for
+    /// [`HmacSha256`](SignatureAlgorithm::HmacSha256).
+    ///
+    /// # Errors
+    /// [`SignatureError::InvalidKey`] if the public key cannot be derived from the key material
+    pub fn verification_key(&self) -> Result<VerificationKey, SignatureError> {
+        let material = crypto::verification_material(self)?;
+        Ok(VerificationKey {
+            id: self.id.clone(),
+            algorithm: self.algorithm,
+            material,
+            source_id: None,
+        })
+    }
+}
+
+impl Debug for SigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("id", &self.id)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Key used by a receiver to verify the signatures of messages, identified by a key ID.
+///
+/// The key material is not included in the [`Debug`] representation.
+#[derive(Clone, PartialEq, Eq)]
+pub struct VerificationKey {
+    id: String,
+    algorithm: SignatureAlgorithm,
+    material: Vec<u8>,
+    source_id: Option<String>,
+}
+
+impl VerificationKey {
+    /// Creates a new [`VerificationKey`], not bound to a sender.
+    ///
+    /// `material` is the [`ED25519_PUBLIC_KEY_LEN`] bytes long public key for
+    /// [`Ed25519`](SignatureAlgorithm::Ed25519), or the shared secret for
+    /// [`HmacSha256`](SignatureAlgorithm::HmacSha256).
+    ///
+    /// # Errors
+    /// [`SignatureError::InvalidKey`] if `id` is empty or not a valid user property value, or if
+    /// `material` is not a valid key for `algorithm`.
+    pub fn new(
+        id: impl Into<String>,
+        algorithm: SignatureAlgorithm,
+        material: impl Into<Vec<u8>>,
+    ) -> Result<Self, SignatureError> {
+        let id = validate_key_id(id.into())?;
+        let material = material.into();
+        crypto::validate_verification_key(algorithm, &material)
+            .map_err(|e| SignatureError::InvalidKey(format!("Invalid key '{id}': {e}")))?;
+        Ok(Self {
+            id,
+            algorithm,
+            material,
+            source_id: None,
+        })
+    }
+
+    /// Binds the key to the sender with source ID `source_id`. Messages signed with the key are
+    /// then only verified if their [`UserProperty::SourceId`] is `source_id`.
+    #[must_use]
+    pub fn with_source_id(mut self, source_id: impl Into<String>) -> Self {
+        self.source_id = Some(source_id.into());
+        self
+    }
+
+    /// ID of the key
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Algorithm the key is used with
+    #[must_use]
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    /// Source ID of the sender the key is bound to, if any
+    #[must_use]
+    pub fn source_id(&self) -> Option<&str> {
+        self.source_id.as_deref()
+    }
+}
+
+impl Debug for VerificationKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerificationKey")
+            .field("id", &self.id)
+            .field("algorithm", &self.algorithm)
+            .field("source_id", &self.source_id)
+            .finish_non_exhaustive()
+    }
+}
+
+fn validate_key_id(id: String) -> Result<String, SignatureError> {
+    if id.trim().is_empty() || super::is_invalid_utf8(&id) {
+        return Err(SignatureError::InvalidKey(format!("Invalid key ID '{id}'")));
+    }
+    Ok(id)
+}
+
+/// Source of the keys trusted to verify the signatures of received messages.
+///
+/// Implement this trait to integrate with a device registry or certificate store.
+/// [`InMemoryTrustStore`] can be used when keys are distributed to the application by other means.
+pub trait TrustStore: Send + Sync {
+    /// Returns the key with ID `key_id`, or [`None`] if the key is not trusted.
+    ///
+    /// # Errors
+    /// Returns a [`TrustStoreError`] if the key cannot be retrieved
+    fn verification_key(&self, key_id: &str) -> Result<Option<VerificationKey>, TrustStoreError>;
+}
+
+/// [`TrustStore`] holding its keys in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryTrustStore {
+    keys: RwLock<HashMap<String, VerificationKey>>,
+}
+
+impl InMemoryTrustStore {
+    /// Creates a new, empty [`InMemoryTrustStore`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts `key`, replacing any key with the same ID.
+    pub fn insert(&self, key: VerificationKey) {
+        self.keys
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key.id.clone(), key);
+    }
+
+    /// Removes the key with ID `key_id`, so that messages signed with it are no longer verified.
+    ///
+    /// Returns `true` if the key was removed.
+    pub fn remove(&self, key_id: &str) -> bool {
+        self.keys
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(key_id)
+            .is_some()
+    }
+}
+
+impl TrustStore for InMemoryTrustStore {
+    fn verification_key(&self, key_id: &str) -> Result<Option<VerificationKey>, TrustStoreError> {
+        Ok(self
+            .keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(key_id)
+            .cloned())
+    }
+}
+
+/// Result of the verification of the signature of a received message
+#[derive(Debug)]
+pub enum SignatureStatus {
+    /// The receiver is not configured with a [`TrustStore`], so the signature was not verified
+    NotVerified,
+    /// The message is not signed
+    Unsigned,
+    /// The signature is valid
+    Verified {
+        /// ID of the key the message was signed with
+        key_id: String,
+    },
+    /// The signature is missing properties, its key is not trusted, or it does not match the
+    /// message
+    Invalid(SignatureError),
+}
+
+impl SignatureStatus {
+    /// Returns `true` if the signature of the message was verified
+    #[must_use]
+    pub fn is_verified(&self) -> bool {
+        matches!(self, SignatureStatus::Verified { .. })
+    }
+}
+
+/// Error signing a message or verifying its signature
+#[derive(thiserror::Error, Debug)]
+pub enum SignatureError {
+    /// The key material or key ID is invalid
+    #[error("{0}")]
+    InvalidKey(String),
+    /// The [`TrustStore`] failed to provide a key
+    #[error("Trust store error: {0}")]
+    TrustStore(#[source] TrustStoreError),
+    /// The message is not signed, but a valid signature is required
+    #[error("Message is not signed, but a valid signature is required")]
+    Unsigned,
+    /// A user property required to verify the signature is missing
+    #[error("Signed message is missing the '{0}' user property")]
+    PropertyMissing(UserProperty),
+    /// The signature algorithm of the message is not supported
+    #[error("Unsupported signature algorithm '{0}'")]
+    UnsupportedAlgorithm(String),
+    /// The key the message was signed with is not trusted
+    #[error("Untrusted signature key '{0}'")]
+    UnknownKey(String),
+    /// The algorithm of the message does not match the algorithm of its key
+    #[error("Signature key '{key_id}' is not used with algorithm '{algorithm}'")]
+    AlgorithmMismatch {
+        /// ID of the key
+        key_id: String,
+        /// Algorithm of the message
+        algorithm: SignatureAlgorithm,
+    },
+    /// The key the message was signed with is bound to another sender
+    #[error("Signature key '{key_id}' is not trusted for source ID '{}'", source_id.as_deref().unwrap_or_default())]
+    SourceMismatch {
+        /// ID of the key
+        key_id: String,
+        /// Source ID of the message, if any
+        source_id: Option<String>,
+    },
+    /// The signature does not match the message, because the message was altered or it was not
+    /// signed with the identified key
+    #[error(
+        "Signature verification failed: the message was altered, or was not signed with the identified key"
+    )]
+    VerificationFailed,
+}
+
+impl SignatureError {
+    /// The name and, if present, the value of the user property causing the error
+    pub(crate) fn invalid_property(&self) -> Option<(String, Option<String>)> {
+        match self {
+            SignatureError::Unsigned => Some((UserProperty::Signature.to_string(), None)),
+            SignatureError::PropertyMissing(property) => Some((property.to_string(), None)),
+            SignatureError::UnknownKey(key_id) => Some((
+                UserProperty::SignatureKeyId.to_string(),
+                Some(key_id.clone()),
+            )),
+            SignatureError::UnsupportedAlgorithm(algorithm) => Some((
+                UserProperty::SignatureAlgorithm.to_string(),
+                Some(algorithm.clone()),
+            )),
+            SignatureError::AlgorithmMismatch { algorithm, .. } => Some((
+                UserProperty::SignatureAlgorithm.to_string(),
+                Some(algorithm.to_string()),
+            )),
+            SignatureError::SourceMismatch { source_id, .. } => {
+                Some((UserProperty::SourceId.to_string(), source_id.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts an error signing an outgoing message into an [`AIOProtocolError`]
+    pub(crate) fn into_sign_error(self, command_name: Option<String>) -> AIOProtocolError {
+        outgoing_message_error(self, "Message signing error", command_name)
+    }
+
+    /// Converts an error verifying the signature of a received message into an
+    /// [`AIOProtocolError`]
+    pub(crate) fn into_verify_error(self, command_name: Option<String>) -> AIOProtocolError {
+        let invalid_property = self.invalid_property();
+        received_message_error(self, invalid_property, command_name)
+    }
+}
+
+/// User properties added to a message by [`sign`]
+const SIGNATURE_PROPERTIES: [UserProperty; 3] = [
+    UserProperty::SignatureKeyId,
+    UserProperty::SignatureAlgorithm,
+    UserProperty::Signature,
+];
+
+/// Content covered by the signature of a message
+fn signed_content(
+    algorithm: SignatureAlgorithm,
+    key_id: &str,
+    topic: &[u8],
+    user_properties: &[(String, String)],
+    content_type: Option<&str>,
+    payload_format_indicator: Option<u8>,
+    payload: &[u8],
+) -> Vec<u8> {
+    let algorithm = algorithm.to_string();
+    // An absent payload format indicator is equivalent to unspecified bytes
+    let payload_format_indicator = [payload_format_indicator.unwrap_or_default()];
+    length_prefixed(&[
+        algorithm.as_bytes(),
+        key_id.as_bytes(),
+        topic,
+        find_property(user_properties, UserProperty::Timestamp)
+            .unwrap_or_default()
+            .as_bytes(),
+        find_property(user_properties, UserProperty::SourceId)
+            .unwrap_or_default()
+            .as_bytes(),
+        find_property(user_properties, UserProperty::ContentEncoding)
+            .unwrap_or_default()
+            .as_bytes(),
+        find_property(user_properties, UserProperty::PayloadFormatIndicator)
+            .unwrap_or_default()
+            .as_bytes(),
+        content_type.unwrap_or_default().as_bytes(),
+        &payload_format_indicator,
+        payload,
+    ])
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        // Writing to a String cannot fail
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Signs a message with `key`, and adds the signature user properties to `user_properties`.
+///
+/// The signature covers `topic`, `content_type`, `payload_format_indicator`, `payload` and the
+/// [`UserProperty::Timestamp`], [`UserProperty::SourceId`], [`UserProperty::ContentEncoding`] and
+/// [`UserProperty::PayloadFormatIndicator`] in `user_properties`, so they must not be modified
+/// afterwards.
+pub(crate) fn sign(
+    key: &SigningKey,
+    topic: &[u8],
+    user_properties: &mut Vec<(String, String)>,
+    content_type: Option<&str>,
+    payload_format_indicator: Option<u8>,
+    payload: &[u8],
+) -> Result<(), SignatureError> {
+    let content = signed_content(
+        key.algorithm,
+        &key.id,
+        topic,
+        user_properties,
+        content_type,
+        payload_format_indicator,
+        payload,
+    );
+    let signature = crypto::sign(key, &content)?;
+    user_properties.push((UserProperty::SignatureKeyId.to_string(), key.id.clone()));
+    user_properties.push((
+        UserProperty::SignatureAlgorithm.to_string(),
+        key.algorithm.to_string(),
+    ));
+    user_properties.push((UserProperty::Signature.to_string(), to_hex(&signature)));
+    Ok(())
+}
+
+/// Verifies the signature of a received message with the keys of `trust_store`, and removes the
+/// signature user properties from `user_properties`.
+///
+/// Returns [`SignatureStatus::NotVerified`] if no `trust_store` is configured.
+pub(crate) fn verify(
+    trust_store: Option<&dyn TrustStore>,
+    topic: &[u8],
+    user_properties: &mut Vec<(String, String)>,
+    content_type: Option<&str>,
+    payload_format_indicator: Option<u8>,
+    payload: &[u8],
+) -> SignatureStatus {
+    let status = match trust_store {
+        Some(trust_store) => {
+            if SIGNATURE_PROPERTIES
+                .iter()
+                .all(|p| find_property(user_properties, *p).is_none())
+            {
+                SignatureStatus::Unsigned
+            } else {
+                match verify_signature(
+                    trust_store,
+                    topic,
+                    user_properties,
+                    content_type,
+                    payload_format_indicator,
+                    payload,
+                ) {
+                    Ok(key_id) => SignatureStatus::Verified { key_id },
+                    Err(e) => SignatureStatus::Invalid(e),
+                }
+            }
+        }
+        None => SignatureStatus::NotVerified,
+    };
+
+    let signature_properties = SIGNATURE_PROPERTIES.map(|p| p.to_string());
+    user_properties.retain(|(k, _)| !signature_properties.contains(k));
+    status
+}
+
+/// Verifies the signature of a signed message, returning the ID of its key.
+fn verify_signature(
+    trust_store: &dyn TrustStore,
+    topic: &[u8],
+    user_properties: &[(String, String)],
+    content_type: Option<&str>,
+    payload_format_indicator: Option<u8>,
+    payload: &[u8],
+) -> Result<String, SignatureError> {
+    let [key_id, algorithm, signature] = SIGNATURE_PROPERTIES
+        .map(|p| find_property(user_properties, p).ok_or(SignatureError::PropertyMissing(p)));
+    let (key_id, algorithm, signature) = (key_id?, algorithm?.parse()?, signature?);
+
+    let key = trust_store
+        .verification_key(key_id)
+        .map_err(SignatureError::TrustStore)?
+        .ok_or_else(|| SignatureError::UnknownKey(key_id.to_string()))?;
+    if key.algorithm != algorithm {
+        return Err(SignatureError::AlgorithmMismatch {
+            key_id: key_id.to_string(),
+            algorithm,
+        });
+    }
+    let source_id = find_property(user_properties, UserProperty::SourceId);
+    if key.source_id.is_some() && key.source_id.as_deref() != source_id {
+        return Err(SignatureError::SourceMismatch {
+            key_id: key_id.to_string(),
+            source_id: source_id.map(ToString::to_string),
+        });
+    }
+
+    let signature = from_hex(signature).ok_or(SignatureError::VerificationFailed)?;
+    let content = signed_content(
+        algorithm,
+        key_id,
+        topic,
+        user_properties,
+        content_type,
+        payload_format_indicator,
+        payload,
+    );
+    if crypto::verify(&key, &content, &signature) {
+        Ok(key_id.to_string())
+    } else {
+        Err(SignatureError::VerificationFailed)
+    }
+}
+
+/// Verifies the signature of a received `publish`, and removes its signature user properties.
+/// See [`verify`].
+pub(crate) fn verify_publish(
+    trust_store: Option<&dyn TrustStore>,
+    publish: &mut Publish,
+) -> SignatureStatus {
+    let Some(properties) = publish.properties.as_mut() else {
+        return match trust_store {
+            Some(_) => SignatureStatus::Unsigned,
+            None => SignatureStatus::NotVerified,
+        };
+    };
+    verify(
+        trust_store,
+        &publish.topic,
+        &mut properties.user_properties,
+        properties.content_type.as_deref(),
+        properties.payload_format_indicator,
+        &publish.payload,
+    )
+}
+
+#[cfg(feature = "signing")]
+mod crypto {
+    use ring::hmac;
+    use ring::signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+
+    use super::{
+        ED25519_PUBLIC_KEY_LEN, MIN_HMAC_KEY_LEN, SignatureAlgorithm, SignatureError, SigningKey,
+        VerificationKey,
+    };
+
+    fn ed25519_key_pair(key: &SigningKey) -> Result<Ed25519KeyPair, SignatureError> {
+        Ed25519KeyPair::from_pkcs8(&key.material)
+            .map_err(|e| SignatureError::InvalidKey(format!("Invalid key '{}': {e}", key.id)))
+    }
+
+    pub(super) fn validate_signing_key(
+        algorithm: SignatureAlgorithm,
+        material: &[u8],
+    ) -> Result<(), String> {
+        match algorithm {
+            SignatureAlgorithm::Ed25519 => Ed25519KeyPair::from_pkcs8(material)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            SignatureAlgorithm::HmacSha256 => validate_hmac_key(material),
+        }
+    }
+
+    pub(super) fn validate_verification_key(
+        algorithm: SignatureAlgorithm,
+        material: &[u8],
+    ) -> Result<(), String> {
+        match algorithm {
+            SignatureAlgorithm::Ed25519 if material.len() != ED25519_PUBLIC_KEY_LEN => Err(
+                format!("public key must be {ED25519_PUBLIC_KEY_LEN} bytes long"),
+            ),
+            SignatureAlgorithm::Ed25519 => Ok(()),
+            SignatureAlgorithm::HmacSha256 => validate_hmac_key(material),
+        }
+    }
+
+    fn validate_hmac_key(material: &[u8]) -> Result<(), String> {
+        if material.len() < MIN_HMAC_KEY_LEN {
+            return Err(format!(
+                "secret must be at least {MIN_HMAC_KEY_LEN} bytes long"
+            ));
+        }
+        Ok(())
+    }
+
+    pub(super) fn verification_material(key: &SigningKey) -> Result<Vec<u8>, SignatureError> {
+        match key.algorithm {
+            SignatureAlgorithm::Ed25519 => {
+                Ok(ed25519_key_pair(key)?.public_key().as_ref().to_vec())
+            }
+            SignatureAlgorithm::HmacSha256 => Ok(key.material.clone()),
+        }
+    }
+
+    pub(super) fn sign(key: &SigningKey, content: &[u8]) -> Result<Vec<u8>, SignatureError> {
+        match key.algorithm {
+            SignatureAlgorithm::Ed25519 => {
+                Ok(ed25519_key_pair(key)?.sign(content).as_ref().to_vec())
+            }
+            SignatureAlgorithm::HmacSha256 => {
+                let hmac_key = hmac::Key::new(hmac::HMAC_SHA256, &key.material);
+                Ok(hmac::sign(&hmac_key, content).as_ref().to_vec())
+            }
+        }
+    }
+
+    pub(super) fn verify(key: &VerificationKey, content: &[u8], signature: &[u8]) -> bool {
+        match key.algorithm {
+            SignatureAlgorithm::Ed25519 => UnparsedPublicKey::new(&ED25519, &key.material)
+                .verify(content, signature)
+                .is_ok(),
+            SignatureAlgorithm::HmacSha256 => {
+                let hmac_key = hmac::Key::new(hmac::HMAC_SHA256, &key.material);
+                // Compares the tags in constant time
+                hmac::verify(&hmac_key, content, signature).is_ok()
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "signing"))]
+mod crypto {
+    use super::{SignatureAlgorithm, SignatureError, SigningKey, VerificationKey};
+
+    pub(super) fn validate_signing_key(
+        algorithm: SignatureAlgorithm,
+        _material: &[u8],
+    ) -> Result<(), String> {
+        match algorithm {}
+    }
+
+    pub(super) fn validate_verification_key(
+        algorithm: SignatureAlgorithm,
+        _material: &[u8],
+    ) -> Result<(), String> {
+        match algorithm {}
+    }
+
+    pub(super) fn verification_material(key: &SigningKey) -> Result<Vec<u8>, SignatureError> {
+        match key.algorithm {}
+    }
+
+    pub(super) fn sign(key: &SigningKey, _content: &[u8]) -> Result<Vec<u8>, SignatureError> {
+        match key.algorithm {}
+    }
+
+    pub(super) fn verify(key: &VerificationKey, _content: &[u8], _signature: &[u8]) -> bool {
+        match key.algorithm {}
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use ring::rand::SystemRandom;
+    use ring::signature::Ed25519KeyPair;
+    use test_case::test_case;
+
+    use super::*;
+
+    const TOPIC: &[u8] = b"test/telemetry";
+    const CONTENT_TYPE: Option<&str> = Some("application/json");
+    const FORMAT_INDICATOR: Option<u8> = Some(0);
+    const PAYLOAD: &[u8] = br#"{"temperature":21.5}"#;
+
+    fn signing_key(id: &str, algorithm: SignatureAlgorithm) -> SigningKey {
+        let material = match algorithm {
+            SignatureAlgorithm::Ed25519 => Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                .unwrap()
+                .as_ref()
+                .to_vec(),
+            SignatureAlgorithm::HmacSha256 => vec![0x42; MIN_HMAC_KEY_LEN],
+        };
+        SigningKey::new(id, algorithm, material).unwrap()
+    }
+
+    fn trust_store(key: &SigningKey) -> InMemoryTrustStore {
+        let trust_store = InMemoryTrustStore::new();
+        trust_store.insert(key.verification_key().unwrap());
+        trust_store
+    }
+
+    fn message_properties() -> Vec<(String, String)> {
+        vec![
+            (
+                UserProperty::Timestamp.to_string(),
+                "1700000000000:0:node".to_string(),
+            ),
+            (UserProperty::SourceId.to_string(), "device1".to_string()),
+            (
+                UserProperty::ContentEncoding.to_string(),
+                "gzip".to_string(),
+            ),
+            (
+                UserProperty::PayloadFormatIndicator.to_string(),
+                "1".to_string(),
+            ),
+            ("custom".to_string(), "value".to_string()),
+        ]
+    }
+
+    fn signed_properties(key: &SigningKey) -> Vec<(String, String)> {
+        let mut user_properties = message_properties();
+        sign(
+            key,
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        )
+        .unwrap();
+        user_properties
+    }
+
+    fn set_property(user_properties: &mut [(String, String)], property: UserProperty, value: &str) {
+        let key = property.to_string();
+        for (k, v) in user_properties.iter_mut() {
+            if *k == key {
+                *v = value.to_string();
+            }
+        }
+    }
+
+    #[test_case(SignatureAlgorithm::Ed25519; "ed25519")]
+    #[test_case(SignatureAlgorithm::HmacSha256; "hmac_sha256")]
+    fn round_trip(algorithm: SignatureAlgorithm) {
+        let key = signing_key("key1", algorithm);
+        let mut user_properties = signed_properties(&key);
+        assert_eq!(
+            find_property(&user_properties, UserProperty::SignatureKeyId),
+            Some("key1")
+        );
+        assert_eq!(
+            find_property(&user_properties, UserProperty::SignatureAlgorithm),
+            Some(algorithm.to_string().as_str())
+        );
+
+        let status = verify(
+            Some(&trust_store(&key)),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        assert!(status.is_verified());
+        assert!(matches!(status, SignatureStatus::Verified { key_id } if key_id == "key1"));
+        assert_eq!(user_properties, message_properties());
+    }
+
+    #[test_case(b"other/topic", CONTENT_TYPE, FORMAT_INDICATOR, PAYLOAD, None; "topic")]
+    #[test_case(TOPIC, Some("application/cbor"), FORMAT_INDICATOR, PAYLOAD, None; "content_type")]
+    #[test_case(TOPIC, None, FORMAT_INDICATOR, PAYLOAD, None; "content_type_removed")]
+    #[test_case(TOPIC, CONTENT_TYPE, Some(1), PAYLOAD, None; "format_indicator")]
+    #[test_case(TOPIC, CONTENT_TYPE, FORMAT_INDICATOR, br#"{"temperature":99.9}"#, None; "payload")]
+    #[test_case(TOPIC, CONTENT_TYPE, FORMAT_INDICATOR, PAYLOAD, Some(UserProperty::Timestamp); "timestamp")]
+    #[test_case(TOPIC, CONTENT_TYPE, FORMAT_INDICATOR, PAYLOAD, Some(UserProperty::SourceId); "source_id")]
+    #[test_case(TOPIC, CONTENT_TYPE, FORMAT_INDICATOR, PAYLOAD, Some(UserProperty::ContentEncoding); "content_encoding")]
+    #[test_case(TOPIC, CONTENT_TYPE, FORMAT_INDICATOR, PAYLOAD, Some(UserProperty::PayloadFormatIndicator); "carried_format_indicator")]
+    fn altered_message(
+        topic: &[u8],
+        content_type: Option<&str>,
+        format_indicator: Option<u8>,
+        payload: &[u8],
+        altered_property: Option<UserProperty>,
+    ) {
+        for algorithm in [SignatureAlgorithm::Ed25519, SignatureAlgorithm::HmacSha256] {
+            let key = signing_key("key1", algorithm);
+            let mut user_properties = signed_properties(&key);
+            if let Some(property) = altered_property {
+                set_property(&mut user_properties, property, "altered");
+            }
+            let status = verify(
+                Some(&trust_store(&key)),
+                topic,
+                &mut user_properties,
+                content_type,
+                format_indicator,
+                payload,
+            );
+            assert!(matches!(
+                status,
+                SignatureStatus::Invalid(SignatureError::VerificationFailed)
+            ));
+        }
+    }
+
+    #[test]
+    fn absent_format_indicator_is_unspecified_bytes() {
+        let key = signing_key("key1", SignatureAlgorithm::Ed25519);
+        let mut user_properties = signed_properties(&key);
+        let status = verify(
+            Some(&trust_store(&key)),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            None,
+            PAYLOAD,
+        );
+        assert!(status.is_verified());
+    }
+
+    #[test_case("zz"; "not_hex")]
+    #[test_case("abc"; "odd_length")]
+    #[test_case("+a"; "sign")]
+    #[test_case(""; "empty")]
+    fn malformed_signature(signature: &str) {
+        let key = signing_key("key1", SignatureAlgorithm::HmacSha256);
+        let mut user_properties = signed_properties(&key);
+        set_property(&mut user_properties, UserProperty::Signature, signature);
+        let status = verify(
+            Some(&trust_store(&key)),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        assert!(matches!(
+            status,
+            SignatureStatus::Invalid(SignatureError::VerificationFailed)
+        ));
+    }
+
+    #[test]
+    fn wrong_key() {
+        let key = signing_key("key1", SignatureAlgorithm::Ed25519);
+        let impostor = signing_key("key1", SignatureAlgorithm::Ed25519);
+        let mut user_properties = signed_properties(&impostor);
+        let status = verify(
+            Some(&trust_store(&key)),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        assert!(matches!(
+            status,
+            SignatureStatus::Invalid(SignatureError::VerificationFailed)
+        ));
+    }
+
+    #[test]
+    fn unknown_key() {
+        let key = signing_key("key1", SignatureAlgorithm::Ed25519);
+        let mut user_properties = signed_properties(&key);
+        let status = verify(
+            Some(&InMemoryTrustStore::new()),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        let SignatureStatus::Invalid(e) = status else {
+            panic!("Expected an invalid signature, got {status:?}");
+        };
+        assert_eq!(
+            e.invalid_property(),
+            Some((
+                UserProperty::SignatureKeyId.to_string(),
+                Some("key1".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    fn removed_key() {
+        let key = signing_key("key1", SignatureAlgorithm::HmacSha256);
+        let trust_store = trust_store(&key);
+        assert!(trust_store.remove("key1"));
+        assert!(!trust_store.remove("key1"));
+        let mut user_properties = signed_properties(&key);
+        let status = verify(
+            Some(&trust_store),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        assert!(matches!(
+            status,
+            SignatureStatus::Invalid(SignatureError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn algorithm_mismatch() {
+        let key = signing_key("key1", SignatureAlgorithm::HmacSha256);
+        let mut user_properties = signed_properties(&key);
+        set_property(
+            &mut user_properties,
+            UserProperty::SignatureAlgorithm,
+            &SignatureAlgorithm::Ed25519.to_string(),
+        );
+        let status = verify(
+            Some(&trust_store(&key)),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        assert!(matches!(
+            status,
+            SignatureStatus::Invalid(SignatureError::AlgorithmMismatch { .. })
+        ));
+    }
+
+    #[test_case(Some("device1"), true; "bound_to_sender")]
+    #[test_case(Some("device2"), false; "bound_to_other_sender")]
+    #[test_case(None, true; "not_bound")]
+    fn source_id_binding(bound_source_id: Option<&str>, verified: bool) {
+        let key = signing_key("key1", SignatureAlgorithm::Ed25519);
+        let mut verification_key = key.verification_key().unwrap();
+        if let Some(source_id) = bound_source_id {
+            verification_key = verification_key.with_source_id(source_id);
+        }
+        let trust_store = InMemoryTrustStore::new();
+        trust_store.insert(verification_key);
+
+        let mut user_properties = signed_properties(&key);
+        let status = verify(
+            Some(&trust_store),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        if verified {
+            assert!(status.is_verified());
+        } else {
+            let SignatureStatus::Invalid(e) = status else {
+                panic!("Expected an invalid signature, got {status:?}");
+            };
+            assert_eq!(
+                e.invalid_property(),
+                Some((
+                    UserProperty::SourceId.to_string(),
+                    Some("device1".to_string())
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn unsigned() {
+        let key = signing_key("key1", SignatureAlgorithm::Ed25519);
+        let mut user_properties = message_properties();
+        let status = verify(
+            Some(&trust_store(&key)),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        assert!(matches!(status, SignatureStatus::Unsigned));
+        assert_eq!(user_properties, message_properties());
+    }
+
+    #[test_case(UserProperty::SignatureKeyId; "key_id")]
+    #[test_case(UserProperty::SignatureAlgorithm; "algorithm")]
+    #[test_case(UserProperty::Signature; "signature")]
+    fn missing_property(missing: UserProperty) {
+        let key = signing_key("key1", SignatureAlgorithm::Ed25519);
+        let mut user_properties = signed_properties(&key);
+        user_properties.retain(|(k, _)| *k != missing.to_string());
+        let status = verify(
+            Some(&trust_store(&key)),
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        let SignatureStatus::Invalid(e) = status else {
+            panic!("Expected an invalid signature, got {status:?}");
+        };
+        assert_eq!(e.invalid_property(), Some((missing.to_string(), None)));
+        assert_eq!(user_properties, message_properties());
+    }
+
+    #[test]
+    fn no_trust_store() {
+        let key = signing_key("key1", SignatureAlgorithm::Ed25519);
+        let mut user_properties = signed_properties(&key);
+        let status = verify(
+            None,
+            TOPIC,
+            &mut user_properties,
+            CONTENT_TYPE,
+            FORMAT_INDICATOR,
+            PAYLOAD,
+        );
+        assert!(matches!(status, SignatureStatus::NotVerified));
+        assert_eq!(user_properties, message_properties());
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case(" "; "whitespace")]
+    #[test_case("key\n1"; "invalid_utf8")]
+    fn invalid_key_id(id: &str) {
+        assert!(matches!(
+            SigningKey::new(id, SignatureAlgorithm::HmacSha256, [0; MIN_HMAC_KEY_LEN]),
+            Err(SignatureError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            VerificationKey::new(id, SignatureAlgorithm::HmacSha256, [0; MIN_HMAC_KEY_LEN]),
+            Err(SignatureError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_key_material() {
+        assert!(SigningKey::new("key1", SignatureAlgorithm::Ed25519, [0; 32]).is_err());
+        assert!(
+            SigningKey::new(
+                "key1",
+                SignatureAlgorithm::HmacSha256,
+                [0; MIN_HMAC_KEY_LEN - 1]
+            )
+            .is_err()
+        );
+        assert!(
+            VerificationKey::new(
+                "key1",
+                SignatureAlgorithm::Ed25519,
+                [0; ED25519_PUBLIC_KEY_LEN + 1]
+            )
+            .is_err()
+        );
+        assert!(
+            VerificationKey::new(
+                "key1",
+                SignatureAlgorithm::HmacSha256,
+                [0; MIN_HMAC_KEY_LEN - 1]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn debug_redacts_key_material() {
+        let key = SigningKey::new(
+            "key1",
+            SignatureAlgorithm::HmacSha256,
+            [0xAB; MIN_HMAC_KEY_LEN],
+        )
+        .unwrap();
+        let verification_key = key.verification_key().unwrap();
+        for debug in [format!("{key:?}"), format!("{verification_key:?}")] {
+            assert!(debug.contains("key1"));
+            assert!(!debug.contains("171"));
+            assert!(!debug.contains("material"));
+        }
+    }
+
+    #[test_case("Ed25519", Some(SignatureAlgorithm::Ed25519); "ed25519")]
+    #[test_case("HS256", Some(SignatureAlgorithm::HmacSha256); "hmac_sha256")]
+    #[test_case("hs256", None; "wrong_case")]
+    #[test_case("RS256", None; "unsupported")]
+    fn algorithm_from_str(s: &str, expected: Option<SignatureAlgorithm>) {
+        assert_eq!(SignatureAlgorithm::from_str(s).ok(), expected);
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        let bytes = (0..=u8::MAX).collect::<Vec<_>>();
+        assert_eq!(from_hex(&to_hex(&bytes)), Some(bytes));
+    }
+}
@@ -37,6 +37,52 @@ pub enum UserProperty {
     /// This property is only used when a command executor rejects a command invocation because the
     /// requested protocol version either wasn't supported or was malformed.
     RequestProtocolVersion,
+    /// User property indicating the codec the payload of a request or message was compressed with,
+    /// as a [`CompressionCodec`](super::compression::CompressionCodec). Absent if the payload is
+    /// not compressed.
+    ContentEncoding,
+    /// User property carrying the MQTT payload format indicator the payload had before it was
+    /// compressed or encrypted, whose result is always sent as unspecified bytes. Absent if the
+    /// payload is not transformed or was already unspecified bytes.
+    PayloadFormatIndicator,
+    /// User property indicating the ID of the key the payload of a message was encrypted with.
+    /// Absent if the payload is not encrypted.
+    EncryptionKeyId,
+    /// User property indicating the algorithm the payload of a message was encrypted with, as an
+    /// [`EncryptionAlgorithm`](super::encryption::EncryptionAlgorithm). Absent if the payload is
+    /// not encrypted.
+    EncryptionAlgorithm,
+    /// User property indicating the ID of the key the telemetry message was signed with.
+    /// Absent if the message is not signed.
+    SignatureKeyId,
+    /// User property indicating the algorithm the telemetry message was signed with, as a
+    /// [`SignatureAlgorithm`](super::signing::SignatureAlgorithm). Absent if the message is not
+    /// signed.
+    SignatureAlgorithm,
+    /// User property containing the hex encoded signature of the telemetry message. Absent if the
+    /// message is not signed.
+    Signature,
+    /// User property indicating the zero-based index of a chunk of a chunked message. Absent if
+    /// the message is not chunked.
+    ChunkIndex,
+    /// User property indicating the number of chunks of a chunked message. Absent if the message
+    /// is not chunked.
+    ChunkCount,
+    /// User property containing the hex encoded CRC-32 checksum of the complete payload of a
+    /// chunked message. Absent if the message is not chunked.
+    ChunkChecksum,
+    /// User property carrying the MQTT payload format indicator of a chunked message, whose chunks
+    /// are always sent as unspecified bytes. Absent if the message is not chunked or its payload
+    /// is unspecified bytes.
+    ChunkFormatIndicator,
+    /// User property indicating the zero-based sequence number of a response in a stream of
+    /// command responses. Absent if the response is not streamed.
+    StreamIndex,
+    /// User property set to "true" on the last response of a stream of command responses.
+    StreamEnd,
+    /// User property set to "true" on a message cancelling the command request with the same
+    /// correlation data, sent with the cancellation protocol version.
+    Cancel,
 }
 
 impl Display for UserProperty {
@@ -53,6 +99,20 @@ impl Display for UserProperty {
             UserProperty::ProtocolVersion => write!(f, "__protVer"),
             UserProperty::SupportedMajorVersions => write!(f, "__supProtMajVer"),
             UserProperty::RequestProtocolVersion => write!(f, "__requestProtVer"),
+            UserProperty::ContentEncoding => write!(f, "__contEnc"),
+            UserProperty::PayloadFormatIndicator => write!(f, "__pfi"),
+            UserProperty::EncryptionKeyId => write!(f, "__encKid"),
+            UserProperty::EncryptionAlgorithm => write!(f, "__encAlg"),
+            UserProperty::SignatureKeyId => write!(f, "__sigKid"),
+            UserProperty::SignatureAlgorithm => write!(f, "__sigAlg"),
+            UserProperty::Signature => write!(f, "__sig"),
+            UserProperty::ChunkIndex => write!(f, "__chunkIdx"),
+            UserProperty::ChunkCount => write!(f, "__chunkCnt"),
+            UserProperty::ChunkChecksum => write!(f, "__chunkSum"),
+            UserProperty::ChunkFormatIndicator => write!(f, "__chunkPfi"),
+            UserProperty::StreamIndex => write!(f, "__streamIdx"),
+            UserProperty::StreamEnd => write!(f, "__streamEnd"),
+            UserProperty::Cancel => write!(f, "__cancel"),
         }
     }
 }
@@ -72,6 +132,20 @@ impl FromStr for UserProperty {
             "__protVer" => Ok(UserProperty::ProtocolVersion),
             "__supProtMajVer" => Ok(UserProperty::SupportedMajorVersions),
             "__requestProtVer" => Ok(UserProperty::RequestProtocolVersion),
+            "__contEnc" => Ok(UserProperty::ContentEncoding),
+            "__pfi" => Ok(UserProperty::PayloadFormatIndicator),
+            "__encKid" => Ok(UserProperty::EncryptionKeyId),
+            "__encAlg" => Ok(UserProperty::EncryptionAlgorithm),
+            "__sigKid" => Ok(UserProperty::SignatureKeyId),
+            "__sigAlg" => Ok(UserProperty::SignatureAlgorithm),
+            "__sig" => Ok(UserProperty::Signature),
+            "__chunkIdx" => Ok(UserProperty::ChunkIndex),
+            "__chunkCnt" => Ok(UserProperty::ChunkCount),
+            "__chunkSum" => Ok(UserProperty::ChunkChecksum),
+            "__chunkPfi" => Ok(UserProperty::ChunkFormatIndicator),
+            "__streamIdx" => Ok(UserProperty::StreamIndex),
+            "__streamEnd" => Ok(UserProperty::StreamEnd),
+            "__cancel" => Ok(UserProperty::Cancel),
             _ => Err(()),
         }
     }
@@ -132,6 +206,20 @@ mod tests {
     #[test_case(UserProperty::ProtocolVersion; "protocol_version")]
     #[test_case(UserProperty::SupportedMajorVersions; "supported_major_versions")]
     #[test_case(UserProperty::RequestProtocolVersion; "request_protocol_version")]
+    #[test_case(UserProperty::ContentEncoding; "content_encoding")]
+    #[test_case(UserProperty::PayloadFormatIndicator; "payload_format_indicator")]
+    #[test_case(UserProperty::EncryptionKeyId; "encryption_key_id")]
+    #[test_case(UserProperty::EncryptionAlgorithm; "encryption_algorithm")]
+    #[test_case(UserProperty::SignatureKeyId; "signature_key_id")]
+    #[test_case(UserProperty::SignatureAlgorithm; "signature_algorithm")]
+    #[test_case(UserProperty::Signature; "signature")]
+    #[test_case(UserProperty::ChunkIndex; "chunk_index")]
+    #[test_case(UserProperty::ChunkCount; "chunk_count")]
+    #[test_case(UserProperty::ChunkChecksum; "chunk_checksum")]
+    #[test_case(UserProperty::ChunkFormatIndicator; "chunk_format_indicator")]
+    #[test_case(UserProperty::StreamIndex; "stream_index")]
+    #[test_case(UserProperty::StreamEnd; "stream_end")]
+    #[test_case(UserProperty::Cancel; "cancel")]
     fn test_to_from_string(prop: UserProperty) {
         assert_eq!(prop, UserProperty::from_str(&prop.to_string()).unwrap());
     }
@@ -5,7 +5,10 @@
 
 use std::str::FromStr;
 
+use azure_iot_operations_mqtt::session::PriorityClassifier;
+
 use crate::ProtocolVersion;
+use crate::common::user_properties::UserProperty;
 
 /// This module contains the command invoker implementation.
 pub mod invoker;
@@ -13,6 +16,9 @@ pub mod invoker;
 /// This module contains the command executor implementation.
 pub mod executor;
 
+/// This module contains the retry policies of the command invoker.
+pub mod retry_policy;
+
 /// Re-export the command invoker and executor for ease of use.
 pub use executor::Executor;
 pub use invoker::Invoker;
@@ -23,6 +29,22 @@ pub(crate) const RPC_COMMAND_PROTOCOL_VERSION: ProtocolVersion =
 /// Assumed version if no version is provided.
 pub(crate) const DEFAULT_RPC_COMMAND_PROTOCOL_VERSION: ProtocolVersion =
     ProtocolVersion { major: 1, minor: 0 };
+/// Protocol version of the cancellation of a command request. Its major version isn't supported
+/// by executors that don't support cancellation, so that they reject the cancellation instead of
+/// processing it as a request.
+pub(crate) const RPC_COMMAND_CANCELLATION_PROTOCOL_VERSION: ProtocolVersion =
+    ProtocolVersion { major: 2, minor: 0 };
+
+/// Return a [`PriorityClassifier`] for the priority lanes of a
+/// [`Session`](azure_iot_operations_mqtt::session::Session) that classifies command requests and
+/// responses as [`Control`](azure_iot_operations_mqtt::session::PublishPriority::Control), and all
+/// other publishes (e.g. telemetry) as [`Bulk`](azure_iot_operations_mqtt::session::PublishPriority::Bulk).
+#[must_use]
+pub fn priority_classifier() -> PriorityClassifier {
+    PriorityClassifier::Rpc {
+        response_properties: vec![UserProperty::Status.to_string()],
+    }
+}
 
 /// Represents the valid status codes for command responses.
 #[repr(u16)]
@@ -112,6 +134,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_priority_classifier_identifies_responses_by_status() {
+        assert!(matches!(
+            priority_classifier(),
+            PriorityClassifier::Rpc { response_properties } if response_properties == ["__stat"]
+        ));
+    }
+
     #[test]
     fn test_invalid_status_code() {
         let test_invalid_code = "not a number";
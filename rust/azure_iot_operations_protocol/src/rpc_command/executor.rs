@@ -1,15 +1,17 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
+use std::borrow::Cow;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
-use std::{collections::HashMap, marker::PhantomData, time::Duration};
+use std::time::{Duration, Instant};
+use std::{collections::HashMap, marker::PhantomData};
 
 use azure_iot_operations_mqtt::control_packet::{PublishProperties, QoS};
 use azure_iot_operations_mqtt::interface::{AckToken, ManagedClient, PubReceiver};
+use azure_iot_operations_mqtt::runtime;
 use bytes::Bytes;
-use tokio::sync::oneshot;
-use tokio::time::{Instant, timeout};
+use tokio::sync::{mpsc, oneshot};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
@@ -17,6 +19,9 @@ use crate::{
     application::{ApplicationContext, ApplicationHybridLogicalClock},
     common::{
         aio_protocol_error::{AIOProtocolError, Value},
+        chunking::{self, ChunkBuffer, Chunking, ReassemblyLimits},
+        compression::{self, CompressionCodec},
+        encryption::{self, KeyProvider},
         hybrid_logical_clock::{HLCErrorKind, HybridLogicalClock},
         is_invalid_utf8,
         payload_serialize::{
@@ -25,7 +30,10 @@ use crate::{
         topic_processor::{TopicPattern, contains_invalid_char, is_valid_replacement},
         user_properties::{PARTITION_KEY, UserProperty, validate_user_properties},
     },
-    rpc_command::{DEFAULT_RPC_COMMAND_PROTOCOL_VERSION, RPC_COMMAND_PROTOCOL_VERSION, StatusCode},
+    rpc_command::{
+        DEFAULT_RPC_COMMAND_PROTOCOL_VERSION, RPC_COMMAND_CANCELLATION_PROTOCOL_VERSION,
+        RPC_COMMAND_PROTOCOL_VERSION, StatusCode,
+    },
     supported_protocol_major_versions_to_string,
 };
 
@@ -39,6 +47,7 @@ const INTERNAL_LOGIC_EXPIRATION_ERROR: &str =
 const SUPPORTED_PROTOCOL_VERSIONS: &[u16] = &[1];
 
 /// Struct to hold response arguments
+#[derive(Clone)]
 struct ResponseArguments {
     command_name: String,
     response_topic: String,
@@ -54,6 +63,37 @@ struct ResponseArguments {
     request_protocol_version: Option<String>,
     cached_key: Option<CacheKey>,
     cached_entry_status: CacheEntryStatus,
+    key_provider: Option<Arc<dyn KeyProvider>>,
+    chunking: Option<Chunking>,
+    stream_position: Option<StreamPosition>,
+}
+
+/// Position of a response in a stream of command responses
+#[derive(Clone, Copy)]
+struct StreamPosition {
+    index: u64,
+    is_last: bool,
+}
+
+/// Response reported by the application for a command request
+enum ResponseMessage<TResp>
+where
+    TResp: PayloadSerialize,
+{
+    /// A single response, completing the command
+    Single(Response<TResp>),
+    /// A stream of responses, sent by a [`StreamResponder`]
+    Stream(mpsc::Receiver<StreamedResponse<TResp>>),
+}
+
+/// Response of a stream, with the sender of the completion of its publish
+struct StreamedResponse<TResp>
+where
+    TResp: PayloadSerialize,
+{
+    response: Response<TResp>,
+    is_last: bool,
+    completion_tx: oneshot::Sender<Result<(), AIOProtocolError>>,
 }
 
 /// Command Executor Request struct.
@@ -81,8 +121,9 @@ where
     pub topic_tokens: HashMap<String, String>,
     // Internal fields
     command_name: String,
-    response_tx: oneshot::Sender<Response<TResp>>,
+    response_tx: oneshot::Sender<ResponseMessage<TResp>>,
     publish_completion_rx: oneshot::Receiver<Result<(), AIOProtocolError>>,
+    cancellation_token: CancellationToken,
 }
 
 impl<TReq, TResp> Request<TReq, TResp>
@@ -107,7 +148,7 @@ where
     /// acknowledgement returns an error.
     ///
     /// [`AIOProtocolError`] of kind [`Cancellation`](crate::common::aio_protocol_error::AIOProtocolErrorKind::Cancellation) if the
-    /// executor is dropped.
+    /// executor is dropped or the invoker cancelled the command request.
     ///
     /// [`AIOProtocolError`] of kind [`InternalLogicError`](crate::common::aio_protocol_error::AIOProtocolErrorKind::InternalLogicError)
     /// if the response publish completion fails. This should not happen.
@@ -116,33 +157,177 @@ where
         // because the executor is shutting down in which case the receive below will fail.
         // If the executor is not shutting down, the receive below will succeed and we'll receive a
         // timeout error since that is the only possible error at this point.
-        let _ = self.response_tx.send(response);
+        let _ = self.response_tx.send(ResponseMessage::Single(response));
 
         self.publish_completion_rx
             .await
-            .map_err(|_| Self::create_cancellation_error(self.command_name))?
+            .map_err(|_| create_cancellation_error(self.command_name))?
     }
 
-    fn create_cancellation_error(command_name: String) -> AIOProtocolError {
-        AIOProtocolError::new_cancellation_error(
-            false,
-            None,
-            Some(
-                "Command Executor has been shutdown and can no longer respond to commands"
-                    .to_string(),
-            ),
-            Some(command_name),
-        )
+    /// Consumes the command request and starts a stream of responses to the invoker, for commands
+    /// that return a sequence of results. Responses are sent with [`StreamResponder::send`], and
+    /// the stream is ended by its last response, sent with [`StreamResponder::complete`].
+    ///
+    /// All responses of the stream share the correlation data of the request and carry their
+    /// sequence number, so that the invoker can order them and discard duplicates. Streamed
+    /// responses are not cached, a duplicate of the request is received again by the executor.
+    #[must_use]
+    pub fn start_stream(self) -> StreamResponder<TResp> {
+        let (stream_tx, stream_rx) = mpsc::channel(1);
+        // We can ignore the error here. If the executor is no longer processing the request, the
+        // first response sent on the stream reports why.
+        let _ = self.response_tx.send(ResponseMessage::Stream(stream_rx));
+        StreamResponder {
+            command_name: self.command_name,
+            stream_tx,
+            publish_completion_rx: Some(self.publish_completion_rx),
+            cancellation_token: self.cancellation_token,
+        }
     }
 
-    /// Check if the command response is no longer expected.
+    /// Check if the command response is no longer expected, because the invoker cancelled the
+    /// command request, the command request expired, or the executor was dropped.
     ///
     /// Returns true if the response is no longer expected, otherwise returns false.
     pub fn is_cancelled(&self) -> bool {
-        self.response_tx.is_closed()
+        self.response_tx.is_closed() || self.cancellation_token.is_cancelled()
+    }
+
+    /// Waits until the command response is no longer expected, so that a long-running command
+    /// can be aborted. See [`Request::is_cancelled`].
+    pub async fn cancelled(&self) {
+        self.cancellation_token.cancelled().await;
     }
 }
 
+/// Command Executor stream of responses to a command request, created by [`Request::start_stream`].
+///
+/// If dropped before the stream is completed, executor will end the stream with an error response
+/// to the invoker
+pub struct StreamResponder<TResp>
+where
+    TResp: PayloadSerialize,
+{
+    command_name: String,
+    stream_tx: mpsc::Sender<StreamedResponse<TResp>>,
+    publish_completion_rx: Option<oneshot::Receiver<Result<(), AIOProtocolError>>>,
+    cancellation_token: CancellationToken,
+}
+
+impl<TResp> StreamResponder<TResp>
+where
+    TResp: PayloadSerialize,
+{
+    /// Sends the next response of the stream to the invoker, waiting for it to be published.
+    ///
+    /// Returns Ok(()) on success, otherwise returns [`AIOProtocolError`].
+    ///
+    /// # Arguments
+    /// * `response` - The [`Response`] to send.
+    ///
+    /// # Errors
+    ///
+    /// [`AIOProtocolError`] of kind [`Timeout`](crate::common::aio_protocol_error::AIOProtocolErrorKind::Timeout) if the command request
+    /// has expired.
+    ///
+    /// [`AIOProtocolError`] of kind [`ClientError`](crate::common::aio_protocol_error::AIOProtocolErrorKind::ClientError) if the response
+    /// acknowledgement returns an error.
+    ///
+    /// [`AIOProtocolError`] of kind [`Cancellation`](crate::common::aio_protocol_error::AIOProtocolErrorKind::Cancellation) if the
+    /// executor is dropped or the invoker cancelled the command request.
+    ///
+    /// [`AIOProtocolError`] of kind [`InternalLogicError`](crate::common::aio_protocol_error::AIOProtocolErrorKind::InternalLogicError)
+    /// if the response publish completion fails. This should not happen.
+    pub async fn send(&mut self, response: Response<TResp>) -> Result<(), AIOProtocolError> {
+        self.send_response(response, false).await
+    }
+
+    /// Consumes the stream and sends its last response to the invoker, ending the stream.
+    ///
+    /// Returns Ok(()) on success, otherwise returns [`AIOProtocolError`].
+    ///
+    /// # Arguments
+    /// * `response` - The last [`Response`] of the stream.
+    ///
+    /// # Errors
+    /// Same as [`StreamResponder::send`].
+    pub async fn complete(mut self, response: Response<TResp>) -> Result<(), AIOProtocolError> {
+        self.send_response(response, true).await
+    }
+
+    /// Check if the responses of the stream are no longer expected, because the invoker cancelled
+    /// the command request, the command request expired, or the executor was dropped.
+    ///
+    /// Returns true if the responses are no longer expected, otherwise returns false.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.stream_tx.is_closed() || self.cancellation_token.is_cancelled()
+    }
+
+    /// Waits until the responses of the stream are no longer expected, so that a long-running
+    /// command can be aborted. See [`StreamResponder::is_cancelled`].
+    pub async fn cancelled(&self) {
+        self.cancellation_token.cancelled().await;
+    }
+
+    async fn send_response(
+        &mut self,
+        response: Response<TResp>,
+        is_last: bool,
+    ) -> Result<(), AIOProtocolError> {
+        let (completion_tx, completion_rx) = oneshot::channel();
+        // We can ignore the error here. If the executor is no longer processing the stream, the
+        // response is dropped along with its completion sender and the receive below fails.
+        let _ = self
+            .stream_tx
+            .send(StreamedResponse {
+                response,
+                is_last,
+                completion_tx,
+            })
+            .await;
+        if let Ok(result) = completion_rx.await {
+            return result;
+        }
+
+        // Report the timeout if the command expired, the executor has been dropped otherwise
+        match self.publish_completion_rx.take() {
+            Some(publish_completion_rx) => match publish_completion_rx.await {
+                Ok(Err(e)) => Err(e),
+                _ => Err(create_cancellation_error(self.command_name.clone())),
+            },
+            None => Err(create_cancellation_error(self.command_name.clone())),
+        }
+    }
+}
+
+/// Creates the error reported to the application when the command expires before its response is
+/// published
+fn create_timeout_error(
+    command_name: &str,
+    message_expiry_interval: Option<u32>,
+) -> AIOProtocolError {
+    AIOProtocolError::new_timeout_error(
+        false,
+        None,
+        command_name,
+        Duration::from_secs(message_expiry_interval.unwrap_or_default().into()),
+        None,
+        Some(command_name.to_string()),
+    )
+}
+
+fn create_cancellation_error(command_name: String) -> AIOProtocolError {
+    AIOProtocolError::new_cancellation_error(
+        false,
+        None,
+        Some(
+            "Command Executor has been shutdown and can no longer respond to commands".to_string(),
+        ),
+        Some(command_name),
+    )
+}
+
 /// Command Executor Response struct.
 /// Used by the [`Executor`]
 #[derive(Builder, Clone, Debug)]
@@ -248,6 +433,43 @@ pub fn application_error_headers(
     Ok(())
 }
 
+/// Checks that a message cancelling a command request was sent with the
+/// [`RPC_COMMAND_CANCELLATION_PROTOCOL_VERSION`] and, if a `key_provider` is configured, that its
+/// payload is its `correlation_data` sealed with a key of the provider.
+///
+/// Returns a description of the problem if the cancellation is invalid.
+fn validate_cancellation(
+    key_provider: Option<&dyn KeyProvider>,
+    topic: &[u8],
+    user_properties: &[(String, String)],
+    correlation_data: &[u8],
+    payload: &[u8],
+) -> Result<(), String> {
+    let protocol_version = user_properties
+        .iter()
+        .find(|(key, _)| UserProperty::from_str(key) == Ok(UserProperty::ProtocolVersion))
+        .map(|(_, value)| value.as_str());
+    if !protocol_version
+        .and_then(ProtocolVersion::parse_protocol_version)
+        .is_some_and(|version| {
+            version.is_supported(&[RPC_COMMAND_CANCELLATION_PROTOCOL_VERSION.major])
+        })
+    {
+        return Err(format!(
+            "Unsupported cancellation protocol version: {}",
+            protocol_version.unwrap_or("none")
+        ));
+    }
+
+    match encryption::open(key_provider, topic, &mut user_properties.to_vec(), payload) {
+        Ok(Some(plaintext)) if plaintext != correlation_data => {
+            Err("The sealed correlation data doesn't match".to_string())
+        }
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 /// Command Executor Cache Key struct.
 ///
 /// Used to uniquely identify a command request.
@@ -269,10 +491,9 @@ struct CacheEntry {
 ///
 /// Used to indicate the status of a cache entry.
 ///
-/// Note: It is not possible for a cache entry to be in progress due to the nature of the underlying
-/// session. If a command request is received, the session will drop duplicates while the original
-/// request is being processed.
-#[derive(PartialEq, Debug)]
+/// Note: A request that is being processed has no cache entry. Duplicates of it, redelivered by the
+/// session or retried by the invoker, are dropped while the original request is being processed.
+#[derive(Clone, PartialEq, Debug)]
 enum CacheEntryStatus {
     /// The cache entry is cached and has not expired
     Cached(CacheEntry),
@@ -340,6 +561,17 @@ pub struct Options {
     /// Service group ID
     #[builder(default = "None")]
     service_group_id: Option<String>,
+    /// Optional end-to-end encryption with the keys of the key provider. If set, requests that
+    /// are not encrypted are rejected, and responses are encrypted with the current key.
+    #[builder(default = "None")]
+    encryption: Option<Arc<dyn KeyProvider>>,
+    /// Optional splitting of response payloads larger than the maximum chunk size into chunks,
+    /// each published as a separate message and reassembled by the invoker.
+    #[builder(default = "None")]
+    chunking: Option<Chunking>,
+    /// Limits applied when reassembling chunked requests
+    #[builder(default)]
+    chunk_reassembly: ReassemblyLimits,
 }
 
 /// Command Executor struct
@@ -392,6 +624,11 @@ where
     request_payload_type: PhantomData<TReq>,
     response_payload_type: PhantomData<TResp>,
     cache: Cache,
+    key_provider: Option<Arc<dyn KeyProvider>>,
+    chunking: Option<Chunking>,
+    chunk_buffer: ChunkBuffer,
+    // Cancellation tokens of the command requests being processed, cancelled once processed
+    in_flight_requests: HashMap<CacheKey, CancellationToken>,
     // Describes state
     executor_state: State,
     // Information to manage state
@@ -430,6 +667,7 @@ where
     ///     [`topic_namespace`](OptionsBuilder::topic_namespace)
     ///     are Some and invalid or contain a token with no valid replacement
     /// - [`topic_token_map`](OptionsBuilder::topic_token_map) is not empty and contains invalid key(s) and/or token(s)
+    /// - [`chunking`](OptionsBuilder::chunking) is Some and its maximum chunk size is zero
     pub fn new(
         application_context: ApplicationContext,
         client: C,
@@ -449,6 +687,10 @@ where
             ));
         }
 
+        if let Some(chunking) = executor_options.chunking {
+            chunking.validate(Some(executor_options.command_name.clone()))?;
+        }
+
         // Create a new Command Pattern, validates topic pattern and options
         let request_topic_pattern = TopicPattern::new(
             &executor_options.request_topic_pattern,
@@ -490,6 +732,10 @@ where
             request_payload_type: PhantomData,
             response_payload_type: PhantomData,
             cache: Cache(Arc::new(Mutex::new(HashMap::new()))),
+            key_provider: executor_options.encryption,
+            chunking: executor_options.chunking,
+            chunk_buffer: ChunkBuffer::new(executor_options.chunk_reassembly),
+            in_flight_requests: HashMap::new(),
             executor_state: State::New,
             executor_cancellation_token: CancellationToken::new(),
         })
@@ -600,6 +846,16 @@ where
     ///
     /// Will also subscribe to the request topic if not already subscribed.
     ///
+    /// Chunked requests are reassembled within the [`chunk_reassembly`](OptionsBuilder::chunk_reassembly)
+    /// limits before being returned. Chunks are acknowledged along with the reassembled request, and a
+    /// request that cannot be reassembled is responded to with a bad request status.
+    ///
+    /// A message cancelling a command request, sent by [`Invoker::invoke_cancellable`](crate::rpc_command::Invoker::invoke_cancellable),
+    /// is not returned. It cancels the [`Request`] with the same correlation data if it is still being
+    /// processed, which is then no longer responded to. A cancellation that wasn't sent with the
+    /// cancellation protocol version, or that isn't encrypted with the correlation data as its payload
+    /// while [`encryption`](OptionsBuilder::encryption) is configured, is ignored.
+    ///
     /// # Errors
     /// [`AIOProtocolError`] of kind [`UnknownError`](crate::common::aio_protocol_error::AIOProtocolErrorKind::UnknownError) if an error occurs while receiving the message.
     ///
@@ -615,7 +871,7 @@ where
             self.executor_state = State::Subscribed;
         }
 
-        loop {
+        'receive: loop {
             match self.mqtt_receiver.recv_manual_ack().await {
                 Some((m, ack_token)) => {
                     let Some(ack_token) = ack_token else {
@@ -624,12 +880,33 @@ where
                         log::warn!("[{}] Received message without ack token", self.command_name);
                         continue;
                     };
+
+                    // Reassemble chunked requests. The ack tokens of buffered chunks are held until
+                    // the reassembled request is acked, and a chunk that cannot be reassembled is
+                    // kept to respond to the invoker with the error.
+                    let chunk = m.clone();
+                    let mut ack_token = Some(ack_token);
+                    let (m, chunk_error) = match self.chunk_buffer.insert(m, &mut ack_token) {
+                        Ok(Some(m)) => (m, None),
+                        Ok(None) => {
+                            log::debug!(
+                                "[{}][pkid: {}] Buffered request chunk",
+                                self.command_name,
+                                chunk.pkid
+                            );
+                            continue;
+                        }
+                        Err(e) => (chunk, Some(e)),
+                    };
+                    let Some(ack_token) = ack_token else {
+                        unreachable!("The ack token is only taken when a chunk is buffered");
+                    };
                     // Process the request
                     log::info!("[{}][pkid: {}] Received request", self.command_name, m.pkid);
                     let message_received_time = Instant::now();
 
                     // Clone properties
-                    let properties = if let Some(properties) = &m.properties {
+                    let mut properties = if let Some(properties) = &m.properties {
                         properties.clone()
                     } else {
                         log::error!(
@@ -637,7 +914,7 @@ where
                             self.command_name,
                             m.pkid
                         );
-                        tokio::task::spawn({
+                        runtime::spawn({
                             let executor_cancellation_token_clone =
                                 self.executor_cancellation_token.clone();
                             async move {
@@ -656,7 +933,7 @@ where
                                 self.command_name,
                                 m.pkid
                             );
-                            tokio::task::spawn({
+                            runtime::spawn({
                                 let executor_cancellation_token_clone =
                                     self.executor_cancellation_token.clone();
                                 async move {
@@ -677,7 +954,7 @@ where
                             self.command_name,
                             m.pkid
                         );
-                        tokio::task::spawn({
+                        runtime::spawn({
                             let executor_cancellation_token_clone =
                                 self.executor_cancellation_token.clone();
                             async move {
@@ -704,6 +981,9 @@ where
                         request_protocol_version: None,
                         cached_key: None,
                         cached_entry_status: CacheEntryStatus::NotFound,
+                        key_provider: self.key_provider.clone(),
+                        chunking: self.chunking,
+                        stream_position: None,
                     };
 
                     // Get message expiry interval
@@ -761,6 +1041,67 @@ where
                             break 'process_request;
                         };
 
+                        // Cancel the command request being processed with the same correlation
+                        // data, without responding to the invoker, once the cancellation is
+                        // authenticated like the request it cancels
+                        if properties.user_properties.iter().any(|(key, value)| {
+                            UserProperty::from_str(key) == Ok(UserProperty::Cancel)
+                                && value == "true"
+                        }) {
+                            if let Err(e) = validate_cancellation(
+                                self.key_provider.as_deref(),
+                                &m.topic,
+                                &properties.user_properties,
+                                &cache_key.correlation_data,
+                                &m.payload,
+                            ) {
+                                log::warn!(
+                                    "[{}][pkid: {}] Invalid cancellation ignored: {e}",
+                                    self.command_name,
+                                    m.pkid
+                                );
+                            } else if let Some(cancellation_token) =
+                                self.in_flight_requests.get(cache_key)
+                            {
+                                log::info!(
+                                    "[{}][pkid: {}] Request cancelled by the invoker",
+                                    self.command_name,
+                                    m.pkid
+                                );
+                                cancellation_token.cancel();
+                            } else {
+                                log::debug!(
+                                    "[{}][pkid: {}] No request being processed to cancel",
+                                    self.command_name,
+                                    m.pkid
+                                );
+                            }
+                            runtime::spawn({
+                                let executor_cancellation_token_clone =
+                                    self.executor_cancellation_token.clone();
+                                async move {
+                                    handle_ack(
+                                        ack_token,
+                                        executor_cancellation_token_clone,
+                                        m.pkid,
+                                    )
+                                    .await;
+                                }
+                            });
+                            continue 'receive;
+                        }
+
+                        // Reject chunked requests that could not be reassembled
+                        if let Some(e) = chunk_error {
+                            response_arguments.status_code = StatusCode::BadRequest;
+                            response_arguments.status_message = Some(e.to_string());
+                            if let Some((name, value)) = e.invalid_property() {
+                                response_arguments.invalid_property_name = Some(name);
+                                response_arguments.invalid_property_value = value;
+                            }
+                            break 'process_request;
+                        }
+
                         // Checking if command expiration time was calculated after correlation
                         // to provide a more accurate response to the invoker.
                         let Some(command_expiration_time) = command_expiration_time else {
@@ -788,6 +1129,34 @@ where
                             break 'process_request;
                         }
 
+                        // Drop a duplicate of a request that is still being processed, such as an
+                        // attempt retried by the invoker, which receives the response of the
+                        // original request
+                        if self
+                            .in_flight_requests
+                            .get(cache_key)
+                            .is_some_and(|token| !token.is_cancelled())
+                        {
+                            log::debug!(
+                                "[{}][pkid: {}] Duplicate request is still being processed",
+                                self.command_name,
+                                m.pkid
+                            );
+                            runtime::spawn({
+                                let executor_cancellation_token_clone =
+                                    self.executor_cancellation_token.clone();
+                                async move {
+                                    handle_ack(
+                                        ack_token,
+                                        executor_cancellation_token_clone,
+                                        m.pkid,
+                                    )
+                                    .await;
+                                }
+                            });
+                            continue 'receive;
+                        }
+
                         // unused beyond validation, but may be used in the future to determine how to handle other fields. Can be moved higher in the future if needed.
                         let mut request_protocol_version = DEFAULT_RPC_COMMAND_PROTOCOL_VERSION; // assume default version if none is provided
                         if let Some((_, protocol_version)) =
@@ -824,9 +1193,31 @@ where
                             break 'process_request;
                         }
 
+                        // Decrypt the payload, rejecting requests that cannot be decrypted
+                        let decrypted_payload = match encryption::open(
+                            self.key_provider.as_deref(),
+                            &m.topic,
+                            &mut properties.user_properties,
+                            &m.payload,
+                        ) {
+                            Ok(decrypted_payload) => decrypted_payload,
+                            Err(e) => {
+                                response_arguments.status_code = StatusCode::BadRequest;
+                                response_arguments.status_message = Some(e.to_string());
+                                if let Some((name, value)) = e.invalid_property() {
+                                    response_arguments.invalid_property_name = Some(name);
+                                    response_arguments.invalid_property_value = value;
+                                }
+                                break 'process_request;
+                            }
+                        };
+                        let payload = decrypted_payload.as_deref().unwrap_or(&m.payload);
+
                         let mut user_data = Vec::new();
                         let mut timestamp = None;
                         let mut invoker_id = None;
+                        let mut content_encoding = None;
+                        let mut carried_format_indicator = None;
                         for (key, value) in properties.user_properties {
                             match UserProperty::from_str(&key) {
                                 Ok(UserProperty::Timestamp) => {
@@ -872,6 +1263,36 @@ where
                                 Ok(UserProperty::ProtocolVersion) => {
                                     // skip, already processed
                                 }
+                                Ok(UserProperty::ContentEncoding) => {
+                                    match CompressionCodec::from_str(&value) {
+                                        Ok(codec) => content_encoding = Some(codec),
+                                        Err(message) => {
+                                            response_arguments.status_code =
+                                                StatusCode::UnsupportedMediaType;
+                                            response_arguments.status_message = Some(message);
+                                            response_arguments.invalid_property_name =
+                                                Some(UserProperty::ContentEncoding.to_string());
+                                            response_arguments.invalid_property_value = Some(value);
+                                            break 'process_request;
+                                        }
+                                    }
+                                }
+                                Ok(UserProperty::PayloadFormatIndicator) => {
+                                    match FormatIndicator::from_user_property(&value) {
+                                        Ok(format_indicator) => {
+                                            carried_format_indicator = Some(format_indicator);
+                                        }
+                                        Err(message) => {
+                                            response_arguments.status_code = StatusCode::BadRequest;
+                                            response_arguments.status_message = Some(message);
+                                            response_arguments.invalid_property_name = Some(
+                                                UserProperty::PayloadFormatIndicator.to_string(),
+                                            );
+                                            response_arguments.invalid_property_value = Some(value);
+                                            break 'process_request;
+                                        }
+                                    }
+                                }
                                 Err(()) => {
                                     if key == PARTITION_KEY {
                                         // Ignore partition key, it is meant for the broker
@@ -903,8 +1324,10 @@ where
 
                         let topic_tokens = self.request_topic_pattern.parse_tokens(topic);
 
-                        // Deserialize payload
-                        let format_indicator = match properties.payload_format_indicator.try_into()
+                        // Deserialize payload, restoring the format indicator it had before it
+                        // was transformed
+                        let format_indicator = match carried_format_indicator
+                            .map_or_else(|| properties.payload_format_indicator.try_into(), Ok)
                         {
                             Ok(format_indicator) => format_indicator,
                             Err(e) => {
@@ -916,8 +1339,20 @@ where
                                 FormatIndicator::default()
                             }
                         };
+                        let payload = match content_encoding {
+                            Some(codec) => match compression::decompress(codec, payload) {
+                                Ok(payload) => Cow::Owned(payload),
+                                Err(message) => {
+                                    response_arguments.status_code = StatusCode::BadRequest;
+                                    response_arguments.status_message =
+                                        Some(format!("Error decompressing payload: {message}"));
+                                    break 'process_request;
+                                }
+                            },
+                            None => Cow::Borrowed(payload),
+                        };
                         let payload = match TReq::deserialize(
-                            &m.payload,
+                            &payload,
                             properties.content_type.as_ref(),
                             &format_indicator,
                         ) {
@@ -946,6 +1381,7 @@ where
                         let (response_tx, response_rx) = oneshot::channel();
                         let (publish_completion_tx, publish_completion_rx) = oneshot::channel();
 
+                        let cancellation_token = self.executor_cancellation_token.child_token();
                         let command_request = Request {
                             payload,
                             content_type: properties.content_type,
@@ -957,12 +1393,20 @@ where
                             command_name: self.command_name.clone(),
                             response_tx,
                             publish_completion_rx,
+                            cancellation_token: cancellation_token.clone(),
                         };
 
                         // Check the command has not expired, if it has, we do not respond to the invoker.
                         if command_expiration_time.elapsed().is_zero() {
                             // Elapsed returns zero if the time has not passed
-                            tokio::task::spawn({
+
+                            // Track the command request until it is processed, so that the invoker
+                            // can cancel it. Requests that have been processed are no longer tracked.
+                            self.in_flight_requests
+                                .retain(|_, token| !token.is_cancelled());
+                            self.in_flight_requests
+                                .insert(cache_key.clone(), cancellation_token.clone());
+                            runtime::spawn({
                                 let app_hlc_clone = self.application_hlc.clone();
                                 let client_clone = self.mqtt_client.clone();
                                 let cache_clone = self.cache.clone();
@@ -972,6 +1416,10 @@ where
                                 async move {
                                     tokio::select! {
                                         () = executor_cancellation_token_clone.cancelled() => { /* executor dropped */},
+                                        () = cancellation_token.cancelled() => {
+                                            // Cancelled by the invoker, which no longer expects a response
+                                            handle_ack(ack_token, executor_cancellation_token_clone, pkid).await;
+                                        },
                                         () = Self::process_command(
                                             app_hlc_clone,
                                             client_clone,
@@ -986,6 +1434,8 @@ where
                                             handle_ack(ack_token, executor_cancellation_token_clone, pkid).await;
                                         },
                                     }
+                                    // The command request is no longer being processed
+                                    cancellation_token.cancel();
                                 }
                             });
                             return Some(Ok(command_request));
@@ -1011,7 +1461,7 @@ where
                             continue;
                         }
                         _ => {
-                            tokio::task::spawn({
+                            runtime::spawn({
                                 let app_hlc_clone = self.application_hlc.clone();
                                 let client_clone = self.mqtt_client.clone();
                                 let cache_clone = self.cache.clone();
@@ -1064,7 +1514,165 @@ where
         client: C,
         pkid: u16,
         mut response_arguments: ResponseArguments,
-        response_rx: Option<oneshot::Receiver<Response<TResp>>>,
+        response_rx: Option<oneshot::Receiver<ResponseMessage<TResp>>>,
+        completion_tx: Option<oneshot::Sender<Result<(), AIOProtocolError>>>,
+        cache: Cache,
+    ) {
+        let mut response = None;
+        if let (Some(command_expiration_time), Some(response_rx)) =
+            (response_arguments.command_expiration_time, response_rx)
+        {
+            // Wait for response
+            match runtime::timeout(
+                command_expiration_time.duration_since(Instant::now()),
+                response_rx,
+            )
+            .await
+            {
+                Ok(Ok(ResponseMessage::Single(response_app))) => response = Some(response_app),
+                Ok(Ok(ResponseMessage::Stream(stream_rx))) => {
+                    Self::process_stream(
+                        application_hlc,
+                        client,
+                        pkid,
+                        response_arguments,
+                        stream_rx,
+                        completion_tx,
+                        cache,
+                    )
+                    .await;
+                    return;
+                }
+                Ok(Err(_)) => {
+                    // Happens when the sender is dropped by the application.
+                    response_arguments.status_code = StatusCode::InternalServerError;
+                    response_arguments.status_message =
+                        Some("Request has been dropped by the application".to_string());
+                    response_arguments.is_application_error = true;
+                }
+                Err(_) => {
+                    log::error!(
+                        "[{}][pkid: {}] Request timed out",
+                        response_arguments.command_name,
+                        pkid
+                    );
+                    // Notify the application that a timeout occurred
+                    if let Some(completion_tx) = completion_tx {
+                        let _ = completion_tx.send(Err(create_timeout_error(
+                            &response_arguments.command_name,
+                            response_arguments.message_expiry_interval,
+                        )));
+                    }
+                    return;
+                }
+            }
+        }
+
+        Self::send_response(
+            application_hlc,
+            client,
+            pkid,
+            response_arguments,
+            response,
+            completion_tx,
+            cache,
+        )
+        .await;
+    }
+
+    /// Publishes the responses of a stream as they are sent by the application, until the last
+    /// one is published or the command expires.
+    async fn process_stream(
+        application_hlc: Arc<ApplicationHybridLogicalClock>,
+        client: C,
+        pkid: u16,
+        response_arguments: ResponseArguments,
+        mut stream_rx: mpsc::Receiver<StreamedResponse<TResp>>,
+        completion_tx: Option<oneshot::Sender<Result<(), AIOProtocolError>>>,
+        cache: Cache,
+    ) {
+        // Always set, responses are only awaited until the command expires
+        let Some(command_expiration_time) = response_arguments.command_expiration_time else {
+            return;
+        };
+
+        let mut index = 0;
+        loop {
+            let mut stream_arguments = response_arguments.clone();
+            match runtime::timeout(
+                command_expiration_time.saturating_duration_since(Instant::now()),
+                stream_rx.recv(),
+            )
+            .await
+            {
+                Ok(Some(streamed_response)) => {
+                    stream_arguments.stream_position = Some(StreamPosition {
+                        index,
+                        is_last: streamed_response.is_last,
+                    });
+                    Self::send_response(
+                        application_hlc.clone(),
+                        client.clone(),
+                        pkid,
+                        stream_arguments,
+                        Some(streamed_response.response),
+                        Some(streamed_response.completion_tx),
+                        cache.clone(),
+                    )
+                    .await;
+                    if streamed_response.is_last {
+                        return;
+                    }
+                    index += 1;
+                }
+                Ok(None) => {
+                    // Happens when the stream is dropped by the application before its last
+                    // response. End the stream with an error response.
+                    stream_arguments.status_code = StatusCode::InternalServerError;
+                    stream_arguments.status_message =
+                        Some("Response stream has been dropped by the application".to_string());
+                    stream_arguments.is_application_error = true;
+                    stream_arguments.stream_position = Some(StreamPosition {
+                        index,
+                        is_last: true,
+                    });
+                    Self::send_response(
+                        application_hlc,
+                        client,
+                        pkid,
+                        stream_arguments,
+                        None,
+                        None,
+                        cache,
+                    )
+                    .await;
+                    return;
+                }
+                Err(_) => {
+                    log::error!(
+                        "[{}][pkid: {}] Request timed out",
+                        response_arguments.command_name,
+                        pkid
+                    );
+                    // Notify the application that a timeout occurred
+                    if let Some(completion_tx) = completion_tx {
+                        let _ = completion_tx.send(Err(create_timeout_error(
+                            &response_arguments.command_name,
+                            response_arguments.message_expiry_interval,
+                        )));
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn send_response(
+        application_hlc: Arc<ApplicationHybridLogicalClock>,
+        client: C,
+        pkid: u16,
+        mut response_arguments: ResponseArguments,
+        response: Option<Response<TResp>>,
         completion_tx: Option<oneshot::Sender<Result<(), AIOProtocolError>>>,
         cache: Cache,
     ) {
@@ -1083,63 +1691,14 @@ where
             serialized_payload = entry.serialized_payload;
         } else {
             let mut user_properties: Vec<(String, String)> = Vec::new();
-            'process_response: {
-                let Some(command_expiration_time) = response_arguments.command_expiration_time
-                else {
-                    break 'process_response;
-                };
-                if let Some(response_rx) = response_rx {
-                    // Wait for response
-                    let response = if let Ok(response_timer) = timeout(
-                        command_expiration_time.duration_since(Instant::now()),
-                        response_rx,
-                    )
-                    .await
-                    {
-                        if let Ok(response_app) = response_timer {
-                            response_app
-                        } else {
-                            // Happens when the sender is dropped by the application.
-                            response_arguments.status_code = StatusCode::InternalServerError;
-                            response_arguments.status_message =
-                                Some("Request has been dropped by the application".to_string());
-                            response_arguments.is_application_error = true;
-                            break 'process_response;
-                        }
-                    } else {
-                        log::error!(
-                            "[{}][pkid: {}] Request timed out",
-                            response_arguments.command_name,
-                            pkid
-                        );
-                        // Notify the application that a timeout occurred
-                        if let Some(completion_tx) = completion_tx {
-                            let _ = completion_tx.send(Err(AIOProtocolError::new_timeout_error(
-                                false,
-                                None,
-                                &response_arguments.command_name,
-                                Duration::from_secs(
-                                    response_arguments
-                                        .message_expiry_interval
-                                        .unwrap_or_default()
-                                        .into(),
-                                ),
-                                None,
-                                Some(response_arguments.command_name.clone()),
-                            )));
-                        }
-                        return;
-                    };
+            if let Some(response) = response {
+                user_properties = response.custom_user_data;
 
-                    user_properties = response.custom_user_data;
+                // Serialize payload
+                serialized_payload = response.serialized_payload;
 
-                    // Serialize payload
-                    serialized_payload = response.serialized_payload;
-
-                    if serialized_payload.payload.is_empty() {
-                        response_arguments.status_code = StatusCode::NoContent;
-                    }
-                } else { /* Error */
+                if serialized_payload.payload.is_empty() {
+                    response_arguments.status_code = StatusCode::NoContent;
                 }
             }
 
@@ -1162,6 +1721,16 @@ where
                 RPC_COMMAND_PROTOCOL_VERSION.to_string(),
             ));
 
+            if let Some(stream_position) = response_arguments.stream_position {
+                user_properties.push((
+                    UserProperty::StreamIndex.to_string(),
+                    stream_position.index.to_string(),
+                ));
+                if stream_position.is_last {
+                    user_properties.push((UserProperty::StreamEnd.to_string(), true.to_string()));
+                }
+            }
+
             // Update HLC and use as the timestamp.
             // If there are errors updating the HLC (unlikely when updating against now),
             // the timestamp will not be added.
@@ -1218,6 +1787,40 @@ where
             publish_properties.user_properties = user_properties;
             publish_properties.subscription_identifiers = Vec::new();
             publish_properties.content_type = Some(serialized_payload.content_type.to_string());
+
+            // Encrypt the payload if configured, binding the topic and timestamp
+            if let Some(key_provider) = &response_arguments.key_provider {
+                match encryption::seal(
+                    key_provider.as_ref(),
+                    response_arguments.response_topic.as_bytes(),
+                    &mut publish_properties.user_properties,
+                    &serialized_payload.payload,
+                ) {
+                    Ok(payload) => {
+                        // The ciphertext is sent as unspecified bytes, carrying the original
+                        // format indicator
+                        serialized_payload.payload = payload;
+                        serialized_payload
+                            .format_indicator
+                            .mark_transformed(&mut publish_properties.user_properties);
+                        publish_properties.payload_format_indicator =
+                            Some(serialized_payload.format_indicator.clone() as u8);
+                    }
+                    Err(e) => {
+                        log::error!(
+                            "[{}][pkid: {}] Response encryption error: {e}",
+                            response_arguments.command_name,
+                            pkid
+                        );
+                        if let Some(completion_tx) = completion_tx {
+                            let _ = completion_tx.send(Err(e.into_encrypt_error(Some(
+                                response_arguments.command_name.clone(),
+                            ))));
+                        }
+                        return;
+                    }
+                }
+            }
         };
 
         match response_arguments.command_expiration_time {
@@ -1233,18 +1836,9 @@ where
                     );
                     // Notify the application that a timeout occurred
                     if let Some(completion_tx) = completion_tx {
-                        let _ = completion_tx.send(Err(AIOProtocolError::new_timeout_error(
-                            false,
-                            None,
+                        let _ = completion_tx.send(Err(create_timeout_error(
                             &response_arguments.command_name,
-                            Duration::from_secs(
-                                response_arguments
-                                    .message_expiry_interval
-                                    .unwrap_or_default()
-                                    .into(),
-                            ),
-                            None,
-                            Some(response_arguments.command_name.clone()),
+                            response_arguments.message_expiry_interval,
                         )));
                     }
                     return;
@@ -1277,8 +1871,13 @@ where
 
                 publish_properties.message_expiry_interval = Some(response_message_expiry_interval);
 
-                // Store cache, even if the response is an error
-                if cache_not_found {
+                // Store cache, even if the response is an error. Streamed responses are not cached,
+                // nor are rejections of requests that were not processed as the executor was
+                // unavailable, so that a retry of the request can be processed.
+                if cache_not_found
+                    && response_arguments.stream_position.is_none()
+                    && response_arguments.status_code != StatusCode::ServiceUnavailable
+                {
                     if let Some(cached_key) = response_arguments.cached_key {
                         let cache_entry = CacheEntry {
                             properties: publish_properties.clone(),
@@ -1302,28 +1901,25 @@ where
             }
         }
 
-        // Try to publish
-        match client
-            .publish_with_properties(
-                response_arguments.response_topic,
-                QoS::AtLeastOnce,
-                false,
-                serialized_payload.payload,
-                publish_properties,
-            )
-            .await
-        {
-            Ok(publish_completion_token) => {
-                // Wait and handle puback
-                match publish_completion_token.await {
-                    Ok(()) => {
-                        if let Some(completion_tx) = completion_tx {
-                            // We ignore the error as the receiver may have been dropped indicating that the
-                            // application is not interested in the completion of the publish.
-                            let _ = completion_tx.send(Ok(()));
-                        }
-                    }
-                    Err(e) => {
+        // Try to publish, split into chunks if configured and needed
+        for (payload, publish_properties) in chunking::split(
+            response_arguments.chunking,
+            serialized_payload.payload,
+            publish_properties,
+        ) {
+            match client
+                .publish_with_properties(
+                    response_arguments.response_topic.clone(),
+                    QoS::AtLeastOnce,
+                    false,
+                    payload,
+                    publish_properties,
+                )
+                .await
+            {
+                Ok(publish_completion_token) => {
+                    // Wait and handle puback, continuing with the next chunk if it is Ok
+                    if let Err(e) = publish_completion_token.await {
                         log::error!(
                             "[{}][pkid: {}] Puback error: {e}",
                             response_arguments.command_name,
@@ -1337,31 +1933,40 @@ where
                                 Some(response_arguments.command_name.clone()),
                             )));
                         }
+                        return;
                     }
                 }
-            }
-            Err(e) => {
-                // Unreachable, we control the topic
-                log::error!(
-                    "[{}][pkid: {}] Client error on command executor response publish: {e}",
-                    response_arguments.command_name,
-                    pkid
-                );
-                // Notify error publishing
-                if let Some(completion_tx) = completion_tx {
-                    // Ignore error as receiver may have been dropped
-                    let _ = completion_tx.send(Err(AIOProtocolError::new_internal_logic_error(
-                        false,
-                        false,
-                        Some(Box::new(e)),
-                        "response_publish",
-                        None,
-                        Some("Error publishing response".to_string()),
-                        Some(response_arguments.command_name.clone()),
-                    )));
+                Err(e) => {
+                    // Unreachable, we control the topic
+                    log::error!(
+                        "[{}][pkid: {}] Client error on command executor response publish: {e}",
+                        response_arguments.command_name,
+                        pkid
+                    );
+                    // Notify error publishing
+                    if let Some(completion_tx) = completion_tx {
+                        // Ignore error as receiver may have been dropped
+                        let _ =
+                            completion_tx.send(Err(AIOProtocolError::new_internal_logic_error(
+                                false,
+                                false,
+                                Some(Box::new(e)),
+                                "response_publish",
+                                None,
+                                Some("Error publishing response".to_string()),
+                                Some(response_arguments.command_name.clone()),
+                            )));
+                    }
+                    return;
                 }
             }
         }
+
+        if let Some(completion_tx) = completion_tx {
+            // We ignore the error as the receiver may have been dropped indicating that the
+            // application is not interested in the completion of the publish.
+            let _ = completion_tx.send(Ok(()));
+        }
     }
 }
 
@@ -1380,7 +1985,7 @@ where
 
         // If the executor has not been unsubscribed, attempt to unsubscribe
         if State::Subscribed == self.executor_state {
-            tokio::spawn({
+            runtime::spawn({
                 let request_topic = self.request_topic_pattern.as_subscribe_topic();
                 let mqtt_client = self.mqtt_client.clone();
                 async move {
@@ -1434,9 +2039,21 @@ mod tests {
     // TODO: This dependency on MqttConnectionSettingsBuilder should be removed in lieu of using a true mock
     use azure_iot_operations_mqtt::MqttConnectionSettingsBuilder;
 
+    use azure_iot_operations_mqtt::{
+        control_packet::{Publish, QoS},
+        interface::{Event, Incoming},
+        interface_mocks::{EventInjector, MockClient, MockClientController, MockEventLoop},
+        session::{
+            managed_client::SessionManagedClient, reconnect_policy::ExponentialBackoffWithJitter,
+        },
+    };
+
     use super::*;
     use crate::application::ApplicationContextBuilder;
-    use crate::common::{aio_protocol_error::AIOProtocolErrorKind, payload_serialize::MockPayload};
+    use crate::common::{
+        aio_protocol_error::AIOProtocolErrorKind,
+        payload_serialize::{BypassPayload, MockPayload},
+    };
 
     // TODO: This should return a mock ManagedClient instead.
     // Until that's possible, need to return a Session so that the Session doesn't go out of
@@ -1515,6 +2132,40 @@ mod tests {
         assert!(executor.is_idempotent);
     }
 
+    #[test_case(1, true; "valid chunking")]
+    #[test_case(0, false; "zero max chunk size")]
+    #[tokio::test]
+    async fn test_new_with_chunking(max_chunk_size: usize, valid: bool) {
+        let session = create_session();
+        let managed_client = session.create_managed_client();
+        let executor_options = OptionsBuilder::default()
+            .request_topic_pattern("test/{commandName}/{executorId}/request")
+            .command_name("test_command_name")
+            .topic_token_map(create_topic_tokens())
+            .chunking(Chunking::new(max_chunk_size))
+            .build()
+            .unwrap();
+
+        let executor: Result<Executor<MockPayload, MockPayload, _>, AIOProtocolError> =
+            Executor::new(
+                ApplicationContextBuilder::default().build().unwrap(),
+                managed_client,
+                executor_options,
+            );
+
+        match executor {
+            Ok(executor) => {
+                assert!(valid);
+                assert_eq!(executor.chunking, Some(Chunking::new(max_chunk_size)));
+            }
+            Err(e) => {
+                assert!(!valid);
+                assert_eq!(e.kind, AIOProtocolErrorKind::ConfigurationInvalid);
+                assert_eq!(e.property_name, Some("chunking.max_chunk_size".to_string()));
+            }
+        }
+    }
+
     #[test_case(""; "empty command name")]
     #[test_case(" "; "whitespace command name")]
     #[tokio::test]
@@ -1693,6 +2344,266 @@ mod tests {
         }
     }
 
+    /// Creates a session over a mock client, along with the controller of the client and the
+    /// injector of events received by the session
+    fn create_mock_session() -> (
+        azure_iot_operations_mqtt::session::session::Session<MockClient, MockEventLoop>,
+        MockClientController,
+        EventInjector,
+    ) {
+        let client = MockClient::new();
+        let controller = client.mock_controller();
+        let (event_loop, injector) = MockEventLoop::new();
+        let session = azure_iot_operations_mqtt::session::session::Session::new_from_injection(
+            client,
+            event_loop,
+            Box::new(ExponentialBackoffWithJitter::default()),
+            "test_client".to_string(),
+            None,
+        );
+        (session, controller, injector)
+    }
+
+    /// Creates an executor of `test/request` over a mock session, configured with `key_provider`
+    fn create_mock_executor(
+        session: &azure_iot_operations_mqtt::session::session::Session<MockClient, MockEventLoop>,
+        key_provider: Option<Arc<dyn KeyProvider>>,
+    ) -> Executor<BypassPayload, BypassPayload, SessionManagedClient<MockClient>> {
+        let mut executor_options_builder = OptionsBuilder::default();
+        executor_options_builder
+            .request_topic_pattern("test/request")
+            .command_name("test_command_name");
+        if let Some(key_provider) = key_provider {
+            executor_options_builder.encryption(key_provider);
+        }
+        let executor_options = executor_options_builder.build().unwrap();
+        Executor::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            executor_options,
+        )
+        .unwrap()
+    }
+
+    /// Creates a message received at least once on `test/request`
+    fn publish_event(
+        pkid: u16,
+        correlation_data: [u8; 16],
+        user_properties: Vec<(String, String)>,
+        payload: Vec<u8>,
+    ) -> Event {
+        Event::Incoming(Incoming::Publish(Publish {
+            qos: QoS::AtLeastOnce,
+            pkid,
+            topic: "test/request".into(),
+            payload: payload.into(),
+            properties: Some(PublishProperties {
+                response_topic: Some("test/response".to_string()),
+                correlation_data: Some(Bytes::copy_from_slice(&correlation_data)),
+                message_expiry_interval: Some(10),
+                user_properties,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+    }
+
+    /// Creates a command request received at least once on `test/request`
+    fn request_event(pkid: u16, correlation_data: [u8; 16], payload: &'static [u8]) -> Event {
+        publish_event(pkid, correlation_data, Vec::new(), payload.to_vec())
+    }
+
+    /// Creates the user properties of a cancellation sent with `protocol_version`
+    fn cancellation_properties(protocol_version: Option<&str>) -> Vec<(String, String)> {
+        let mut user_properties = vec![(UserProperty::Cancel.to_string(), "true".to_string())];
+        if let Some(protocol_version) = protocol_version {
+            user_properties.push((
+                UserProperty::ProtocolVersion.to_string(),
+                protocol_version.to_string(),
+            ));
+        }
+        user_properties
+    }
+
+    fn create_bypass_response() -> Response<BypassPayload> {
+        ResponseBuilder::default()
+            .payload(BypassPayload {
+                content_type: "text/plain".to_string(),
+                format_indicator: FormatIndicator::Utf8EncodedCharacterData,
+                payload: b"response".to_vec(),
+            })
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    /// Waits for the mock client to have acked `count` messages
+    async fn wait_for_ack_count(controller: &MockClientController, count: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while controller.ack_count() < count {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(controller.ack_count(), count);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_request_in_progress_is_dropped() {
+        let (session, controller, injector) = create_mock_session();
+        let mut executor = create_mock_executor(&session, None);
+
+        // A retry of the first request is received while it is being processed
+        injector
+            .inject(request_event(1, [1; 16], b"first"))
+            .unwrap();
+        injector
+            .inject(request_event(2, [1; 16], b"first"))
+            .unwrap();
+        injector
+            .inject(request_event(3, [2; 16], b"second"))
+            .unwrap();
+
+        let test = async {
+            let first = executor.recv().await.unwrap().unwrap();
+            assert_eq!(first.payload.payload, b"first".to_vec());
+            // The retry is not delivered to the application
+            let second = executor.recv().await.unwrap().unwrap();
+            assert_eq!(second.payload.payload, b"second".to_vec());
+
+            // The retry is acked along with the first request, in order, with a single response
+            first.complete(create_bypass_response()).await.unwrap();
+            wait_for_ack_count(&controller, 2).await;
+            assert_eq!(controller.publish_count(), 1);
+
+            second.complete(create_bypass_response()).await.unwrap();
+            wait_for_ack_count(&controller, 3).await;
+            assert_eq!(controller.publish_count(), 2);
+        };
+        tokio::select! {
+            () = test => {},
+            _ = session.run() => panic!("Session exited unexpectedly"),
+        }
+    }
+
+    #[test_case(Some("2.0"), true; "cancellation_protocol_version")]
+    #[test_case(Some("1.0"), false; "request_protocol_version")]
+    #[test_case(None, false; "no_protocol_version")]
+    #[tokio::test]
+    async fn test_cancel_request(protocol_version: Option<&str>, cancelled: bool) {
+        let (session, controller, injector) = create_mock_session();
+        let mut executor = create_mock_executor(&session, None);
+
+        injector
+            .inject(request_event(1, [1; 16], b"first"))
+            .unwrap();
+        injector
+            .inject(request_event(2, [2; 16], b"second"))
+            .unwrap();
+        injector
+            .inject(publish_event(
+                3,
+                [1; 16],
+                cancellation_properties(protocol_version),
+                Vec::new(),
+            ))
+            .unwrap();
+        // Received once the cancellation has been handled
+        injector
+            .inject(request_event(4, [3; 16], b"third"))
+            .unwrap();
+
+        let test = async {
+            let first = executor.recv().await.unwrap().unwrap();
+            let second = executor.recv().await.unwrap().unwrap();
+            let third = executor.recv().await.unwrap().unwrap();
+            assert_eq!(third.payload.payload, b"third".to_vec());
+
+            // Only the request with the correlation data of the cancellation is cancelled
+            assert_eq!(first.is_cancelled(), cancelled);
+            if cancelled {
+                first.cancelled().await;
+            }
+            assert!(!second.is_cancelled());
+            assert!(!third.is_cancelled());
+            // The cancellation is not responded to
+            assert_eq!(controller.publish_count(), 0);
+        };
+        tokio::select! {
+            () = test => {},
+            _ = session.run() => panic!("Session exited unexpectedly"),
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test_case(true, [1; 16], true; "sealed_correlation_data")]
+    #[test_case(true, [2; 16], false; "sealed_other_correlation_data")]
+    #[test_case(false, [1; 16], false; "not_sealed")]
+    #[tokio::test]
+    async fn test_cancel_encrypted_request(
+        sealed: bool,
+        sealed_correlation_data: [u8; 16],
+        cancelled: bool,
+    ) {
+        use crate::common::encryption::{
+            EncryptionAlgorithm, EncryptionKey, InMemoryKeyProvider, KEY_LEN,
+        };
+
+        let key_provider: Arc<dyn KeyProvider> = Arc::new(InMemoryKeyProvider::new(
+            EncryptionKey::new("key1", EncryptionAlgorithm::Aes256Gcm, [1; KEY_LEN]).unwrap(),
+        ));
+        let sealed_event = |pkid, correlation_data, mut user_properties, payload: &[u8]| {
+            let payload = encryption::seal(
+                key_provider.as_ref(),
+                b"test/request",
+                &mut user_properties,
+                payload,
+            )
+            .unwrap();
+            publish_event(pkid, correlation_data, user_properties, payload)
+        };
+
+        let (session, _controller, injector) = create_mock_session();
+        let mut executor = create_mock_executor(&session, Some(key_provider.clone()));
+
+        injector
+            .inject(sealed_event(1, [1; 16], Vec::new(), b"first"))
+            .unwrap();
+        let cancellation_properties =
+            cancellation_properties(Some(&RPC_COMMAND_CANCELLATION_PROTOCOL_VERSION.to_string()));
+        injector
+            .inject(if sealed {
+                sealed_event(
+                    2,
+                    [1; 16],
+                    cancellation_properties,
+                    &sealed_correlation_data,
+                )
+            } else {
+                publish_event(2, [1; 16], cancellation_properties, Vec::new())
+            })
+            .unwrap();
+        // Received once the cancellation has been handled
+        injector
+            .inject(sealed_event(3, [3; 16], Vec::new(), b"second"))
+            .unwrap();
+
+        let test = async {
+            let first = executor.recv().await.unwrap().unwrap();
+            let second = executor.recv().await.unwrap().unwrap();
+            assert_eq!(second.payload.payload, b"second".to_vec());
+
+            // Only a cancellation authenticated like the request cancels it
+            assert_eq!(first.is_cancelled(), cancelled);
+            assert!(!second.is_cancelled());
+        };
+        tokio::select! {
+            () = test => {},
+            _ = session.run() => panic!("Session exited unexpectedly"),
+        }
+    }
+
     #[tokio::test]
     async fn test_cache_not_found() {
         let cache = Cache(Arc::new(Mutex::new(HashMap::new())));
@@ -1804,6 +2715,133 @@ mod tests {
         assert_eq!(status, CacheEntryStatus::Cached(new_entry));
     }
 
+    fn create_stream_response() -> Response<MockPayload> {
+        let mut mock_response_payload = MockPayload::new();
+        mock_response_payload
+            .expect_serialize()
+            .returning(|| {
+                Ok(SerializedPayload {
+                    payload: Vec::new(),
+                    content_type: "application/json".to_string(),
+                    format_indicator: FormatIndicator::Utf8EncodedCharacterData,
+                })
+            })
+            .times(1);
+        ResponseBuilder::default()
+            .payload(mock_response_payload)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_responder_send_and_complete() {
+        let (stream_tx, mut stream_rx) = mpsc::channel(1);
+        let (_publish_completion_tx, publish_completion_rx) = oneshot::channel();
+        let mut responder = StreamResponder {
+            command_name: "test_command_name".to_string(),
+            stream_tx,
+            publish_completion_rx: Some(publish_completion_rx),
+            cancellation_token: CancellationToken::new(),
+        };
+
+        // Acts as the executor, publishing each response of the stream
+        let executor_task = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while let Some(streamed_response) = stream_rx.recv().await {
+                received.push(streamed_response.is_last);
+                streamed_response.completion_tx.send(Ok(())).unwrap();
+            }
+            received
+        });
+
+        assert!(!responder.is_cancelled());
+        responder.send(create_stream_response()).await.unwrap();
+        responder.send(create_stream_response()).await.unwrap();
+        responder.complete(create_stream_response()).await.unwrap();
+        assert_eq!(executor_task.await.unwrap(), vec![false, false, true]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_responder_timeout() {
+        let (stream_tx, stream_rx) = mpsc::channel(1);
+        let (publish_completion_tx, publish_completion_rx) = oneshot::channel();
+        let mut responder = StreamResponder {
+            command_name: "test_command_name".to_string(),
+            stream_tx,
+            publish_completion_rx: Some(publish_completion_rx),
+            cancellation_token: CancellationToken::new(),
+        };
+
+        // The executor stops processing the stream once the command has expired
+        drop(stream_rx);
+        publish_completion_tx
+            .send(Err(create_timeout_error("test_command_name", Some(10))))
+            .unwrap();
+
+        assert!(responder.is_cancelled());
+        let e = responder.send(create_stream_response()).await.unwrap_err();
+        assert_eq!(e.kind, AIOProtocolErrorKind::Timeout);
+        assert_eq!(e.command_name, Some("test_command_name".to_string()));
+        let e = responder
+            .complete(create_stream_response())
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind, AIOProtocolErrorKind::Cancellation);
+    }
+
+    #[tokio::test]
+    async fn test_stream_responder_executor_dropped() {
+        let (stream_tx, stream_rx) = mpsc::channel(1);
+        let (publish_completion_tx, publish_completion_rx) = oneshot::channel();
+        let responder = StreamResponder {
+            command_name: "test_command_name".to_string(),
+            stream_tx,
+            publish_completion_rx: Some(publish_completion_rx),
+            cancellation_token: CancellationToken::new(),
+        };
+
+        drop(stream_rx);
+        drop(publish_completion_tx);
+
+        let e = responder
+            .complete(create_stream_response())
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind, AIOProtocolErrorKind::Cancellation);
+    }
+
+    #[tokio::test]
+    async fn test_request_cancelled() {
+        let (response_tx, _response_rx) = oneshot::channel();
+        let (_publish_completion_tx, publish_completion_rx) = oneshot::channel();
+        let cancellation_token = CancellationToken::new();
+        let request: Request<MockPayload, MockPayload> = Request {
+            payload: MockPayload::new(),
+            content_type: None,
+            format_indicator: FormatIndicator::default(),
+            custom_user_data: Vec::new(),
+            timestamp: None,
+            invoker_id: None,
+            topic_tokens: HashMap::new(),
+            command_name: "test_command_name".to_string(),
+            response_tx,
+            publish_completion_rx,
+            cancellation_token: cancellation_token.clone(),
+        };
+
+        assert!(!request.is_cancelled());
+        // Cancelled by the invoker
+        cancellation_token.cancel();
+        assert!(request.is_cancelled());
+        request.cancelled().await;
+
+        // The cancellation carries over to the stream of responses
+        let responder = request.start_stream();
+        assert!(responder.is_cancelled());
+        responder.cancelled().await;
+    }
+
     #[test]
     fn test_response_add_empty_error_payload_success() {
         let mut mock_response_payload = MockPayload::new();
@@ -1,20 +1,29 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
-use std::{collections::HashMap, marker::PhantomData, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
 
 use azure_iot_operations_mqtt::control_packet::{Publish, PublishProperties, QoS};
+use azure_iot_operations_mqtt::error::ErrorClassification;
 use azure_iot_operations_mqtt::interface::{ManagedClient, PubReceiver};
+use azure_iot_operations_mqtt::runtime;
 use bytes::Bytes;
+use futures::{Stream, stream};
 use iso8601_duration;
-use tokio::{
-    sync::{
-        Mutex, Notify,
-        broadcast::{Sender, error::RecvError},
-    },
-    task::{self, JoinHandle},
-    time,
+use tokio::sync::{
+    Mutex, Notify,
+    broadcast::{Receiver, Sender, error::RecvError},
+    mpsc,
 };
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use crate::common::user_properties::{PARTITION_KEY, validate_invoker_user_properties};
@@ -23,6 +32,9 @@ use crate::{
     application::{ApplicationContext, ApplicationHybridLogicalClock},
     common::{
         aio_protocol_error::{AIOProtocolError, AIOProtocolErrorKind, Value},
+        chunking::{self, ChunkBuffer, Chunking, ReassemblyLimits},
+        compression::Compression,
+        encryption::{self, KeyProvider},
         hybrid_logical_clock::HybridLogicalClock,
         is_invalid_utf8,
         payload_serialize::{
@@ -33,8 +45,9 @@ use crate::{
     },
     parse_supported_protocol_major_versions,
     rpc_command::{
-        DEFAULT_RPC_COMMAND_PROTOCOL_VERSION, RPC_COMMAND_PROTOCOL_VERSION, StatusCode,
-        StatusCodeParseError,
+        DEFAULT_RPC_COMMAND_PROTOCOL_VERSION, RPC_COMMAND_CANCELLATION_PROTOCOL_VERSION,
+        RPC_COMMAND_PROTOCOL_VERSION, StatusCode, StatusCodeParseError,
+        retry_policy::RetryPolicy,
     },
 };
 
@@ -108,11 +121,7 @@ impl<TReq: PayloadSerialize> RequestBuilder<TReq> {
     ///
     /// Note: Will be rounded up to the nearest second.
     pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
-        self.timeout = Some(if timeout.subsec_nanos() != 0 {
-            Duration::from_secs(timeout.as_secs().saturating_add(1))
-        } else {
-            timeout
-        });
+        self.timeout = Some(round_up_to_seconds(timeout));
 
         self
     }
@@ -161,6 +170,53 @@ where
     pub timestamp: Option<HybridLogicalClock>,
 }
 
+/// Command Response stream.
+/// Returned by [`Invoker::invoke_stream`]
+///
+/// Yields the responses of a streaming command in the order they were sent by the executor,
+/// without duplicates. The stream ends after its last response, after an error, or once the
+/// timeout of the request has elapsed, which is yielded as an error.
+pub struct ResponseStream<TResp>
+where
+    TResp: PayloadSerialize,
+{
+    inner: Pin<Box<dyn Stream<Item = Result<Response<TResp>, AIOProtocolError>> + Send>>,
+}
+
+impl<TResp> Stream for ResponseStream<TResp>
+where
+    TResp: PayloadSerialize,
+{
+    type Item = Result<Response<TResp>, AIOProtocolError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// Handle to cancel a command invocation.
+/// Returned by [`Invoker::invoke_cancellable`]
+#[derive(Clone)]
+pub struct CancellationHandle {
+    cancellation_token: CancellationToken,
+}
+
+impl CancellationHandle {
+    /// Cancels the command invocation. The invocation stops waiting for a response and asks the
+    /// executor to cancel the command request.
+    pub fn cancel(&self) {
+        self.cancellation_token.cancel();
+    }
+
+    /// Check if the command invocation has been cancelled.
+    ///
+    /// Returns true if [`cancel`](CancellationHandle::cancel) has been called, otherwise returns false.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation_token.is_cancelled()
+    }
+}
+
 /// Helper function to return the application error code and payload, if present in `custom_user_data`.
 ///
 /// Returns a [`(Option<String>, Option<String>)`] tuple where:
@@ -233,6 +289,7 @@ impl From<RemoteError> for AIOProtocolError {
             command_name: None, // Will need to update this after return
             protocol_version: Some(value.protocol_version.to_string()),
             supported_protocol_major_versions: value.supported_protocol_major_versions,
+            attempt_count: None,
         };
 
         match value.status_code {
@@ -352,6 +409,7 @@ where
             UserProperty::ProtocolVersion,
             UserProperty::SupportedMajorVersions,
             UserProperty::RequestProtocolVersion,
+            UserProperty::PayloadFormatIndicator,
         ];
         let mut response_custom_user_data = vec![];
         let mut response_aio_data = HashMap::new();
@@ -470,10 +528,25 @@ where
             // Response with payload
             StatusCode::Ok | StatusCode::NoContent => {
                 let content_type = publish_properties.content_type;
-                let format_indicator = publish_properties.payload_format_indicator.try_into().unwrap_or_else(|e| {
-                    log::error!("Received invalid payload format indicator: {e}. This should not be possible to receive from the broker. Using default.");
-                    FormatIndicator::default()
-                });
+                // Restore the format indicator the payload had before it was encrypted
+                let format_indicator = match response_aio_data
+                    .get(&UserProperty::PayloadFormatIndicator)
+                {
+                    Some(format_indicator) => FormatIndicator::from_user_property(format_indicator)
+                        .map_err(|message| {
+                            AIOProtocolError::new_header_invalid_error(
+                                &UserProperty::PayloadFormatIndicator.to_string(),
+                                format_indicator,
+                                false,
+                                Some(message),
+                                None,
+                            )
+                        })?,
+                    None => publish_properties.payload_format_indicator.try_into().unwrap_or_else(|e| {
+                        log::error!("Received invalid payload format indicator: {e}. This should not be possible to receive from the broker. Using default.");
+                        FormatIndicator::default()
+                    }),
+                };
 
                 if matches!(status_code, StatusCode::NoContent) && !value.payload.is_empty() {
                     return Err(AIOProtocolError::new_payload_invalid_error(
@@ -540,9 +613,222 @@ where
     }
 }
 
+/// State of a [`ResponseStream`]
+struct ResponseStreamState {
+    stream_rx: mpsc::UnboundedReceiver<Result<Publish, AIOProtocolError>>,
+    deadline: Instant,
+    command_timeout: Duration,
+    key_provider: Option<Arc<dyn KeyProvider>>,
+    application_hlc: Arc<ApplicationHybridLogicalClock>,
+    command_name: String,
+}
+
+/// Request published by an [`Invoker`], awaiting its responses
+struct PendingRequest {
+    correlation_data: Bytes,
+    response_rx: Receiver<Option<Publish>>,
+    pub_task: runtime::JoinHandle<Result<(), AIOProtocolError>>,
+}
+
+/// Decrypts and parses a response, updating the application HLC with its timestamp.
+///
+/// Returns the [`Response`] if the response reports success, otherwise returns the reported
+/// error or the error processing the response.
+fn process_response<TResp>(
+    mut rsp_pub: Publish,
+    key_provider: Option<&dyn KeyProvider>,
+    application_hlc: &ApplicationHybridLogicalClock,
+    command_name: &str,
+) -> Result<Response<TResp>, AIOProtocolError>
+where
+    TResp: PayloadSerialize,
+{
+    // Decrypt the response payload. Responses without a payload, such as errors from an
+    // executor that could not decrypt the request, carry nothing confidential and are
+    // accepted unencrypted.
+    let key_provider = if rsp_pub.payload.is_empty()
+        && !rsp_pub
+            .properties
+            .as_ref()
+            .is_some_and(|p| encryption::is_encrypted(&p.user_properties))
+    {
+        None
+    } else {
+        key_provider
+    };
+    encryption::open_publish(key_provider, &mut rsp_pub)
+        .map_err(|e| e.into_decrypt_error(Some(command_name.to_string())))?;
+
+    // validate and parse the response pub that is for this request
+    let command_result: CommandResult<TResp> =
+        rsp_pub.try_into().map_err(|mut e: AIOProtocolError| {
+            // Add command name to the error
+            e.command_name = Some(command_name.to_string());
+            e
+        })?;
+
+    match command_result {
+        CommandResult::Ok(response) => {
+            // Update application HLC
+            if let Some(hlc) = &response.timestamp {
+                application_hlc.update(hlc).map_err(|e| {
+                    let mut aio_error: AIOProtocolError = e.into();
+                    aio_error.command_name = Some(command_name.to_string());
+                    aio_error
+                })?;
+            }
+            Ok(response)
+        }
+        CommandResult::Err(remote_e) => {
+            // Update application HLC
+            if let Some(hlc) = &remote_e.timestamp {
+                application_hlc.update(hlc).map_err(|e| {
+                    let mut aio_error: AIOProtocolError = e.into();
+                    aio_error.command_name = Some(command_name.to_string());
+                    aio_error
+                })?;
+            }
+            // Convert into AIOProtocolError and return
+            let mut aio_e: AIOProtocolError = remote_e.into();
+            aio_e.command_name = Some(command_name.to_string());
+            Err(aio_e)
+        }
+    }
+}
+
+/// Orders the responses of a stream by their [`UserProperty::StreamIndex`], discarding duplicates
+/// and responses after the last one.
+#[derive(Default)]
+struct ResponseSequencer {
+    /// Index of the next response of the stream
+    next_index: u64,
+    /// Index of the last response of the stream, once received
+    last_index: Option<u64>,
+    /// Responses received ahead of the next response
+    pending: BTreeMap<u64, Publish>,
+    finished: bool,
+}
+
+impl ResponseSequencer {
+    /// Inserts a received response of the stream, removing its stream user properties.
+    ///
+    /// Returns the responses that are now in order, if any. A response without a
+    /// [`UserProperty::StreamIndex`], such as an error from an executor that rejected the
+    /// request, or with an invalid one, finishes the stream.
+    fn insert(&mut self, mut rsp_pub: Publish) -> Vec<Result<Publish, AIOProtocolError>> {
+        if self.finished {
+            return Vec::new();
+        }
+
+        let position = match rsp_pub.properties.as_mut() {
+            Some(properties) => take_stream_position(&mut properties.user_properties),
+            None => Ok(None),
+        };
+        let (index, is_last) = match position {
+            Ok(Some(position)) => position,
+            Ok(None) => {
+                self.finished = true;
+                return vec![Ok(rsp_pub)];
+            }
+            Err(e) => {
+                self.finished = true;
+                return vec![Err(e)];
+            }
+        };
+
+        if index < self.next_index
+            || self.pending.contains_key(&index)
+            || self.last_index.is_some_and(|last_index| index > last_index)
+        {
+            log::debug!("Duplicate or unexpected response {index} of stream ignored");
+            return Vec::new();
+        }
+        if is_last {
+            self.last_index = Some(index);
+            // Responses after the last one are not part of the stream
+            self.pending
+                .retain(|pending_index, _| *pending_index < index);
+        }
+        self.pending.insert(index, rsp_pub);
+
+        let mut ready = Vec::new();
+        while let Some(rsp_pub) = self.pending.remove(&self.next_index) {
+            ready.push(Ok(rsp_pub));
+            if self.last_index == Some(self.next_index) {
+                self.finished = true;
+            }
+            self.next_index += 1;
+        }
+        ready
+    }
+
+    /// Returns true once the last response of the stream has been returned
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Removes the [`UserProperty::StreamIndex`] and [`UserProperty::StreamEnd`] user properties.
+///
+/// Returns the index of the response in its stream and whether it is the last one, or [`None`]
+/// if the response is not part of a stream.
+///
+/// # Errors
+/// [`AIOProtocolError`] of kind [`HeaderInvalid`](AIOProtocolErrorKind::HeaderInvalid) if the
+/// index isn't an unsigned integer.
+fn take_stream_position(
+    user_properties: &mut Vec<(String, String)>,
+) -> Result<Option<(u64, bool)>, AIOProtocolError> {
+    let mut index = None;
+    let mut is_last = false;
+    user_properties.retain(|(key, value)| match UserProperty::from_str(key) {
+        Ok(UserProperty::StreamIndex) => {
+            index = Some(value.clone());
+            false
+        }
+        Ok(UserProperty::StreamEnd) => {
+            is_last = value == "true";
+            false
+        }
+        _ => true,
+    });
+
+    index
+        .map(|index| {
+            index.parse::<u64>().map(|i| (i, is_last)).map_err(|_| {
+                AIOProtocolError::new_header_invalid_error(
+                    &UserProperty::StreamIndex.to_string(),
+                    &index,
+                    false,
+                    Some(format!(
+                        "Could not parse stream index in response '{index}' as an integer"
+                    )),
+                    None,
+                )
+            })
+        })
+        .transpose()
+}
+
+/// Creates new correlation data for a command request.
+fn new_correlation_data() -> Bytes {
+    Bytes::from(Uuid::new_v4().as_bytes().to_vec())
+}
+
+/// Rounds a duration up to the nearest second.
+fn round_up_to_seconds(duration: Duration) -> Duration {
+    if duration.subsec_nanos() != 0 {
+        Duration::from_secs(duration.as_secs().saturating_add(1))
+    } else {
+        duration
+    }
+}
+
 /// Command Invoker Options struct
 #[derive(Builder, Clone)]
 #[builder(setter(into))]
+// `Compression` is uninhabited when no compression codec features are enabled
+#[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unreachable_code))]
 pub struct Options {
     /// Topic pattern for the command request.
     /// Must align with [topic-structure.md](https://github.com/Azure/iot-operations-sdks/blob/main/doc/reference/topic-structure.md)
@@ -571,6 +857,26 @@ pub struct Options {
     /// based on the request topic in the form: `clients/<client_id>/<request_topic>`
     #[builder(default = "None")]
     response_topic_suffix: Option<String>,
+    /// Optional compression of the serialized payload of each request.
+    /// Payloads below the compression threshold are sent uncompressed.
+    #[builder(default = "None")]
+    compression: Option<Compression>,
+    /// Optional end-to-end encryption of the serialized payload of each request, after
+    /// compression if any, with the current key of the key provider. If set, responses must be
+    /// encrypted too, unless they have no payload.
+    #[builder(default = "None")]
+    encryption: Option<Arc<dyn KeyProvider>>,
+    /// Optional splitting of request payloads larger than the maximum chunk size into chunks,
+    /// each published as a separate message and reassembled by the executor.
+    #[builder(default = "None")]
+    chunking: Option<Chunking>,
+    /// Limits applied when reassembling chunked responses
+    #[builder(default)]
+    chunk_reassembly: ReassemblyLimits,
+    /// Optional policy for retrying a command invocation that failed with an error that is safe
+    /// to retry, within the timeout of the request. Not applied to streaming commands.
+    #[builder(default = "None")]
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
 }
 
 /// Command Invoker struct
@@ -626,6 +932,10 @@ where
     response_topic_pattern: TopicPattern,
     request_payload_type: PhantomData<TReq>,
     response_payload_type: PhantomData<TResp>,
+    compression: Option<Compression>,
+    key_provider: Option<Arc<dyn KeyProvider>>,
+    chunking: Option<Chunking>,
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
     // Describes state
     invoker_state_mutex: Arc<Mutex<State>>,
     // Used to send information to manage state
@@ -672,6 +982,7 @@ where
     ///     [`response_topic_suffix`](OptionsBuilder::response_topic_suffix),
     ///     are Some and invalid or contain a token with no valid replacement
     /// - [`topic_token_map`](OptionsBuilder::topic_token_map) isn't empty and contains invalid key(s)/token(s)
+    /// - [`chunking`](OptionsBuilder::chunking) is Some and its maximum chunk size is zero
     pub fn new(
         application_context: ApplicationContext,
         client: C,
@@ -690,6 +1001,10 @@ where
             ));
         }
 
+        if let Some(chunking) = invoker_options.chunking {
+            chunking.validate(Some(invoker_options.command_name.clone()))?;
+        }
+
         // If no response_topic_pattern is specified, generate one based on the request_topic_pattern, response_topic_prefix, and response_topic_suffix
         let mut response_topic_pattern;
         if let Some(pattern) = invoker_options.response_topic_pattern {
@@ -770,16 +1085,18 @@ where
         let shutdown_notifier = Arc::new(Notify::new());
 
         // Start the receive response loop
-        task::spawn({
+        runtime::spawn({
             let response_tx_clone = response_tx.clone();
             let shutdown_notifier_clone = shutdown_notifier.clone();
             let command_name_clone = invoker_options.command_name.clone();
+            let chunk_buffer = ChunkBuffer::new(invoker_options.chunk_reassembly);
             async move {
                 Self::receive_response_loop(
                     mqtt_receiver,
                     response_tx_clone,
                     shutdown_notifier_clone,
                     command_name_clone,
+                    chunk_buffer,
                 )
                 .await;
             }
@@ -793,6 +1110,10 @@ where
             response_topic_pattern,
             request_payload_type: PhantomData,
             response_payload_type: PhantomData,
+            compression: invoker_options.compression,
+            key_provider: invoker_options.encryption,
+            chunking: invoker_options.chunking,
+            retry_policy: invoker_options.retry_policy,
             invoker_state_mutex,
             shutdown_notifier,
             response_tx,
@@ -811,6 +1132,9 @@ where
     ///
     /// [`AIOProtocolError`] of kind [`PayloadInvalid`](AIOProtocolErrorKind::PayloadInvalid) if
     /// - [`response_payload`][Response::payload] deserialization fails
+    /// - [`compression`](OptionsBuilder::compression) is configured and the request payload could not be compressed
+    /// - [`encryption`](OptionsBuilder::encryption) is configured and the request payload could not be encrypted,
+    ///     or the response payload could not be decrypted
     /// - The response has a [`UserProperty::Status`] of [`StatusCode::NoContent`] but the payload isn't empty
     /// - The response has a [`UserProperty::Status`] of [`StatusCode::BadRequest`] and there is no [`UserProperty::InvalidPropertyName`] or [`UserProperty::InvalidPropertyValue`] specified
     ///
@@ -824,6 +1148,17 @@ where
     /// - The publish fails
     /// - The puback reason code doesn't indicate success.
     ///
+    /// If a [`retry_policy`](OptionsBuilder::retry_policy) is configured, an invocation that failed with a
+    /// [retriable](azure_iot_operations_mqtt::error::ErrorClassification::is_retriable) error is retried with the same
+    /// correlation data for as long as the policy allows and the [`timeout`](RequestBuilder::timeout) of the request
+    /// hasn't elapsed. The error of the last attempt is returned with the number of attempts made as its
+    /// [`attempt_count`](AIOProtocolError::attempt_count).
+    ///
+    /// If [`chunking`](OptionsBuilder::chunking) is configured and the request payload is split into chunks, the chunks
+    /// are published in order, each after the puback of the previous one. Chunked responses are reassembled within the
+    /// [`chunk_reassembly`](OptionsBuilder::chunk_reassembly) limits; a response that cannot be reassembled is discarded,
+    /// and the command invoke times out.
+    ///
     /// [`AIOProtocolError`] of kind [`Cancellation`](AIOProtocolErrorKind::Cancellation) if the [`Invoker`] has been dropped
     ///
     /// [`AIOProtocolError`] of kind [`HeaderInvalid`](AIOProtocolErrorKind::HeaderInvalid) if
@@ -832,10 +1167,12 @@ where
     /// - The response has a [`UserProperty::Status`] that can't be parsed as an integer
     /// - The response has a [`UserProperty::Status`] of [`StatusCode::BadRequest`] and a [`UserProperty::InvalidPropertyValue`] is specified
     /// - The response has a [`UserProperty::Status`] of [`StatusCode::UnsupportedMediaType`]
+    /// - The response is encrypted with an unknown key or unsupported algorithm
     ///
     /// [`AIOProtocolError`] of kind [`HeaderMissing`](AIOProtocolErrorKind::HeaderMissing) if
     /// - The response has a [`UserProperty::Status`] of [`StatusCode::BadRequest`] and [`UserProperty::InvalidPropertyName`] is specified, but [`UserProperty::InvalidPropertyValue`] isn't specified
     /// - The response doesn't specify a [`UserProperty::Status`]
+    /// - The response has a payload but isn't encrypted while [`encryption`](OptionsBuilder::encryption) is configured
     ///
     /// [`AIOProtocolError`] of kind [`UnknownError`](AIOProtocolErrorKind::UnknownError) if
     /// - The response has a [`UserProperty::Status`] that isn't one of [`StatusCode`]
@@ -853,36 +1190,278 @@ where
     pub async fn invoke(
         &self,
         request: Request<TReq>,
+    ) -> Result<Response<TResp>, AIOProtocolError> {
+        self.invoke_with_correlation_data(request, new_correlation_data())
+            .await
+    }
+
+    /// Invokes a command that can be cancelled.
+    ///
+    /// Returns a [`CancellationHandle`] and the invocation, which completes like
+    /// [`invoke`](Invoker::invoke). If the invocation is cancelled with the handle before it
+    /// completes, it stops waiting for a response and publishes a message on the request topic
+    /// with the same correlation data, asking the executor to cancel the command request. The
+    /// [`executor::Request`](crate::rpc_command::executor::Request) is then cancelled if it is
+    /// still being processed. If [`encryption`](OptionsBuilder::encryption) is configured, the
+    /// message is authenticated by sealing its correlation data with the current key. An executor
+    /// that doesn't support cancellation rejects the message for its protocol version.
+    ///
+    /// # Arguments
+    /// * `request` - [`Request`] to invoke
+    ///
+    /// # Errors
+    /// Any error of [`invoke`](Invoker::invoke).
+    ///
+    /// [`AIOProtocolError`] of kind [`Cancellation`](AIOProtocolErrorKind::Cancellation) if the
+    /// invocation is cancelled.
+    ///
+    /// [`AIOProtocolError`] of kind [`ClientError`](AIOProtocolErrorKind::ClientError) if the
+    /// invocation is cancelled and the publish of the cancellation fails or its puback reason
+    /// code doesn't indicate success.
+    pub fn invoke_cancellable(
+        &self,
+        request: Request<TReq>,
+    ) -> (
+        CancellationHandle,
+        impl Future<Output = Result<Response<TResp>, AIOProtocolError>> + '_,
+    ) {
+        let cancellation_token = CancellationToken::new();
+        let handle = CancellationHandle {
+            cancellation_token: cancellation_token.clone(),
+        };
+
+        let invocation = async move {
+            let correlation_data = new_correlation_data();
+            let topic_tokens = request.topic_tokens.clone();
+            let message_expiry_interval = request.timeout;
+            tokio::select! {
+                result = self.invoke_with_correlation_data(request, correlation_data.clone()) => result,
+                () = cancellation_token.cancelled() => {
+                    log::info!("[{}] Command invoke cancelled", self.command_name);
+                    // Ask the executor to cancel the request, reporting if this fails
+                    self.publish_cancellation(&topic_tokens, correlation_data, message_expiry_interval)
+                        .await
+                        .and_then(|()| {
+                            Err(AIOProtocolError::new_cancellation_error(
+                                false,
+                                None,
+                                Some("Command invoke was cancelled".to_string()),
+                                Some(self.command_name.clone()),
+                            ))
+                        })
+                }
+            }
+        };
+        (handle, invocation)
+    }
+
+    /// Invokes a command with the given correlation data, retrying it if a retry policy is
+    /// configured.
+    async fn invoke_with_correlation_data(
+        &self,
+        request: Request<TReq>,
+        correlation_data: Bytes,
+    ) -> Result<Response<TResp>, AIOProtocolError> {
+        // The correlation data is the same for every attempt so that the executor can drop or
+        // respond from its cache to an attempt of a request it has already received
+        let Some(retry_policy) = &self.retry_policy else {
+            return self.invoke_attempt(request, correlation_data).await;
+        };
+
+        let deadline = Instant::now() + request.timeout;
+        let mut attempt_count = 1;
+        loop {
+            // Limit the attempt to the timeout of the policy, and to the remaining timeout of the
+            // request, which is also used as the message expiry interval of the attempt
+            let remaining_timeout = deadline.saturating_duration_since(Instant::now());
+            let attempt_timeout = retry_policy
+                .attempt_timeout()
+                .map_or(remaining_timeout, |t| t.min(remaining_timeout));
+            let mut attempt_request = request.clone();
+            attempt_request.timeout =
+                round_up_to_seconds(attempt_timeout).max(Duration::from_secs(1));
+
+            let mut error = match runtime::timeout(
+                attempt_timeout,
+                self.invoke_internal(attempt_request, correlation_data.clone()),
+            )
+            .await
+            {
+                Ok(Ok(response)) => return Ok(response),
+                Ok(Err(e)) => e,
+                Err(e) => self.create_timeout_error(e, attempt_timeout),
+            };
+
+            let retry_delay = if error.is_retriable() {
+                retry_policy.next_retry_delay(attempt_count, &error)
+            } else {
+                None
+            };
+            match retry_delay {
+                Some(delay) if Instant::now() + delay < deadline => {
+                    log::warn!(
+                        "[{command_name}] Command invoke attempt {attempt_count} failed, retrying in {delay:?}: {error}",
+                        command_name = self.command_name,
+                    );
+                    runtime::sleep(delay).await;
+                    attempt_count += 1;
+                }
+                _ => {
+                    error.attempt_count = Some(attempt_count);
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    /// Invokes a command once with the given correlation data, wrapped within the timeout of the
+    /// request.
+    async fn invoke_attempt(
+        &self,
+        request: Request<TReq>,
+        correlation_data: Bytes,
     ) -> Result<Response<TResp>, AIOProtocolError> {
         // Get the timeout duration to use
         let command_timeout = request.timeout;
 
         // Call invoke, wrapped within a timeout
-        let invoke_result = time::timeout(request.timeout, self.invoke_internal(request)).await;
+        let invoke_result = runtime::timeout(
+            command_timeout,
+            self.invoke_internal(request, correlation_data),
+        )
+        .await;
 
         // Return the timeout error or the result from the command invocation.
         match invoke_result {
-            Ok(result) => match result {
-                Ok(response) => Ok(response),
-                Err(e) => Err(e),
-            },
-            Err(e) => {
-                log::error!(
-                    "[{command_name}] Command invoke timed out after {command_timeout:?}",
-                    command_name = self.command_name,
-                );
-                Err(AIOProtocolError::new_timeout_error(
-                    false,
-                    Some(Box::new(e)),
-                    &self.command_name,
-                    command_timeout,
-                    None,
-                    Some(self.command_name.clone()),
-                ))
-            }
+            Ok(result) => result,
+            Err(e) => Err(self.create_timeout_error(e, command_timeout)),
         }
     }
 
+    /// Creates the error of a command invocation that timed out after `command_timeout`.
+    fn create_timeout_error(
+        &self,
+        elapsed: runtime::Elapsed,
+        command_timeout: Duration,
+    ) -> AIOProtocolError {
+        log::error!(
+            "[{command_name}] Command invoke timed out after {command_timeout:?}",
+            command_name = self.command_name,
+        );
+        AIOProtocolError::new_timeout_error(
+            false,
+            Some(Box::new(elapsed)),
+            &self.command_name,
+            command_timeout,
+            None,
+            Some(self.command_name.clone()),
+        )
+    }
+
+    /// Invokes a streaming command, which responds with a sequence of responses.
+    ///
+    /// Returns Ok([`ResponseStream`]) once the request has been published, otherwise returns
+    /// [`AIOProtocolError`]. The responses are yielded by the stream as they are received, in the
+    /// order they were sent by the executor. Duplicate responses are discarded. The stream ends
+    /// after the last response, or with an error if the [`timeout`](RequestBuilder::timeout) of
+    /// the request elapses first. A response that is not part of a stream, such as an error from an
+    /// executor that rejected the request, is yielded as the only response of the stream.
+    ///
+    /// # Arguments
+    /// * `request` - [`Request`] to invoke
+    ///
+    /// # Errors
+    /// Any error of [`invoke`](Invoker::invoke) that occurs while publishing the request. Errors
+    /// processing a response, including errors reported by the executor, are yielded by the
+    /// stream and end it.
+    pub async fn invoke_stream(
+        &self,
+        request: Request<TReq>,
+    ) -> Result<ResponseStream<TResp>, AIOProtocolError>
+    where
+        TResp: Send,
+    {
+        let command_timeout = request.timeout;
+        let deadline = Instant::now() + command_timeout;
+
+        // Publish the request, wrapped within the timeout. Responses are forwarded as they are
+        // received while the request is published, so that the response_tx won't lag
+        let publish_result = runtime::timeout(command_timeout, async {
+            let PendingRequest {
+                correlation_data,
+                response_rx,
+                pub_task,
+            } = self
+                .publish_request(request, new_correlation_data())
+                .await?;
+            let (stream_tx, stream_rx) = mpsc::unbounded_channel();
+            runtime::spawn(Self::forward_stream_responses(
+                response_rx,
+                correlation_data,
+                stream_tx,
+                self.command_name.clone(),
+            ));
+            pub_task.await?;
+            Ok::<_, AIOProtocolError>(stream_rx)
+        })
+        .await;
+        let stream_rx = match publish_result {
+            Ok(result) => result?,
+            Err(e) => return Err(self.create_timeout_error(e, command_timeout)),
+        };
+
+        let state = ResponseStreamState {
+            stream_rx,
+            deadline,
+            command_timeout,
+            key_provider: self.key_provider.clone(),
+            application_hlc: self.application_hlc.clone(),
+            command_name: self.command_name.clone(),
+        };
+        let inner = stream::unfold(Some(state), |state| async move {
+            let mut state = state?;
+            let recv_result = runtime::timeout(
+                state.deadline.saturating_duration_since(Instant::now()),
+                state.stream_rx.recv(),
+            )
+            .await;
+            match recv_result {
+                Ok(Some(Ok(rsp_pub))) => {
+                    let response = process_response(
+                        rsp_pub,
+                        state.key_provider.as_deref(),
+                        &state.application_hlc,
+                        &state.command_name,
+                    );
+                    Some((response, Some(state)))
+                }
+                Ok(Some(Err(e))) => Some((Err(e), None)),
+                // The last response of the stream has been yielded
+                Ok(None) => None,
+                Err(e) => {
+                    log::error!(
+                        "[{command_name}] Command invoke timed out after {command_timeout:?}",
+                        command_name = state.command_name,
+                        command_timeout = state.command_timeout,
+                    );
+                    let timeout_error = AIOProtocolError::new_timeout_error(
+                        false,
+                        Some(Box::new(e)),
+                        &state.command_name,
+                        state.command_timeout,
+                        None,
+                        Some(state.command_name.clone()),
+                    );
+                    Some((Err(timeout_error), None))
+                }
+            }
+        });
+
+        Ok(ResponseStream {
+            inner: Box::pin(inner),
+        })
+    }
+
     /// Subscribes to the response topic filter.
     ///
     /// Returns `Ok()` on success, otherwise returns [`AIOProtocolError`].
@@ -924,42 +1503,116 @@ where
 
     async fn invoke_internal(
         &self,
-        mut request: Request<TReq>,
+        request: Request<TReq>,
+        correlation_data: Bytes,
     ) -> Result<Response<TResp>, AIOProtocolError> {
-        // Validate parameters. Custom user data, timeout, and payload serialization have already been validated in RequestBuilder
-        // Validate message expiry interval
-        let message_expiry_interval: u32 = match request.timeout.as_secs().try_into() {
-            Ok(val) => val,
-            Err(_) => {
-                // should be validated in RequestBuilder
-                unreachable!();
+        let PendingRequest {
+            correlation_data,
+            mut response_rx,
+            pub_task,
+        } = self.publish_request(request, correlation_data).await?;
+
+        // task to receive incoming responses and check for the one that is for this request
+        let response_task = runtime::spawn({
+            let command_name = self.command_name.clone();
+            async move {
+                loop {
+                    // wait for incoming pub
+                    match response_rx.recv().await {
+                        Ok(rsp_pub) => {
+                            if let Some(rsp_pub) = rsp_pub {
+                                // check correlation id for match, otherwise loop again
+                                if let Some(ref rsp_properties) = rsp_pub.properties {
+                                    if let Some(ref response_correlation_data) =
+                                        rsp_properties.correlation_data
+                                    {
+                                        if *response_correlation_data == correlation_data {
+                                            // This is implicit validation of the correlation data - if it's malformed it won't match the request
+                                            // This is the response for this request, stop listening for more responses and validate and parse it and send it back to the application
+                                            return Ok(rsp_pub);
+                                        }
+                                    }
+                                }
+                            } else {
+                                log::error!(
+                                    "Command Invoker has been shutdown and will no longer receive a response"
+                                );
+                                return Err(AIOProtocolError::new_cancellation_error(
+                                    false,
+                                    None,
+                                    Some(
+                                        "Command Invoker has been shutdown and will no longer receive a response"
+                                            .to_string(),
+                                    ),
+                                    Some(command_name),
+                                ));
+                            }
+                            // If the publish doesn't have properties, correlation_data, or the correlation data doesn't match, keep waiting for the next one
+                        }
+                        Err(RecvError::Lagged(e)) => {
+                            log::error!(
+                                "[ERROR] Invoker response receiver lagged. Response may not be received. Number of skipped messages: {e}"
+                            );
+                            // Keep waiting for response even though it may have gotten overwritten.
+                            continue;
+                        }
+                        Err(RecvError::Closed) => {
+                            log::error!(
+                                "[ERROR] MQTT Receiver has been cleaned up and will no longer send a response"
+                            );
+                            return Err(AIOProtocolError::new_cancellation_error(
+                                false,
+                                None,
+                                Some(
+                                    "MQTT Receiver has been cleaned up and will no longer send a response"
+                                        .to_string(),
+                                ),
+                                Some(command_name),
+                            ));
+                        }
+                    }
+                }
             }
-        };
+        });
 
-        // Get request topic. Validates dynamic topic tokens
-        let request_topic = self
-            .request_topic_pattern
-            .as_publish_topic(&request.topic_tokens)
-            .map_err(|e| {
-                AIOProtocolError::config_invalid_from_topic_pattern_error(
-                    e,
-                    "request_topic_pattern",
-                )
-            })?;
-        // Get response topic. Validates dynamic topic tokens
-        let response_topic = self
-            .response_topic_pattern
-            .as_publish_topic(&request.topic_tokens)
-            .map_err(|e| {
-                AIOProtocolError::config_invalid_from_topic_pattern_error(
-                    e,
-                    "response_topic_pattern",
-                )
-            })?;
+        // wait for pub to be completed and response to be received, immediately returning any errors returned.
+        let rsp_pub = match tokio::try_join!(pub_task, response_task) {
+            Ok(((), rsp_pub)) => rsp_pub,
+            // Return any error that occurs
+            Err(e) => {
+                return Err(e);
+            }
+        };
+
+        process_response(
+            rsp_pub,
+            self.key_provider.as_deref(),
+            &self.application_hlc,
+            &self.command_name,
+        )
+    }
 
-        // Create correlation id
-        let correlation_id = Uuid::new_v4();
-        let correlation_data = Bytes::from(correlation_id.as_bytes().to_vec());
+    /// Subscribes to the response topic if needed and publishes the request with the given
+    /// correlation data, split into chunks if configured and needed, in a task.
+    ///
+    /// Returns the [`PendingRequest`], with a receiver of responses created before the request is
+    /// published.
+    async fn publish_request(
+        &self,
+        mut request: Request<TReq>,
+        correlation_data: Bytes,
+    ) -> Result<PendingRequest, AIOProtocolError> {
+        // Validate parameters. Custom user data, timeout, and payload serialization have already been validated in RequestBuilder
+        // Validate message expiry interval
+        let message_expiry_interval: u32 = match request.timeout.as_secs().try_into() {
+            Ok(val) => val,
+            Err(_) => {
+                // should be validated in RequestBuilder
+                unreachable!();
+            }
+        };
+
+        let (request_topic, response_topic) = self.publish_topics(&request.topic_tokens)?;
 
         // Get updated timestamp
         let timestamp_str = self.application_hlc.update_now()?;
@@ -981,6 +1634,46 @@ where
             self.mqtt_client.client_id().to_string(),
         ));
 
+        // Compress the payload if configured, advertising the codec if it was applied
+        if let Some(compression) = self.compression {
+            let (payload, codec) = compression
+                .compress(request.serialized_payload.payload)
+                .map_err(|e| {
+                    AIOProtocolError::new_payload_invalid_error(
+                        true,
+                        false,
+                        Some(e.into()),
+                        Some("Payload compression error".to_string()),
+                        Some(self.command_name.clone()),
+                    )
+                })?;
+            request.serialized_payload.payload = payload;
+            if let Some(codec) = codec {
+                request
+                    .custom_user_data
+                    .push((UserProperty::ContentEncoding.to_string(), codec.to_string()));
+                request
+                    .serialized_payload
+                    .format_indicator
+                    .mark_transformed(&mut request.custom_user_data);
+            }
+        }
+
+        // Encrypt the payload if configured, binding the topic and timestamp
+        if let Some(key_provider) = &self.key_provider {
+            request.serialized_payload.payload = encryption::seal(
+                key_provider.as_ref(),
+                request_topic.as_bytes(),
+                &mut request.custom_user_data,
+                &request.serialized_payload.payload,
+            )
+            .map_err(|e| e.into_encrypt_error(Some(self.command_name.clone())))?;
+            request
+                .serialized_payload
+                .format_indicator
+                .mark_transformed(&mut request.custom_user_data);
+        }
+
         // Create MQTT Properties
         let publish_properties = PublishProperties {
             correlation_data: Some(correlation_data.clone()),
@@ -1018,158 +1711,252 @@ where
         }
 
         // Create receiver for response
-        let mut response_rx = self.response_tx.subscribe();
-
-        // Send publish
-        let publish_result = self
-            .mqtt_client
-            .publish_with_properties(
-                request_topic,
-                QoS::AtLeastOnce,
-                false,
-                request.serialized_payload.payload,
-                publish_properties,
-            )
-            .await;
+        let response_rx = self.response_tx.subscribe();
 
-        // Await for publish to complete in a task that concurrently polls the response_rx
-        // so that the response_tx won't lag if the puback takes long to return
+        // Publish the request, split into chunks if configured and needed, in a task that
+        // concurrently polls the response_rx so that the response_tx won't lag if the pubacks take
+        // long to return
         // TODO: this could be fixed more elegantly by using a dispatcher instead of a broadcast channel for the response_tx/rx
-        let pub_task = tokio::task::spawn({
+        let pub_task = runtime::spawn({
             let command_name = self.command_name.clone();
+            let mqtt_client = self.mqtt_client.clone();
+            let chunks = chunking::split(
+                self.chunking,
+                request.serialized_payload.payload,
+                publish_properties,
+            );
             async move {
-                match publish_result {
-                    Ok(publish_completion_token) => {
-                        // Wait for and handle the puback
-                        match publish_completion_token.await {
-                            // if puback is Ok, continue and wait for the response
-                            Ok(()) => Ok(()),
-                            Err(e) => {
+                for (payload, publish_properties) in chunks {
+                    let publish_result = mqtt_client
+                        .publish_with_properties(
+                            request_topic.clone(),
+                            QoS::AtLeastOnce,
+                            false,
+                            payload,
+                            publish_properties,
+                        )
+                        .await;
+                    match publish_result {
+                        Ok(publish_completion_token) => {
+                            // Wait for and handle the puback, continuing with the next chunk if
+                            // it is Ok, and then waiting for the response
+                            if let Err(e) = publish_completion_token.await {
                                 log::error!("[ERROR] puback error: {e}");
-                                Err(AIOProtocolError::new_mqtt_error(
+                                return Err(AIOProtocolError::new_mqtt_error(
                                     Some("MQTT Error on command invoke puback".to_string()),
                                     Box::new(e),
                                     Some(command_name),
-                                ))
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        log::error!("[ERROR] client error while publishing: {e}");
-                        Err(AIOProtocolError::new_mqtt_error(
-                            Some("Client error on command invoker request publish".to_string()),
-                            Box::new(e),
-                            Some(command_name),
-                        ))
-                    }
-                }
-            }
-        });
-        // task to receive incoming responses and check for the one that is for this request
-        let response_task = tokio::task::spawn({
-            let command_name = self.command_name.clone();
-            async move {
-                loop {
-                    // wait for incoming pub
-                    match response_rx.recv().await {
-                        Ok(rsp_pub) => {
-                            if let Some(rsp_pub) = rsp_pub {
-                                // check correlation id for match, otherwise loop again
-                                if let Some(ref rsp_properties) = rsp_pub.properties {
-                                    if let Some(ref response_correlation_data) =
-                                        rsp_properties.correlation_data
-                                    {
-                                        if *response_correlation_data == correlation_data {
-                                            // This is implicit validation of the correlation data - if it's malformed it won't match the request
-                                            // This is the response for this request, stop listening for more responses and validate and parse it and send it back to the application
-                                            return Ok(rsp_pub);
-                                        }
-                                    }
-                                }
-                            } else {
-                                log::error!(
-                                    "Command Invoker has been shutdown and will no longer receive a response"
-                                );
-                                return Err(AIOProtocolError::new_cancellation_error(
-                                    false,
-                                    None,
-                                    Some(
-                                        "Command Invoker has been shutdown and will no longer receive a response"
-                                            .to_string(),
-                                    ),
-                                    Some(command_name),
                                 ));
                             }
-                            // If the publish doesn't have properties, correlation_data, or the correlation data doesn't match, keep waiting for the next one
                         }
-                        Err(RecvError::Lagged(e)) => {
-                            log::error!(
-                                "[ERROR] Invoker response receiver lagged. Response may not be received. Number of skipped messages: {e}"
-                            );
-                            // Keep waiting for response even though it may have gotten overwritten.
-                            continue;
-                        }
-                        Err(RecvError::Closed) => {
-                            log::error!(
-                                "[ERROR] MQTT Receiver has been cleaned up and will no longer send a response"
-                            );
-                            return Err(AIOProtocolError::new_cancellation_error(
-                                false,
-                                None,
-                                Some(
-                                    "MQTT Receiver has been cleaned up and will no longer send a response"
-                                        .to_string(),
-                                ),
+                        Err(e) => {
+                            log::error!("[ERROR] client error while publishing: {e}");
+                            return Err(AIOProtocolError::new_mqtt_error(
+                                Some("Client error on command invoker request publish".to_string()),
+                                Box::new(e),
                                 Some(command_name),
                             ));
                         }
                     }
                 }
+                Ok(())
             }
         });
+        Ok(PendingRequest {
+            correlation_data,
+            response_rx,
+            pub_task,
+        })
+    }
 
-        // wait for pub to be completed and response to be received, immediately returning any errors returned.
-        let rsp_pub = match tokio::try_join!(flatten(pub_task), flatten(response_task)) {
-            Ok(((), rsp_pub)) => rsp_pub,
-            // Return any error that occurs
-            Err(e) => {
-                return Err(e);
+    /// Publishes a message on the request topic cancelling the command request with the given
+    /// correlation data, and waits for its puback. The message is sent with the
+    /// [`RPC_COMMAND_CANCELLATION_PROTOCOL_VERSION`], so that an executor that doesn't support
+    /// cancellation rejects it without processing it as a request.
+    async fn publish_cancellation(
+        &self,
+        topic_tokens: &HashMap<String, String>,
+        correlation_data: Bytes,
+        timeout: Duration,
+    ) -> Result<(), AIOProtocolError> {
+        let (request_topic, response_topic) = self.publish_topics(topic_tokens)?;
+        let message_expiry_interval: u32 = match timeout.as_secs().try_into() {
+            Ok(val) => val,
+            Err(_) => {
+                // should be validated in RequestBuilder
+                unreachable!();
             }
         };
 
-        // validate and parse the response pub that is for this request
-        let command_result: CommandResult<TResp> =
-            rsp_pub.try_into().map_err(|mut e: AIOProtocolError| {
-                // Add command name to the error
-                e.command_name = Some(self.command_name.clone());
-                e
-            })?;
+        // The response topic and partition key are the same as the request's, so that the
+        // cancellation reaches the executor processing the request and matches it
+        let mut user_properties = vec![
+            (UserProperty::Cancel.to_string(), "true".to_string()),
+            (
+                UserProperty::SourceId.to_string(),
+                self.mqtt_client.client_id().to_string(),
+            ),
+            (
+                UserProperty::Timestamp.to_string(),
+                self.application_hlc.update_now()?,
+            ),
+            (
+                UserProperty::ProtocolVersion.to_string(),
+                RPC_COMMAND_CANCELLATION_PROTOCOL_VERSION.to_string(),
+            ),
+            (
+                PARTITION_KEY.to_string(),
+                self.mqtt_client.client_id().to_string(),
+            ),
+        ];
 
-        match command_result {
-            CommandResult::Ok(response) => {
-                // Update application HLC
-                if let Some(hlc) = &response.timestamp {
-                    self.application_hlc.update(hlc).map_err(|e| {
-                        let mut aio_error: AIOProtocolError = e.into();
-                        aio_error.command_name = Some(self.command_name.clone());
-                        aio_error
-                    })?;
-                }
-                Ok(response)
+        // Seal the correlation data as the payload if encryption is configured, so that the
+        // executor authenticates the cancellation like the request it cancels
+        let payload = match &self.key_provider {
+            Some(key_provider) => encryption::seal(
+                key_provider.as_ref(),
+                request_topic.as_bytes(),
+                &mut user_properties,
+                &correlation_data,
+            )
+            .map_err(|e| e.into_encrypt_error(Some(self.command_name.clone())))?,
+            None => Vec::new(),
+        };
+
+        let publish_properties = PublishProperties {
+            correlation_data: Some(correlation_data),
+            response_topic: Some(response_topic),
+            message_expiry_interval: Some(message_expiry_interval),
+            user_properties,
+            ..Default::default()
+        };
+
+        let publish_result = self
+            .mqtt_client
+            .publish_with_properties(
+                request_topic,
+                QoS::AtLeastOnce,
+                false,
+                payload,
+                publish_properties,
+            )
+            .await;
+        match publish_result {
+            Ok(publish_completion_token) => publish_completion_token.await.map_err(|e| {
+                log::error!("[ERROR] puback error: {e}");
+                AIOProtocolError::new_mqtt_error(
+                    Some("MQTT Error on command cancellation puback".to_string()),
+                    Box::new(e),
+                    Some(self.command_name.clone()),
+                )
+            }),
+            Err(e) => {
+                log::error!("[ERROR] client error while publishing: {e}");
+                Err(AIOProtocolError::new_mqtt_error(
+                    Some("Client error on command invoker cancellation publish".to_string()),
+                    Box::new(e),
+                    Some(self.command_name.clone()),
+                ))
             }
-            CommandResult::Err(remote_e) => {
-                // Update application HLC
-                if let Some(hlc) = &remote_e.timestamp {
-                    self.application_hlc.update(hlc).map_err(|e| {
-                        let mut aio_error: AIOProtocolError = e.into();
-                        aio_error.command_name = Some(self.command_name.clone());
-                        aio_error
-                    })?;
+        }
+    }
+
+    /// Gets the request and response topics of a request. Validates dynamic topic tokens.
+    fn publish_topics(
+        &self,
+        topic_tokens: &HashMap<String, String>,
+    ) -> Result<(String, String), AIOProtocolError> {
+        let request_topic = self
+            .request_topic_pattern
+            .as_publish_topic(topic_tokens)
+            .map_err(|e| {
+                AIOProtocolError::config_invalid_from_topic_pattern_error(
+                    e,
+                    "request_topic_pattern",
+                )
+            })?;
+        let response_topic = self
+            .response_topic_pattern
+            .as_publish_topic(topic_tokens)
+            .map_err(|e| {
+                AIOProtocolError::config_invalid_from_topic_pattern_error(
+                    e,
+                    "response_topic_pattern",
+                )
+            })?;
+        Ok((request_topic, response_topic))
+    }
+
+    /// Forwards the responses to a streaming request in order, until the last one is forwarded or
+    /// the receiver of the [`ResponseStream`] is dropped.
+    async fn forward_stream_responses(
+        mut response_rx: Receiver<Option<Publish>>,
+        correlation_data: Bytes,
+        stream_tx: mpsc::UnboundedSender<Result<Publish, AIOProtocolError>>,
+        command_name: String,
+    ) {
+        let mut sequencer = ResponseSequencer::default();
+        loop {
+            tokio::select! {
+                // the stream has ended or been dropped
+                () = stream_tx.closed() => return,
+                recv_result = response_rx.recv() => match recv_result {
+                    Ok(Some(rsp_pub)) => {
+                        // check correlation id for match, otherwise keep waiting
+                        let response_correlation_data = rsp_pub
+                            .properties
+                            .as_ref()
+                            .and_then(|p| p.correlation_data.as_ref());
+                        if response_correlation_data != Some(&correlation_data) {
+                            continue;
+                        }
+                        for response in sequencer.insert(rsp_pub) {
+                            // Ignore error as the stream may have been dropped
+                            let _ = stream_tx.send(response.map_err(|mut e| {
+                                e.command_name = Some(command_name.clone());
+                                e
+                            }));
+                        }
+                        if sequencer.is_finished() {
+                            return;
+                        }
+                    }
+                    Ok(None) => {
+                        log::error!(
+                            "Command Invoker has been shutdown and will no longer receive a response"
+                        );
+                        let _ = stream_tx.send(Err(AIOProtocolError::new_cancellation_error(
+                            false,
+                            None,
+                            Some(
+                                "Command Invoker has been shutdown and will no longer receive a response"
+                                    .to_string(),
+                            ),
+                            Some(command_name),
+                        )));
+                        return;
+                    }
+                    Err(RecvError::Lagged(e)) => {
+                        log::error!(
+                            "[ERROR] Invoker response receiver lagged. Responses of the stream may not be received. Number of skipped messages: {e}"
+                        );
+                    }
+                    Err(RecvError::Closed) => {
+                        log::error!(
+                            "[ERROR] MQTT Receiver has been cleaned up and will no longer send a response"
+                        );
+                        let _ = stream_tx.send(Err(AIOProtocolError::new_cancellation_error(
+                            false,
+                            None,
+                            Some(
+                                "MQTT Receiver has been cleaned up and will no longer send a response"
+                                    .to_string(),
+                            ),
+                            Some(command_name),
+                        )));
+                        return;
+                    }
                 }
-                // Convert into AIOProtocolError and return
-                let mut aio_e: AIOProtocolError = remote_e.into();
-                aio_e.command_name = Some(self.command_name.clone());
-                Err(aio_e)
             }
         }
     }
@@ -1179,6 +1966,7 @@ where
         response_tx: Sender<Option<Publish>>,
         shutdown_notifier: Arc<Notify>,
         command_name: String,
+        mut chunk_buffer: ChunkBuffer,
     ) {
         loop {
             tokio::select! {
@@ -1189,12 +1977,21 @@ where
                     log::info!("[{command_name}] MQTT Receiver closed");
                   },
                   recv_result = mqtt_receiver.recv_manual_ack() => {
-                    if let Some((m, ack_token)) = recv_result {
-                        // Send to pending command listeners
-                        match response_tx.send(Some(m)) {
-                            Ok(_) => { },
+                    if let Some((m, mut ack_token)) = recv_result {
+                        // Reassemble chunked responses, then send to pending command listeners. The
+                        // ack tokens of buffered chunks are held until the response is acked.
+                        match chunk_buffer.insert(m, &mut ack_token) {
+                            Ok(Some(m)) => match response_tx.send(Some(m)) {
+                                Ok(_) => { },
+                                Err(e) => {
+                                    log::debug!("[{command_name}] Message ignored, no pending commands: {e}");
+                                }
+                            },
+                            Ok(None) => {
+                                log::debug!("[{command_name}] Buffered chunk of a chunked response");
+                            }
                             Err(e) => {
-                                log::debug!("[{command_name}] Message ignored, no pending commands: {e}");
+                                log::error!("[{command_name}] Chunked response discarded: {e}");
                             }
                         }
                         // Manually ack
@@ -1288,7 +2085,7 @@ where
 {
     fn drop(&mut self) {
         // drop can't be async, but we can spawn a task to unsubscribe
-        tokio::spawn({
+        runtime::spawn({
             let invoker_state_mutex = self.invoker_state_mutex.clone();
             let unsubscribe_filter = self.response_topic_pattern.as_subscribe_topic();
             let mqtt_client = self.mqtt_client.clone();
@@ -1331,21 +2128,6 @@ async fn drop_unsubscribe<C: ManagedClient + Clone + Send + Sync + 'static>(
     *invoker_state_mutex_guard = State::ShutdownSuccessful;
 }
 
-/// convenience fn to flatten the result of a `JoinHandle`
-async fn flatten<T>(
-    handle: JoinHandle<Result<T, AIOProtocolError>>,
-) -> Result<T, AIOProtocolError> {
-    match handle.await {
-        Ok(Ok(result)) => Ok(result),
-        Ok(Err(e)) => Err(e),
-        Err(e) => {
-            // tasks can't panic
-            log::error!("Join Error: {e}");
-            unreachable!()
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use test_case::test_case;
@@ -1359,6 +2141,7 @@ mod tests {
         aio_protocol_error::AIOProtocolErrorKind,
         payload_serialize::{DESERIALIZE_MTX, FormatIndicator, MockPayload},
     };
+    use crate::rpc_command::retry_policy::ExponentialBackoffRetryPolicy;
 
     // TODO: This should return a mock ManagedClient instead.
     // Until that's possible, need to return a Session so that the Session doesn't go out of
@@ -1404,6 +2187,7 @@ mod tests {
             invoker.response_topic_pattern.as_subscribe_topic(),
             "clients/test_client/test/test_command_name/+/request"
         );
+        assert_eq!(invoker.compression, None);
     }
 
     #[tokio::test]
@@ -1434,6 +2218,39 @@ mod tests {
         );
     }
 
+    #[test_case(1, true; "new_chunking_valid")]
+    #[test_case(0, false; "new_chunking_zero_max_chunk_size")]
+    #[tokio::test]
+    async fn test_new_with_chunking(max_chunk_size: usize, valid: bool) {
+        let session = create_session();
+        let managed_client = session.create_managed_client();
+        let invoker_options = OptionsBuilder::default()
+            .request_topic_pattern("test/{commandName}/{executorId}/request")
+            .command_name("test_command_name")
+            .topic_token_map(create_topic_tokens())
+            .chunking(Chunking::new(max_chunk_size))
+            .build()
+            .unwrap();
+
+        let result: Result<Invoker<MockPayload, MockPayload, _>, AIOProtocolError> = Invoker::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            managed_client,
+            invoker_options,
+        );
+        match result {
+            Ok(invoker) => {
+                assert!(valid);
+                assert_eq!(invoker.chunking, Some(Chunking::new(max_chunk_size)));
+            }
+            Err(e) => {
+                assert!(!valid);
+                assert_eq!(e.kind, AIOProtocolErrorKind::ConfigurationInvalid);
+                assert_eq!(e.property_name, Some("chunking.max_chunk_size".to_string()));
+                assert_eq!(e.command_name, Some("test_command_name".to_string()));
+            }
+        }
+    }
+
     #[test_case("command_name", ""; "new_empty_command_name")]
     #[test_case("command_name", " "; "new_whitespace_command_name")]
     #[test_case("request_topic_pattern", ""; "new_empty_request_topic_pattern")]
@@ -1793,6 +2610,80 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cancellation_handle() {
+        let handle = CancellationHandle {
+            cancellation_token: CancellationToken::new(),
+        };
+        let handle_clone = handle.clone();
+        assert!(!handle.is_cancelled());
+        handle_clone.cancel();
+        assert!(handle.is_cancelled());
+    }
+
+    // Tests failure: Every attempt of an invocation with a retry policy times out, and a `Timeout` error is returned
+    // with the number of attempts made within the timeout of the request
+    #[tokio::test]
+    async fn test_invoke_with_retry_policy_times_out() {
+        let session = create_session();
+        let managed_client = session.create_managed_client();
+        let invoker_options = OptionsBuilder::default()
+            .request_topic_pattern("test/req/topic")
+            .command_name("test_command_name")
+            .topic_token_map(create_topic_tokens())
+            .retry_policy(Some(Arc::new(ExponentialBackoffRetryPolicy {
+                base_delay: Duration::from_millis(10),
+                max_wait: Duration::from_millis(10),
+                max_retries: 5,
+                attempt_timeout: Some(Duration::from_millis(900)),
+            }) as Arc<dyn RetryPolicy>))
+            .build()
+            .unwrap();
+
+        let invoker: Invoker<MockPayload, MockPayload, _> = Invoker::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            managed_client,
+            invoker_options,
+        )
+        .unwrap();
+
+        let mut mock_request_payload = MockPayload::new();
+        mock_request_payload
+            .expect_serialize()
+            .returning(|| {
+                Ok(SerializedPayload {
+                    payload: Vec::new(),
+                    content_type: "application/json".to_string(),
+                    format_indicator: FormatIndicator::Utf8EncodedCharacterData,
+                })
+            })
+            .times(1);
+
+        let response = invoker
+            .invoke(
+                RequestBuilder::default()
+                    .payload(mock_request_payload)
+                    .unwrap()
+                    .timeout(Duration::from_secs(2))
+                    .build()
+                    .unwrap(),
+            )
+            .await;
+        match response {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                assert_eq!(e.kind, AIOProtocolErrorKind::Timeout);
+                assert!(!e.is_remote);
+                assert_eq!(e.timeout_name, Some("test_command_name".to_string()));
+                // Two attempts of 900ms fit within the timeout of the request, and a third is
+                // started unless scheduling delays leave no time for it. No attempt outlasts the
+                // timeout of the policy.
+                assert!(matches!(e.attempt_count, Some(2..=3)));
+                assert!(e.timeout_value <= Some(Duration::from_millis(900)));
+            }
+        }
+    }
+
     #[tokio::test]
     #[ignore] // test ignored because waiting for the suback hangs forever. Leaving the test for now until we have a full testing framework
     async fn test_invoke_deserialize_error() {
@@ -2056,6 +2947,346 @@ mod tests {
         assert!(request_builder_result.is_err());
     }
 
+    /// Creates a response carrying `index` as its stream index and payload, ending the stream if `is_last`
+    fn create_stream_response(index: Option<&str>, is_last: bool) -> Publish {
+        let mut user_properties = vec![("custom".to_string(), "value".to_string())];
+        if let Some(index) = index {
+            user_properties.push((UserProperty::StreamIndex.to_string(), index.to_string()));
+        }
+        if is_last {
+            user_properties.push((UserProperty::StreamEnd.to_string(), "true".to_string()));
+        }
+        Publish {
+            topic: "test/response".into(),
+            payload: Bytes::from(index.unwrap_or_default().to_string()),
+            properties: Some(PublishProperties {
+                user_properties,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn sequenced_payloads(sequenced: Vec<Result<Publish, AIOProtocolError>>) -> Vec<Bytes> {
+        sequenced
+            .into_iter()
+            .map(|rsp_pub| {
+                let rsp_pub = rsp_pub.unwrap();
+                // Stream user properties are removed
+                assert_eq!(
+                    rsp_pub.properties.unwrap().user_properties,
+                    vec![("custom".to_string(), "value".to_string())]
+                );
+                rsp_pub.payload
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sequencer_in_order() {
+        let mut sequencer = ResponseSequencer::default();
+        for index in ["0", "1"] {
+            assert_eq!(
+                sequenced_payloads(sequencer.insert(create_stream_response(Some(index), false))),
+                vec![Bytes::from(index)]
+            );
+            assert!(!sequencer.is_finished());
+        }
+        assert_eq!(
+            sequenced_payloads(sequencer.insert(create_stream_response(Some("2"), true))),
+            vec![Bytes::from("2")]
+        );
+        assert!(sequencer.is_finished());
+    }
+
+    #[test]
+    fn test_sequencer_out_of_order_and_duplicates() {
+        let mut sequencer = ResponseSequencer::default();
+        assert!(
+            sequencer
+                .insert(create_stream_response(Some("2"), true))
+                .is_empty()
+        );
+        // Responses after the last one are ignored
+        assert!(
+            sequencer
+                .insert(create_stream_response(Some("3"), false))
+                .is_empty()
+        );
+        assert_eq!(
+            sequenced_payloads(sequencer.insert(create_stream_response(Some("0"), false))),
+            vec![Bytes::from("0")]
+        );
+        // Duplicates of yielded and pending responses are ignored
+        assert!(
+            sequencer
+                .insert(create_stream_response(Some("0"), false))
+                .is_empty()
+        );
+        assert!(
+            sequencer
+                .insert(create_stream_response(Some("2"), true))
+                .is_empty()
+        );
+        assert!(!sequencer.is_finished());
+        assert_eq!(
+            sequenced_payloads(sequencer.insert(create_stream_response(Some("1"), false))),
+            vec![Bytes::from("1"), Bytes::from("2")]
+        );
+        assert!(sequencer.is_finished());
+        assert!(
+            sequencer
+                .insert(create_stream_response(Some("1"), false))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_sequencer_response_not_streamed() {
+        let mut sequencer = ResponseSequencer::default();
+        assert_eq!(
+            sequenced_payloads(sequencer.insert(create_stream_response(None, false))),
+            vec![Bytes::new()]
+        );
+        assert!(sequencer.is_finished());
+    }
+
+    #[test_case("-1"; "negative_index")]
+    #[test_case("first"; "non_numeric_index")]
+    fn test_sequencer_invalid_index(index: &str) {
+        let mut sequencer = ResponseSequencer::default();
+        let mut responses = sequencer.insert(create_stream_response(Some(index), false));
+        assert_eq!(responses.len(), 1);
+        let e = responses.pop().unwrap().unwrap_err();
+        assert_eq!(e.kind, AIOProtocolErrorKind::HeaderInvalid);
+        assert_eq!(e.header_name, Some(UserProperty::StreamIndex.to_string()));
+        assert_eq!(e.header_value, Some(index.to_string()));
+        assert!(sequencer.is_finished());
+    }
+
+    /// Invokes a command over a mock session with an invoker configured with `key_provider`, and
+    /// cancels the invocation once its request is published.
+    ///
+    /// Returns the request and the cancellation that were published.
+    async fn invoke_and_cancel(
+        key_provider: Option<Arc<dyn KeyProvider>>,
+    ) -> (
+        azure_iot_operations_mqtt::interface_mocks::PublishCall,
+        azure_iot_operations_mqtt::interface_mocks::PublishCall,
+    ) {
+        use azure_iot_operations_mqtt::{
+            interface_mocks::{MockClient, MockClientCall, MockEventLoop},
+            session::{reconnect_policy::ExponentialBackoffWithJitter, session},
+        };
+
+        use crate::common::payload_serialize::BypassPayload;
+
+        let client = MockClient::new();
+        let controller = client.mock_controller();
+        let (event_loop, _injector) = MockEventLoop::new();
+        let session = session::Session::new_from_injection(
+            client,
+            event_loop,
+            Box::new(ExponentialBackoffWithJitter::default()),
+            "test_client".to_string(),
+            None,
+        );
+        let invoker_options = OptionsBuilder::default()
+            .request_topic_pattern("test/request")
+            .command_name("test_command_name")
+            .encryption(key_provider)
+            .build()
+            .unwrap();
+        let invoker: Invoker<BypassPayload, BypassPayload, _> = Invoker::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            invoker_options,
+        )
+        .unwrap();
+
+        let request = RequestBuilder::default()
+            .payload(BypassPayload {
+                content_type: "text/plain".to_string(),
+                format_indicator: FormatIndicator::Utf8EncodedCharacterData,
+                payload: b"request".to_vec(),
+            })
+            .unwrap()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap();
+        let (handle, invocation) = invoker.invoke_cancellable(request);
+        let cancel = async {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while controller.publish_count() == 0 {
+                    tokio::task::yield_now().await;
+                }
+            })
+            .await
+            .unwrap();
+            handle.cancel();
+        };
+        let (result, ()) = tokio::join!(invocation, cancel);
+        let Err(e) = result else {
+            panic!("Expected error");
+        };
+        assert_eq!(e.kind, AIOProtocolErrorKind::Cancellation);
+        assert!(handle.is_cancelled());
+
+        let mut publishes = controller
+            .call_sequence()
+            .into_iter()
+            .filter_map(|call| match call {
+                MockClientCall::Publish(publish) => Some(publish),
+                _ => None,
+            });
+        let (Some(request), Some(cancellation), None) =
+            (publishes.next(), publishes.next(), publishes.next())
+        else {
+            panic!("Expected a request and a cancellation to be published");
+        };
+        (request, cancellation)
+    }
+
+    #[tokio::test]
+    async fn test_invoke_cancellable_publishes_cancellation() {
+        let (request, cancellation) = invoke_and_cancel(None).await;
+        let request_properties = request.properties.unwrap();
+        let cancellation_properties = cancellation.properties.unwrap();
+
+        // The cancellation matches the request it cancels
+        assert_eq!(cancellation.topic, request.topic);
+        assert_eq!(cancellation.qos, QoS::AtLeastOnce);
+        assert_eq!(
+            cancellation_properties.correlation_data,
+            request_properties.correlation_data
+        );
+        assert_eq!(
+            cancellation_properties.response_topic,
+            request_properties.response_topic
+        );
+        assert!(cancellation.payload.is_empty());
+
+        // Executors that don't support cancellation reject its protocol version
+        let user_property = |key: UserProperty| {
+            cancellation_properties
+                .user_properties
+                .iter()
+                .find(|(k, _)| *k == key.to_string())
+                .map(|(_, v)| v.clone())
+        };
+        assert_eq!(
+            user_property(UserProperty::Cancel),
+            Some("true".to_string())
+        );
+        assert_eq!(
+            user_property(UserProperty::ProtocolVersion),
+            Some(RPC_COMMAND_CANCELLATION_PROTOCOL_VERSION.to_string())
+        );
+    }
+
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_invoke_cancellable_seals_cancellation() {
+        use crate::common::encryption::{
+            EncryptionAlgorithm, EncryptionKey, InMemoryKeyProvider, KEY_LEN,
+        };
+
+        let key_provider = Arc::new(InMemoryKeyProvider::new(
+            EncryptionKey::new("key1", EncryptionAlgorithm::Aes256Gcm, [1; KEY_LEN]).unwrap(),
+        ));
+        let (_, cancellation) =
+            invoke_and_cancel(Some(key_provider.clone() as Arc<dyn KeyProvider>)).await;
+        let mut properties = cancellation.properties.unwrap();
+
+        // The payload is the correlation data, sealed like a request
+        let correlation_data = encryption::open(
+            Some(key_provider.as_ref()),
+            cancellation.topic.as_bytes(),
+            &mut properties.user_properties,
+            &cancellation.payload,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(Some(correlation_data.into()), properties.correlation_data);
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypted_response_format_indicator_is_restored() {
+        use crate::common::{
+            encryption::{EncryptionAlgorithm, EncryptionKey, InMemoryKeyProvider, KEY_LEN},
+            payload_serialize::BypassPayload,
+        };
+
+        let key_provider = InMemoryKeyProvider::new(
+            EncryptionKey::new("key1", EncryptionAlgorithm::Aes256Gcm, [0; KEY_LEN]).unwrap(),
+        );
+        let topic = "test/response";
+        let payload = br#"{"response": true}"#.to_vec();
+
+        // Encrypt the response the way the executor does, sending it as unspecified bytes
+        let mut format_indicator = FormatIndicator::Utf8EncodedCharacterData;
+        let mut user_properties = vec![(UserProperty::Status.to_string(), "200".to_string())];
+        let sealed = encryption::seal(
+            &key_provider,
+            topic.as_bytes(),
+            &mut user_properties,
+            &payload,
+        )
+        .unwrap();
+        format_indicator.mark_transformed(&mut user_properties);
+        let mut publish = Publish {
+            topic: topic.into(),
+            payload: sealed.into(),
+            properties: Some(PublishProperties {
+                payload_format_indicator: Some(format_indicator as u8),
+                content_type: Some("application/json".to_string()),
+                user_properties,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        encryption::open_publish(Some(&key_provider), &mut publish).unwrap();
+        let Ok(CommandResult::Ok(response)) = CommandResult::<BypassPayload>::try_from(publish)
+        else {
+            panic!("Expected a successful response");
+        };
+        assert_eq!(response.payload.payload, payload);
+        assert_eq!(
+            response.format_indicator,
+            FormatIndicator::Utf8EncodedCharacterData
+        );
+        assert!(response.custom_user_data.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_carried_format_indicator() {
+        let publish = Publish {
+            payload: b"payload".to_vec().into(),
+            properties: Some(PublishProperties {
+                user_properties: vec![
+                    (UserProperty::Status.to_string(), "200".to_string()),
+                    (
+                        UserProperty::PayloadFormatIndicator.to_string(),
+                        "2".to_string(),
+                    ),
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let Err(e) = CommandResult::<Vec<u8>>::try_from(publish) else {
+            panic!("Expected an error");
+        };
+        assert_eq!(e.kind, AIOProtocolErrorKind::HeaderInvalid);
+        assert_eq!(
+            e.header_name,
+            Some(UserProperty::PayloadFormatIndicator.to_string())
+        );
+        assert_eq!(e.header_value, Some("2".to_string()));
+    }
+
     /// Tests success: application_error_headers() returns no Application Error Code and Payload since custom_user_data has none.
     #[tokio::test]
     async fn test_no_app_error_code_and_payload() {
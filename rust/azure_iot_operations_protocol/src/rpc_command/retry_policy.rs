@@ -0,0 +1,174 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Retry policies for a command [`Invoker`](super::Invoker).
+
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::common::aio_protocol_error::AIOProtocolError;
+
+/// Trait defining interface for retry policies.
+///
+/// A retry policy is only consulted for errors that are
+/// [retriable](azure_iot_operations_mqtt::error::ErrorClassification::is_retriable). Every attempt
+/// of a command invocation uses the same correlation data, so an executor drops an attempt of a
+/// request that it is still processing, and responds to an attempt of a request that it has
+/// processed with its cached response, until the request expires. An executor that does not
+/// track the requests it is processing runs the command again for each attempt it receives.
+pub trait RetryPolicy: Send + Sync {
+    /// Get the delay before the next attempt, given the number of attempts made so far and the
+    /// error of the last one.
+    /// Returns None if no retry should be attempted.
+    fn next_retry_delay(&self, prev_attempts: u32, error: &AIOProtocolError) -> Option<Duration>;
+
+    /// Get the timeout of a single attempt.
+    /// Returns None if an attempt may take up to the remaining timeout of the request, in which
+    /// case a timed out attempt is never retried.
+    fn attempt_timeout(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// A retry policy that will exponentially backoff the delay between attempts.
+///
+/// Delays start at the specified base delay and double with each attempt, up to the specified
+/// max wait time, before applying jitter.
+//  Jitter can subtract up to 10% of the delay
+#[derive(Clone)]
+pub struct ExponentialBackoffRetryPolicy {
+    /// The delay before the first retry.
+    pub base_delay: Duration,
+    /// The longest possible time to wait between attempts.
+    pub max_wait: Duration,
+    /// The max number of retries after the first attempt before giving up.
+    pub max_retries: u32,
+    /// The timeout of a single attempt. If None, an attempt may take up to the remaining timeout
+    /// of the request.
+    pub attempt_timeout: Option<Duration>,
+}
+
+impl ExponentialBackoffRetryPolicy {
+    /// Calculate the delay for the next attempt.
+    fn calculate_delay(&self, prev_attempts: u32) -> Duration {
+        let multiplier = 2u32.saturating_pow(prev_attempts.saturating_sub(1));
+        let interval = self
+            .base_delay
+            .saturating_mul(multiplier)
+            .min(self.max_wait);
+
+        // Add jitter to prevent multiple invokers from retrying at the same time
+        let jitter_multiplier = rand::thread_rng().gen_range(0.90..=1.0);
+        interval.mul_f64(jitter_multiplier)
+    }
+}
+
+impl Default for ExponentialBackoffRetryPolicy {
+    /// Up to 3 retries, starting with a delay of 200 ms, with a max wait time of 10 seconds and
+    /// no timeout for a single attempt.
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_millis(200),
+            max_wait: Duration::from_secs(10),
+            max_retries: 3,
+            attempt_timeout: None,
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoffRetryPolicy {
+    fn next_retry_delay(&self, prev_attempts: u32, _error: &AIOProtocolError) -> Option<Duration> {
+        if prev_attempts <= self.max_retries {
+            Some(self.calculate_delay(prev_attempts))
+        } else {
+            None
+        }
+    }
+
+    fn attempt_timeout(&self) -> Option<Duration> {
+        self.attempt_timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use azure_iot_operations_mqtt::error::ErrorClassification;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn create_policy() -> ExponentialBackoffRetryPolicy {
+        ExponentialBackoffRetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_wait: Duration::from_millis(350),
+            max_retries: 4,
+            attempt_timeout: Some(Duration::from_secs(1)),
+        }
+    }
+
+    fn create_timeout_error() -> AIOProtocolError {
+        AIOProtocolError::new_timeout_error(
+            false,
+            None,
+            "test_command_name",
+            Duration::from_secs(1),
+            None,
+            None,
+        )
+    }
+
+    #[test_case(1, Duration::from_millis(100); "first_retry")]
+    #[test_case(2, Duration::from_millis(200); "second_retry")]
+    #[test_case(3, Duration::from_millis(350); "third_retry_max_wait")]
+    #[test_case(4, Duration::from_millis(350); "last_retry")]
+    fn test_next_retry_delay(prev_attempts: u32, max_delay: Duration) {
+        let policy = create_policy();
+        let delay = policy
+            .next_retry_delay(prev_attempts, &create_timeout_error())
+            .unwrap();
+        assert!(delay <= max_delay);
+        assert!(delay >= max_delay.mul_f64(0.9));
+    }
+
+    #[test]
+    fn test_next_retry_delay_max_retries() {
+        let policy = create_policy();
+        assert!(
+            policy
+                .next_retry_delay(5, &create_timeout_error())
+                .is_none()
+        );
+        assert_eq!(policy.attempt_timeout(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_is_retriable() {
+        assert!(create_timeout_error().is_retriable());
+        assert!(
+            AIOProtocolError::new_mqtt_error(
+                None,
+                Box::new(std::io::Error::other("publish failed")),
+                None,
+            )
+            .is_retriable()
+        );
+
+        // Reported by the executor with a status of ServiceUnavailable
+        let mut service_unavailable =
+            AIOProtocolError::new_state_invalid_error("test_property", None, None, None);
+        service_unavailable.is_shallow = false;
+        assert!(!service_unavailable.is_retriable());
+        service_unavailable.is_remote = true;
+        assert!(service_unavailable.is_retriable());
+
+        assert!(!AIOProtocolError::new_cancellation_error(false, None, None, None).is_retriable());
+
+        // Errors detected before any network communication are not retried
+        let mut shallow_timeout = create_timeout_error();
+        shallow_timeout.is_shallow = true;
+        assert!(!shallow_timeout.is_retriable());
+    }
+}
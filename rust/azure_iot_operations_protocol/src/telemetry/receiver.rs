@@ -1,10 +1,13 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
-use std::{collections::HashMap, fmt::Display, marker::PhantomData, str::FromStr, sync::Arc};
+use std::{
+    borrow::Cow, collections::HashMap, fmt::Display, marker::PhantomData, str::FromStr, sync::Arc,
+};
 
 use azure_iot_operations_mqtt::{
     control_packet::{Publish, QoS},
     interface::{AckToken, ManagedClient, PubReceiver},
+    runtime,
 };
 use chrono::{DateTime, Utc};
 use tokio_util::sync::CancellationToken;
@@ -14,8 +17,12 @@ use crate::{
     application::{ApplicationContext, ApplicationHybridLogicalClock},
     common::{
         aio_protocol_error::{AIOProtocolError, Value},
+        chunking::{ChunkBuffer, ReassemblyLimits},
+        compression::{self, CompressionCodec},
+        encryption::{self, KeyProvider},
         hybrid_logical_clock::HybridLogicalClock,
         payload_serialize::{FormatIndicator, PayloadSerialize},
+        signing::{self, SignatureError, SignatureStatus, TrustStore},
         topic_processor::TopicPattern,
         user_properties::UserProperty,
     },
@@ -220,6 +227,10 @@ pub struct Message<T: PayloadSerialize> {
     pub topic_tokens: HashMap<String, String>,
     /// Incoming message topic
     pub topic: String,
+    /// Result of the verification of the signature of the telemetry message.
+    /// [`SignatureStatus::NotVerified`] unless the receiver is configured with a
+    /// [`trust_store`](OptionsBuilder::trust_store).
+    pub signature: SignatureStatus,
 }
 
 impl<T> TryFrom<Publish> for Message<T>
@@ -231,9 +242,9 @@ where
     fn try_from(value: Publish) -> Result<Message<T>, Self::Error> {
         // NOTE: User properties are parsed out into a new HashMap because:
         // 1) It makes the code more readable/maintanable to do HashMap lookups
-        // 2) When this logic is extracted to a ChunkBuffer, it will be more memory efficient as
-        //  we won't want to keep entire copies of all Publishes, so we will just copy the
-        //  properties once.
+        // 2) Chunked messages are reassembled by a ChunkBuffer before this conversion, which
+        //  only keeps the properties of one chunk per message, so the properties are still
+        //  copied only once.
 
         let publish_properties = value.properties.ok_or("Publish contains no properties")?;
 
@@ -242,6 +253,8 @@ where
             UserProperty::Timestamp,
             UserProperty::ProtocolVersion,
             UserProperty::SourceId,
+            UserProperty::ContentEncoding,
+            UserProperty::PayloadFormatIndicator,
         ];
         let mut telemetry_custom_user_data = vec![];
         let mut telemetry_aio_data = HashMap::new();
@@ -298,13 +311,23 @@ where
             .map_err(|e| e.to_string())?
             .to_string();
 
-        // Deserialize payload
-        let format_indicator = publish_properties.payload_format_indicator.try_into().unwrap_or_else(|e| {
-            log::error!("Received invalid payload format indicator: {e}. This should not be possible to receive from the broker. Using default.");
-            FormatIndicator::default()
-        });
+        // Deserialize payload, restoring the format indicator it had before it was transformed
+        let format_indicator = match telemetry_aio_data.get(&UserProperty::PayloadFormatIndicator) {
+            Some(format_indicator) => FormatIndicator::from_user_property(format_indicator)?,
+            None => publish_properties.payload_format_indicator.try_into().unwrap_or_else(|e| {
+                log::error!("Received invalid payload format indicator: {e}. This should not be possible to receive from the broker. Using default.");
+                FormatIndicator::default()
+            }),
+        };
         let content_type = publish_properties.content_type;
-        let payload = T::deserialize(&value.payload, content_type.as_ref(), &format_indicator)
+        let payload = match telemetry_aio_data.get(&UserProperty::ContentEncoding) {
+            Some(content_encoding) => {
+                let codec = CompressionCodec::from_str(content_encoding)?;
+                Cow::Owned(compression::decompress(codec, &value.payload)?)
+            }
+            None => Cow::Borrowed(&value.payload[..]),
+        };
+        let payload = T::deserialize(&payload, content_type.as_ref(), &format_indicator)
             .map_err(|e| format!("{e:?}"))?;
 
         let telemetry_message = Message {
@@ -317,6 +340,8 @@ where
             // NOTE: Topic Tokens cannot be created from just a Publish, they need additional information
             topic_tokens: HashMap::default(),
             topic,
+            // NOTE: The signature is verified by the Receiver, before the payload is decrypted
+            signature: SignatureStatus::NotVerified,
         };
         Ok(telemetry_message)
     }
@@ -342,6 +367,21 @@ pub struct Options {
     #[allow(unused)]
     #[builder(default = "None")]
     service_group_id: Option<String>,
+    /// Optional end-to-end decryption of received payloads with the keys of the key provider.
+    /// If set, messages that are not encrypted are rejected.
+    #[builder(default = "None")]
+    encryption: Option<Arc<dyn KeyProvider>>,
+    /// Optional verification of the signatures of received messages with the keys of the trust
+    /// store. The result is reported on each received [`Message`].
+    #[builder(default = "None")]
+    trust_store: Option<Arc<dyn TrustStore>>,
+    /// If true, messages that are not signed or whose signature is invalid are rejected.
+    /// Requires a [`trust_store`](OptionsBuilder::trust_store).
+    #[builder(default = "false")]
+    reject_unverified: bool,
+    /// Limits applied when reassembling chunked messages
+    #[builder(default)]
+    chunk_reassembly: ReassemblyLimits,
 }
 
 /// Telemetry Receiver struct
@@ -387,6 +427,10 @@ where
     receiver_cancellation_token: CancellationToken,
     // User autoack setting
     auto_ack: bool,
+    key_provider: Option<Arc<dyn KeyProvider>>,
+    trust_store: Option<Arc<dyn TrustStore>>,
+    reject_unverified: bool,
+    chunk_buffer: ChunkBuffer,
 }
 
 /// Describes state of receiver
@@ -420,6 +464,8 @@ where
     ///   or contain a token with no valid replacement
     /// - [`topic_token_map`](OptionsBuilder::topic_token_map) is not empty
     ///   and contains invalid key(s) and/or token(s)
+    /// - [`reject_unverified`](OptionsBuilder::reject_unverified) is true and no
+    ///   [`trust_store`](OptionsBuilder::trust_store) is configured
     #[allow(clippy::needless_pass_by_value)]
     pub fn new(
         application_context: ApplicationContext,
@@ -441,6 +487,16 @@ where
             )
         })?;
 
+        if receiver_options.reject_unverified && receiver_options.trust_store.is_none() {
+            return Err(AIOProtocolError::new_configuration_invalid_error(
+                None,
+                "reject_unverified",
+                Value::Boolean(true),
+                Some("Rejecting unverified messages requires a trust store".to_string()),
+                None,
+            ));
+        }
+
         // Get the telemetry topic
         let telemetry_topic = topic_pattern.as_subscribe_topic();
 
@@ -467,6 +523,10 @@ where
             receiver_state: State::New,
             receiver_cancellation_token: CancellationToken::new(),
             auto_ack: receiver_options.auto_ack,
+            key_provider: receiver_options.encryption,
+            trust_store: receiver_options.trust_store,
+            reject_unverified: receiver_options.reject_unverified,
+            chunk_buffer: ChunkBuffer::new(receiver_options.chunk_reassembly),
         })
     }
 
@@ -555,6 +615,29 @@ where
         Ok(())
     }
 
+    /// Acks a message in the background, to prevent redelivery of a message that could not be
+    /// processed, or of a chunk buffered for reassembly.
+    fn ack_in_background(&self, ack_token: Option<AckToken>, pkid: u16) {
+        if let Some(ack_token) = ack_token {
+            runtime::spawn({
+                let receiver_cancellation_token_clone = self.receiver_cancellation_token.clone();
+                async move {
+                    tokio::select! {
+                        () = receiver_cancellation_token_clone.cancelled() => { /* Received loop cancelled */ },
+                        ack_res = ack_token.ack() => {
+                            match ack_res {
+                                Ok(_) => { /* Success */ }
+                                Err(e) => {
+                                    log::error!("[pkid: {pkid}] Ack error {e}");
+                                }
+                            }
+                        }
+                    }
+                }
+            });
+        }
+    }
+
     /// Receives a telemetry message or [`None`] if there will be no more messages.
     /// If there are messages:
     /// - Returns Ok([`Message`], [`Option<AckToken>`]) on success
@@ -562,11 +645,36 @@ where
     /// - Returns [`AIOProtocolError`] on error.
     ///
     /// A received message can be acknowledged via the [`AckToken`] by calling [`AckToken::ack`] or dropping the [`AckToken`].
+    /// A message that cannot be processed (e.g. a poison message) can be rejected by calling [`AckToken::nack`],
+    /// which reports the failure reason code to the broker.
     ///
     /// Will also subscribe to the telemetry topic if not already subscribed.
     ///
     /// # Errors
     /// [`AIOProtocolError`] of kind [`ClientError`](crate::common::aio_protocol_error::AIOProtocolErrorKind::ClientError) if the subscribe fails or if the suback reason code doesn't indicate success.
+    ///
+    /// [`AIOProtocolError`] of kind [`HeaderMissing`](crate::common::aio_protocol_error::AIOProtocolErrorKind::HeaderMissing),
+    /// [`HeaderInvalid`](crate::common::aio_protocol_error::AIOProtocolErrorKind::HeaderInvalid) or
+    /// [`PayloadInvalid`](crate::common::aio_protocol_error::AIOProtocolErrorKind::PayloadInvalid) if a received message
+    /// cannot be decrypted, for example because it is not encrypted while [`encryption`](OptionsBuilder::encryption)
+    /// is configured, its key is unknown, or its payload, topic or timestamp were altered. The message is acked,
+    /// and further messages can still be received.
+    ///
+    /// [`AIOProtocolError`] of kind [`HeaderMissing`](crate::common::aio_protocol_error::AIOProtocolErrorKind::HeaderMissing),
+    /// [`HeaderInvalid`](crate::common::aio_protocol_error::AIOProtocolErrorKind::HeaderInvalid) or
+    /// [`PayloadInvalid`](crate::common::aio_protocol_error::AIOProtocolErrorKind::PayloadInvalid) if
+    /// [`reject_unverified`](OptionsBuilder::reject_unverified) is true and a received message is not signed, or its
+    /// signature is invalid. The message is acked, and further messages can still be received.
+    ///
+    /// [`AIOProtocolError`] of kind [`HeaderMissing`](crate::common::aio_protocol_error::AIOProtocolErrorKind::HeaderMissing),
+    /// [`HeaderInvalid`](crate::common::aio_protocol_error::AIOProtocolErrorKind::HeaderInvalid) or
+    /// [`PayloadInvalid`](crate::common::aio_protocol_error::AIOProtocolErrorKind::PayloadInvalid) if a chunk of a
+    /// chunked message is invalid, or the message cannot be reassembled within the
+    /// [`chunk_reassembly`](OptionsBuilder::chunk_reassembly) limits. The chunks received for the message are discarded
+    /// and acked, and further messages can still be received.
+    ///
+    /// A chunked message is returned with the [`AckToken`] of its last received chunk, which acks all of its chunks.
+    /// Chunks are not acked until then, unless the message is discarded.
     pub async fn recv(
         &mut self,
     ) -> Option<Result<(Message<T>, Option<AckToken>), AIOProtocolError>> {
@@ -594,6 +702,53 @@ where
                     // Process the received message
                     log::info!("[pkid: {pkid}] Received message");
 
+                    // Reassemble chunked messages, holding the ack tokens of buffered chunks
+                    // until the reassembled message is acked
+                    let mut m = match self.chunk_buffer.insert(m, &mut ack_token) {
+                        Ok(Some(m)) => m,
+                        Ok(None) => {
+                            log::debug!("[pkid: {pkid}] Buffered chunk of a chunked message");
+                            continue;
+                        }
+                        Err(e) => {
+                            log::error!("[pkid: {pkid}] {e}");
+                            // Ack on error to prevent redelivery
+                            self.ack_in_background(ack_token, pkid);
+                            return Some(Err(e.into_reassembly_error(None)));
+                        }
+                    };
+
+                    // Verify the signature of the message as published, rejecting unverified
+                    // messages if configured
+                    let signature =
+                        match signing::verify_publish(self.trust_store.as_deref(), &mut m) {
+                            SignatureStatus::Unsigned if self.reject_unverified => {
+                                Err(SignatureError::Unsigned)
+                            }
+                            SignatureStatus::Invalid(e) if self.reject_unverified => Err(e),
+                            signature => Ok(signature),
+                        };
+                    let signature = match signature {
+                        Ok(signature) => signature,
+                        Err(e) => {
+                            log::error!("[pkid: {pkid}] {e}");
+                            // Ack on error to prevent redelivery
+                            self.ack_in_background(ack_token, pkid);
+                            return Some(Err(e.into_verify_error(None)));
+                        }
+                    };
+                    if let SignatureStatus::Invalid(e) = &signature {
+                        log::warn!("[pkid: {pkid}] {e}");
+                    }
+
+                    // Decrypt the payload, rejecting messages that cannot be decrypted
+                    if let Err(e) = encryption::open_publish(self.key_provider.as_deref(), &mut m) {
+                        log::error!("[pkid: {pkid}] {e}");
+                        // Ack on error to prevent redelivery
+                        self.ack_in_background(ack_token, pkid);
+                        return Some(Err(e.into_decrypt_error(None)));
+                    }
+
                     match TryInto::<Message<T>>::try_into(m) {
                         Ok(mut message) => {
                             // Update the topic tokens
@@ -602,6 +757,7 @@ where
                             message
                                 .topic_tokens
                                 .extend(self.topic_pattern.parse_tokens(&message.topic));
+                            message.signature = signature;
 
                             // Update application HLC
                             if let Some(hlc) = &message.timestamp {
@@ -617,25 +773,7 @@ where
                             log::error!("[pkid: {pkid}] {e_string}");
 
                             // Ack on error to prevent redelivery
-                            if let Some(ack_token) = ack_token {
-                                tokio::spawn({
-                                    let receiver_cancellation_token_clone =
-                                        self.receiver_cancellation_token.clone();
-                                    async move {
-                                        tokio::select! {
-                                            () = receiver_cancellation_token_clone.cancelled() => { /* Received loop cancelled */ },
-                                            ack_res = ack_token.ack() => {
-                                                match ack_res {
-                                                    Ok(_) => { /* Success */ }
-                                                    Err(e) => {
-                                                        log::error!("[pkid: {pkid}] Ack error {e}");
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                });
-                            }
+                            self.ack_in_background(ack_token, pkid);
                         }
                     }
                 }
@@ -662,7 +800,7 @@ where
 
         // If the receiver has not unsubscribed, attempt to unsubscribe
         if State::Subscribed == self.receiver_state {
-            tokio::spawn({
+            runtime::spawn({
                 let telemetry_topic = self.telemetry_topic.clone();
                 let mqtt_client = self.mqtt_client.clone();
                 async move {
@@ -691,7 +829,10 @@ mod tests {
     use super::*;
     use crate::{
         application::ApplicationContextBuilder,
-        common::{aio_protocol_error::AIOProtocolErrorKind, payload_serialize::MockPayload},
+        common::{
+            aio_protocol_error::AIOProtocolErrorKind,
+            payload_serialize::{BypassPayload, MockPayload},
+        },
         telemetry::receiver::{OptionsBuilder, Receiver},
     };
     use azure_iot_operations_mqtt::{
@@ -741,6 +882,10 @@ mod tests {
             .topic_pattern("test/{telemetryName}/receiver")
             .topic_namespace("test_namespace")
             .topic_token_map(create_topic_tokens())
+            .chunk_reassembly(ReassemblyLimits {
+                timeout: std::time::Duration::from_secs(5),
+                ..Default::default()
+            })
             .build()
             .unwrap();
 
@@ -752,6 +897,81 @@ mod tests {
         .unwrap();
     }
 
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_new_with_encryption() {
+        use crate::common::encryption::{
+            EncryptionAlgorithm, EncryptionKey, InMemoryKeyProvider, KEY_LEN,
+        };
+
+        let session = get_session();
+        let key_provider: Arc<dyn KeyProvider> = Arc::new(InMemoryKeyProvider::new(
+            EncryptionKey::new("key1", EncryptionAlgorithm::Aes256Gcm, [0; KEY_LEN]).unwrap(),
+        ));
+        let receiver_options = OptionsBuilder::default()
+            .topic_pattern("test/receiver")
+            .encryption(key_provider)
+            .build()
+            .unwrap();
+
+        let receiver = Receiver::<MockPayload, _>::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            receiver_options,
+        )
+        .unwrap();
+        assert!(receiver.key_provider.is_some());
+    }
+
+    #[test]
+    fn test_new_reject_unverified_with_trust_store() {
+        use crate::common::signing::InMemoryTrustStore;
+
+        let session = get_session();
+        let trust_store: Arc<dyn TrustStore> = Arc::new(InMemoryTrustStore::new());
+        let receiver_options = OptionsBuilder::default()
+            .topic_pattern("test/receiver")
+            .trust_store(trust_store)
+            .reject_unverified(true)
+            .build()
+            .unwrap();
+
+        let receiver = Receiver::<MockPayload, _>::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            receiver_options,
+        )
+        .unwrap();
+        assert!(receiver.trust_store.is_some());
+        assert!(receiver.reject_unverified);
+    }
+
+    #[test]
+    fn test_new_reject_unverified_without_trust_store() {
+        let session = get_session();
+        let receiver_options = OptionsBuilder::default()
+            .topic_pattern("test/receiver")
+            .reject_unverified(true)
+            .build()
+            .unwrap();
+
+        let result: Result<Receiver<MockPayload, _>, _> = Receiver::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            receiver_options,
+        );
+        match result {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                assert_eq!(e.kind, AIOProtocolErrorKind::ConfigurationInvalid);
+                assert!(e.is_shallow);
+                assert!(!e.is_remote);
+                assert_eq!(e.property_name, Some("reject_unverified".to_string()));
+                assert_eq!(e.property_value, Some(Value::Boolean(true)));
+            }
+        }
+    }
+
     #[test_case(""; "new_empty_topic_pattern")]
     #[test_case(" "; "new_whitespace_topic_pattern")]
     fn test_new_empty_topic_pattern(topic_pattern: &str) {
@@ -800,6 +1020,280 @@ mod tests {
         .unwrap();
         assert!(receiver.shutdown().await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_chunk_acks_held_until_message_acked() {
+        use azure_iot_operations_mqtt::{
+            control_packet::QoS,
+            interface::{Event, Incoming},
+            interface_mocks::{MockClient, MockEventLoop},
+            session::{reconnect_policy::ExponentialBackoffWithJitter, session},
+        };
+
+        use crate::common::chunking::{self, Chunking};
+
+        let client = MockClient::new();
+        let controller = client.mock_controller();
+        let (event_loop, injector) = MockEventLoop::new();
+        let session = session::Session::new_from_injection(
+            client,
+            event_loop,
+            Box::new(ExponentialBackoffWithJitter::default()),
+            "test_client".to_string(),
+            None,
+        );
+        let receiver_options = OptionsBuilder::default()
+            .topic_pattern("test/receiver")
+            .auto_ack(false)
+            .build()
+            .unwrap();
+        let mut receiver = Receiver::<BypassPayload, _>::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            receiver_options,
+        )
+        .unwrap();
+
+        let chunks = chunking::split(
+            Some(Chunking::new(100)),
+            vec![b'a'; 250],
+            azure_iot_operations_mqtt::control_packet::PublishProperties {
+                correlation_data: Some(b"id".to_vec().into()),
+                content_type: Some("text/plain".to_string()),
+                payload_format_indicator: Some(FormatIndicator::Utf8EncodedCharacterData as u8),
+                ..Default::default()
+            },
+        );
+        let chunk_count = chunks.len();
+        for (pkid, (payload, properties)) in (1..).zip(chunks) {
+            injector
+                .inject(Event::Incoming(Incoming::Publish(Publish {
+                    qos: QoS::AtLeastOnce,
+                    pkid,
+                    topic: "test/receiver".into(),
+                    payload,
+                    properties: Some(properties),
+                    ..Default::default()
+                })))
+                .unwrap();
+        }
+
+        let test = async {
+            let (message, ack_token) = receiver.recv().await.unwrap().unwrap();
+            assert_eq!(message.payload.payload, vec![b'a'; 250]);
+            assert_eq!(
+                message.payload.format_indicator,
+                FormatIndicator::Utf8EncodedCharacterData
+            );
+            // No chunk is acked before the reassembled message is
+            assert_eq!(controller.ack_count(), 0);
+            ack_token.unwrap().ack().await.unwrap().await.unwrap();
+            assert_eq!(controller.ack_count(), chunk_count);
+        };
+        tokio::select! {
+            () = test => {},
+            _ = session.run() => panic!("Session exited unexpectedly"),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_incomplete_chunked_message_acked_on_timeout() {
+        use azure_iot_operations_mqtt::{
+            control_packet::QoS,
+            interface::{Event, Incoming},
+            interface_mocks::{MockClient, MockEventLoop},
+            session::{reconnect_policy::ExponentialBackoffWithJitter, session},
+        };
+
+        use crate::common::chunking::{self, Chunking};
+
+        let client = MockClient::new();
+        let controller = client.mock_controller();
+        let (event_loop, injector) = MockEventLoop::new();
+        let session = session::Session::new_from_injection(
+            client,
+            event_loop,
+            Box::new(ExponentialBackoffWithJitter::default()),
+            "test_client".to_string(),
+            None,
+        );
+        let receiver_options = OptionsBuilder::default()
+            .topic_pattern("test/receiver")
+            .auto_ack(false)
+            .chunk_reassembly(ReassemblyLimits {
+                timeout: std::time::Duration::from_secs(5),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let mut receiver = Receiver::<BypassPayload, _>::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            receiver_options,
+        )
+        .unwrap();
+
+        // Only the first two of three chunks are received, followed by an unchunked message
+        let chunks = chunking::split(
+            Some(Chunking::new(100)),
+            vec![b'a'; 250],
+            azure_iot_operations_mqtt::control_packet::PublishProperties {
+                correlation_data: Some(b"id".to_vec().into()),
+                ..Default::default()
+            },
+        );
+        let publishes = chunks.into_iter().take(2).chain([(
+            b"unchunked".to_vec().into(),
+            azure_iot_operations_mqtt::control_packet::PublishProperties::default(),
+        )]);
+        for (pkid, (payload, properties)) in (1..).zip(publishes) {
+            injector
+                .inject(Event::Incoming(Incoming::Publish(Publish {
+                    qos: QoS::AtLeastOnce,
+                    pkid,
+                    topic: "test/receiver".into(),
+                    payload,
+                    properties: Some(properties),
+                    ..Default::default()
+                })))
+                .unwrap();
+        }
+
+        let test = async {
+            let start = tokio::time::Instant::now();
+            let (message, ack_token) = receiver.recv().await.unwrap().unwrap();
+            assert_eq!(message.payload.payload, b"unchunked".to_vec());
+            // The ack of the message waits for the held chunks, which are acked on the timeout
+            let ack = async { ack_token.unwrap().ack().await.unwrap().await };
+            tokio::time::timeout(std::time::Duration::from_secs(60), ack)
+                .await
+                .expect("The held chunks should be acked on the timeout")
+                .unwrap();
+            assert!(start.elapsed() >= std::time::Duration::from_secs(5));
+            assert_eq!(controller.ack_count(), 3);
+        };
+        tokio::select! {
+            () = test => {},
+            _ = session.run() => panic!("Session exited unexpectedly"),
+        }
+    }
+
+    fn publish_with_user_properties(
+        payload: Vec<u8>,
+        user_properties: Vec<(String, String)>,
+    ) -> Publish {
+        Publish {
+            topic: "test/receiver".into(),
+            payload: payload.into(),
+            properties: Some(
+                azure_iot_operations_mqtt::control_packet::PublishProperties {
+                    user_properties,
+                    ..Default::default()
+                },
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    #[test]
+    fn test_compressed_payload_is_decompressed() {
+        use crate::common::compression::{Compression, CompressionCodec};
+
+        #[cfg(feature = "gzip")]
+        let codec = CompressionCodec::Gzip;
+        #[cfg(not(feature = "gzip"))]
+        let codec = CompressionCodec::Zstd;
+
+        let payload = b"repetitive telemetry ".repeat(100);
+        let (compressed, applied) = Compression::new(codec).compress(payload.clone()).unwrap();
+        assert_eq!(applied, Some(codec));
+
+        let publish = publish_with_user_properties(
+            compressed,
+            vec![
+                (UserProperty::ContentEncoding.to_string(), codec.to_string()),
+                ("custom".to_string(), "value".to_string()),
+            ],
+        );
+        let message = Message::<BypassPayload>::try_from(publish).unwrap();
+        assert_eq!(message.payload.payload, payload);
+        // The content encoding is not surfaced as custom user data
+        assert_eq!(
+            message.custom_user_data,
+            vec![("custom".to_string(), "value".to_string())]
+        );
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    #[test]
+    fn test_compressed_payload_format_indicator_is_restored() {
+        use crate::common::compression::{Compression, CompressionCodec};
+
+        #[cfg(feature = "gzip")]
+        let codec = CompressionCodec::Gzip;
+        #[cfg(not(feature = "gzip"))]
+        let codec = CompressionCodec::Zstd;
+
+        let payload = br#"{"telemetry": "repetitive"}"#.repeat(100);
+        let (compressed, _) = Compression::new(codec).compress(payload.clone()).unwrap();
+
+        // The compressed payload is sent as unspecified bytes, carrying the original indicator
+        let publish = publish_with_user_properties(
+            compressed,
+            vec![
+                (UserProperty::ContentEncoding.to_string(), codec.to_string()),
+                (
+                    UserProperty::PayloadFormatIndicator.to_string(),
+                    "1".to_string(),
+                ),
+            ],
+        );
+        let message = Message::<BypassPayload>::try_from(publish).unwrap();
+        assert_eq!(message.payload.payload, payload);
+        assert_eq!(
+            message.format_indicator,
+            FormatIndicator::Utf8EncodedCharacterData
+        );
+        assert_eq!(
+            message.payload.format_indicator,
+            FormatIndicator::Utf8EncodedCharacterData
+        );
+        assert!(message.custom_user_data.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_carried_format_indicator() {
+        let publish = publish_with_user_properties(
+            b"payload".to_vec(),
+            vec![(
+                UserProperty::PayloadFormatIndicator.to_string(),
+                "2".to_string(),
+            )],
+        );
+        assert!(Message::<BypassPayload>::try_from(publish).is_err());
+    }
+
+    #[test]
+    fn test_uncompressed_payload_is_unchanged() {
+        let publish = publish_with_user_properties(b"payload".to_vec(), vec![]);
+        let message = Message::<BypassPayload>::try_from(publish).unwrap();
+        assert_eq!(message.payload.payload, b"payload".to_vec());
+    }
+
+    #[test_case("br"; "unknown content encoding")]
+    #[test_case("gzip"; "gzip content encoding with invalid payload")]
+    #[test_case("zstd"; "zstd content encoding with invalid payload")]
+    fn test_invalid_content_encoding(content_encoding: &str) {
+        let publish = publish_with_user_properties(
+            b"payload".to_vec(),
+            vec![(
+                UserProperty::ContentEncoding.to_string(),
+                content_encoding.to_string(),
+            )],
+        );
+        assert!(Message::<BypassPayload>::try_from(publish).is_err());
+    }
 }
 
 // Test cases for recv telemetry
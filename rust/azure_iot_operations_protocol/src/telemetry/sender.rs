@@ -16,8 +16,12 @@ use crate::{
     application::{ApplicationContext, ApplicationHybridLogicalClock},
     common::{
         aio_protocol_error::{AIOProtocolError, Value},
+        chunking::{self, Chunking},
+        compression::Compression,
+        encryption::{self, KeyProvider},
         is_invalid_utf8,
         payload_serialize::{PayloadSerialize, SerializedPayload},
+        signing::{self, SigningKey},
         topic_processor::TopicPattern,
         user_properties::{UserProperty, validate_user_properties},
     },
@@ -287,6 +291,8 @@ impl<T: PayloadSerialize> MessageBuilder<T> {
 /// Telemetry Sender Options struct
 #[derive(Builder, Clone)]
 #[builder(setter(into, strip_option))]
+// `Compression` is uninhabited when no compression codec features are enabled
+#[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unreachable_code))]
 pub struct Options {
     /// Topic pattern for the telemetry message.
     /// Must align with [topic-structure.md](https://github.com/Azure/iot-operations-sdks/blob/main/doc/reference/topic-structure.md)
@@ -297,6 +303,23 @@ pub struct Options {
     /// Topic token keys/values to be permanently replaced in the topic pattern
     #[builder(default)]
     topic_token_map: HashMap<String, String>,
+    /// Optional compression of the serialized payload of each message.
+    /// Payloads below the compression threshold are sent uncompressed.
+    #[builder(default = "None")]
+    compression: Option<Compression>,
+    /// Optional end-to-end encryption of the serialized payload of each message, after
+    /// compression if any, with the current key of the key provider.
+    #[builder(default = "None")]
+    encryption: Option<Arc<dyn KeyProvider>>,
+    /// Optional signing of each message with the signing key, after compression and encryption
+    /// if any. The signature covers the payload, topic, timestamp, source ID, content type,
+    /// payload format indicator and content encoding.
+    #[builder(default = "None")]
+    signing: Option<SigningKey>,
+    /// Optional splitting of payloads larger than the maximum chunk size into chunks, each
+    /// published as a separate message and reassembled by the receiver.
+    #[builder(default = "None")]
+    chunking: Option<Chunking>,
 }
 
 /// Telemetry Sender struct
@@ -343,6 +366,10 @@ where
     mqtt_client: C,
     message_payload_type: PhantomData<T>,
     topic_pattern: TopicPattern,
+    compression: Option<Compression>,
+    key_provider: Option<Arc<dyn KeyProvider>>,
+    signing_key: Option<SigningKey>,
+    chunking: Option<Chunking>,
 }
 
 /// Implementation of Telemetry Sender
@@ -366,6 +393,7 @@ where
     ///     [`topic_namespace`](OptionsBuilder::topic_namespace),
     ///     are Some and invalid or contain a token with no valid replacement
     /// - [`topic_token_map`](OptionsBuilder::topic_token_map) isn't empty and contains invalid key(s)/token(s)
+    /// - [`chunking`](OptionsBuilder::chunking) is Some and its maximum chunk size is zero
     #[allow(clippy::needless_pass_by_value)]
     pub fn new(
         application_context: ApplicationContext,
@@ -385,12 +413,19 @@ where
                 "sender_options.topic_pattern",
             )
         })?;
+        if let Some(chunking) = sender_options.chunking {
+            chunking.validate(None)?;
+        }
 
         Ok(Self {
             application_hlc: application_context.application_hlc,
             mqtt_client: client,
             message_payload_type: PhantomData,
             topic_pattern,
+            compression: sender_options.compression,
+            key_provider: sender_options.encryption,
+            signing_key: sender_options.signing,
+            chunking: sender_options.chunking,
         })
     }
 
@@ -404,11 +439,20 @@ where
     /// - The publish fails
     /// - The puback reason code doesn't indicate success.
     ///
+    /// If [`chunking`](OptionsBuilder::chunking) is configured and the payload is split into chunks, the chunks are
+    /// published in order, each after the puback of the previous one, and the first error is returned. Chunks published
+    /// before the error are discarded by the receiver after its reassembly timeout.
+    ///
     /// [`AIOProtocolError`] of kind [`InternalLogicError`](crate::common::aio_protocol_error::AIOProtocolErrorKind::InternalLogicError) if
     /// - the [`ApplicationHybridLogicalClock`]'s counter would be incremented and overflow beyond [`u64::MAX`] when preparing the timestamp for the message
     ///
     /// [`AIOProtocolError`] of kind [`StateInvalid`](crate::common::aio_protocol_error::AIOProtocolErrorKind::StateInvalid) if
     /// - the [`ApplicationHybridLogicalClock`]'s timestamp is too far in the future
+    ///
+    /// [`AIOProtocolError`] of kind [`PayloadInvalid`](crate::common::aio_protocol_error::AIOProtocolErrorKind::PayloadInvalid) if
+    /// - [`compression`](OptionsBuilder::compression) is configured and the payload could not be compressed
+    /// - [`encryption`](OptionsBuilder::encryption) is configured and the payload could not be encrypted
+    /// - [`signing`](OptionsBuilder::signing) is configured and the message could not be signed
     pub async fn send(&self, mut message: Message<T>) -> Result<(), AIOProtocolError> {
         // Validate parameters. Custom user data, timeout, QoS, and payload serialization have already been validated in TelemetryMessageBuilder
         let message_expiry_interval: u32 = match message.message_expiry.as_secs().try_into() {
@@ -457,6 +501,59 @@ where
             self.mqtt_client.client_id().to_string(),
         ));
 
+        // Compress the payload if configured, advertising the codec if it was applied
+        if let Some(compression) = self.compression {
+            let (payload, codec) = compression
+                .compress(message.serialized_payload.payload)
+                .map_err(|e| {
+                    AIOProtocolError::new_payload_invalid_error(
+                        true,
+                        false,
+                        Some(e.into()),
+                        Some("Payload compression error".to_string()),
+                        None,
+                    )
+                })?;
+            message.serialized_payload.payload = payload;
+            if let Some(codec) = codec {
+                message
+                    .custom_user_data
+                    .push((UserProperty::ContentEncoding.to_string(), codec.to_string()));
+                message
+                    .serialized_payload
+                    .format_indicator
+                    .mark_transformed(&mut message.custom_user_data);
+            }
+        }
+
+        // Encrypt the payload if configured, binding the topic and timestamp
+        if let Some(key_provider) = &self.key_provider {
+            message.serialized_payload.payload = encryption::seal(
+                key_provider.as_ref(),
+                message_topic.as_bytes(),
+                &mut message.custom_user_data,
+                &message.serialized_payload.payload,
+            )
+            .map_err(|e| e.into_encrypt_error(None))?;
+            message
+                .serialized_payload
+                .format_indicator
+                .mark_transformed(&mut message.custom_user_data);
+        }
+
+        // Sign the message if configured, now that the payload and metadata are final
+        if let Some(signing_key) = &self.signing_key {
+            signing::sign(
+                signing_key,
+                message_topic.as_bytes(),
+                &mut message.custom_user_data,
+                Some(&message.serialized_payload.content_type),
+                Some(message.serialized_payload.format_indicator.clone() as u8),
+                &message.serialized_payload.payload,
+            )
+            .map_err(|e| e.into_sign_error(None))?;
+        }
+
         // Create MQTT Properties
         let publish_properties = PublishProperties {
             correlation_data: Some(correlation_data),
@@ -469,42 +566,46 @@ where
             subscription_identifiers: Vec::new(),
         };
 
-        // Send publish
-        let publish_result = self
-            .mqtt_client
-            .publish_with_properties(
-                message_topic,
-                message.qos,
-                false,
-                message.serialized_payload.payload,
-                publish_properties,
-            )
-            .await;
-
-        match publish_result {
-            Ok(publish_completion_token) => {
-                // Wait for and handle the puback
-                match publish_completion_token.await {
-                    Ok(()) => Ok(()),
-                    Err(e) => {
+        // Send publish, split into chunks if configured and needed
+        for (payload, publish_properties) in chunking::split(
+            self.chunking,
+            message.serialized_payload.payload,
+            publish_properties,
+        ) {
+            let publish_result = self
+                .mqtt_client
+                .publish_with_properties(
+                    message_topic.clone(),
+                    message.qos,
+                    false,
+                    payload,
+                    publish_properties,
+                )
+                .await;
+
+            match publish_result {
+                Ok(publish_completion_token) => {
+                    // Wait for and handle the puback
+                    if let Err(e) = publish_completion_token.await {
                         log::error!("Puback error: {e}");
-                        Err(AIOProtocolError::new_mqtt_error(
+                        return Err(AIOProtocolError::new_mqtt_error(
                             Some("MQTT Error on telemetry send puback".to_string()),
                             Box::new(e),
                             None,
-                        ))
+                        ));
                     }
                 }
-            }
-            Err(e) => {
-                log::error!("Publish error: {e}");
-                Err(AIOProtocolError::new_mqtt_error(
-                    Some("MQTT Error on telemetry send publish".to_string()),
-                    Box::new(e),
-                    None,
-                ))
+                Err(e) => {
+                    log::error!("Publish error: {e}");
+                    return Err(AIOProtocolError::new_mqtt_error(
+                        Some("MQTT Error on telemetry send publish".to_string()),
+                        Box::new(e),
+                        None,
+                    ));
+                }
             }
         }
+        Ok(())
     }
 }
 
@@ -518,6 +619,7 @@ mod tests {
         application::ApplicationContextBuilder,
         common::{
             aio_protocol_error::{AIOProtocolErrorKind, Value},
+            chunking::Chunking,
             payload_serialize::{FormatIndicator, MockPayload, SerializedPayload},
         },
         telemetry::sender::{OptionsBuilder, Sender},
@@ -560,6 +662,315 @@ mod tests {
         .unwrap();
     }
 
+    #[test_case(1, true; "valid_max_chunk_size")]
+    #[test_case(0, false; "zero_max_chunk_size")]
+    fn test_new_with_chunking(max_chunk_size: usize, valid: bool) {
+        let session = get_session();
+        let sender_options = OptionsBuilder::default()
+            .topic_pattern("test/test_telemetry")
+            .chunking(Chunking::new(max_chunk_size))
+            .build()
+            .unwrap();
+
+        let result = Sender::<MockPayload, _>::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            sender_options,
+        );
+        match result {
+            Ok(sender) => {
+                assert!(valid);
+                assert_eq!(sender.chunking, Some(Chunking::new(max_chunk_size)));
+            }
+            Err(e) => {
+                assert!(!valid);
+                assert_eq!(e.kind, AIOProtocolErrorKind::ConfigurationInvalid);
+                assert_eq!(e.property_name, Some("chunking.max_chunk_size".to_string()));
+            }
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_new_with_compression() {
+        use crate::common::compression::{Compression, CompressionCodec};
+
+        let session = get_session();
+        let sender_options = OptionsBuilder::default()
+            .topic_pattern("test/test_telemetry")
+            .compression(Compression::new(CompressionCodec::Gzip).with_threshold(512))
+            .build()
+            .unwrap();
+
+        let sender = Sender::<MockPayload, _>::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            sender_options,
+        )
+        .unwrap();
+        assert_eq!(
+            sender.compression,
+            Some(Compression {
+                codec: CompressionCodec::Gzip,
+                threshold: 512
+            })
+        );
+    }
+
+    /// The UTF-8 JSON payload sent by [`send_utf8_payload`]
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "encryption"))]
+    fn utf8_payload() -> Vec<u8> {
+        br#"{"telemetry": "repetitive"}"#.repeat(100)
+    }
+
+    /// Sends a UTF-8 JSON payload with a sender built from `sender_options` over a mock client,
+    /// and returns the publish that was sent.
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "encryption"))]
+    async fn send_utf8_payload(
+        sender_options: super::Options,
+    ) -> azure_iot_operations_mqtt::control_packet::Publish {
+        use azure_iot_operations_mqtt::{
+            control_packet::Publish,
+            interface_mocks::{MockClient, MockClientCall, MockEventLoop},
+            session::{reconnect_policy::ExponentialBackoffWithJitter, session},
+        };
+
+        use crate::common::payload_serialize::BypassPayload;
+
+        let client = MockClient::new();
+        let controller = client.mock_controller();
+        let (event_loop, _injector) = MockEventLoop::new();
+        let session = session::Session::new_from_injection(
+            client,
+            event_loop,
+            Box::new(ExponentialBackoffWithJitter::default()),
+            "test_client".to_string(),
+            None,
+        );
+        let sender = Sender::<BypassPayload, _>::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            sender_options,
+        )
+        .unwrap();
+
+        let message = MessageBuilder::default()
+            .payload(BypassPayload {
+                payload: utf8_payload(),
+                content_type: "application/json".to_string(),
+                format_indicator: FormatIndicator::Utf8EncodedCharacterData,
+            })
+            .unwrap()
+            .build()
+            .unwrap();
+        sender.send(message).await.unwrap();
+
+        let Some(MockClientCall::Publish(publish)) = controller.call_sequence().pop() else {
+            panic!("Expected a publish");
+        };
+        Publish {
+            topic: publish.topic.into(),
+            payload: publish.payload,
+            properties: publish.properties,
+            ..Default::default()
+        }
+    }
+
+    /// Sends a UTF-8 JSON payload with [`send_utf8_payload`], and returns the publish properties
+    /// of the sent message along with the message converted back by the receiver, after
+    /// decrypting it with `key_provider` if any.
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "encryption"))]
+    async fn send_and_receive_utf8_payload(
+        sender_options: super::Options,
+        key_provider: Option<&dyn crate::common::encryption::KeyProvider>,
+    ) -> (
+        azure_iot_operations_mqtt::control_packet::PublishProperties,
+        crate::telemetry::receiver::Message<crate::common::payload_serialize::BypassPayload>,
+    ) {
+        use crate::common::{encryption, payload_serialize::BypassPayload};
+
+        let mut publish = send_utf8_payload(sender_options).await;
+        let properties = publish.properties.clone().unwrap();
+        encryption::open_publish(key_provider, &mut publish).unwrap();
+        let received =
+            crate::telemetry::receiver::Message::<BypassPayload>::try_from(publish).unwrap();
+        assert_eq!(received.payload.payload, utf8_payload());
+        (properties, received)
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    #[tokio::test]
+    async fn test_send_compressed_utf8_payload() {
+        use crate::common::{
+            compression::{Compression, CompressionCodec},
+            user_properties::UserProperty,
+        };
+
+        #[cfg(feature = "gzip")]
+        let codec = CompressionCodec::Gzip;
+        #[cfg(not(feature = "gzip"))]
+        let codec = CompressionCodec::Zstd;
+
+        let sender_options = OptionsBuilder::default()
+            .topic_pattern("test/test_telemetry")
+            .compression(Compression::new(codec))
+            .build()
+            .unwrap();
+        let (properties, received) = send_and_receive_utf8_payload(sender_options, None).await;
+
+        // The compressed payload is no longer UTF-8, so it is sent as unspecified bytes
+        assert_eq!(
+            properties.payload_format_indicator,
+            Some(FormatIndicator::UnspecifiedBytes as u8)
+        );
+        assert!(properties.user_properties.contains(&(
+            UserProperty::PayloadFormatIndicator.to_string(),
+            "1".to_string()
+        )));
+        // The receiver restores the original format indicator after decompression
+        assert_eq!(
+            received.format_indicator,
+            FormatIndicator::Utf8EncodedCharacterData
+        );
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test_case(crate::common::encryption::EncryptionAlgorithm::Aes256Gcm; "aes_256_gcm")]
+    #[test_case(crate::common::encryption::EncryptionAlgorithm::ChaCha20Poly1305; "chacha20_poly1305")]
+    #[tokio::test]
+    async fn test_send_encrypted_utf8_payload(
+        algorithm: crate::common::encryption::EncryptionAlgorithm,
+    ) {
+        use std::sync::Arc;
+
+        use crate::common::{
+            encryption::{EncryptionKey, InMemoryKeyProvider, KEY_LEN, KeyProvider},
+            user_properties::UserProperty,
+        };
+
+        let key_provider: Arc<dyn KeyProvider> = Arc::new(InMemoryKeyProvider::new(
+            EncryptionKey::new("key1", algorithm, [0; KEY_LEN]).unwrap(),
+        ));
+        let sender_options = OptionsBuilder::default()
+            .topic_pattern("test/test_telemetry")
+            .encryption(key_provider.clone())
+            .build()
+            .unwrap();
+        let (properties, received) =
+            send_and_receive_utf8_payload(sender_options, Some(key_provider.as_ref())).await;
+
+        // The ciphertext is not UTF-8, so it is sent as unspecified bytes
+        assert_eq!(
+            properties.payload_format_indicator,
+            Some(FormatIndicator::UnspecifiedBytes as u8)
+        );
+        assert!(properties.user_properties.contains(&(
+            UserProperty::PayloadFormatIndicator.to_string(),
+            "1".to_string()
+        )));
+        // The receiver restores the original format indicator after decryption
+        assert_eq!(
+            received.format_indicator,
+            FormatIndicator::Utf8EncodedCharacterData
+        );
+    }
+
+    #[cfg(all(feature = "signing", any(feature = "gzip", feature = "zstd")))]
+    #[tokio::test]
+    async fn test_send_signed_compressed_payload() {
+        use std::sync::Arc;
+
+        use crate::common::{
+            compression::{Compression, CompressionCodec},
+            signing::{
+                self, InMemoryTrustStore, MIN_HMAC_KEY_LEN, SignatureAlgorithm, SignatureError,
+                SignatureStatus, SigningKey,
+            },
+            user_properties::UserProperty,
+        };
+
+        #[cfg(feature = "gzip")]
+        let codec = CompressionCodec::Gzip;
+        #[cfg(not(feature = "gzip"))]
+        let codec = CompressionCodec::Zstd;
+
+        let signing_key = SigningKey::new(
+            "key1",
+            SignatureAlgorithm::HmacSha256,
+            [0; MIN_HMAC_KEY_LEN],
+        )
+        .unwrap();
+        let trust_store = Arc::new(InMemoryTrustStore::new());
+        trust_store.insert(signing_key.verification_key().unwrap());
+        let sender_options = OptionsBuilder::default()
+            .topic_pattern("test/test_telemetry")
+            .compression(Compression::new(codec))
+            .signing(signing_key)
+            .build()
+            .unwrap();
+        let publish = send_utf8_payload(sender_options).await;
+
+        // The signature covers the message as sent, after compression
+        assert!(
+            signing::verify_publish(Some(trust_store.as_ref()), &mut publish.clone()).is_verified()
+        );
+
+        // Altering how the payload is to be decoded invalidates the signature
+        let mut altered = publish.clone();
+        altered
+            .properties
+            .as_mut()
+            .unwrap()
+            .payload_format_indicator = Some(FormatIndicator::Utf8EncodedCharacterData as u8);
+        assert!(matches!(
+            signing::verify_publish(Some(trust_store.as_ref()), &mut altered),
+            SignatureStatus::Invalid(SignatureError::VerificationFailed)
+        ));
+        for property in [
+            UserProperty::ContentEncoding,
+            UserProperty::PayloadFormatIndicator,
+        ] {
+            let mut altered = publish.clone();
+            altered
+                .properties
+                .as_mut()
+                .unwrap()
+                .user_properties
+                .retain(|(key, _)| *key != property.to_string());
+            assert!(matches!(
+                signing::verify_publish(Some(trust_store.as_ref()), &mut altered),
+                SignatureStatus::Invalid(SignatureError::VerificationFailed)
+            ));
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_new_with_signing() {
+        use crate::common::signing::{MIN_HMAC_KEY_LEN, SignatureAlgorithm, SigningKey};
+
+        let session = get_session();
+        let signing_key = SigningKey::new(
+            "key1",
+            SignatureAlgorithm::HmacSha256,
+            [0; MIN_HMAC_KEY_LEN],
+        )
+        .unwrap();
+        let sender_options = OptionsBuilder::default()
+            .topic_pattern("test/test_telemetry")
+            .signing(signing_key.clone())
+            .build()
+            .unwrap();
+
+        let sender = Sender::<MockPayload, _>::new(
+            ApplicationContextBuilder::default().build().unwrap(),
+            session.create_managed_client(),
+            sender_options,
+        )
+        .unwrap();
+        assert_eq!(sender.signing_key, Some(signing_key));
+    }
+
     #[test]
     fn test_new_override_defaults() {
         let session = get_session();
@@ -3,7 +3,8 @@
 
 use async_trait::async_trait;
 use azure_iot_operations_mqtt::control_packet::{
-    AuthProperties, Publish, PublishProperties, QoS, SubscribeProperties, UnsubscribeProperties,
+    AuthProperties, NackReasonCode, Publish, PublishProperties, QoS, SubscribeProperties,
+    SubscriptionOptions, UnsubscribeProperties,
 };
 use azure_iot_operations_mqtt::error::{
     AckError, DisconnectError, PublishError, ReauthError, SubscribeError, UnsubscribeError,
@@ -166,6 +167,22 @@ impl MqttPubSub for MqttDriver {
         Ok(self.subscribe_with_optional_properties(topic, qos, Some(properties)))
     }
 
+    #[allow(clippy::unused_async)]
+    async fn subscribe_with_options(
+        &self,
+        topic: impl Into<String> + Send,
+        qos: QoS,
+        options: SubscriptionOptions,
+    ) -> Result<CompletionToken, SubscribeError> {
+        let properties = options
+            .subscription_identifier
+            .map(|id| SubscribeProperties {
+                id: Some(id),
+                user_properties: vec![],
+            });
+        Ok(self.subscribe_with_optional_properties(topic, qos, properties))
+    }
+
     #[allow(clippy::unused_async)]
     async fn unsubscribe(
         &self,
@@ -192,6 +209,19 @@ impl MqttAck for MqttDriver {
             .unwrap();
         Ok(CompletionToken(Box::new(async { Ok(()) })))
     }
+
+    async fn nack(
+        &self,
+        publish: &Publish,
+        _reason_code: NackReasonCode,
+        _reason_string: Option<String>,
+    ) -> Result<CompletionToken, AckError> {
+        // NOTE: The emulated broker does not distinguish PUBACK reason codes
+        self.operation_tx
+            .send(MqttOperation::Ack { pkid: publish.pkid })
+            .unwrap();
+        Ok(CompletionToken(Box::new(async { Ok(()) })))
+    }
 }
 
 #[async_trait]
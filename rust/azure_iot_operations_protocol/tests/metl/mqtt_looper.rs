@@ -2,8 +2,8 @@
 // Licensed under the MIT License.
 
 use async_trait::async_trait;
-use azure_iot_operations_mqtt::error::ConnectionError;
-use azure_iot_operations_mqtt::interface::{Event, MqttEventLoop};
+use azure_iot_operations_mqtt::error::{ConnectionError, ConnectionTargetError};
+use azure_iot_operations_mqtt::interface::{ConnectionTarget, Event, MqttEventLoop};
 use bytes::Bytes;
 use tokio::sync::mpsc;
 
@@ -37,4 +37,19 @@ impl MqttEventLoop for MqttLooper {
     fn set_authentication_method(&mut self, _authentication_method: Option<String>) {}
 
     fn set_authentication_data(&mut self, _authentication_data: Option<Bytes>) {}
+
+    fn set_connection_target(
+        &mut self,
+        _target: &ConnectionTarget,
+    ) -> Result<(), ConnectionTargetError> {
+        Ok(())
+    }
+
+    fn set_broker(&mut self, _hostname: String, _tcp_port: Option<u16>) {}
+
+    fn take_server_reference(&mut self) -> Option<String> {
+        None
+    }
+
+    fn drop_connection(&mut self) {}
 }
@@ -648,6 +648,7 @@ where
             command_name: None,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
 
         protocol_error.ensure_error_message();
@@ -636,6 +636,7 @@ where
             command_name: None,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
 
         protocol_error.ensure_error_message();
@@ -667,6 +668,7 @@ where
             command_name: None,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
 
         protocol_error.ensure_error_message();
@@ -698,6 +700,7 @@ where
             command_name: None,
             protocol_version: None,
             supported_protocol_major_versions: None,
+            attempt_count: None,
         };
 
         protocol_error.ensure_error_message();
@@ -5,6 +5,7 @@
 
 use core::fmt::Debug;
 
+use azure_iot_operations_mqtt::error::ErrorClassification;
 use azure_iot_operations_protocol::common::aio_protocol_error::AIOProtocolError;
 use thiserror::Error;
 
@@ -35,6 +36,16 @@ impl Error {
     }
 }
 
+impl ErrorClassification for Error {
+    fn is_retriable(&self) -> bool {
+        self.0.is_retriable()
+    }
+
+    fn is_auth_related(&self) -> bool {
+        self.0.is_auth_related()
+    }
+}
+
 impl From<state_store::Error> for Error {
     fn from(error: state_store::Error) -> Self {
         let kind: ErrorKind = (error.consuming_kind()).into();
@@ -72,6 +83,26 @@ pub enum ErrorKind {
     DuplicateObserve,
 }
 
+impl ErrorClassification for ErrorKind {
+    fn is_retriable(&self) -> bool {
+        match self {
+            // The lease may be released by its current holder
+            ErrorKind::LeaseAlreadyHeld => true,
+            ErrorKind::AIOProtocolError(e) => e.is_retriable(),
+            // Errors returned by the service and invalid requests will be repeated on retry
+            _ => false,
+        }
+    }
+
+    fn is_auth_related(&self) -> bool {
+        match self {
+            ErrorKind::AIOProtocolError(e) => e.is_auth_related(),
+            ErrorKind::ServiceError(e) => matches!(e, ServiceError::NotAuthorized),
+            _ => false,
+        }
+    }
+}
+
 impl From<state_store::ErrorKind> for ErrorKind {
     fn from(kind: state_store::ErrorKind) -> Self {
         match kind {
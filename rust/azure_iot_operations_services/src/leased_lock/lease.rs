@@ -10,6 +10,7 @@ use tokio::{select, sync::Notify};
 use crate::leased_lock::{Error, ErrorKind, LeaseObservation, SetCondition, SetOptions};
 use crate::state_store;
 use azure_iot_operations_mqtt::interface::ManagedClient;
+use azure_iot_operations_mqtt::runtime;
 use azure_iot_operations_protocol::common::hybrid_logical_clock::HybridLogicalClock;
 
 /// Lease client struct.
@@ -170,14 +171,14 @@ where
             if renewal_period > Duration::ZERO {
                 let self_clone = self.clone();
 
-                tokio::task::spawn({
+                runtime::spawn({
                     async move {
                         loop {
                             select! {
                                 () = self_clone.auto_renewal_notify.notified() => {
                                     break; // Auto-renewal is cancelled.
                                 }
-                                () = tokio::time::sleep(renewal_period) => {
+                                () = runtime::sleep(renewal_period) => {
                                     if self_clone
                                         .internal_acquire(lease_expiration, request_timeout)
                                         .await
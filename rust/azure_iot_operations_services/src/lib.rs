@@ -10,6 +10,7 @@
 //!
 //! - `all`: Enables all features.
 //! - `schema_registry`: Enables the Schema Registry Client.
+//! - `avro`: Enables Avro payloads resolved through the Schema Registry. Implies `schema_registry`.
 //! - `state_store`: Enables the State Store Client.
 //! - `leased_lock`: Enables the Lease and Lock Clients.
 //!
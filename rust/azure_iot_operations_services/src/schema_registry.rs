@@ -6,6 +6,7 @@
 use core::fmt::Debug;
 use std::collections::HashMap;
 
+use azure_iot_operations_mqtt::error::ErrorClassification;
 use azure_iot_operations_protocol::common::aio_protocol_error::{
     AIOProtocolError, AIOProtocolErrorKind,
 };
@@ -14,6 +15,9 @@ use thiserror::Error;
 
 pub use schemaregistry_gen::schema_registry::client::{Format, Schema, SchemaType};
 
+/// Avro payloads resolved through the Schema Registry
+#[cfg(feature = "avro")]
+pub mod avro;
 /// Schema Registry Client implementation wrapper
 mod client;
 /// Schema Registry generated code
@@ -55,6 +59,33 @@ pub enum ErrorKind {
     ServiceError(ServiceError),
 }
 
+impl ErrorClassification for Error {
+    fn is_retriable(&self) -> bool {
+        self.0.is_retriable()
+    }
+
+    fn is_auth_related(&self) -> bool {
+        self.0.is_auth_related()
+    }
+}
+
+impl ErrorClassification for ErrorKind {
+    fn is_retriable(&self) -> bool {
+        match self {
+            ErrorKind::AIOProtocolError(e) => e.is_retriable(),
+            // Errors returned by the service and invalid requests will be repeated on retry
+            _ => false,
+        }
+    }
+
+    fn is_auth_related(&self) -> bool {
+        match self {
+            ErrorKind::AIOProtocolError(e) => e.is_auth_related(),
+            _ => false,
+        }
+    }
+}
+
 impl From<AIOProtocolError> for ErrorKind {
     fn from(error: AIOProtocolError) -> Self {
         match error.kind {
@@ -115,7 +146,7 @@ impl GetRequestBuilder {
 
 /// Request to put a schema in the schema registry.
 #[derive(Builder, Clone, Debug)]
-#[builder(setter(into))]
+#[builder(setter(into), build_fn(validate = "Self::validate"))]
 pub struct PutRequest {
     /// The content of the schema to be added or updated in the registry.
     content: String,
@@ -131,3 +162,34 @@ pub struct PutRequest {
     #[builder(default = "DEFAULT_SCHEMA_VERSION.to_string()")]
     version: String,
 }
+
+impl PutRequestBuilder {
+    /// Set the content of the request to the provided Avro schema, with format [`Format::Avro1`].
+    ///
+    /// # Panics
+    /// Panics if the schema cannot be represented as JSON. Not possible for a parsed schema.
+    #[cfg(feature = "avro")]
+    pub fn avro_schema(&mut self, schema: &apache_avro::Schema) -> &mut Self {
+        self.content = Some(
+            serde_json::to_string(schema).expect("Parsed Avro schemas should serialize to JSON"),
+        );
+        self.format = Some(Format::Avro1);
+        self
+    }
+
+    /// Validate the [`PutRequest`].
+    ///
+    /// # Errors
+    /// Returns a `String` describing the error if the `format` is [`Format::Avro1`] and the
+    /// `content` is not a valid Avro schema. Only validated if the `avro` feature is enabled.
+    #[allow(clippy::unnecessary_wraps)] // Always succeeds if the `avro` feature is disabled
+    fn validate(&self) -> Result<(), String> {
+        #[cfg(feature = "avro")]
+        if let (Some(content), Some(Format::Avro1)) = (&self.content, &self.format) {
+            apache_avro::Schema::parse_str(content)
+                .map_err(|e| format!("content is not a valid Avro schema: {e}"))?;
+        }
+
+        Ok(())
+    }
+}
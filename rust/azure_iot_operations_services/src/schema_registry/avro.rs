@@ -0,0 +1,595 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Apache Avro payloads identified by a schema registered in the Schema Registry.
+//!
+//! To use this module, the `avro` feature must be enabled.
+//!
+//! Payloads are sent as [`EncodedAvro`], which implements
+//! [`PayloadSerialize`] and carries the ID and version of its writer schema in its content type
+//! (e.g. `application/avro; schemaId=<id>; schemaVersion=<version>`).
+//! - Senders encode values against their writer schema with an [`AvroWriter`].
+//! - Receivers decode values with an [`AvroReader`], which resolves the writer schema of each
+//!   payload through the Schema Registry [`Client`] (caching resolved schemas), and performs Avro
+//!   schema resolution from the writer schema to the reader schema.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use apache_avro::Schema as AvroSchema;
+use azure_iot_operations_mqtt::interface::ManagedClient;
+use azure_iot_operations_protocol::common::payload_serialize::{
+    DeserializationError, FormatIndicator, PayloadSerialize, SerializedPayload,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::schema_registry::{self, Client, Format, GetRequestBuilder};
+
+/// Content type of Avro payloads, excluding parameters
+pub const AVRO_CONTENT_TYPE: &str = "application/avro";
+
+/// Name of the content type parameter carrying the ID of the writer schema
+const SCHEMA_ID_PARAMETER: &str = "schemaId";
+/// Name of the content type parameter carrying the version of the writer schema
+const SCHEMA_VERSION_PARAMETER: &str = "schemaVersion";
+
+/// Represents an error that occurred encoding or decoding an Avro payload.
+#[derive(Debug, Error)]
+#[allow(clippy::large_enum_variant)]
+pub enum AvroError {
+    /// An error occurred in the Avro library, e.g. the value does not match the schema, or the
+    /// writer schema cannot be resolved to the reader schema.
+    #[error(transparent)]
+    Avro(#[from] apache_avro::Error),
+    /// An error occurred retrieving the writer schema from the Schema Registry.
+    #[error(transparent)]
+    SchemaRegistry(#[from] schema_registry::Error),
+    /// The writer schema was not found in the Schema Registry.
+    #[error("writer schema '{id}' version '{version}' was not found in the Schema Registry")]
+    SchemaNotFound {
+        /// ID of the writer schema
+        id: String,
+        /// Version of the writer schema
+        version: String,
+    },
+    /// The writer schema registered in the Schema Registry is not an Avro schema.
+    #[error("writer schema '{id}' version '{version}' is not an Avro schema")]
+    UnsupportedFormat {
+        /// ID of the writer schema
+        id: String,
+        /// Version of the writer schema
+        version: String,
+    },
+}
+
+/// Avro payload, encoded against the writer schema it identifies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodedAvro {
+    /// ID of the writer schema in the Schema Registry
+    pub schema_id: String,
+    /// Version of the writer schema in the Schema Registry
+    pub schema_version: String,
+    /// Avro datum encoded against the writer schema
+    pub payload: Vec<u8>,
+}
+
+impl PayloadSerialize for EncodedAvro {
+    type Error = String;
+
+    fn serialize(self) -> Result<SerializedPayload, String> {
+        Ok(SerializedPayload {
+            payload: self.payload,
+            content_type: format!(
+                "{AVRO_CONTENT_TYPE}; {SCHEMA_ID_PARAMETER}=\"{}\"; {SCHEMA_VERSION_PARAMETER}=\"{}\"",
+                self.schema_id, self.schema_version
+            ),
+            format_indicator: FormatIndicator::UnspecifiedBytes,
+        })
+    }
+
+    fn deserialize(
+        payload: &[u8],
+        content_type: Option<&String>,
+        _format_indicator: &FormatIndicator,
+    ) -> Result<Self, DeserializationError<String>> {
+        let Some(content_type) = content_type else {
+            return Err(DeserializationError::UnsupportedContentType(format!(
+                "Missing content type. Must be '{AVRO_CONTENT_TYPE}' with a '{SCHEMA_ID_PARAMETER}' parameter"
+            )));
+        };
+        let mut parts = content_type.split(';');
+        let media_type = parts.next().unwrap_or_default().trim();
+        if !media_type.eq_ignore_ascii_case(AVRO_CONTENT_TYPE) {
+            return Err(DeserializationError::UnsupportedContentType(format!(
+                "Invalid content type: '{content_type}'. Must be '{AVRO_CONTENT_TYPE}'"
+            )));
+        }
+        let parameters: HashMap<&str, &str> = parts
+            .filter_map(|parameter| parameter.split_once('='))
+            .map(|(name, value)| (name.trim(), value.trim().trim_matches('"')))
+            .collect();
+        let Some(schema_id) = parameters.get(SCHEMA_ID_PARAMETER) else {
+            return Err(DeserializationError::UnsupportedContentType(format!(
+                "Invalid content type: '{content_type}'. Must have a '{SCHEMA_ID_PARAMETER}' parameter"
+            )));
+        };
+        Ok(EncodedAvro {
+            schema_id: (*schema_id).to_string(),
+            schema_version: parameters.get(SCHEMA_VERSION_PARAMETER).map_or_else(
+                || schema_registry::DEFAULT_SCHEMA_VERSION.to_string(),
+                ToString::to_string,
+            ),
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// Encodes values against a writer schema registered in the Schema Registry.
+#[derive(Clone, Debug)]
+pub struct AvroWriter {
+    schema: Arc<AvroSchema>,
+    schema_id: String,
+    schema_version: String,
+}
+
+impl AvroWriter {
+    /// Create a new [`AvroWriter`] for the provided writer schema, which must be registered in the
+    /// Schema Registry with the provided ID and version (e.g. with [`Client::put`]).
+    #[must_use]
+    pub fn new(schema: AvroSchema, schema_id: String, schema_version: String) -> Self {
+        Self {
+            schema: Arc::new(schema),
+            schema_id,
+            schema_version,
+        }
+    }
+
+    /// Encode a value against the writer schema.
+    ///
+    /// # Errors
+    /// [`AvroError::Avro`] if the value cannot be represented by the writer schema.
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<EncodedAvro, AvroError> {
+        let value = apache_avro::to_value(value)?.resolve(&self.schema)?;
+        Ok(EncodedAvro {
+            schema_id: self.schema_id.clone(),
+            schema_version: self.schema_version.clone(),
+            payload: apache_avro::to_avro_datum(&self.schema, value)?,
+        })
+    }
+}
+
+/// Writer schemas resolved through the Schema Registry, keyed by schema ID and version
+type WriterSchemaCache = Arc<Mutex<HashMap<(String, String), Arc<AvroSchema>>>>;
+
+/// Decodes Avro payloads to a reader schema, resolving their writer schemas through the Schema
+/// Registry.
+///
+/// Resolved writer schemas are cached for the lifetime of the reader, as a registered schema
+/// version does not change. Clones of the reader share the cache.
+#[derive(Clone)]
+pub struct AvroReader<C>
+where
+    C: ManagedClient + Clone + Send + Sync + 'static,
+    C::PubReceiver: Send + Sync,
+{
+    client: Client<C>,
+    reader_schema: Arc<AvroSchema>,
+    timeout: Duration,
+    writer_schemas: WriterSchemaCache,
+}
+
+impl<C> AvroReader<C>
+where
+    C: ManagedClient + Clone + Send + Sync + 'static,
+    C::PubReceiver: Send + Sync,
+{
+    /// Create a new [`AvroReader`].
+    ///
+    /// # Arguments
+    /// * `client` - Schema Registry [`Client`] used to resolve writer schemas.
+    /// * `reader_schema` - Schema that decoded values conform to.
+    /// * `timeout` - Timeout for requests to the Schema Registry, rounded up to the nearest second.
+    #[must_use]
+    pub fn new(client: Client<C>, reader_schema: AvroSchema, timeout: Duration) -> Self {
+        Self {
+            client,
+            reader_schema: Arc::new(reader_schema),
+            timeout,
+            writer_schemas: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Decode a payload to a value of the reader schema.
+    ///
+    /// # Errors
+    /// [`AvroError::SchemaRegistry`] if the writer schema cannot be retrieved from the Schema
+    /// Registry.
+    ///
+    /// [`AvroError::SchemaNotFound`] if the writer schema is not registered in the Schema Registry.
+    ///
+    /// [`AvroError::UnsupportedFormat`] if the writer schema is not an Avro schema.
+    ///
+    /// [`AvroError::Avro`] if the writer schema is invalid or cannot be resolved to the reader
+    /// schema, or the payload cannot be decoded.
+    pub async fn decode<T: DeserializeOwned>(&self, payload: &EncodedAvro) -> Result<T, AvroError> {
+        let writer_schema = self
+            .writer_schema(&payload.schema_id, &payload.schema_version)
+            .await?;
+        decode_with(&writer_schema, &self.reader_schema, &payload.payload)
+    }
+
+    /// Get a writer schema from the cache, or from the Schema Registry if not cached.
+    async fn writer_schema(&self, id: &str, version: &str) -> Result<Arc<AvroSchema>, AvroError> {
+        let key = (id.to_string(), version.to_string());
+        if let Some(schema) = self.writer_schemas.lock().unwrap().get(&key) {
+            return Ok(schema.clone());
+        }
+
+        let get_request = GetRequestBuilder::default()
+            .id(id)
+            .version(version)
+            .build()
+            .map_err(|e| {
+                schema_registry::Error(schema_registry::ErrorKind::InvalidArgument(e.to_string()))
+            })?;
+        let not_found = || AvroError::SchemaNotFound {
+            id: id.to_string(),
+            version: version.to_string(),
+        };
+        let schema = self
+            .client
+            .get(get_request, self.timeout)
+            .await?
+            .ok_or_else(not_found)?;
+        if !matches!(schema.format, None | Some(Format::Avro1)) {
+            return Err(AvroError::UnsupportedFormat {
+                id: id.to_string(),
+                version: version.to_string(),
+            });
+        }
+        let content = schema.schema_content.ok_or_else(not_found)?;
+        let schema = Arc::new(AvroSchema::parse_str(&content)?);
+
+        log::debug!("Resolved Avro writer schema '{id}' version '{version}'");
+        self.writer_schemas
+            .lock()
+            .unwrap()
+            .insert(key, schema.clone());
+        Ok(schema)
+    }
+}
+
+/// Decode an Avro datum encoded against the writer schema, resolving it to the reader schema.
+fn decode_with<T: DeserializeOwned>(
+    writer_schema: &AvroSchema,
+    reader_schema: &AvroSchema,
+    mut datum: &[u8],
+) -> Result<T, AvroError> {
+    let value = apache_avro::from_avro_datum(writer_schema, &mut datum, Some(reader_schema))?;
+    Ok(apache_avro::from_value(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use azure_iot_operations_mqtt::control_packet::{Publish, PublishProperties, QoS};
+    use azure_iot_operations_mqtt::interface::{Event, Incoming};
+    use azure_iot_operations_mqtt::interface_mocks::{
+        EventInjector, MockClient, MockClientCall, MockClientController, MockEventLoop,
+    };
+    use azure_iot_operations_mqtt::session::{
+        reconnect_policy::ExponentialBackoffWithJitter, session::Session,
+    };
+    use azure_iot_operations_protocol::application::ApplicationContextBuilder;
+    use serde::{Deserialize, Serialize};
+    use test_case::test_case;
+
+    use super::*;
+
+    const WRITER_SCHEMA: &str = r#"
+    {
+        "type": "record",
+        "name": "Reading",
+        "fields": [
+            { "name": "sensor", "type": "string" },
+            { "name": "temperature", "type": "double" }
+        ]
+    }
+    "#;
+
+    // Adds a field with a default, and drops the `sensor` field of the writer schema
+    const READER_SCHEMA: &str = r#"
+    {
+        "type": "record",
+        "name": "Reading",
+        "fields": [
+            { "name": "temperature", "type": "double" },
+            { "name": "unit", "type": "string", "default": "celsius" }
+        ]
+    }
+    "#;
+
+    #[derive(Debug, PartialEq, Serialize)]
+    struct WriterReading {
+        sensor: String,
+        temperature: f64,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct ReaderReading {
+        temperature: f64,
+        unit: String,
+    }
+
+    fn writer() -> AvroWriter {
+        AvroWriter::new(
+            AvroSchema::parse_str(WRITER_SCHEMA).unwrap(),
+            "reading".to_string(),
+            "2".to_string(),
+        )
+    }
+
+    #[test]
+    fn encoded_avro_round_trip() {
+        let encoded = writer()
+            .encode(&WriterReading {
+                sensor: "s1".to_string(),
+                temperature: 21.5,
+            })
+            .unwrap();
+        let serialized = encoded.clone().serialize().unwrap();
+        assert_eq!(
+            serialized.content_type,
+            "application/avro; schemaId=\"reading\"; schemaVersion=\"2\""
+        );
+        let deserialized = EncodedAvro::deserialize(
+            &serialized.payload,
+            Some(&serialized.content_type),
+            &serialized.format_indicator,
+        )
+        .unwrap();
+        assert_eq!(deserialized, encoded);
+    }
+
+    #[test]
+    fn default_schema_version() {
+        let deserialized = EncodedAvro::deserialize(
+            &[],
+            Some(&"application/avro;schemaId=reading".to_string()),
+            &FormatIndicator::UnspecifiedBytes,
+        )
+        .unwrap();
+        assert_eq!(deserialized.schema_id, "reading");
+        assert_eq!(
+            deserialized.schema_version,
+            schema_registry::DEFAULT_SCHEMA_VERSION
+        );
+    }
+
+    #[test_case(None; "no content type")]
+    #[test_case(Some("application/json; schemaId=reading"); "other media type")]
+    #[test_case(Some("application/avro"); "no schema id")]
+    #[test_case(Some("application/avro; schemaVersion=1"); "only schema version")]
+    fn unsupported_content_type(content_type: Option<&str>) {
+        assert!(matches!(
+            EncodedAvro::deserialize(
+                &[],
+                content_type.map(ToString::to_string).as_ref(),
+                &FormatIndicator::UnspecifiedBytes,
+            ),
+            Err(DeserializationError::UnsupportedContentType(_))
+        ));
+    }
+
+    #[test]
+    fn encode_invalid_value() {
+        #[derive(Serialize)]
+        struct Other {
+            name: String,
+        }
+        assert!(matches!(
+            writer().encode(&Other {
+                name: "other".to_string()
+            }),
+            Err(AvroError::Avro(_))
+        ));
+    }
+
+    #[test]
+    fn schema_resolution() {
+        let encoded = writer()
+            .encode(&WriterReading {
+                sensor: "s1".to_string(),
+                temperature: 21.5,
+            })
+            .unwrap();
+        let reading: ReaderReading = decode_with(
+            &AvroSchema::parse_str(WRITER_SCHEMA).unwrap(),
+            &AvroSchema::parse_str(READER_SCHEMA).unwrap(),
+            &encoded.payload,
+        )
+        .unwrap();
+        assert_eq!(
+            reading,
+            ReaderReading {
+                temperature: 21.5,
+                unit: "celsius".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn incompatible_schema_resolution() {
+        let reader_schema = AvroSchema::parse_str(
+            r#"{ "type": "record", "name": "Reading", "fields": [ { "name": "humidity", "type": "int" } ] }"#,
+        )
+        .unwrap();
+        let encoded = writer()
+            .encode(&WriterReading {
+                sensor: "s1".to_string(),
+                temperature: 21.5,
+            })
+            .unwrap();
+        assert!(matches!(
+            decode_with::<ReaderReading>(
+                &AvroSchema::parse_str(WRITER_SCHEMA).unwrap(),
+                &reader_schema,
+                &encoded.payload,
+            ),
+            Err(AvroError::Avro(_))
+        ));
+    }
+
+    fn mock_session() -> (
+        Session<MockClient, MockEventLoop>,
+        MockClientController,
+        EventInjector,
+    ) {
+        let client = MockClient::new();
+        let controller = client.mock_controller();
+        let (event_loop, injector) = MockEventLoop::new();
+        let session = Session::new_from_injection(
+            client,
+            event_loop,
+            Box::new(ExponentialBackoffWithJitter::default()),
+            "avro_test_client".to_string(),
+            None,
+        );
+        (session, controller, injector)
+    }
+
+    /// Respond to the next Schema Registry request published on the mock client with the provided
+    /// schema, and return the published request payload.
+    async fn respond_with_schema(
+        controller: &MockClientController,
+        injector: &EventInjector,
+        schema: serde_json::Value,
+    ) -> serde_json::Value {
+        let request = loop {
+            let request = controller
+                .call_sequence()
+                .into_iter()
+                .find_map(|call| match call {
+                    MockClientCall::Publish(publish) => Some(publish),
+                    _ => None,
+                });
+            if let Some(request) = request {
+                break request;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        let request_properties = request.properties.unwrap();
+        let response = serde_json::json!({ "schema": schema });
+        injector
+            .inject(Event::Incoming(Incoming::Publish(Publish {
+                dup: false,
+                qos: QoS::AtLeastOnce,
+                retain: false,
+                topic: request_properties.response_topic.unwrap().into(),
+                pkid: 1,
+                payload: serde_json::to_vec(&response).unwrap().into(),
+                properties: Some(PublishProperties {
+                    correlation_data: request_properties.correlation_data,
+                    content_type: Some("application/json".to_string()),
+                    user_properties: vec![("__stat".to_string(), "200".to_string())],
+                    ..Default::default()
+                }),
+            })))
+            .unwrap();
+        serde_json::from_slice(&request.payload).unwrap()
+    }
+
+    #[tokio::test]
+    async fn decode_resolves_writer_schema_through_registry() {
+        let (session, controller, injector) = mock_session();
+        let reader = AvroReader::new(
+            Client::new(
+                ApplicationContextBuilder::default().build().unwrap(),
+                &session.create_managed_client(),
+            ),
+            AvroSchema::parse_str(READER_SCHEMA).unwrap(),
+            Duration::from_secs(10),
+        );
+        let encoded = writer()
+            .encode(&WriterReading {
+                sensor: "s1".to_string(),
+                temperature: 21.5,
+            })
+            .unwrap();
+
+        let test = async {
+            let (reading, request) = tokio::join!(
+                reader.decode::<ReaderReading>(&encoded),
+                respond_with_schema(
+                    &controller,
+                    &injector,
+                    serde_json::json!({
+                        "format": "Avro/1.0",
+                        "schemaContent": WRITER_SCHEMA,
+                        "schemaType": "MessageSchema",
+                        "name": "reading",
+                        "version": "2",
+                    }),
+                ),
+            );
+            assert_eq!(request["getSchemaRequest"]["name"], "reading");
+            assert_eq!(request["getSchemaRequest"]["version"], "2");
+            let expected = ReaderReading {
+                temperature: 21.5,
+                unit: "celsius".to_string(),
+            };
+            assert_eq!(reading.unwrap(), expected);
+
+            // The writer schema is cached, so it is not requested again
+            let reading = reader.decode::<ReaderReading>(&encoded).await.unwrap();
+            assert_eq!(reading, expected);
+            assert_eq!(controller.publish_count(), 1);
+        };
+        tokio::select! {
+            () = test => {}
+            _ = session.run() => panic!("Session ended unexpectedly"),
+        }
+    }
+
+    #[tokio::test]
+    async fn decode_non_avro_writer_schema() {
+        let (session, controller, injector) = mock_session();
+        let reader = AvroReader::new(
+            Client::new(
+                ApplicationContextBuilder::default().build().unwrap(),
+                &session.create_managed_client(),
+            ),
+            AvroSchema::parse_str(READER_SCHEMA).unwrap(),
+            Duration::from_secs(10),
+        );
+        let encoded = writer()
+            .encode(&WriterReading {
+                sensor: "s1".to_string(),
+                temperature: 21.5,
+            })
+            .unwrap();
+
+        let test = async {
+            let (reading, _) = tokio::join!(
+                reader.decode::<ReaderReading>(&encoded),
+                respond_with_schema(
+                    &controller,
+                    &injector,
+                    serde_json::json!({
+                        "format": "JsonSchema/draft-07",
+                        "schemaContent": "{}",
+                        "name": "reading",
+                        "version": "2",
+                    }),
+                ),
+            );
+            assert!(matches!(reading, Err(AvroError::UnsupportedFormat { .. })));
+        };
+        tokio::select! {
+            () = test => {}
+            _ = session.run() => panic!("Session ended unexpectedly"),
+        }
+    }
+}
@@ -288,6 +288,37 @@ mod tests {
         assert_eq!(put_request.version, DEFAULT_SCHEMA_VERSION.to_string());
     }
 
+    #[cfg(feature = "avro")]
+    #[tokio::test]
+    async fn test_put_request_avro() {
+        use crate::schema_registry::PutRequestBuilderError;
+
+        let schema = apache_avro::Schema::parse_str(
+            r#"{ "type": "record", "name": "Reading", "fields": [ { "name": "temperature", "type": "double", "default": 0.0 } ] }"#,
+        )
+        .unwrap();
+        let put_request = PutRequestBuilder::default()
+            .avro_schema(&schema)
+            .build()
+            .unwrap();
+
+        assert!(matches!(put_request.format, Format::Avro1));
+        assert_eq!(
+            apache_avro::Schema::parse_str(&put_request.content).unwrap(),
+            schema
+        );
+
+        let put_request = PutRequestBuilder::default()
+            .content(TEST_SCHEMA_CONTENT.to_string())
+            .format(Format::Avro1)
+            .build();
+
+        assert!(matches!(
+            put_request.unwrap_err(),
+            PutRequestBuilderError::ValidationError(_)
+        ));
+    }
+
     #[tokio::test]
     async fn test_get_timeout_invalid() {
         let session = create_session();
@@ -5,6 +5,8 @@ use serde::{Deserialize, Serialize};
 /// Supported schema formats
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Format {
+    #[serde(rename = "Avro/1.0")]
+    Avro1,
     #[serde(rename = "Delta/1.0")]
     Delta1,
     #[serde(rename = "JsonSchema/draft-07")]
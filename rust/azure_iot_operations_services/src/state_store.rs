@@ -5,6 +5,7 @@
 
 use core::fmt::Debug;
 
+use azure_iot_operations_mqtt::error::ErrorClassification;
 use azure_iot_operations_protocol::{
     common::{aio_protocol_error::AIOProtocolError, hybrid_logical_clock::HybridLogicalClock},
     rpc_command,
@@ -42,6 +43,16 @@ impl Error {
     }
 }
 
+impl ErrorClassification for Error {
+    fn is_retriable(&self) -> bool {
+        self.0.is_retriable()
+    }
+
+    fn is_auth_related(&self) -> bool {
+        self.0.is_auth_related()
+    }
+}
+
 /// Represents the kinds of errors that occur in the Azure IoT Operations State Store implementation.
 #[derive(Error, Debug)]
 #[allow(clippy::large_enum_variant)]
@@ -66,6 +77,24 @@ pub enum ErrorKind {
     DuplicateObserve,
 }
 
+impl ErrorClassification for ErrorKind {
+    fn is_retriable(&self) -> bool {
+        match self {
+            ErrorKind::AIOProtocolError(e) => e.is_retriable(),
+            // Errors returned by the service and invalid requests will be repeated on retry
+            _ => false,
+        }
+    }
+
+    fn is_auth_related(&self) -> bool {
+        match self {
+            ErrorKind::AIOProtocolError(e) => e.is_auth_related(),
+            ErrorKind::ServiceError(e) => matches!(e, ServiceError::NotAuthorized),
+            _ => false,
+        }
+    }
+}
+
 /// Represents the errors that occur in the Azure IoT Operations State Store Service.
 #[derive(Error, Debug)]
 pub enum ServiceError {
@@ -9,6 +9,7 @@ use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use azure_iot_operations_mqtt::{
     interface::{AckToken, ManagedClient},
+    runtime,
     session::SessionConnectionMonitor,
 };
 use azure_iot_operations_protocol::{
@@ -17,7 +18,7 @@ use azure_iot_operations_protocol::{
 };
 use data_encoding::HEXUPPER;
 use derive_builder::Builder;
-use tokio::{sync::Notify, task};
+use tokio::sync::Notify;
 
 use crate::common::dispatcher::{DispatchError, DispatchErrorKind, Dispatcher, Receiver};
 use crate::state_store::{self, Error, ErrorKind, FENCING_TOKEN_USER_PROPERTY, SetOptions};
@@ -142,7 +143,7 @@ where
         let notification_dispatcher = Arc::new(Dispatcher::new());
 
         // Start the receive key notification loop
-        task::spawn({
+        runtime::spawn({
             let notification_receiver: telemetry::Receiver<state_store::resp3::Operation, C> =
                 telemetry::Receiver::new(application_context, client, receiver_options)
                     .map_err(ErrorKind::from)?;
@@ -677,10 +678,12 @@ mod tests {
 
     // TODO: This dependency on MqttConnectionSettingsBuilder should be removed in lieu of using a true mock
     use azure_iot_operations_mqtt::MqttConnectionSettingsBuilder;
+    use azure_iot_operations_mqtt::error::ErrorClassification;
     use azure_iot_operations_mqtt::session::{Session, SessionOptionsBuilder};
     use azure_iot_operations_protocol::application::ApplicationContextBuilder;
+    use azure_iot_operations_protocol::common::aio_protocol_error::AIOProtocolError;
 
-    use crate::state_store::{Error, ErrorKind, SetOptions};
+    use crate::state_store::{Error, ErrorKind, ServiceError, SetOptions};
 
     // TODO: This should return a mock ManagedClient instead.
     // Until that's possible, need to return a Session so that the Session doesn't go out of
@@ -965,4 +968,27 @@ mod tests {
             Error(ErrorKind::InvalidArgument(_))
         ));
     }
+
+    #[test]
+    fn test_error_classification() {
+        let timeout = Error(ErrorKind::AIOProtocolError(
+            AIOProtocolError::new_timeout_error(
+                false,
+                None,
+                "request_timeout",
+                Duration::from_secs(10),
+                None,
+                Some("set".to_string()),
+            ),
+        ));
+        assert!(timeout.is_retriable());
+        assert!(!timeout.is_auth_related());
+
+        let not_authorized = Error(ErrorKind::ServiceError(ServiceError::NotAuthorized));
+        assert!(not_authorized.is_fatal());
+        assert!(not_authorized.is_auth_related());
+
+        let invalid_argument = Error(ErrorKind::InvalidArgument("key".to_string()));
+        assert!(invalid_argument.is_fatal());
+    }
 }